@@ -9,3 +9,12 @@ CREATE DICTIONARY SALUS_METRICS.api_key_dictionary (
     `site` String,
     `customer` String
 ) PRIMARY KEY (api_key, site) SOURCE (CLICKHOUSE (TABLE 'API_KEY')) LAYOUT (COMPLEX_KEY_HASHED ()) LIFETIME (60);
+
+-- Secrets that trusted backends use to submit events through the
+-- server-to-server API. Only the lowercase hex SHA-256 digest of each secret
+-- is stored, e.g. `printf '%s' "$SECRET" | sha256sum`. Delete a row to revoke
+-- the corresponding key.
+CREATE TABLE SALUS_METRICS.SERVER_KEY (
+    `api_key` LowCardinality (String) CODEC (ZSTD (1)),
+    `key_sha256` String CODEC (ZSTD (1))
+) ENGINE = ReplacingMergeTree PRIMARY KEY (api_key, key_sha256);
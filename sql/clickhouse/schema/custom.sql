@@ -0,0 +1,45 @@
+DROP TABLE IF EXISTS SALUS_METRICS.CUSTOM_EVENT;
+
+CREATE TABLE SALUS_METRICS.CUSTOM_EVENT (
+    `api_key` LowCardinality (String) CODEC (ZSTD),
+    `site` LowCardinality (String) CODEC (ZSTD),
+    `name` LowCardinality (String) CODEC (ZSTD),
+    `location` String ALIAS attrs['location'],
+    `path` String ALIAS path(attrs['location']),
+    `id` UUID CODEC (ZSTD),
+    `ts` DateTime CODEC(Delta, ZSTD),
+    `parent` UUID CODEC(ZSTD),
+    `props` Map (LowCardinality (String), String) CODEC (ZSTD),
+    `attrs` Map (LowCardinality (String), String) CODEC (ZSTD)
+) ENGINE = MergeTree
+ORDER BY
+    (api_key, site, name, id)
+SETTINGS non_replicated_deduplication_window = 1000
+;
+
+
+DROP TABLE IF EXISTS SALUS_METRICS.custom_event_mv;
+
+-- Props are stored in attrs with a `prop.` prefix, see
+-- `CUSTOM_PROP_ATTR_PREFIX` in the ingest crate
+CREATE MATERIALIZED VIEW SALUS_METRICS.custom_event_mv TO SALUS_METRICS.CUSTOM_EVENT AS
+SELECT
+    api_key,
+    site,
+    attrs['name'] as name,
+    id,
+    ts,
+    toUUID(attrs['parent']) as parent,
+    mapApply((k, v) -> (substring(k, 6), v), mapFilter((k, v) -> startsWith(k, 'prop.'), attrs)) as props,
+    attrs
+FROM
+    SALUS_METRICS.EVENT
+WHERE
+    event_type = 'Custom'
+    AND dictHas (
+        'SALUS_METRICS.api_key_dictionary',
+        (api_key, site)
+    ) = 1
+    AND attrs['parent'] > ''
+    AND attrs['name'] > ''
+;
@@ -1,5 +1,8 @@
 -- Want to use asynchronous insert to get best use of resources.
 -- Also use query cache for best performance SETTINGS use_query_cache = true
+-- Each insert carries an insert_deduplication_token derived from its event
+-- ids, so that a retried batch is dropped by the event tables, which keep a
+-- non_replicated_deduplication_window of recent insert tokens.
 DROP TABLE IF EXISTS SALUS_METRICS.EVENT;
 
 CREATE TABLE SALUS_METRICS.EVENT (
@@ -9,7 +12,9 @@ CREATE TABLE SALUS_METRICS.EVENT (
         'Visitor' = 1,
         'Session' = 2,
         'Section' = 3,
-        'Click' = 4
+        'Click' = 4,
+        'Custom' = 5,
+        'Identify' = 6
     ),
     `id` UUID,
     `ts` DateTime DEFAULT UUIDv7ToDateTime (id),
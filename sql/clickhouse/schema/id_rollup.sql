@@ -17,7 +17,7 @@ CREATE MATERIALIZED VIEW SALUS_METRICS.heirarchy_event_mv TO SALUS_METRICS.HEIRA
 SELECT
     SALUS_METRICS.SECTION_EVENT.api_key as api_key,
     SALUS_METRICS.SECTION_EVENT.site as site,
-    SALUS_METRICS.VISITOR_EVENT.id as visitor,
+    VISITOR.id as visitor,
     SALUS_METRICS.SESSION_EVENT.id as session,
     SALUS_METRICS.SECTION_EVENT.id as section,
     SALUS_METRICS.SECTION_EVENT.ts as ts
@@ -28,8 +28,16 @@ FROM
         AND SALUS_METRICS.SESSION_EVENT.api_key = SALUS_METRICS.SECTION_EVENT.api_key
         AND SALUS_METRICS.SESSION_EVENT.site = SALUS_METRICS.SECTION_EVENT.site
     )
-    INNER JOIN SALUS_METRICS.VISITOR_EVENT ON (
-        SALUS_METRICS.SESSION_EVENT.parent = SALUS_METRICS.VISITOR_EVENT.id
-        AND SALUS_METRICS.SESSION_EVENT.api_key = SALUS_METRICS.VISITOR_EVENT.api_key
-        AND SALUS_METRICS.SESSION_EVENT.site = SALUS_METRICS.VISITOR_EVENT.site
+    -- VISITOR_EVENT may hold a visitor more than once until its rows are merged
+    INNER JOIN (
+        SELECT DISTINCT
+            api_key,
+            site,
+            id
+        FROM
+            SALUS_METRICS.VISITOR_EVENT
+    ) AS VISITOR ON (
+        SALUS_METRICS.SESSION_EVENT.parent = VISITOR.id
+        AND SALUS_METRICS.SESSION_EVENT.api_key = VISITOR.api_key
+        AND SALUS_METRICS.SESSION_EVENT.site = VISITOR.site
     );
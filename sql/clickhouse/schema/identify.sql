@@ -0,0 +1,75 @@
+DROP TABLE IF EXISTS SALUS_METRICS.IDENTIFY_EVENT;
+
+CREATE TABLE SALUS_METRICS.IDENTIFY_EVENT (
+    `api_key` LowCardinality (String) CODEC (ZSTD),
+    `site` LowCardinality (String) CODEC (ZSTD),
+    `user_hash` String CODEC (ZSTD),
+    `id` UUID CODEC (ZSTD),
+    `ts` DateTime CODEC(Delta, ZSTD),
+    `parent` UUID CODEC(ZSTD),
+    `traits` Map (LowCardinality (String), String) CODEC (ZSTD)
+) ENGINE = MergeTree
+ORDER BY
+    (api_key, site, user_hash, id)
+SETTINGS non_replicated_deduplication_window = 1000
+;
+
+
+DROP TABLE IF EXISTS SALUS_METRICS.identify_event_mv;
+
+-- Traits are stored in attrs with a `trait.` prefix, see
+-- `IDENTIFY_TRAIT_ATTR_PREFIX` in the ingest crate. The user_hash is the
+-- HMAC-SHA256 of the user id under the identity key of the source.
+CREATE MATERIALIZED VIEW SALUS_METRICS.identify_event_mv TO SALUS_METRICS.IDENTIFY_EVENT AS
+SELECT
+    api_key,
+    site,
+    attrs['user_hash'] as user_hash,
+    id,
+    ts,
+    toUUID(attrs['parent']) as parent,
+    mapApply((k, v) -> (substring(k, 7), v), mapFilter((k, v) -> startsWith(k, 'trait.'), attrs)) as traits
+FROM
+    SALUS_METRICS.EVENT
+WHERE
+    event_type = 'Identify'
+    AND dictHas (
+        'SALUS_METRICS.api_key_dictionary',
+        (api_key, site)
+    ) = 1
+    AND attrs['parent'] > ''
+    AND attrs['user_hash'] > ''
+;
+
+
+DROP TABLE IF EXISTS SALUS_METRICS.VISITOR_USER;
+
+-- One row per visitor and user, keeping the first and latest time that the
+-- visitor was identified as the user. Counting distinct user_hash across
+-- visitors gives logged-in users across devices.
+CREATE TABLE SALUS_METRICS.VISITOR_USER (
+    `api_key` LowCardinality (String) CODEC (ZSTD),
+    `site` LowCardinality (String) CODEC (ZSTD),
+    `user_hash` String CODEC (ZSTD),
+    `visitor_id` UUID CODEC (ZSTD),
+    `first_identified` SimpleAggregateFunction (min, DateTime) CODEC (Delta, ZSTD),
+    `last_identified` SimpleAggregateFunction (max, DateTime) CODEC (Delta, ZSTD)
+) ENGINE = AggregatingMergeTree
+ORDER BY
+    (api_key, site, user_hash, visitor_id)
+;
+
+
+DROP TABLE IF EXISTS SALUS_METRICS.visitor_user_mv;
+
+CREATE MATERIALIZED VIEW SALUS_METRICS.visitor_user_mv TO SALUS_METRICS.VISITOR_USER AS
+SELECT
+    api_key,
+    site,
+    user_hash,
+    parent as visitor_id,
+    ts as first_identified,
+    ts as last_identified
+FROM
+    SALUS_METRICS.IDENTIFY_EVENT
+;
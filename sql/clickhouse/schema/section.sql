@@ -4,6 +4,7 @@ CREATE TABLE SALUS_METRICS.SECTION_EVENT (
     `api_key` LowCardinality (String) CODEC (ZSTD),
     `site` LowCardinality (String) CODEC (ZSTD),
     `path` String CODEC (ZSTD),
+    `raw_path` String ALIAS path(attrs['location']),
     `query` String ALIAS queryString(attrs['location']),
     `fragment` String ALIAS fragment(attrs['location']),
     `title` String ALIAS attrs['title'],
@@ -14,7 +15,9 @@ CREATE TABLE SALUS_METRICS.SECTION_EVENT (
 ) ENGINE = MergeTree
 ORDER BY
     (api_key, site, id)
-TTL ts + INTERVAL 1 WEEK
+-- Imported history is kept, as it would otherwise expire as soon as it lands
+TTL ts + INTERVAL 1 WEEK DELETE WHERE attrs['origin'] != 'import'
+SETTINGS non_replicated_deduplication_window = 1000
 ;
 
 
@@ -24,7 +27,7 @@ CREATE MATERIALIZED VIEW SALUS_METRICS.section_event_mv TO SALUS_METRICS.SECTION
 SELECT
     api_key,
     site,
-    path(attrs['location']) as path,
+    if(attrs['path'] > '', attrs['path'], path(attrs['location'])) as path,
     id,
     ts,
     toUUID(attrs['parent']) as parent,
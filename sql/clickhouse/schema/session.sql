@@ -15,13 +15,15 @@ CREATE TABLE SALUS_METRICS.SESSION_EVENT (
     `user_agent` String ALIAS attrs['user_agent'],
     `ipv4` Nullable(IPv4) ALIAS attrs['ipv4'],
     `ipv6` Nullable(IPv6) ALIAS attrs['ipv6'],
+    `referrer` String ALIAS attrs['referrer'],
     `country_code` String,
     `state` String,
     `city` String,
     `attrs` Map (LowCardinality (String), String) CODEC (ZSTD (1))
 ) ENGINE = MergeTree
 ORDER BY
-    (api_key, site, id);
+    (api_key, site, id)
+SETTINGS non_replicated_deduplication_window = 1000;
 
 
 DROP TABLE IF EXISTS SALUS_METRICS.session_event_mv;
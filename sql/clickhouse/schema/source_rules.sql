@@ -0,0 +1,36 @@
+-- Per-source processing rules that the ingest server loads together with the
+-- API_KEY table. Sources without any rows here are processed with defaults.
+
+-- Query parameters that should be kept (Allow) or removed (Deny) from the
+-- location of section events. If any Allow rows exist for a source, only
+-- those parameters are kept. Deny rows always take precedence.
+CREATE TABLE SALUS_METRICS.SOURCE_QUERY_PARAM (
+    `api_key` LowCardinality (String) CODEC (ZSTD (1)),
+    `site` LowCardinality (String) CODEC (ZSTD (1)),
+    `param` String CODEC (ZSTD (1)),
+    `rule` Enum8 ('Allow' = 1, 'Deny' = 2)
+) ENGINE = ReplacingMergeTree PRIMARY KEY (api_key, site, param);
+
+-- Path normalization for the location of section events. The normalized
+-- path is stored in SECTION_EVENT.path while the raw path stays available
+-- through SECTION_EVENT.raw_path. Steps apply in a fixed order: lowercase,
+-- rewrites (pattern, replacement) in array order, UUID segments to ':uuid',
+-- numeric segments to ':id' and finally trailing slash removal.
+CREATE TABLE SALUS_METRICS.SOURCE_PATH_RULE (
+    `api_key` LowCardinality (String) CODEC (ZSTD (1)),
+    `site` LowCardinality (String) CODEC (ZSTD (1)),
+    `rewrites` Array (Tuple (String, String)) CODEC (ZSTD (1)),
+    `collapse_numeric` Bool DEFAULT false,
+    `collapse_uuid` Bool DEFAULT false,
+    `trim_trailing_slash` Bool DEFAULT false,
+    `lowercase` Bool DEFAULT false
+) ENGINE = ReplacingMergeTree PRIMARY KEY (api_key, site);
+
+-- Secret that the user ids of identify events are hashed with (HMAC-SHA256)
+-- before they are stored. Identify events are rejected for sources without a
+-- key. Changing the key changes the hash of every user from then on.
+CREATE TABLE SALUS_METRICS.SOURCE_IDENTITY_KEY (
+    `api_key` LowCardinality (String) CODEC (ZSTD (1)),
+    `site` LowCardinality (String) CODEC (ZSTD (1)),
+    `identity_key` String CODEC (ZSTD (1))
+) ENGINE = ReplacingMergeTree PRIMARY KEY (api_key, site);
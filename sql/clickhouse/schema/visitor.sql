@@ -1,14 +1,19 @@
 DROP TABLE IF EXISTS SALUS_METRICS.VISITOR_EVENT;
 
+-- One row per visitor id. The ingest server derives the ids of synthesized
+-- visitors from their client, so a visitor that the server no longer
+-- remembers is emitted again with the same id and a later ts. Rows of the same
+-- id are merged, keeping the first ts.
 CREATE TABLE SALUS_METRICS.VISITOR_EVENT (
     `api_key` LowCardinality (String) CODEC (ZSTD),
     `site` LowCardinality (String) CODEC (ZSTD),
     `id` UUID CODEC (ZSTD),
-    `ts` DateTime CODEC (Delta, ZSTD),
-    `attrs` Map (LowCardinality (String), String) CODEC (ZSTD)
-) ENGINE = MergeTree
+    `ts` SimpleAggregateFunction (min, DateTime) CODEC (Delta, ZSTD),
+    `attrs` SimpleAggregateFunction (any, Map (LowCardinality (String), String)) CODEC (ZSTD)
+) ENGINE = AggregatingMergeTree
 ORDER BY
-    (api_key, site, id);
+    (api_key, site, id)
+SETTINGS non_replicated_deduplication_window = 1000;
 
 DROP TABLE IF EXISTS SALUS_METRICS.visitor_event_mv;
 
@@ -32,11 +37,13 @@ WHERE
 -- ---------------------------------------------------------------------------
 DROP TABLE IF EXISTS SALUS_METRICS.VISITOR_TIMESERIES;
 
+-- Visitors are counted by distinct id, so that a visitor emitted again is not
+-- counted twice within a time range.
 CREATE TABLE SALUS_METRICS.VISITOR_TIMESERIES (
     `api_key` LowCardinality (String) CODEC (ZSTD),
     `site` LowCardinality (String) CODEC (ZSTD),
     `ts` DateTime CODEC (Delta, ZSTD),
-    `visitors` AggregateFunction (sum, UInt32)
+    `visitors` AggregateFunction (uniq, UUID)
 ) ENGINE = AggregatingMergeTree
 ORDER BY
     (api_key, site, ts);
@@ -48,7 +55,7 @@ SELECT
     api_key,
     site,
     toStartOfFiveMinutes (ts) as ts,
-    sumState (toUInt32 (1)) as visitors
+    uniqState (id) as visitors
 FROM
     SALUS_METRICS.VISITOR_EVENT
 GROUP BY
@@ -321,7 +321,7 @@ SELECT
     {% else %}
       toStartOfFiveMinutes(`ts`) as ts,
     {% endif %}
-    sumMerge(visitors) as visitors
+    uniqMerge(visitors) as visitors
 FROM VISITOR_TIMESERIES
 WHERE (
     api_key in {{filter_values('api_key')|where_in}}
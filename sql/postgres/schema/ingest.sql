@@ -0,0 +1,69 @@
+-- Schema of the PostgreSQL backend of the ingest server. The server applies
+-- it on startup, so every statement must be safe to repeat. The tables mirror
+-- those of ClickHouse in sql/clickhouse/schema.
+
+-- Api key and site combinations that events are accepted from
+CREATE TABLE IF NOT EXISTS api_key (
+    api_key TEXT NOT NULL,
+    site TEXT NOT NULL,
+    customer TEXT NOT NULL DEFAULT '',
+    PRIMARY KEY (api_key, site)
+);
+
+-- Lowercase hex SHA-256 digests of the secrets that trusted backends use to
+-- submit events through the server-to-server API
+CREATE TABLE IF NOT EXISTS server_key (
+    api_key TEXT NOT NULL,
+    key_sha256 TEXT NOT NULL,
+    PRIMARY KEY (api_key, key_sha256)
+);
+
+-- Query parameters that are kept (Allow) or removed (Deny) from locations
+CREATE TABLE IF NOT EXISTS source_query_param (
+    api_key TEXT NOT NULL,
+    site TEXT NOT NULL,
+    param TEXT NOT NULL,
+    rule TEXT NOT NULL CHECK (rule IN ('Allow', 'Deny')),
+    PRIMARY KEY (api_key, site, param)
+);
+
+-- Path normalization of section locations. `rewrites` is a JSON array of
+-- [pattern, replacement] pairs that are applied in order.
+CREATE TABLE IF NOT EXISTS source_path_rule (
+    api_key TEXT NOT NULL,
+    site TEXT NOT NULL,
+    rewrites JSONB NOT NULL DEFAULT '[]',
+    collapse_numeric BOOLEAN NOT NULL DEFAULT false,
+    collapse_uuid BOOLEAN NOT NULL DEFAULT false,
+    trim_trailing_slash BOOLEAN NOT NULL DEFAULT false,
+    lowercase BOOLEAN NOT NULL DEFAULT false,
+    PRIMARY KEY (api_key, site)
+);
+
+-- Secret that the user ids of identify events are hashed with
+CREATE TABLE IF NOT EXISTS source_identity_key (
+    api_key TEXT NOT NULL,
+    site TEXT NOT NULL,
+    identity_key TEXT NOT NULL,
+    PRIMARY KEY (api_key, site)
+);
+
+-- Every ingested event. Unlike the ClickHouse EVENT table, which only feeds
+-- materialized views, events are stored here and queried directly. The
+-- primary key includes `ts` so that the table can become a hypertable, so
+-- the ingest server skips events whose id is already stored before inserting.
+-- This keeps a retried batch, or a synthesized visitor emitted again with a
+-- later `ts`, from being stored twice.
+CREATE TABLE IF NOT EXISTS event (
+    api_key TEXT NOT NULL,
+    site TEXT NOT NULL,
+    event_type TEXT NOT NULL CHECK (
+        event_type IN ('Visitor', 'Session', 'Section', 'Click', 'Custom', 'Identify')
+    ),
+    id UUID NOT NULL,
+    ts TIMESTAMPTZ NOT NULL,
+    attrs JSONB NOT NULL DEFAULT '{}',
+    PRIMARY KEY (api_key, site, id, ts)
+);
+
+CREATE INDEX IF NOT EXISTS event_site_ts ON event (api_key, site, ts);
@@ -0,0 +1,67 @@
+-- Schema of the embedded SQLite backend of the ingest server. The server
+-- applies it on startup, so every statement must be safe to repeat. The
+-- tables mirror those of PostgreSQL in sql/postgres/schema, with booleans
+-- stored as 0 or 1 and JSON stored as TEXT.
+
+-- Api key and site combinations that events are accepted from
+CREATE TABLE IF NOT EXISTS api_key (
+    api_key TEXT NOT NULL,
+    site TEXT NOT NULL,
+    customer TEXT NOT NULL DEFAULT '',
+    PRIMARY KEY (api_key, site)
+);
+
+-- Lowercase hex SHA-256 digests of the secrets that trusted backends use to
+-- submit events through the server-to-server API
+CREATE TABLE IF NOT EXISTS server_key (
+    api_key TEXT NOT NULL,
+    key_sha256 TEXT NOT NULL,
+    PRIMARY KEY (api_key, key_sha256)
+);
+
+-- Query parameters that are kept (Allow) or removed (Deny) from locations
+CREATE TABLE IF NOT EXISTS source_query_param (
+    api_key TEXT NOT NULL,
+    site TEXT NOT NULL,
+    param TEXT NOT NULL,
+    rule TEXT NOT NULL CHECK (rule IN ('Allow', 'Deny')),
+    PRIMARY KEY (api_key, site, param)
+);
+
+-- Path normalization of section locations. `rewrites` is a JSON array of
+-- [pattern, replacement] pairs that are applied in order.
+CREATE TABLE IF NOT EXISTS source_path_rule (
+    api_key TEXT NOT NULL,
+    site TEXT NOT NULL,
+    rewrites TEXT NOT NULL DEFAULT '[]',
+    collapse_numeric INTEGER NOT NULL DEFAULT 0,
+    collapse_uuid INTEGER NOT NULL DEFAULT 0,
+    trim_trailing_slash INTEGER NOT NULL DEFAULT 0,
+    lowercase INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (api_key, site)
+);
+
+-- Secret that the user ids of identify events are hashed with
+CREATE TABLE IF NOT EXISTS source_identity_key (
+    api_key TEXT NOT NULL,
+    site TEXT NOT NULL,
+    identity_key TEXT NOT NULL,
+    PRIMARY KEY (api_key, site)
+);
+
+-- Every ingested event. `ts` holds milliseconds since the Unix epoch, which
+-- `datetime(ts / 1000, 'unixepoch')` turns into a date, and `attrs` a JSON
+-- object. The primary key makes a retried batch insert nothing.
+CREATE TABLE IF NOT EXISTS event (
+    api_key TEXT NOT NULL,
+    site TEXT NOT NULL,
+    event_type TEXT NOT NULL CHECK (
+        event_type IN ('Visitor', 'Session', 'Section', 'Click', 'Custom', 'Identify')
+    ),
+    id TEXT NOT NULL,
+    ts INTEGER NOT NULL,
+    attrs TEXT NOT NULL DEFAULT '{}',
+    PRIMARY KEY (api_key, site, id)
+);
+
+CREATE INDEX IF NOT EXISTS event_site_ts ON event (api_key, site, ts);
@@ -0,0 +1,136 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use super::{configuration_error::ConfigurationError, dev::MemoryBackend};
+
+/// `BackendKind` names the database in which ingested events are stored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum BackendKind {
+    #[default]
+    Clickhouse,
+    Postgres,
+    Sqlite,
+}
+
+/// `BackendSettings` selects the storage backend of an app. ClickHouse is
+/// configured through the `MetricsDatabaseSettings`, PostgreSQL requires a
+/// `url` and SQLite the `path` of its database file. `timescale` only applies
+/// to PostgreSQL.
+#[derive(Debug, Clone, Default)]
+pub struct BackendSettings {
+    pub kind: Option<BackendKind>,
+    pub url: Option<String>,
+    pub timescale: Option<bool>,
+    pub path: Option<String>,
+}
+
+/// `PostgresBackend` is the connection string of a PostgreSQL database and
+/// whether the event table should be made a TimescaleDB hypertable
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PostgresBackend {
+    pub url: String,
+    pub timescale: bool,
+}
+
+/// `SqliteBackend` is the path of an embedded SQLite database file, which is
+/// created if it does not exist
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SqliteBackend {
+    pub path: PathBuf,
+}
+
+/// `MetricsBackend` is the validated storage backend of an app
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MetricsBackend {
+    Clickhouse,
+    Postgres(PostgresBackend),
+    Sqlite(SqliteBackend),
+    Memory(MemoryBackend),
+}
+
+impl TryFrom<&BackendSettings> for MetricsBackend {
+    type Error = ConfigurationError;
+    fn try_from(value: &BackendSettings) -> Result<Self, Self::Error> {
+        match value.kind.unwrap_or_default() {
+            BackendKind::Clickhouse => Ok(Self::Clickhouse),
+            BackendKind::Postgres => {
+                let Some(url) = value.url.as_ref().filter(|url| !url.trim().is_empty()) else {
+                    tracing::error!("PostgreSQL backend requires a url");
+                    return Err(ConfigurationError::Invalid);
+                };
+                Ok(Self::Postgres(PostgresBackend {
+                    url: url.to_owned(),
+                    timescale: value.timescale.unwrap_or(false),
+                }))
+            }
+            BackendKind::Sqlite => {
+                let Some(path) = value.path.as_ref().filter(|path| !path.trim().is_empty()) else {
+                    tracing::error!("SQLite backend requires a path");
+                    return Err(ConfigurationError::Invalid);
+                };
+                Ok(Self::Sqlite(SqliteBackend {
+                    path: PathBuf::from(path),
+                }))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics_backend() {
+        // Positive test cases
+        assert_eq!(
+            MetricsBackend::try_from(&BackendSettings::default()).unwrap(),
+            MetricsBackend::Clickhouse,
+            "Expected ClickHouse to be the default backend"
+        );
+        let postgres = BackendSettings {
+            kind: Some(BackendKind::Postgres),
+            url: Some("postgres://salus@localhost/salus".to_owned()),
+            timescale: Some(true),
+            ..BackendSettings::default()
+        };
+        assert_eq!(
+            MetricsBackend::try_from(&postgres).unwrap(),
+            MetricsBackend::Postgres(PostgresBackend {
+                url: "postgres://salus@localhost/salus".to_owned(),
+                timescale: true,
+            })
+        );
+        let sqlite = BackendSettings {
+            kind: Some(BackendKind::Sqlite),
+            path: Some("/var/lib/salus/salus.db".to_owned()),
+            ..BackendSettings::default()
+        };
+        assert_eq!(
+            MetricsBackend::try_from(&sqlite).unwrap(),
+            MetricsBackend::Sqlite(SqliteBackend {
+                path: PathBuf::from("/var/lib/salus/salus.db"),
+            })
+        );
+
+        // Negative test cases
+        let missing_url = BackendSettings {
+            kind: Some(BackendKind::Postgres),
+            ..BackendSettings::default()
+        };
+        assert_eq!(
+            MetricsBackend::try_from(&missing_url).unwrap_err(),
+            ConfigurationError::Invalid
+        );
+        let missing_path = BackendSettings {
+            kind: Some(BackendKind::Sqlite),
+            path: Some(" ".to_owned()),
+            ..BackendSettings::default()
+        };
+        assert_eq!(
+            MetricsBackend::try_from(&missing_path).unwrap_err(),
+            ConfigurationError::Invalid
+        );
+    }
+}
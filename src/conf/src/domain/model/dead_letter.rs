@@ -0,0 +1,122 @@
+use super::configuration_error::ConfigurationError;
+
+/// Number of rejected requests kept per site when no capacity is given
+pub const DEFAULT_DEAD_LETTER_CAPACITY: usize = 100;
+
+/// Keep every rejected request when no sample rate is given
+pub const DEFAULT_DEAD_LETTER_SAMPLE: u64 = 1;
+
+/// Number of bytes of a rejected body kept when no maximum is given
+pub const DEFAULT_DEAD_LETTER_MAX_BODY: usize = 16 * 1024;
+
+/// `DeadLetterSettings` turns on the dead-letter store of an app, in which
+/// rejected requests are kept for review. `capacity` bounds the number of
+/// requests kept per site, `sample` keeps one in every `sample` rejected
+/// requests of a site, `maxbody` bounds the number of bytes kept of each body
+/// and `adminkey` is the key that the admin API must be called with.
+#[derive(Debug, Clone, Default)]
+pub struct DeadLetterSettings {
+    pub capacity: Option<usize>,
+    pub sample: Option<u64>,
+    pub maxbody: Option<usize>,
+    pub adminkey: Option<String>,
+}
+
+/// `DeadLetterConfig` is the validated configuration of the dead-letter store
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeadLetterConfig {
+    pub capacity: usize,
+    pub sample: u64,
+    pub max_body: usize,
+    pub admin_key: String,
+}
+
+impl TryFrom<&DeadLetterSettings> for DeadLetterConfig {
+    type Error = ConfigurationError;
+    fn try_from(value: &DeadLetterSettings) -> Result<Self, Self::Error> {
+        let capacity = value.capacity.unwrap_or(DEFAULT_DEAD_LETTER_CAPACITY);
+        let sample = value.sample.unwrap_or(DEFAULT_DEAD_LETTER_SAMPLE);
+        let max_body = value.maxbody.unwrap_or(DEFAULT_DEAD_LETTER_MAX_BODY);
+        if capacity == 0 || sample == 0 || max_body == 0 {
+            tracing::error!("Dead-letter capacity, sample and maxbody must be greater than zero");
+            return Err(ConfigurationError::Invalid);
+        }
+        // Kept requests hold the bodies sent by customers, so they are only
+        // ever served with an admin key
+        let Some(admin_key) = value
+            .adminkey
+            .as_ref()
+            .filter(|admin_key| !admin_key.trim().is_empty())
+        else {
+            tracing::error!("Dead-letter store requires an admin key");
+            return Err(ConfigurationError::Invalid);
+        };
+        Ok(Self {
+            capacity,
+            sample,
+            max_body,
+            admin_key: admin_key.to_owned(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dead_letter_config() {
+        // Positive test cases
+        let settings = DeadLetterSettings {
+            adminkey: Some("secret".to_owned()),
+            ..DeadLetterSettings::default()
+        };
+        assert_eq!(
+            DeadLetterConfig::try_from(&settings).unwrap(),
+            DeadLetterConfig {
+                capacity: DEFAULT_DEAD_LETTER_CAPACITY,
+                sample: DEFAULT_DEAD_LETTER_SAMPLE,
+                max_body: DEFAULT_DEAD_LETTER_MAX_BODY,
+                admin_key: "secret".to_owned(),
+            }
+        );
+        let settings = DeadLetterSettings {
+            capacity: Some(10),
+            sample: Some(5),
+            maxbody: Some(1024),
+            adminkey: Some("secret".to_owned()),
+        };
+        assert_eq!(
+            DeadLetterConfig::try_from(&settings).unwrap(),
+            DeadLetterConfig {
+                capacity: 10,
+                sample: 5,
+                max_body: 1024,
+                admin_key: "secret".to_owned(),
+            }
+        );
+
+        // Negative test cases
+        assert_eq!(
+            DeadLetterConfig::try_from(&DeadLetterSettings::default()).unwrap_err(),
+            ConfigurationError::Invalid,
+            "Expected an admin key to be required"
+        );
+        let blank_admin_key = DeadLetterSettings {
+            adminkey: Some("  ".to_owned()),
+            ..DeadLetterSettings::default()
+        };
+        assert_eq!(
+            DeadLetterConfig::try_from(&blank_admin_key).unwrap_err(),
+            ConfigurationError::Invalid
+        );
+        let zero_sample = DeadLetterSettings {
+            sample: Some(0),
+            ..settings
+        };
+        assert_eq!(
+            DeadLetterConfig::try_from(&zero_sample).unwrap_err(),
+            ConfigurationError::Invalid
+        );
+    }
+}
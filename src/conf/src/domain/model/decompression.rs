@@ -0,0 +1,90 @@
+use tower_http::decompression::RequestDecompressionLayer;
+
+use super::configuration_error::ConfigurationError;
+
+/// Default maximum number of bytes that a request body may expand to once
+/// decompressed
+pub const DEFAULT_DECOMPRESSION_LIMIT: usize = 8 * 1024 * 1024;
+
+/// `DecompressionSettings` allows the setup of `tower-http`
+/// `RequestDecompressionLayer` so that clients can send encoded request
+/// bodies. `gzip`, `deflate`, `br` and `zstd` are booleans that control
+/// those encodings respectively and all default to true. Bodies with any other
+/// encoding are rejected with 415 Unsupported Media Type. `limit` is the
+/// maximum number of bytes that a body may expand to, which guards against
+/// small payloads that decompress to an enormous size.
+#[derive(Debug, Clone)]
+pub struct DecompressionSettings {
+    pub gzip: Option<bool>,
+    pub deflate: Option<bool>,
+    pub br: Option<bool>,
+    pub zstd: Option<bool>,
+    pub limit: Option<usize>,
+}
+
+impl DecompressionSettings {
+    /// Attempt to determine the maximum decompressed size of a request body
+    pub fn try_limit(&self) -> Result<usize, ConfigurationError> {
+        match self.limit {
+            Some(0) => {
+                tracing::error!("Decompression limit must be greater than zero bytes");
+                Err(ConfigurationError::Invalid)
+            }
+            Some(limit) => Ok(limit),
+            None => Ok(DEFAULT_DECOMPRESSION_LIMIT),
+        }
+    }
+}
+
+impl From<&DecompressionSettings> for RequestDecompressionLayer {
+    fn from(value: &DecompressionSettings) -> Self {
+        RequestDecompressionLayer::new()
+            .gzip(value.gzip.unwrap_or(true))
+            .deflate(value.deflate.unwrap_or(true))
+            .br(value.br.unwrap_or(true))
+            .zstd(value.zstd.unwrap_or(true))
+    }
+}
+
+impl Default for DecompressionSettings {
+    fn default() -> Self {
+        Self {
+            gzip: Some(true),
+            deflate: Some(true),
+            br: Some(true),
+            zstd: Some(true),
+            limit: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_http::decompression::RequestDecompressionLayer;
+
+    use super::*;
+
+    #[test]
+    fn test_decompression_settings() {
+        // Positive test cases
+        let test_settings = DecompressionSettings::default();
+        let _ = RequestDecompressionLayer::from(&test_settings);
+        assert_eq!(test_settings.try_limit(), Ok(DEFAULT_DECOMPRESSION_LIMIT));
+
+        let limited_settings = DecompressionSettings {
+            limit: Some(1024),
+            ..DecompressionSettings::default()
+        };
+        assert_eq!(limited_settings.try_limit(), Ok(1024));
+
+        // Negative test case
+        let invalid_settings = DecompressionSettings {
+            limit: Some(0),
+            ..DecompressionSettings::default()
+        };
+        assert_eq!(
+            invalid_settings.try_limit().unwrap_err(),
+            ConfigurationError::Invalid
+        );
+    }
+}
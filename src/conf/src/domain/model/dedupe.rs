@@ -0,0 +1,55 @@
+use std::time::Duration;
+
+use super::configuration_error::ConfigurationError;
+
+/// `DedupeSettings` determines for how long an app remembers the ids of saved
+/// events in order to skip events that are submitted again, such as when a
+/// client retries a request. When not specified, the app uses its own default.
+#[derive(Debug, Clone)]
+pub struct DedupeSettings {
+    pub secs: u64,
+}
+
+impl DedupeSettings {
+    /// `DedupeSettings` constructor
+    pub fn new(secs: u64) -> Self {
+        Self { secs }
+    }
+}
+
+impl TryFrom<&DedupeSettings> for Duration {
+    type Error = ConfigurationError;
+    fn try_from(value: &DedupeSettings) -> Result<Self, Self::Error> {
+        if value.secs == 0 {
+            tracing::error!("Dedupe horizon must be greater than zero seconds");
+            return Err(ConfigurationError::Invalid);
+        }
+        Ok(Duration::from_secs(value.secs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::domain::model::configuration_error::ConfigurationError;
+
+    use super::DedupeSettings;
+
+    #[test]
+    fn test_dedupe_settings() {
+        // Positive test case
+        let valid_settings = DedupeSettings { secs: 3600 };
+        assert_eq!(
+            Duration::try_from(&valid_settings).unwrap(),
+            Duration::from_secs(3600)
+        );
+
+        // Negative test case
+        let invalid_settings = DedupeSettings { secs: 0 };
+        assert_eq!(
+            Duration::try_from(&invalid_settings).unwrap_err(),
+            ConfigurationError::Invalid
+        );
+    }
+}
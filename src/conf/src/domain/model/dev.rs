@@ -0,0 +1,135 @@
+use super::configuration_error::ConfigurationError;
+
+/// Number of events kept by the in-memory backend when no capacity is given
+pub const DEFAULT_DEV_CAPACITY: usize = 10_000;
+
+/// `DevSettings` turns on the dev mode of an app, in which events are kept in
+/// memory instead of being stored in a database. `capacity` bounds the number
+/// of events that are kept, `sources` is a whitespace separated list of
+/// `api_key:site` pairs that events are accepted from and `serverkey` is a
+/// server key that is accepted for every api key. Without `sources` events
+/// are accepted from any source.
+#[derive(Debug, Clone, Default)]
+pub struct DevSettings {
+    pub mode: Option<bool>,
+    pub capacity: Option<usize>,
+    pub sources: Option<String>,
+    pub serverkey: Option<String>,
+}
+
+/// `DevSource` is an api key and site combination accepted in dev mode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DevSource {
+    pub api_key: String,
+    pub site: String,
+}
+
+/// `MemoryBackend` is the validated in-memory storage backend of dev mode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryBackend {
+    pub capacity: usize,
+    pub sources: Vec<DevSource>,
+    pub server_key: Option<String>,
+}
+
+impl DevSettings {
+    /// Whether dev mode has been turned on
+    pub fn is_enabled(&self) -> bool {
+        self.mode.unwrap_or(false)
+    }
+}
+
+impl TryFrom<&DevSettings> for MemoryBackend {
+    type Error = ConfigurationError;
+    fn try_from(value: &DevSettings) -> Result<Self, Self::Error> {
+        let capacity = value.capacity.unwrap_or(DEFAULT_DEV_CAPACITY);
+        if capacity == 0 {
+            tracing::error!("Dev mode capacity must be greater than zero");
+            return Err(ConfigurationError::Invalid);
+        }
+        let sources = value
+            .sources
+            .as_deref()
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(|source| match source.split_once(':') {
+                Some((api_key, site)) if !api_key.is_empty() && !site.is_empty() => Ok(DevSource {
+                    api_key: api_key.to_owned(),
+                    site: site.to_owned(),
+                }),
+                _ => {
+                    tracing::error!("Dev mode source {source} must be given as api_key:site");
+                    Err(ConfigurationError::Invalid)
+                }
+            })
+            .collect::<Result<Vec<DevSource>, ConfigurationError>>()?;
+        Ok(Self {
+            capacity,
+            sources,
+            server_key: value
+                .serverkey
+                .as_ref()
+                .filter(|server_key| !server_key.trim().is_empty())
+                .cloned(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_backend() {
+        // Positive test cases
+        assert_eq!(
+            MemoryBackend::try_from(&DevSettings::default()).unwrap(),
+            MemoryBackend {
+                capacity: DEFAULT_DEV_CAPACITY,
+                sources: Vec::new(),
+                server_key: None,
+            }
+        );
+        let settings = DevSettings {
+            mode: Some(true),
+            capacity: Some(100),
+            sources: Some("abc-123:localhost def-456:www.example.com".to_owned()),
+            serverkey: Some("secret".to_owned()),
+        };
+        assert_eq!(
+            MemoryBackend::try_from(&settings).unwrap(),
+            MemoryBackend {
+                capacity: 100,
+                sources: vec![
+                    DevSource {
+                        api_key: "abc-123".to_owned(),
+                        site: "localhost".to_owned(),
+                    },
+                    DevSource {
+                        api_key: "def-456".to_owned(),
+                        site: "www.example.com".to_owned(),
+                    },
+                ],
+                server_key: Some("secret".to_owned()),
+            }
+        );
+
+        // Negative test cases
+        let zero_capacity = DevSettings {
+            capacity: Some(0),
+            ..DevSettings::default()
+        };
+        assert_eq!(
+            MemoryBackend::try_from(&zero_capacity).unwrap_err(),
+            ConfigurationError::Invalid
+        );
+        let missing_site = DevSettings {
+            sources: Some("abc-123".to_owned()),
+            ..DevSettings::default()
+        };
+        assert_eq!(
+            MemoryBackend::try_from(&missing_site).unwrap_err(),
+            ConfigurationError::Invalid
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use tower_http::limit::RequestBodyLimitLayer;
+
+use super::configuration_error::ConfigurationError;
+
+/// Default maximum number of bytes in a request body as it is received
+pub const DEFAULT_BODY_LIMIT: usize = 1024 * 1024;
+
+/// Default maximum number of events in a single request
+pub const DEFAULT_EVENTS_LIMIT: usize = 500;
+
+/// Default maximum number of attrs of a single event
+pub const DEFAULT_ATTRS_LIMIT: usize = 64;
+
+/// Default maximum number of characters in the title of an event
+pub const DEFAULT_TITLE_LIMIT: usize = 1024;
+
+/// Default maximum number of characters in the location of an event
+pub const DEFAULT_LOCATION_LIMIT: usize = 2048;
+
+/// `LimitSettings` bounds the size of incoming requests. `body` is the maximum
+/// number of bytes of a request body as it is received, before any
+/// decompression. `events` is the maximum number of events in a single
+/// request, `attrs` the maximum number of attrs of each event and `title` and
+/// `location` the maximum number of characters of those attrs. Every limit has
+/// a default and must be greater than zero when specified.
+#[derive(Debug, Clone, Default)]
+pub struct LimitSettings {
+    pub body: Option<usize>,
+    pub events: Option<usize>,
+    pub attrs: Option<usize>,
+    pub title: Option<usize>,
+    pub location: Option<usize>,
+}
+
+/// `BatchLimits` are the validated limits on the events within a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchLimits {
+    pub events: usize,
+    pub attrs: usize,
+    pub title: usize,
+    pub location: usize,
+}
+
+impl Default for BatchLimits {
+    fn default() -> Self {
+        Self {
+            events: DEFAULT_EVENTS_LIMIT,
+            attrs: DEFAULT_ATTRS_LIMIT,
+            title: DEFAULT_TITLE_LIMIT,
+            location: DEFAULT_LOCATION_LIMIT,
+        }
+    }
+}
+
+/// Use the given limit or its default, rejecting a limit of zero
+fn try_limit(
+    name: &str,
+    limit: Option<usize>,
+    default: usize,
+) -> Result<usize, ConfigurationError> {
+    match limit {
+        Some(0) => {
+            tracing::error!("{name} limit must be greater than zero");
+            Err(ConfigurationError::Invalid)
+        }
+        Some(limit) => Ok(limit),
+        None => Ok(default),
+    }
+}
+
+impl TryFrom<&LimitSettings> for RequestBodyLimitLayer {
+    type Error = ConfigurationError;
+    fn try_from(value: &LimitSettings) -> Result<Self, Self::Error> {
+        Ok(RequestBodyLimitLayer::new(try_limit(
+            "Body",
+            value.body,
+            DEFAULT_BODY_LIMIT,
+        )?))
+    }
+}
+
+impl TryFrom<&LimitSettings> for BatchLimits {
+    type Error = ConfigurationError;
+    fn try_from(value: &LimitSettings) -> Result<Self, Self::Error> {
+        Ok(Self {
+            events: try_limit("Events", value.events, DEFAULT_EVENTS_LIMIT)?,
+            attrs: try_limit("Attrs", value.attrs, DEFAULT_ATTRS_LIMIT)?,
+            title: try_limit("Title", value.title, DEFAULT_TITLE_LIMIT)?,
+            location: try_limit("Location", value.location, DEFAULT_LOCATION_LIMIT)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_http::limit::RequestBodyLimitLayer;
+
+    use super::*;
+
+    #[test]
+    fn test_limit_settings() {
+        // Positive test cases
+        let default_settings = LimitSettings::default();
+        assert!(RequestBodyLimitLayer::try_from(&default_settings).is_ok());
+        assert_eq!(
+            BatchLimits::try_from(&default_settings).unwrap(),
+            BatchLimits::default()
+        );
+
+        let test_settings = LimitSettings {
+            events: Some(10),
+            title: Some(100),
+            ..LimitSettings::default()
+        };
+        assert_eq!(
+            BatchLimits::try_from(&test_settings).unwrap(),
+            BatchLimits {
+                events: 10,
+                title: 100,
+                ..BatchLimits::default()
+            }
+        );
+
+        // Negative test cases
+        let invalid_settings = LimitSettings {
+            body: Some(0),
+            attrs: Some(0),
+            ..LimitSettings::default()
+        };
+        assert_eq!(
+            RequestBodyLimitLayer::try_from(&invalid_settings).unwrap_err(),
+            ConfigurationError::Invalid
+        );
+        assert_eq!(
+            BatchLimits::try_from(&invalid_settings).unwrap_err(),
+            ConfigurationError::Invalid
+        );
+    }
+}
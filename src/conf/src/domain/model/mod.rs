@@ -1,8 +1,18 @@
+pub mod backend;
 pub mod compression;
 pub mod configuration_error;
 pub mod cors;
+pub mod dead_letter;
+pub mod decompression;
+pub mod dedupe;
+pub mod dev;
 pub mod ip_source;
+pub mod limit;
 pub mod listener;
 pub mod metrics_db;
+pub mod reload;
+pub mod scrub;
+pub mod sink;
+pub mod synthetic;
 pub mod timeout;
 pub mod tracing;
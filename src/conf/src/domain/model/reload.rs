@@ -0,0 +1,55 @@
+use std::time::Duration;
+
+use super::configuration_error::ConfigurationError;
+
+/// `ReloadSettings` determines how often an app refreshes data that it caches
+/// from its backing store, such as the allowed event sources and their rules
+/// for the ingest app. When not specified, the data is only loaded at startup.
+#[derive(Debug, Clone)]
+pub struct ReloadSettings {
+    pub secs: u64,
+}
+
+impl ReloadSettings {
+    /// `ReloadSettings` constructor
+    pub fn new(secs: u64) -> Self {
+        Self { secs }
+    }
+}
+
+impl TryFrom<&ReloadSettings> for Duration {
+    type Error = ConfigurationError;
+    fn try_from(value: &ReloadSettings) -> Result<Self, Self::Error> {
+        if value.secs == 0 {
+            tracing::error!("Reload interval must be greater than zero seconds");
+            return Err(ConfigurationError::Invalid);
+        }
+        Ok(Duration::from_secs(value.secs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::domain::model::configuration_error::ConfigurationError;
+
+    use super::ReloadSettings;
+
+    #[test]
+    fn test_reload_settings() {
+        // Positive test case
+        let valid_settings = ReloadSettings { secs: 60 };
+        assert_eq!(
+            Duration::try_from(&valid_settings).unwrap(),
+            Duration::from_secs(60)
+        );
+
+        // Negative test case
+        let invalid_settings = ReloadSettings { secs: 0 };
+        assert_eq!(
+            Duration::try_from(&invalid_settings).unwrap_err(),
+            ConfigurationError::Invalid
+        );
+    }
+}
@@ -0,0 +1,63 @@
+use regex::Regex;
+use tracing::instrument;
+
+use super::configuration_error::ConfigurationError;
+
+/// `ScrubSettings` represents the list of regex patterns that should be
+/// redacted from free text fields such as locations and titles before any
+/// event is persisted. When specified, these patterns replace the built-in
+/// defaults of the ingest app entirely rather than extending them.
+#[derive(Debug, Clone)]
+pub struct ScrubSettings {
+    pub patterns: Vec<String>,
+}
+
+impl ScrubSettings {
+    /// `ScrubSettings` constructor
+    pub fn new(patterns: Vec<String>) -> Self {
+        Self { patterns }
+    }
+}
+
+impl TryFrom<&ScrubSettings> for Vec<Regex> {
+    type Error = ConfigurationError;
+    #[instrument]
+    fn try_from(value: &ScrubSettings) -> Result<Self, Self::Error> {
+        let mut patterns: Vec<Regex> = Vec::with_capacity(value.patterns.len());
+        for pattern in value.patterns.iter() {
+            patterns.push(Regex::new(pattern).map_err(|e| {
+                tracing::error!("Error parsing scrub pattern: {e}");
+                ConfigurationError::Parse
+            })?);
+        }
+        Ok(patterns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+
+    use crate::domain::model::configuration_error::ConfigurationError;
+
+    use super::ScrubSettings;
+
+    #[test]
+    fn test_scrub_settings() {
+        // Positive test case
+        let valid_settings = ScrubSettings {
+            patterns: vec!["secret-[0-9]+".to_owned(), r"token=\w+".to_owned()],
+        };
+        let patterns = Vec::<Regex>::try_from(&valid_settings).unwrap();
+        assert_eq!(patterns.len(), 2, "Expected both patterns to be compiled");
+
+        // Negative test case
+        let invalid_settings = ScrubSettings {
+            patterns: vec!["secret-[0-9".to_owned()],
+        };
+        assert_eq!(
+            Vec::<Regex>::try_from(&invalid_settings).unwrap_err(),
+            ConfigurationError::Parse
+        );
+    }
+}
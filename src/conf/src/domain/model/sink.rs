@@ -0,0 +1,343 @@
+use std::{path::PathBuf, time::Duration};
+
+use http::Uri;
+use serde::{Deserialize, Serialize};
+
+use super::configuration_error::ConfigurationError;
+
+/// `SinkPolicy` determines how a failure of a sink affects the saving of
+/// events. `Required` sinks must accept every batch before it is reported as
+/// saved, `BestEffort` sinks are written before responding but their failures
+/// are only logged and `Async` sinks are written in the background.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum SinkPolicy {
+    Required,
+    BestEffort,
+    Async,
+}
+
+/// Size in bytes at which a rotating archive file is closed when no maximum
+/// is given
+pub const DEFAULT_ARCHIVE_MAX_BYTES: u64 = 128 * 1024 * 1024;
+
+/// Age in seconds at which a rotating archive file is closed when no maximum
+/// is given
+pub const DEFAULT_ARCHIVE_MAX_SECS: u64 = 3600;
+
+/// `ArchiveFormat` is the format of the files written by a rotating archive
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ArchiveFormat {
+    #[default]
+    Jsonl,
+    Parquet,
+}
+
+/// `RotatingArchive` is a directory of archive files that are partitioned by
+/// site and date. Each file is closed once it holds `max_bytes` or has been
+/// open for `max_age`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RotatingArchive {
+    pub dir: PathBuf,
+    pub format: ArchiveFormat,
+    pub max_bytes: u64,
+    pub max_age: Duration,
+}
+
+/// `SinkTarget` is the destination to which a sink writes events
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SinkTarget {
+    /// Local file to which events are appended as JSON Lines
+    Archive(PathBuf),
+    /// Local directory of archive files that are rotated by size and age
+    RotatingArchive(RotatingArchive),
+    /// `http` URL to which each batch of events is POSTed as JSON
+    Webhook(Uri),
+}
+
+/// `SinkConfig` is a validated sink together with its `SinkPolicy`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SinkConfig {
+    pub target: SinkTarget,
+    pub policy: SinkPolicy,
+}
+
+/// `ArchiveSinkSettings` configures a sink that either appends events to the
+/// single file at `path` or writes them to rotated files within `dir`. Only
+/// the rotated files accept a `format` and are closed after `maxbytes` bytes
+/// or `maxsecs` seconds. The policy defaults to `SinkPolicy::BestEffort`.
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveSinkSettings {
+    pub path: Option<String>,
+    pub dir: Option<String>,
+    pub format: Option<ArchiveFormat>,
+    pub maxbytes: Option<u64>,
+    pub maxsecs: Option<u64>,
+    pub policy: Option<SinkPolicy>,
+}
+
+/// `WebhookSinkSettings` configures a sink that POSTs events to `url`, which
+/// must be an `https` URL unless it points at the local host. The policy
+/// defaults to `SinkPolicy::Async`, so that a slow endpoint does not delay
+/// responses.
+#[derive(Debug, Clone)]
+pub struct WebhookSinkSettings {
+    pub url: String,
+    pub policy: Option<SinkPolicy>,
+}
+
+/// `SinkSettings` lists the destinations to which saved events are mirrored
+/// in addition to the metrics database
+#[derive(Debug, Clone, Default)]
+pub struct SinkSettings {
+    pub archive: Option<ArchiveSinkSettings>,
+    pub webhook: Option<WebhookSinkSettings>,
+}
+
+impl TryFrom<&ArchiveSinkSettings> for SinkConfig {
+    type Error = ConfigurationError;
+    fn try_from(value: &ArchiveSinkSettings) -> Result<Self, Self::Error> {
+        let target = match (&value.path, &value.dir) {
+            (Some(path), None) => {
+                if path.trim().is_empty() {
+                    tracing::error!("Archive sink path must not be empty");
+                    return Err(ConfigurationError::Invalid);
+                }
+                if value.format.is_some() || value.maxbytes.is_some() || value.maxsecs.is_some() {
+                    tracing::error!(
+                        "Archive sink rotation settings require a dir instead of a path"
+                    );
+                    return Err(ConfigurationError::Invalid);
+                }
+                SinkTarget::Archive(PathBuf::from(path))
+            }
+            (None, Some(dir)) => {
+                if dir.trim().is_empty() {
+                    tracing::error!("Archive sink dir must not be empty");
+                    return Err(ConfigurationError::Invalid);
+                }
+                let max_bytes = value.maxbytes.unwrap_or(DEFAULT_ARCHIVE_MAX_BYTES);
+                let max_secs = value.maxsecs.unwrap_or(DEFAULT_ARCHIVE_MAX_SECS);
+                if max_bytes == 0 || max_secs == 0 {
+                    tracing::error!("Archive sink maxbytes and maxsecs must be greater than zero");
+                    return Err(ConfigurationError::Invalid);
+                }
+                SinkTarget::RotatingArchive(RotatingArchive {
+                    dir: PathBuf::from(dir),
+                    format: value.format.unwrap_or_default(),
+                    max_bytes,
+                    max_age: Duration::from_secs(max_secs),
+                })
+            }
+            _ => {
+                tracing::error!("Archive sink requires exactly one of path or dir");
+                return Err(ConfigurationError::Invalid);
+            }
+        };
+        Ok(Self {
+            target,
+            policy: value.policy.unwrap_or(SinkPolicy::BestEffort),
+        })
+    }
+}
+
+/// Hosts to which a webhook sink may POST over plain `http`
+const LOCAL_HOSTS: [&str; 3] = ["localhost", "127.0.0.1", "[::1]"];
+
+impl TryFrom<&WebhookSinkSettings> for SinkConfig {
+    type Error = ConfigurationError;
+    fn try_from(value: &WebhookSinkSettings) -> Result<Self, Self::Error> {
+        let url = value.url.parse::<Uri>().map_err(|e| {
+            tracing::error!("Error parsing webhook sink url: {e}");
+            ConfigurationError::Parse
+        })?;
+        let Some(host) = url.host() else {
+            tracing::error!("Webhook sink url must have a host");
+            return Err(ConfigurationError::Invalid);
+        };
+        match url.scheme_str() {
+            Some("https") => {}
+            Some("http") if LOCAL_HOSTS.contains(&host) => {}
+            _ => {
+                tracing::error!(
+                    "Webhook sink url must be an https url, or http for the local host"
+                );
+                return Err(ConfigurationError::Invalid);
+            }
+        }
+        Ok(Self {
+            target: SinkTarget::Webhook(url),
+            policy: value.policy.unwrap_or(SinkPolicy::Async),
+        })
+    }
+}
+
+impl TryFrom<&SinkSettings> for Vec<SinkConfig> {
+    type Error = ConfigurationError;
+    fn try_from(value: &SinkSettings) -> Result<Self, Self::Error> {
+        let mut sinks = Vec::new();
+        if let Some(archive) = &value.archive {
+            sinks.push(archive.try_into()?);
+        }
+        if let Some(webhook) = &value.webhook {
+            sinks.push(webhook.try_into()?);
+        }
+        Ok(sinks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sink_settings() {
+        // Positive test cases
+        assert!(
+            Vec::<SinkConfig>::try_from(&SinkSettings::default())
+                .unwrap()
+                .is_empty()
+        );
+        let valid_settings = SinkSettings {
+            archive: Some(ArchiveSinkSettings {
+                path: Some("/var/lib/salus/events.jsonl".to_owned()),
+                ..ArchiveSinkSettings::default()
+            }),
+            webhook: Some(WebhookSinkSettings {
+                url: "http://localhost:9000/events".to_owned(),
+                policy: Some(SinkPolicy::Required),
+            }),
+        };
+        assert_eq!(
+            Vec::<SinkConfig>::try_from(&valid_settings).unwrap(),
+            vec![
+                SinkConfig {
+                    target: SinkTarget::Archive(PathBuf::from("/var/lib/salus/events.jsonl")),
+                    policy: SinkPolicy::BestEffort,
+                },
+                SinkConfig {
+                    target: SinkTarget::Webhook(Uri::from_static("http://localhost:9000/events")),
+                    policy: SinkPolicy::Required,
+                },
+            ]
+        );
+        let https_settings = SinkSettings {
+            webhook: Some(WebhookSinkSettings {
+                url: "https://example.com/events".to_owned(),
+                policy: None,
+            }),
+            ..SinkSettings::default()
+        };
+        assert_eq!(
+            Vec::<SinkConfig>::try_from(&https_settings).unwrap(),
+            vec![SinkConfig {
+                target: SinkTarget::Webhook(Uri::from_static("https://example.com/events")),
+                policy: SinkPolicy::Async,
+            }]
+        );
+        let rotating_settings = SinkSettings {
+            archive: Some(ArchiveSinkSettings {
+                dir: Some("/var/lib/salus/archive".to_owned()),
+                format: Some(ArchiveFormat::Parquet),
+                maxsecs: Some(600),
+                ..ArchiveSinkSettings::default()
+            }),
+            ..SinkSettings::default()
+        };
+        assert_eq!(
+            Vec::<SinkConfig>::try_from(&rotating_settings).unwrap(),
+            vec![SinkConfig {
+                target: SinkTarget::RotatingArchive(RotatingArchive {
+                    dir: PathBuf::from("/var/lib/salus/archive"),
+                    format: ArchiveFormat::Parquet,
+                    max_bytes: DEFAULT_ARCHIVE_MAX_BYTES,
+                    max_age: Duration::from_secs(600),
+                }),
+                policy: SinkPolicy::BestEffort,
+            }]
+        );
+
+        // Negative test cases
+        let empty_path = SinkSettings {
+            archive: Some(ArchiveSinkSettings {
+                path: Some(" ".to_owned()),
+                ..ArchiveSinkSettings::default()
+            }),
+            ..SinkSettings::default()
+        };
+        assert_eq!(
+            Vec::<SinkConfig>::try_from(&empty_path).unwrap_err(),
+            ConfigurationError::Invalid
+        );
+        let path_and_dir = SinkSettings {
+            archive: Some(ArchiveSinkSettings {
+                path: Some("/var/lib/salus/events.jsonl".to_owned()),
+                dir: Some("/var/lib/salus/archive".to_owned()),
+                ..ArchiveSinkSettings::default()
+            }),
+            ..SinkSettings::default()
+        };
+        assert_eq!(
+            Vec::<SinkConfig>::try_from(&path_and_dir).unwrap_err(),
+            ConfigurationError::Invalid
+        );
+        let rotated_path = SinkSettings {
+            archive: Some(ArchiveSinkSettings {
+                path: Some("/var/lib/salus/events.jsonl".to_owned()),
+                maxbytes: Some(1024),
+                ..ArchiveSinkSettings::default()
+            }),
+            ..SinkSettings::default()
+        };
+        assert_eq!(
+            Vec::<SinkConfig>::try_from(&rotated_path).unwrap_err(),
+            ConfigurationError::Invalid,
+            "Expected rotation settings to be refused for a single file"
+        );
+        let zero_max_secs = SinkSettings {
+            archive: Some(ArchiveSinkSettings {
+                dir: Some("/var/lib/salus/archive".to_owned()),
+                maxsecs: Some(0),
+                ..ArchiveSinkSettings::default()
+            }),
+            ..SinkSettings::default()
+        };
+        assert_eq!(
+            Vec::<SinkConfig>::try_from(&zero_max_secs).unwrap_err(),
+            ConfigurationError::Invalid
+        );
+        let remote_http_url = SinkSettings {
+            webhook: Some(WebhookSinkSettings {
+                url: "http://example.com/events".to_owned(),
+                policy: None,
+            }),
+            ..SinkSettings::default()
+        };
+        assert_eq!(
+            Vec::<SinkConfig>::try_from(&remote_http_url).unwrap_err(),
+            ConfigurationError::Invalid,
+            "Expected plain http to be refused for a remote host"
+        );
+        let ftp_url = SinkSettings {
+            webhook: Some(WebhookSinkSettings {
+                url: "ftp://localhost/events".to_owned(),
+                policy: None,
+            }),
+            ..SinkSettings::default()
+        };
+        assert_eq!(
+            Vec::<SinkConfig>::try_from(&ftp_url).unwrap_err(),
+            ConfigurationError::Invalid
+        );
+        let invalid_url = SinkSettings {
+            webhook: Some(WebhookSinkSettings {
+                url: "http://exa mple.com".to_owned(),
+                policy: None,
+            }),
+            ..SinkSettings::default()
+        };
+        assert_eq!(
+            Vec::<SinkConfig>::try_from(&invalid_url).unwrap_err(),
+            ConfigurationError::Parse
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use super::configuration_error::ConfigurationError;
+
+/// `SyntheticSettings` holds the secret with which an app derives the ids of
+/// the visitors it creates for clients that do not track them, such as the
+/// compatibility APIs for other analytics tools. The same client is given the
+/// same visitor id by every instance that shares the secret, and across
+/// restarts. When not specified, ids are derived without a secret.
+#[derive(Debug, Clone)]
+pub struct SyntheticSettings {
+    pub visitorkey: String,
+}
+
+impl SyntheticSettings {
+    /// `SyntheticSettings` constructor
+    pub fn new(visitorkey: impl Into<String>) -> Self {
+        Self {
+            visitorkey: visitorkey.into(),
+        }
+    }
+
+    /// Attempts to return the secret for visitor ids, which must not be blank
+    pub fn try_visitor_key(&self) -> Result<String, ConfigurationError> {
+        let visitor_key = self.visitorkey.trim();
+        if visitor_key.is_empty() {
+            tracing::error!("Synthetic visitor key must not be blank");
+            return Err(ConfigurationError::Invalid);
+        }
+        Ok(visitor_key.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::model::configuration_error::ConfigurationError;
+
+    use super::SyntheticSettings;
+
+    #[test]
+    fn test_synthetic_settings() {
+        // Positive test case
+        let valid_settings = SyntheticSettings::new(" secret ");
+        assert_eq!(valid_settings.try_visitor_key().unwrap(), "secret");
+
+        // Negative test case
+        let invalid_settings = SyntheticSettings::new("  ");
+        assert_eq!(
+            invalid_settings.try_visitor_key().unwrap_err(),
+            ConfigurationError::Invalid
+        );
+    }
+}
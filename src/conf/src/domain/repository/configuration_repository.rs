@@ -1,9 +1,13 @@
 use thiserror::Error;
 
 use crate::domain::model::{
-    compression::CompressionSettings, configuration_error::ConfigurationError, cors::CorsSettings,
-    ip_source::IpSourceSettings, listener::ListenerSettings, metrics_db::MetricsDatabaseSettings,
-    timeout::TimeoutSettings, tracing::TracingSettings,
+    backend::BackendSettings, compression::CompressionSettings,
+    configuration_error::ConfigurationError, cors::CorsSettings, dead_letter::DeadLetterSettings,
+    decompression::DecompressionSettings, dedupe::DedupeSettings, dev::DevSettings,
+    ip_source::IpSourceSettings, limit::LimitSettings, listener::ListenerSettings,
+    metrics_db::MetricsDatabaseSettings, reload::ReloadSettings, scrub::ScrubSettings,
+    sink::SinkSettings, synthetic::SyntheticSettings, timeout::TimeoutSettings,
+    tracing::TracingSettings,
 };
 
 /// `ConfigurationRepositoryError` represents the domain errors that can arise
@@ -28,6 +32,9 @@ pub enum ConfigurationRepositoryError {
 /// available for all structs that will provide access to the underlying
 /// configuration settings.
 pub trait ConfigurationRepository: 'static + Clone + Send + Sync {
+    /// `try_backend_settings` attempts to fetch `BackendSettings`
+    fn try_backend_settings(&self) -> Result<BackendSettings, ConfigurationRepositoryError>;
+
     /// `try_compression_settings` attempts fetch `CompressionSettings`
     fn try_compression_settings(&self)
     -> Result<CompressionSettings, ConfigurationRepositoryError>;
@@ -35,9 +42,26 @@ pub trait ConfigurationRepository: 'static + Clone + Send + Sync {
     /// `try_cors_settings` attempts to fetch `CorsSettings`
     fn try_cors_settings(&self) -> Result<CorsSettings, ConfigurationRepositoryError>;
 
+    /// `try_dead_letter_settings` attempts to fetch `DeadLetterSettings`
+    fn try_dead_letter_settings(&self) -> Result<DeadLetterSettings, ConfigurationRepositoryError>;
+
+    /// `try_decompression_settings` attempts to fetch `DecompressionSettings`
+    fn try_decompression_settings(
+        &self,
+    ) -> Result<DecompressionSettings, ConfigurationRepositoryError>;
+
+    /// `try_dedupe_settings` attempts to fetch `DedupeSettings`
+    fn try_dedupe_settings(&self) -> Result<DedupeSettings, ConfigurationRepositoryError>;
+
+    /// `try_dev_settings` attempts to fetch `DevSettings`
+    fn try_dev_settings(&self) -> Result<DevSettings, ConfigurationRepositoryError>;
+
     /// `try_ip_source_settings` attempts to fetch `IpSourceSettings`
     fn try_ip_source_settings(&self) -> Result<IpSourceSettings, ConfigurationRepositoryError>;
 
+    /// `try_limit_settings` attempts to fetch `LimitSettings`
+    fn try_limit_settings(&self) -> Result<LimitSettings, ConfigurationRepositoryError>;
+
     /// `try_timeout_settings` attempts to fetch `TimeoutSettings`
     fn try_timeout_settings(&self) -> Result<TimeoutSettings, ConfigurationRepositoryError>;
 
@@ -49,6 +73,18 @@ pub trait ConfigurationRepository: 'static + Clone + Send + Sync {
     /// `try_listener_settings` attempts to fetch `ListenerSettings`
     fn try_listener_settings(&self) -> Result<ListenerSettings, ConfigurationRepositoryError>;
 
+    /// `try_reload_settings` attempts to fetch `ReloadSettings`
+    fn try_reload_settings(&self) -> Result<ReloadSettings, ConfigurationRepositoryError>;
+
+    /// `try_scrub_settings` attempts to fetch `ScrubSettings`
+    fn try_scrub_settings(&self) -> Result<ScrubSettings, ConfigurationRepositoryError>;
+
+    /// `try_sink_settings` attempts to fetch `SinkSettings`
+    fn try_sink_settings(&self) -> Result<SinkSettings, ConfigurationRepositoryError>;
+
+    /// `try_synthetic_settings` attempts to fetch `SyntheticSettings`
+    fn try_synthetic_settings(&self) -> Result<SyntheticSettings, ConfigurationRepositoryError>;
+
     /// `try_tracing_settings` attempts to fetch `TracingSettings`
     fn try_tracing_settings(&self) -> Result<TracingSettings, ConfigurationRepositoryError>;
 }
@@ -61,16 +97,33 @@ pub(crate) mod tests {
 
     #[derive(Clone, Default, Debug)]
     pub(crate) struct MockConfigurationRepository {
+        backend_result: Option<Result<BackendSettings, ConfigurationRepositoryError>>,
         compression_result: Option<Result<CompressionSettings, ConfigurationRepositoryError>>,
         cors_result: Option<Result<CorsSettings, ConfigurationRepositoryError>>,
+        dead_letter_result: Option<Result<DeadLetterSettings, ConfigurationRepositoryError>>,
+        decompression_result: Option<Result<DecompressionSettings, ConfigurationRepositoryError>>,
+        dedupe_result: Option<Result<DedupeSettings, ConfigurationRepositoryError>>,
+        dev_result: Option<Result<DevSettings, ConfigurationRepositoryError>>,
         ip_source_result: Option<Result<IpSourceSettings, ConfigurationRepositoryError>>,
+        limit_result: Option<Result<LimitSettings, ConfigurationRepositoryError>>,
         listener_result: Option<Result<ListenerSettings, ConfigurationRepositoryError>>,
         metrics_db_result: Option<Result<MetricsDatabaseSettings, ConfigurationRepositoryError>>,
+        reload_result: Option<Result<ReloadSettings, ConfigurationRepositoryError>>,
+        scrub_result: Option<Result<ScrubSettings, ConfigurationRepositoryError>>,
+        sink_result: Option<Result<SinkSettings, ConfigurationRepositoryError>>,
+        synthetic_result: Option<Result<SyntheticSettings, ConfigurationRepositoryError>>,
         timeout_result: Option<Result<TimeoutSettings, ConfigurationRepositoryError>>,
         tracing_result: Option<Result<TracingSettings, ConfigurationRepositoryError>>,
     }
 
     impl MockConfigurationRepository {
+        pub(crate) fn set_backend_result(
+            &mut self,
+            backend: Result<BackendSettings, ConfigurationRepositoryError>,
+        ) {
+            self.backend_result = Some(backend)
+        }
+
         pub(crate) fn set_compression_result(
             &mut self,
             compression: Result<CompressionSettings, ConfigurationRepositoryError>,
@@ -85,6 +138,34 @@ pub(crate) mod tests {
             self.cors_result = Some(cors)
         }
 
+        pub(crate) fn set_dead_letter_result(
+            &mut self,
+            dead_letter: Result<DeadLetterSettings, ConfigurationRepositoryError>,
+        ) {
+            self.dead_letter_result = Some(dead_letter)
+        }
+
+        pub(crate) fn set_decompression_result(
+            &mut self,
+            decompression: Result<DecompressionSettings, ConfigurationRepositoryError>,
+        ) {
+            self.decompression_result = Some(decompression)
+        }
+
+        pub(crate) fn set_dedupe_result(
+            &mut self,
+            dedupe: Result<DedupeSettings, ConfigurationRepositoryError>,
+        ) {
+            self.dedupe_result = Some(dedupe)
+        }
+
+        pub(crate) fn set_dev_result(
+            &mut self,
+            dev: Result<DevSettings, ConfigurationRepositoryError>,
+        ) {
+            self.dev_result = Some(dev)
+        }
+
         pub(crate) fn set_ip_source_result(
             &mut self,
             ip_source: Result<IpSourceSettings, ConfigurationRepositoryError>,
@@ -92,6 +173,13 @@ pub(crate) mod tests {
             self.ip_source_result = Some(ip_source)
         }
 
+        pub(crate) fn set_limit_result(
+            &mut self,
+            limit: Result<LimitSettings, ConfigurationRepositoryError>,
+        ) {
+            self.limit_result = Some(limit)
+        }
+
         pub(crate) fn set_listener_result(
             &mut self,
             listener: Result<ListenerSettings, ConfigurationRepositoryError>,
@@ -106,6 +194,34 @@ pub(crate) mod tests {
             self.metrics_db_result = Some(metrics_db)
         }
 
+        pub(crate) fn set_reload_result(
+            &mut self,
+            reload: Result<ReloadSettings, ConfigurationRepositoryError>,
+        ) {
+            self.reload_result = Some(reload)
+        }
+
+        pub(crate) fn set_scrub_result(
+            &mut self,
+            scrub: Result<ScrubSettings, ConfigurationRepositoryError>,
+        ) {
+            self.scrub_result = Some(scrub)
+        }
+
+        pub(crate) fn set_sink_result(
+            &mut self,
+            sink: Result<SinkSettings, ConfigurationRepositoryError>,
+        ) {
+            self.sink_result = Some(sink)
+        }
+
+        pub(crate) fn set_synthetic_result(
+            &mut self,
+            synthetic: Result<SyntheticSettings, ConfigurationRepositoryError>,
+        ) {
+            self.synthetic_result = Some(synthetic)
+        }
+
         pub(crate) fn set_timeout_result(
             &mut self,
             timeout: Result<TimeoutSettings, ConfigurationRepositoryError>,
@@ -122,6 +238,10 @@ pub(crate) mod tests {
     }
 
     impl ConfigurationRepository for MockConfigurationRepository {
+        fn try_backend_settings(&self) -> Result<BackendSettings, ConfigurationRepositoryError> {
+            self.backend_result.to_owned().unwrap()
+        }
+
         fn try_compression_settings(
             &self,
         ) -> Result<CompressionSettings, ConfigurationRepositoryError> {
@@ -132,10 +252,34 @@ pub(crate) mod tests {
             self.cors_result.to_owned().unwrap()
         }
 
+        fn try_dead_letter_settings(
+            &self,
+        ) -> Result<DeadLetterSettings, ConfigurationRepositoryError> {
+            self.dead_letter_result.to_owned().unwrap()
+        }
+
+        fn try_decompression_settings(
+            &self,
+        ) -> Result<DecompressionSettings, ConfigurationRepositoryError> {
+            self.decompression_result.to_owned().unwrap()
+        }
+
+        fn try_dedupe_settings(&self) -> Result<DedupeSettings, ConfigurationRepositoryError> {
+            self.dedupe_result.to_owned().unwrap()
+        }
+
+        fn try_dev_settings(&self) -> Result<DevSettings, ConfigurationRepositoryError> {
+            self.dev_result.to_owned().unwrap()
+        }
+
         fn try_ip_source_settings(&self) -> Result<IpSourceSettings, ConfigurationRepositoryError> {
             self.ip_source_result.to_owned().unwrap()
         }
 
+        fn try_limit_settings(&self) -> Result<LimitSettings, ConfigurationRepositoryError> {
+            self.limit_result.to_owned().unwrap()
+        }
+
         fn try_listener_settings(&self) -> Result<ListenerSettings, ConfigurationRepositoryError> {
             self.listener_result.to_owned().unwrap()
         }
@@ -146,6 +290,24 @@ pub(crate) mod tests {
             self.metrics_db_result.to_owned().unwrap()
         }
 
+        fn try_reload_settings(&self) -> Result<ReloadSettings, ConfigurationRepositoryError> {
+            self.reload_result.to_owned().unwrap()
+        }
+
+        fn try_scrub_settings(&self) -> Result<ScrubSettings, ConfigurationRepositoryError> {
+            self.scrub_result.to_owned().unwrap()
+        }
+
+        fn try_sink_settings(&self) -> Result<SinkSettings, ConfigurationRepositoryError> {
+            self.sink_result.to_owned().unwrap()
+        }
+
+        fn try_synthetic_settings(
+            &self,
+        ) -> Result<SyntheticSettings, ConfigurationRepositoryError> {
+            self.synthetic_result.to_owned().unwrap()
+        }
+
         fn try_timeout_settings(&self) -> Result<TimeoutSettings, ConfigurationRepositoryError> {
             self.timeout_result.to_owned().unwrap()
         }
@@ -160,6 +322,7 @@ pub(crate) mod tests {
         let mut repo = MockConfigurationRepository::default();
 
         // Set each response we want
+        repo.set_backend_result(Ok(BackendSettings::default()));
         repo.set_compression_result(Ok(CompressionSettings {
             gzip: Some(true),
             deflate: Some(false),
@@ -168,7 +331,12 @@ pub(crate) mod tests {
             max_age_secs: Some(10),
             origins: vec!["test.com".to_owned()],
         }));
+        repo.set_dead_letter_result(Ok(DeadLetterSettings::default()));
+        repo.set_decompression_result(Ok(DecompressionSettings::default()));
+        repo.set_dedupe_result(Ok(DedupeSettings { secs: 3600 }));
+        repo.set_dev_result(Ok(DevSettings::default()));
         repo.set_ip_source_result(Ok(IpSourceSettings::default()));
+        repo.set_limit_result(Ok(LimitSettings::default()));
         repo.set_listener_result(Ok(ListenerSettings {
             port: 9000,
             ipv4: Some(Ipv4Addr::LOCALHOST),
@@ -180,12 +348,23 @@ pub(crate) mod tests {
             "username",
             "password",
         )));
+        repo.set_reload_result(Ok(ReloadSettings { secs: 60 }));
+        repo.set_scrub_result(Ok(ScrubSettings {
+            patterns: vec!["secret-[0-9]+".to_owned()],
+        }));
+        repo.set_sink_result(Ok(SinkSettings::default()));
+        repo.set_synthetic_result(Ok(SyntheticSettings::new("secret")));
         repo.set_timeout_result(Ok(TimeoutSettings { millis: 15000 }));
         repo.set_tracing_result(Ok(TracingSettings {
             directive: "trace".to_owned(),
         }));
 
         // Test each method of the mock repo
+        assert!(
+            repo.try_backend_settings().is_ok(),
+            "Expected result for backend settings"
+        );
+
         assert!(
             repo.try_compression_settings().is_ok(),
             "Expected result for compression settings"
@@ -196,11 +375,36 @@ pub(crate) mod tests {
             "Expected result for CORS settings"
         );
 
+        assert!(
+            repo.try_dead_letter_settings().is_ok(),
+            "Expected result for dead-letter settings"
+        );
+
+        assert!(
+            repo.try_decompression_settings().is_ok(),
+            "Expected result for decompression settings"
+        );
+
+        assert!(
+            repo.try_dedupe_settings().is_ok(),
+            "Expected result for dedupe settings"
+        );
+
+        assert!(
+            repo.try_dev_settings().is_ok(),
+            "Expected result for dev settings"
+        );
+
         assert!(
             repo.try_ip_source_settings().is_ok(),
             "Expected result for ip source settings"
         );
 
+        assert!(
+            repo.try_limit_settings().is_ok(),
+            "Expected result for limit settings"
+        );
+
         assert!(
             repo.try_listener_settings().is_ok(),
             "Expected result for listener settings"
@@ -211,6 +415,26 @@ pub(crate) mod tests {
             "Expected result for metrics db settings"
         );
 
+        assert!(
+            repo.try_reload_settings().is_ok(),
+            "Expected result for reload settings"
+        );
+
+        assert!(
+            repo.try_scrub_settings().is_ok(),
+            "Expected result for scrub settings"
+        );
+
+        assert!(
+            repo.try_sink_settings().is_ok(),
+            "Expected result for sink settings"
+        );
+
+        assert!(
+            repo.try_synthetic_settings().is_ok(),
+            "Expected result for synthetic settings"
+        );
+
         assert!(
             repo.try_timeout_settings().is_ok(),
             "Expected result for timeout settings"
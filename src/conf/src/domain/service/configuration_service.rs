@@ -1,9 +1,21 @@
-use std::net::SocketAddr;
+use std::{net::SocketAddr, time::Duration};
 
 use axum_client_ip::ClientIpSource;
 use clickhouse::Client;
+use regex::Regex;
 use thiserror::Error;
-use tower_http::{compression::CompressionLayer, cors::CorsLayer, timeout::TimeoutLayer};
+use tower_http::{
+    compression::CompressionLayer, cors::CorsLayer, decompression::RequestDecompressionLayer,
+    limit::RequestBodyLimitLayer, timeout::TimeoutLayer,
+};
+
+pub use crate::domain::model::backend::{MetricsBackend, PostgresBackend, SqliteBackend};
+pub use crate::domain::model::dead_letter::DeadLetterConfig;
+pub use crate::domain::model::dev::{DevSource, MemoryBackend};
+use crate::domain::model::limit::BatchLimits;
+pub use crate::domain::model::sink::{
+    ArchiveFormat, RotatingArchive, SinkConfig, SinkPolicy, SinkTarget,
+};
 
 /// `ConfigurationServiceError` represents the domain errors that can arise
 /// when calling a given `ConfigurationService`
@@ -27,6 +39,15 @@ pub enum ConfigurationServiceError {
 /// for an application. This includes a wide range of configuration options
 /// from tracing settings to database clients and HTTP listener setup.
 pub trait ConfigurationService: 'static + Send + Sync {
+    /// `try_batch_limits` attempts to return the `BatchLimits` on the number
+    /// and size of the events within a single request
+    fn try_batch_limits(&self) -> Result<BatchLimits, ConfigurationServiceError>;
+
+    /// `try_body_limit_layer` attempts to create and return a
+    /// `tower_http::limit::RequestBodyLimitLayer` that bounds the size of
+    /// request bodies as they are received
+    fn try_body_limit_layer(&self) -> Result<RequestBodyLimitLayer, ConfigurationServiceError>;
+
     /// `try_compression_layer` attempts to configure and return
     /// `tower_http::compression::CompressionLayer`
     fn try_compression_layer(&self) -> Result<CompressionLayer, ConfigurationServiceError>;
@@ -35,15 +56,60 @@ pub trait ConfigurationService: 'static + Send + Sync {
     /// `tower_http::cors::CorsLayer`
     fn try_cors_layer(&self) -> Result<CorsLayer, ConfigurationServiceError>;
 
+    /// `try_dead_letters` attempts to return the `DeadLetterConfig` of the
+    /// store in which rejected requests are kept for review. A `Missing` error
+    /// indicates that rejected requests are not kept.
+    fn try_dead_letters(&self) -> Result<DeadLetterConfig, ConfigurationServiceError>;
+
+    /// `try_decompression_layer` attempts to configure and return
+    /// `tower_http::decompression::RequestDecompressionLayer`
+    fn try_decompression_layer(
+        &self,
+    ) -> Result<RequestDecompressionLayer, ConfigurationServiceError>;
+
+    /// `try_decompression_limit` attempts to return the maximum number of
+    /// bytes that a request body may expand to once decompressed
+    fn try_decompression_limit(&self) -> Result<usize, ConfigurationServiceError>;
+
+    /// `try_dedupe_horizon` attempts to return the `std::time::Duration` for
+    /// which the ids of saved events are remembered to skip duplicates. A
+    /// `Missing` error indicates that the app should use its own default.
+    fn try_dedupe_horizon(&self) -> Result<Duration, ConfigurationServiceError>;
+
     /// `try_ip_source attempts to create and return a
     /// `axum_client_ip::ClientIpSource` value that can be used to add an
     /// extension to axum for determining the IP of a connecting http client
     fn try_ip_source(&self) -> Result<ClientIpSource, ConfigurationServiceError>;
 
+    /// `try_reload_interval` attempts to return the `std::time::Duration`
+    /// between reloads of data that the app caches from its backing store. A
+    /// `Missing` error indicates that the data should only be loaded once.
+    fn try_reload_interval(&self) -> Result<Duration, ConfigurationServiceError>;
+
+    /// `try_scrub_patterns` attempts to compile and return the list of
+    /// `regex::Regex` patterns that should be redacted from event text. A
+    /// `Missing` error indicates that the app should use its own defaults.
+    fn try_scrub_patterns(&self) -> Result<Vec<Regex>, ConfigurationServiceError>;
+
+    /// `try_sinks` attempts to validate and return the `SinkConfig` of every
+    /// destination to which saved events are mirrored. A `Missing` error
+    /// indicates that events are only saved to the metrics database.
+    fn try_sinks(&self) -> Result<Vec<SinkConfig>, ConfigurationServiceError>;
+
+    /// `try_synthetic_visitor_key` attempts to return the secret from which
+    /// the ids of synthesized visitors are derived. A `Missing` error
+    /// indicates that the ids are derived without a secret.
+    fn try_synthetic_visitor_key(&self) -> Result<String, ConfigurationServiceError>;
+
     /// `try_timeout_layer` attempts to create and return a
     /// `tower_http::timeout::TimeoutLayer`
     fn try_timeout_layer(&self) -> Result<TimeoutLayer, ConfigurationServiceError>;
 
+    /// `try_metrics_backend` attempts to return the `MetricsBackend` in which
+    /// the app stores events. ClickHouse is used unless another is configured,
+    /// and in dev mode events are kept in memory whatever is configured.
+    fn try_metrics_backend(&self) -> Result<MetricsBackend, ConfigurationServiceError>;
+
     /// `try_metrics_db_client` attempts to create and return a
     /// `clickhouse::Client` client to access the Clickhouse database for this
     /// application
@@ -8,7 +8,9 @@ use crate::domain::repository::configuration_repository::{
 
 use super::env_settings::*;
 use crate::domain::model::{
-    compression::*, cors::*, ip_source::*, listener::*, metrics_db::*, timeout::*, tracing::*,
+    backend::*, compression::*, cors::*, dead_letter::*, decompression::*, dedupe::*, dev::*,
+    ip_source::*, limit::*, listener::*, metrics_db::*, reload::*, scrub::*, sink::*, synthetic::*,
+    timeout::*, tracing::*,
 };
 
 /// `EnvRepository` provides a `ConfigurationRepository` based on the
@@ -19,21 +21,31 @@ use crate::domain::model::{
 /// graph of names is similarly separated by`_`.
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct EnvRepository {
+    backend: Option<EnvBackendSettings>,
+    deadletter: Option<EnvDeadLetterSettings>,
+    dedupe: Option<EnvDedupeSettings>,
+    dev: Option<EnvDevSettings>,
     ip: Option<EnvIpSettings>,
     layer: Option<EnvLayerSettings>,
+    limit: Option<EnvLimitSettings>,
     listener: Option<EnvListenerSettings>,
     metricsdb: Option<EnvMetricsDatabaseSettings>,
+    reload: Option<EnvReloadSettings>,
+    scrub: Option<EnvScrubSettings>,
+    sink: Option<EnvSinkSettings>,
+    synthetic: Option<EnvSyntheticSettings>,
     tracing: Option<EnvTracingSettings>,
 }
 
-/// `LayerSettings` wraps the `CorsSettings`, `CompressionSettings`
-/// and `TimeoutSettings` into a common struct which can be used to handle all
+/// `LayerSettings` wraps the `CorsSettings`, `CompressionSettings`,
+/// `DecompressionSettings` and `TimeoutSettings` into a common struct which can be used to handle all
 /// in a clean manner which will optionally set up a CORS layer if any is
 /// specified.
 #[derive(Debug, Deserialize, Serialize, Clone)]
 struct EnvLayerSettings {
     compression: Option<EnvCompressionSettings>,
     cors: Option<EnvCorsSettings>,
+    decompression: Option<EnvDecompressionSettings>,
     timeout: Option<EnvTimeoutSettings>,
 }
 
@@ -50,6 +62,7 @@ impl EnvRepository {
             .add_source(
                 Environment::with_prefix(app_prefix.as_ref())
                     .with_list_parse_key("layer.cors.origins")
+                    .with_list_parse_key("scrub.patterns")
                     .try_parsing(true)
                     .separator("_")
                     .list_separator(" "),
@@ -62,6 +75,15 @@ impl EnvRepository {
 }
 
 impl ConfigurationRepository for EnvRepository {
+    #[instrument]
+    fn try_backend_settings(&self) -> Result<BackendSettings, ConfigurationRepositoryError> {
+        let Some(ref backend_settings) = self.backend else {
+            tracing::info!("Using default storage backend");
+            return Ok(BackendSettings::default());
+        };
+        Ok(backend_settings.into())
+    }
+
     #[instrument]
     fn try_compression_settings(
         &self,
@@ -90,6 +112,47 @@ impl ConfigurationRepository for EnvRepository {
         Ok(cors_settings.into())
     }
 
+    #[instrument]
+    fn try_dead_letter_settings(&self) -> Result<DeadLetterSettings, ConfigurationRepositoryError> {
+        let Some(ref dead_letter_settings) = self.deadletter else {
+            tracing::info!("No dead-letter store configured in ENV");
+            return Err(ConfigurationRepositoryError::Missing);
+        };
+        Ok(dead_letter_settings.into())
+    }
+
+    #[instrument]
+    fn try_decompression_settings(
+        &self,
+    ) -> Result<DecompressionSettings, ConfigurationRepositoryError> {
+        let Some(ref layer_settings) = self.layer else {
+            tracing::info!("Using default HTTP Decompression Layer Settings");
+            return Ok(DecompressionSettings::default());
+        };
+        let Some(ref settings) = layer_settings.decompression else {
+            tracing::info!("Using default HTTP Decompression Layer Settings");
+            return Ok(DecompressionSettings::default());
+        };
+        Ok(settings.into())
+    }
+
+    #[instrument]
+    fn try_dedupe_settings(&self) -> Result<DedupeSettings, ConfigurationRepositoryError> {
+        let Some(ref dedupe_settings) = self.dedupe else {
+            tracing::info!("No dedupe horizon configured in ENV");
+            return Err(ConfigurationRepositoryError::Missing);
+        };
+        Ok(dedupe_settings.into())
+    }
+
+    #[instrument]
+    fn try_dev_settings(&self) -> Result<DevSettings, ConfigurationRepositoryError> {
+        let Some(ref dev_settings) = self.dev else {
+            return Ok(DevSettings::default());
+        };
+        Ok(dev_settings.into())
+    }
+
     #[instrument]
     fn try_ip_source_settings(&self) -> Result<IpSourceSettings, ConfigurationRepositoryError> {
         let Some(ref ip_settings) = self.ip else {
@@ -100,6 +163,15 @@ impl ConfigurationRepository for EnvRepository {
         Ok(ip_settings.into())
     }
 
+    #[instrument]
+    fn try_limit_settings(&self) -> Result<LimitSettings, ConfigurationRepositoryError> {
+        let Some(ref limit_settings) = self.limit else {
+            tracing::info!("Using default request limit settings");
+            return Ok(LimitSettings::default());
+        };
+        Ok(limit_settings.into())
+    }
+
     #[instrument]
     fn try_listener_settings(&self) -> Result<ListenerSettings, ConfigurationRepositoryError> {
         let Some(ref listener_settings) = self.listener else {
@@ -120,6 +192,42 @@ impl ConfigurationRepository for EnvRepository {
         Ok(metrics_db_settings.into())
     }
 
+    #[instrument]
+    fn try_reload_settings(&self) -> Result<ReloadSettings, ConfigurationRepositoryError> {
+        let Some(ref reload_settings) = self.reload else {
+            tracing::info!("No reload interval configured in ENV");
+            return Err(ConfigurationRepositoryError::Missing);
+        };
+        Ok(reload_settings.into())
+    }
+
+    #[instrument]
+    fn try_scrub_settings(&self) -> Result<ScrubSettings, ConfigurationRepositoryError> {
+        let Some(ref scrub_settings) = self.scrub else {
+            tracing::info!("No scrub patterns configured in ENV");
+            return Err(ConfigurationRepositoryError::Missing);
+        };
+        Ok(scrub_settings.into())
+    }
+
+    #[instrument]
+    fn try_sink_settings(&self) -> Result<SinkSettings, ConfigurationRepositoryError> {
+        let Some(ref sink_settings) = self.sink else {
+            tracing::info!("No event sinks configured in ENV");
+            return Err(ConfigurationRepositoryError::Missing);
+        };
+        Ok(sink_settings.into())
+    }
+
+    #[instrument]
+    fn try_synthetic_settings(&self) -> Result<SyntheticSettings, ConfigurationRepositoryError> {
+        let Some(ref synthetic_settings) = self.synthetic else {
+            tracing::info!("No synthetic visitor key configured in ENV");
+            return Err(ConfigurationRepositoryError::Missing);
+        };
+        Ok(synthetic_settings.into())
+    }
+
     #[instrument]
     fn try_timeout_settings(&self) -> Result<TimeoutSettings, ConfigurationRepositoryError> {
         let Some(ref layer_settings) = self.layer else {
@@ -155,6 +263,18 @@ mod tests {
     use super::*;
 
     const VALID_SETTINGS_ARR: &[(&str, &str, &str)] = &[
+        ("BACKEND", "KIND", "Postgres"),
+        ("BACKEND", "URL", "postgres://salus@localhost:5432/salus"),
+        ("BACKEND", "TIMESCALE", "true"),
+        ("BACKEND", "PATH", "/var/lib/salus/salus.db"),
+        ("DEADLETTER", "CAPACITY", "50"),
+        ("DEADLETTER", "SAMPLE", "10"),
+        ("DEADLETTER", "ADMINKEY", "secret"),
+        ("DEDUPE", "SECS", "1800"),
+        ("DEV", "MODE", "true"),
+        ("DEV", "CAPACITY", "500"),
+        ("DEV", "SOURCES", "abc-123:localhost"),
+        ("DEV", "SERVERKEY", "secret"),
         ("IP", "SOURCE", "CfConnectingIp"),
         ("LAYER", "COMPRESSION_DEFLATE", "false"),
         ("LAYER", "COMPRESSION_GZIP", "true"),
@@ -164,13 +284,23 @@ mod tests {
             "http://localhost:3000 http://127.0.0.1:3000",
         ),
         ("LAYER", "CORS_MAX_AGE_SECS", "60"),
+        ("LAYER", "DECOMPRESSION_BR", "false"),
+        ("LAYER", "DECOMPRESSION_LIMIT", "1048576"),
         ("LAYER", "TIMEOUT_MILLIS", "4400"),
+        ("LIMIT", "BODY", "65536"),
+        ("LIMIT", "EVENTS", "100"),
         ("LISTENER", "IPV4", "0.0.0.0"),
         ("LISTENER", "PORT", "3000"),
         ("METRICSDB", "URL", "http://localhost:8123"),
         ("METRICSDB", "DATABASE", "TEST"),
         ("METRICSDB", "USER", "TEST"),
         ("METRICSDB", "PASS", "TEST"),
+        ("RELOAD", "SECS", "300"),
+        ("SCRUB", "PATTERNS", r"secret-[0-9]+ token=\w+"),
+        ("SINK", "ARCHIVE_PATH", "/tmp/salus/events.jsonl"),
+        ("SINK", "WEBHOOK_URL", "http://localhost:9000/events"),
+        ("SINK", "WEBHOOK_POLICY", "Required"),
+        ("SYNTHETIC", "VISITORKEY", "secret"),
         ("TRACING", "DIRECTIVE", "trace"),
     ];
 
@@ -179,16 +309,70 @@ mod tests {
         // positive cases
         let repo = create_valid_repo();
 
+        // Test backend
+        let Ok(backend_settings) = repo.try_backend_settings() else {
+            panic!("Expected valid backend settings");
+        };
+        assert_eq!(backend_settings.kind, Some(BackendKind::Postgres));
+        assert_eq!(
+            backend_settings.url.as_deref(),
+            Some("postgres://salus@localhost:5432/salus")
+        );
+        assert_eq!(backend_settings.timescale, Some(true));
+        assert_eq!(
+            backend_settings.path.as_deref(),
+            Some("/var/lib/salus/salus.db")
+        );
+
         // Test compression
         if repo.try_compression_settings().is_err() {
             panic!("Expected compression layer to be created");
         }
 
+        // Test decompression
+        let Ok(decompression_settings) = repo.try_decompression_settings() else {
+            panic!("Expected valid decompression settings");
+        };
+        assert_eq!(decompression_settings.br, Some(false));
+        assert_eq!(decompression_settings.limit, Some(1048576));
+
+        // Test dead letters
+        let Ok(dead_letter_settings) = repo.try_dead_letter_settings() else {
+            panic!("Expected valid dead-letter settings");
+        };
+        assert_eq!(dead_letter_settings.capacity, Some(50));
+        assert_eq!(dead_letter_settings.sample, Some(10));
+        assert_eq!(dead_letter_settings.maxbody, None);
+        assert_eq!(dead_letter_settings.adminkey.as_deref(), Some("secret"));
+
+        // Test dedupe
+        let Ok(dedupe_settings) = repo.try_dedupe_settings() else {
+            panic!("Expected valid dedupe settings");
+        };
+        assert_eq!(dedupe_settings.secs, 1800);
+
+        // Test dev
+        let Ok(dev_settings) = repo.try_dev_settings() else {
+            panic!("Expected valid dev settings");
+        };
+        assert!(dev_settings.is_enabled());
+        assert_eq!(dev_settings.capacity, Some(500));
+        assert_eq!(dev_settings.sources.as_deref(), Some("abc-123:localhost"));
+        assert_eq!(dev_settings.serverkey.as_deref(), Some("secret"));
+
         // Test IP Source
         if repo.try_ip_source_settings().is_err() {
             panic!("Expected valid ip source to be created");
         }
 
+        // Test limits
+        let Ok(limit_settings) = repo.try_limit_settings() else {
+            panic!("Expected valid limit settings");
+        };
+        assert_eq!(limit_settings.body, Some(65536));
+        assert_eq!(limit_settings.events, Some(100));
+        assert_eq!(limit_settings.attrs, None);
+
         // Test timeout
         if repo.try_timeout_settings().is_err() {
             panic!("Expected timeout layer to be created");
@@ -209,12 +393,51 @@ mod tests {
             panic!("Expected valid db settings");
         }
 
+        // Test reload
+        let Ok(reload_settings) = repo.try_reload_settings() else {
+            panic!("Expected valid reload settings");
+        };
+        assert_eq!(reload_settings.secs, 300);
+
+        // Test scrub patterns
+        let Ok(scrub_settings) = repo.try_scrub_settings() else {
+            panic!("Expected valid scrub settings");
+        };
+        assert_eq!(
+            scrub_settings.patterns.len(),
+            2,
+            "Expected space separated scrub patterns to be parsed as a list"
+        );
+
+        // Test sinks
+        let Ok(sink_settings) = repo.try_sink_settings() else {
+            panic!("Expected valid sink settings");
+        };
+        let archive = sink_settings.archive.expect("Expected archive sink");
+        assert_eq!(archive.path.as_deref(), Some("/tmp/salus/events.jsonl"));
+        assert_eq!(archive.dir, None);
+        assert_eq!(archive.policy, None);
+        let webhook = sink_settings.webhook.expect("Expected webhook sink");
+        assert_eq!(webhook.url, "http://localhost:9000/events");
+        assert_eq!(webhook.policy, Some(SinkPolicy::Required));
+
+        // Test synthetic visitors
+        let Ok(synthetic_settings) = repo.try_synthetic_settings() else {
+            panic!("Expected valid synthetic settings");
+        };
+        assert_eq!(synthetic_settings.visitorkey, "secret");
+
         // Test tracing - Commented out because this can only be called once
         // and is covered by an existing test in the tracing module.
         // settings.tracing.try_init_tracing_subscriber().unwrap();
 
         // negative cases
         let empty_repo = EnvRepository::try_new("INVALID_APP_NAME").unwrap();
+        assert_eq!(
+            empty_repo.try_backend_settings().unwrap().kind,
+            None,
+            "Expected default backend settings"
+        );
         assert_eq!(
             empty_repo.try_cors_settings().unwrap_err(),
             ConfigurationRepositoryError::Missing
@@ -227,6 +450,34 @@ mod tests {
             empty_repo.try_metrics_db_settings().unwrap_err(),
             ConfigurationRepositoryError::Missing
         );
+        assert_eq!(
+            empty_repo.try_dead_letter_settings().unwrap_err(),
+            ConfigurationRepositoryError::Missing
+        );
+        assert_eq!(
+            empty_repo.try_dedupe_settings().unwrap_err(),
+            ConfigurationRepositoryError::Missing
+        );
+        assert!(
+            !empty_repo.try_dev_settings().unwrap().is_enabled(),
+            "Expected dev mode to be off by default"
+        );
+        assert_eq!(
+            empty_repo.try_reload_settings().unwrap_err(),
+            ConfigurationRepositoryError::Missing
+        );
+        assert_eq!(
+            empty_repo.try_scrub_settings().unwrap_err(),
+            ConfigurationRepositoryError::Missing
+        );
+        assert_eq!(
+            empty_repo.try_sink_settings().unwrap_err(),
+            ConfigurationRepositoryError::Missing
+        );
+        assert_eq!(
+            empty_repo.try_synthetic_settings().unwrap_err(),
+            ConfigurationRepositoryError::Missing
+        );
     }
 
     /// Self-contained method for establishing test settings
@@ -4,11 +4,48 @@ use axum_client_ip::ClientIpSource;
 use serde::{Deserialize, Serialize};
 
 use crate::domain::model::{
-    compression::CompressionSettings, cors::CorsSettings, ip_source::IpSourceSettings,
-    listener::ListenerSettings, metrics_db::MetricsDatabaseSettings, timeout::TimeoutSettings,
+    backend::{BackendKind, BackendSettings},
+    compression::CompressionSettings,
+    cors::CorsSettings,
+    dead_letter::DeadLetterSettings,
+    decompression::DecompressionSettings,
+    dedupe::DedupeSettings,
+    dev::DevSettings,
+    ip_source::IpSourceSettings,
+    limit::LimitSettings,
+    listener::ListenerSettings,
+    metrics_db::MetricsDatabaseSettings,
+    reload::ReloadSettings,
+    scrub::ScrubSettings,
+    sink::{ArchiveFormat, ArchiveSinkSettings, SinkPolicy, SinkSettings, WebhookSinkSettings},
+    synthetic::SyntheticSettings,
+    timeout::TimeoutSettings,
     tracing::TracingSettings,
 };
 
+/// `EnvBackendSettings` selects the storage backend. `kind` is one of
+/// `Clickhouse`, `Postgres` or `Sqlite`, `url` is the PostgreSQL connection
+/// string, `timescale` makes the PostgreSQL event table a TimescaleDB
+/// hypertable and `path` is the SQLite database file.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EnvBackendSettings {
+    kind: Option<BackendKind>,
+    url: Option<String>,
+    timescale: Option<bool>,
+    path: Option<String>,
+}
+
+impl From<&EnvBackendSettings> for BackendSettings {
+    fn from(value: &EnvBackendSettings) -> Self {
+        Self {
+            kind: value.kind,
+            url: value.url.to_owned(),
+            timescale: value.timescale,
+            path: value.path.to_owned(),
+        }
+    }
+}
+
 /// `EnvCompressionSettings` allows the setup of `tower-http` `CompressionLayer`
 /// `gzip` and `deflate` are booleans that control those attributes respectively
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -51,6 +88,55 @@ impl From<&EnvCorsSettings> for CorsSettings {
     }
 }
 
+/// `EnvDecompressionSettings` allows the setup of `tower-http`
+/// `RequestDecompressionLayer`. `gzip`, `deflate`, `br` and `zstd` are
+/// booleans that control those encodings and `limit` is the maximum number of
+/// bytes that a request body may expand to once decompressed.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EnvDecompressionSettings {
+    gzip: Option<bool>,
+    deflate: Option<bool>,
+    br: Option<bool>,
+    zstd: Option<bool>,
+    limit: Option<usize>,
+}
+
+impl From<&EnvDecompressionSettings> for DecompressionSettings {
+    fn from(value: &EnvDecompressionSettings) -> Self {
+        Self {
+            gzip: value.gzip,
+            deflate: value.deflate,
+            br: value.br,
+            zstd: value.zstd,
+            limit: value.limit,
+        }
+    }
+}
+
+/// `EnvLimitSettings` bounds the size of incoming requests. `body` is in bytes
+/// as received, `events` and `attrs` are counts per request and per event and
+/// `title` and `location` are in characters.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EnvLimitSettings {
+    body: Option<usize>,
+    events: Option<usize>,
+    attrs: Option<usize>,
+    title: Option<usize>,
+    location: Option<usize>,
+}
+
+impl From<&EnvLimitSettings> for LimitSettings {
+    fn from(value: &EnvLimitSettings) -> Self {
+        Self {
+            body: value.body,
+            events: value.events,
+            attrs: value.attrs,
+            title: value.title,
+            location: value.location,
+        }
+    }
+}
+
 /// `EnvListenerSettings` are used to determine the HTTP listener characteristics
 /// of a given metrics application. These include IPv4 or IPv6 address
 /// (exclusive) should be attached to as well as the port.
@@ -104,6 +190,157 @@ impl From<&EnvIpSettings> for IpSourceSettings {
     }
 }
 
+/// `EnvDeadLetterSettings` turns on the store of rejected requests. `capacity`
+/// bounds the requests kept per site, one in every `sample` rejected requests
+/// is kept, `maxbody` bounds the bytes kept of each body and `adminkey` is
+/// required to read them back.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EnvDeadLetterSettings {
+    capacity: Option<usize>,
+    sample: Option<u64>,
+    maxbody: Option<usize>,
+    adminkey: Option<String>,
+}
+
+impl From<&EnvDeadLetterSettings> for DeadLetterSettings {
+    fn from(value: &EnvDeadLetterSettings) -> Self {
+        Self {
+            capacity: value.capacity,
+            sample: value.sample,
+            maxbody: value.maxbody,
+            adminkey: value.adminkey.to_owned(),
+        }
+    }
+}
+
+/// `EnvDedupeSettings` specifies the number of seconds for which the ids of
+/// saved events are remembered in order to skip duplicates
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EnvDedupeSettings {
+    secs: u64,
+}
+
+impl From<&EnvDedupeSettings> for DedupeSettings {
+    fn from(value: &EnvDedupeSettings) -> Self {
+        Self { secs: value.secs }
+    }
+}
+
+/// `EnvDevSettings` turns on dev mode with `mode` and optionally bounds the
+/// number of events kept in memory with `capacity`. `sources` lists the
+/// accepted `api_key:site` pairs and `serverkey` is accepted for every api key.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EnvDevSettings {
+    mode: Option<bool>,
+    capacity: Option<usize>,
+    sources: Option<String>,
+    serverkey: Option<String>,
+}
+
+impl From<&EnvDevSettings> for DevSettings {
+    fn from(value: &EnvDevSettings) -> Self {
+        Self {
+            mode: value.mode,
+            capacity: value.capacity,
+            sources: value.sources.to_owned(),
+            serverkey: value.serverkey.to_owned(),
+        }
+    }
+}
+
+/// `EnvReloadSettings` specifies the number of seconds between reloads of
+/// data that an app caches from its backing store
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EnvReloadSettings {
+    secs: u64,
+}
+
+impl From<&EnvReloadSettings> for ReloadSettings {
+    fn from(value: &EnvReloadSettings) -> Self {
+        Self { secs: value.secs }
+    }
+}
+
+/// `EnvScrubSettings` lists the regex patterns that should be redacted from
+/// event locations and titles. Patterns are separated by spaces, so any
+/// pattern that needs to match a space should use `\s` or `\x20` instead.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EnvScrubSettings {
+    patterns: Vec<String>,
+}
+
+impl From<&EnvScrubSettings> for ScrubSettings {
+    fn from(value: &EnvScrubSettings) -> Self {
+        Self {
+            patterns: value.patterns.clone(),
+        }
+    }
+}
+
+/// `EnvSinkSettings` lists the optional sinks to which saved events are
+/// mirrored. Each sink accepts a `policy` of `Required`, `BestEffort` or
+/// `Async`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EnvSinkSettings {
+    archive: Option<EnvArchiveSinkSettings>,
+    webhook: Option<EnvWebhookSinkSettings>,
+}
+
+/// `EnvArchiveSinkSettings` specifies either the `path` of a single JSON Lines
+/// archive or the `dir` of rotated archive files. Rotated files are written
+/// in the `format` of `Jsonl` or `Parquet` and closed after `maxbytes` bytes
+/// or `maxsecs` seconds.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EnvArchiveSinkSettings {
+    path: Option<String>,
+    dir: Option<String>,
+    format: Option<ArchiveFormat>,
+    maxbytes: Option<u64>,
+    maxsecs: Option<u64>,
+    policy: Option<SinkPolicy>,
+}
+
+/// `EnvWebhookSinkSettings` specifies the `url` to which events are POSTed
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EnvWebhookSinkSettings {
+    url: String,
+    policy: Option<SinkPolicy>,
+}
+
+impl From<&EnvSinkSettings> for SinkSettings {
+    fn from(value: &EnvSinkSettings) -> Self {
+        Self {
+            archive: value.archive.as_ref().map(|archive| ArchiveSinkSettings {
+                path: archive.path.to_owned(),
+                dir: archive.dir.to_owned(),
+                format: archive.format,
+                maxbytes: archive.maxbytes,
+                maxsecs: archive.maxsecs,
+                policy: archive.policy,
+            }),
+            webhook: value.webhook.as_ref().map(|webhook| WebhookSinkSettings {
+                url: webhook.url.to_owned(),
+                policy: webhook.policy,
+            }),
+        }
+    }
+}
+
+/// `EnvSyntheticSettings` specifies the `visitorkey` secret from which the ids
+/// of synthesized visitors are derived
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EnvSyntheticSettings {
+    visitorkey: String,
+}
+
+impl From<&EnvSyntheticSettings> for SyntheticSettings {
+    fn from(value: &EnvSyntheticSettings) -> Self {
+        Self {
+            visitorkey: value.visitorkey.to_owned(),
+        }
+    }
+}
+
 /// `TimeoutSettings` allows the customization of a given app's TimeoutLayer
 /// which determines how long the server will wait before responding with a
 /// timeout. If none is specified, then default value will be used. The value
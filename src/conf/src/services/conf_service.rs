@@ -1,7 +1,10 @@
 use tracing::instrument;
 
 use crate::domain::{
-    model::configuration_error::ConfigurationError,
+    model::{
+        backend::MetricsBackend, configuration_error::ConfigurationError,
+        dead_letter::DeadLetterConfig, dev::MemoryBackend, limit::BatchLimits, sink::SinkConfig,
+    },
     repository::configuration_repository::{ConfigurationRepository, ConfigurationRepositoryError},
     service::configuration_service::{ConfigurationService, ConfigurationServiceError},
 };
@@ -21,6 +24,28 @@ impl<T> ConfigurationService for ConfService<T>
 where
     T: ConfigurationRepository + std::fmt::Debug,
 {
+    #[instrument]
+    fn try_batch_limits(&self) -> Result<BatchLimits, ConfigurationServiceError> {
+        (&self
+            .conf_repository
+            .try_limit_settings()
+            .map_err(map_repo_err_to_service_err)?)
+            .try_into()
+            .map_err(map_configuration_err_to_service_err)
+    }
+
+    #[instrument]
+    fn try_body_limit_layer(
+        &self,
+    ) -> Result<tower_http::limit::RequestBodyLimitLayer, ConfigurationServiceError> {
+        (&self
+            .conf_repository
+            .try_limit_settings()
+            .map_err(map_repo_err_to_service_err)?)
+            .try_into()
+            .map_err(map_configuration_err_to_service_err)
+    }
+
     #[instrument]
     fn try_compression_layer(
         &self,
@@ -42,6 +67,47 @@ where
             .map_err(map_configuration_err_to_service_err)
     }
 
+    #[instrument]
+    fn try_dead_letters(&self) -> Result<DeadLetterConfig, ConfigurationServiceError> {
+        (&self
+            .conf_repository
+            .try_dead_letter_settings()
+            .map_err(map_repo_err_to_service_err)?)
+            .try_into()
+            .map_err(map_configuration_err_to_service_err)
+    }
+
+    #[instrument]
+    fn try_decompression_layer(
+        &self,
+    ) -> Result<tower_http::decompression::RequestDecompressionLayer, ConfigurationServiceError>
+    {
+        Ok((&self
+            .conf_repository
+            .try_decompression_settings()
+            .map_err(map_repo_err_to_service_err)?)
+            .into())
+    }
+
+    #[instrument]
+    fn try_decompression_limit(&self) -> Result<usize, ConfigurationServiceError> {
+        self.conf_repository
+            .try_decompression_settings()
+            .map_err(map_repo_err_to_service_err)?
+            .try_limit()
+            .map_err(map_configuration_err_to_service_err)
+    }
+
+    #[instrument]
+    fn try_dedupe_horizon(&self) -> Result<std::time::Duration, ConfigurationServiceError> {
+        (&self
+            .conf_repository
+            .try_dedupe_settings()
+            .map_err(map_repo_err_to_service_err)?)
+            .try_into()
+            .map_err(map_configuration_err_to_service_err)
+    }
+
     #[instrument]
     fn try_ip_source(&self) -> Result<axum_client_ip::ClientIpSource, ConfigurationServiceError> {
         Ok((&self
@@ -61,6 +127,26 @@ where
             .map_err(map_configuration_err_to_service_err)
     }
 
+    #[instrument]
+    fn try_metrics_backend(&self) -> Result<MetricsBackend, ConfigurationServiceError> {
+        let dev_settings = self
+            .conf_repository
+            .try_dev_settings()
+            .map_err(map_repo_err_to_service_err)?;
+        if dev_settings.is_enabled() {
+            tracing::warn!("Dev mode is on, events are only kept in memory");
+            return MemoryBackend::try_from(&dev_settings)
+                .map(MetricsBackend::Memory)
+                .map_err(map_configuration_err_to_service_err);
+        }
+        (&self
+            .conf_repository
+            .try_backend_settings()
+            .map_err(map_repo_err_to_service_err)?)
+            .try_into()
+            .map_err(map_configuration_err_to_service_err)
+    }
+
     #[instrument]
     fn try_metrics_db_client(&self) -> Result<clickhouse::Client, ConfigurationServiceError> {
         Ok((&self
@@ -70,6 +156,45 @@ where
             .into())
     }
 
+    #[instrument]
+    fn try_reload_interval(&self) -> Result<std::time::Duration, ConfigurationServiceError> {
+        (&self
+            .conf_repository
+            .try_reload_settings()
+            .map_err(map_repo_err_to_service_err)?)
+            .try_into()
+            .map_err(map_configuration_err_to_service_err)
+    }
+
+    #[instrument]
+    fn try_scrub_patterns(&self) -> Result<Vec<regex::Regex>, ConfigurationServiceError> {
+        (&self
+            .conf_repository
+            .try_scrub_settings()
+            .map_err(map_repo_err_to_service_err)?)
+            .try_into()
+            .map_err(map_configuration_err_to_service_err)
+    }
+
+    #[instrument]
+    fn try_sinks(&self) -> Result<Vec<SinkConfig>, ConfigurationServiceError> {
+        (&self
+            .conf_repository
+            .try_sink_settings()
+            .map_err(map_repo_err_to_service_err)?)
+            .try_into()
+            .map_err(map_configuration_err_to_service_err)
+    }
+
+    #[instrument]
+    fn try_synthetic_visitor_key(&self) -> Result<String, ConfigurationServiceError> {
+        self.conf_repository
+            .try_synthetic_settings()
+            .map_err(map_repo_err_to_service_err)?
+            .try_visitor_key()
+            .map_err(map_configuration_err_to_service_err)
+    }
+
     #[instrument]
     fn try_timeout_layer(
         &self,
@@ -132,11 +257,21 @@ mod tests {
     use std::net::Ipv4Addr;
 
     use super::*;
+    use crate::domain::model::backend::{BackendKind, BackendSettings};
     use crate::domain::model::compression::CompressionSettings;
     use crate::domain::model::cors::CorsSettings;
+    use crate::domain::model::dead_letter::DeadLetterSettings;
+    use crate::domain::model::decompression::DecompressionSettings;
+    use crate::domain::model::dedupe::DedupeSettings;
+    use crate::domain::model::dev::DevSettings;
     use crate::domain::model::ip_source::IpSourceSettings;
+    use crate::domain::model::limit::LimitSettings;
     use crate::domain::model::listener::ListenerSettings;
     use crate::domain::model::metrics_db::MetricsDatabaseSettings;
+    use crate::domain::model::reload::ReloadSettings;
+    use crate::domain::model::scrub::ScrubSettings;
+    use crate::domain::model::sink::{ArchiveSinkSettings, SinkSettings};
+    use crate::domain::model::synthetic::SyntheticSettings;
     use crate::domain::model::timeout::TimeoutSettings;
     use crate::domain::model::tracing::TracingSettings;
     use crate::domain::repository::configuration_repository::tests::MockConfigurationRepository;
@@ -147,12 +282,21 @@ mod tests {
         // Positive test cases
         let mut test_success_repo = MockConfigurationRepository::default();
 
+        test_success_repo.set_backend_result(Ok(BackendSettings::default()));
         test_success_repo.set_compression_result(Ok(CompressionSettings::default()));
         test_success_repo.set_cors_result(Ok(CorsSettings {
             max_age_secs: Some(20),
             origins: vec!["test.com".to_owned()],
         }));
+        test_success_repo.set_dead_letter_result(Ok(DeadLetterSettings {
+            adminkey: Some("secret".to_owned()),
+            ..DeadLetterSettings::default()
+        }));
+        test_success_repo.set_decompression_result(Ok(DecompressionSettings::default()));
+        test_success_repo.set_dedupe_result(Ok(DedupeSettings { secs: 600 }));
+        test_success_repo.set_dev_result(Ok(DevSettings::default()));
         test_success_repo.set_ip_source_result(Ok(IpSourceSettings::default()));
+        test_success_repo.set_limit_result(Ok(LimitSettings::default()));
         test_success_repo.set_listener_result(Ok(ListenerSettings {
             port: 8444,
             ipv4: Some(Ipv4Addr::LOCALHOST),
@@ -164,12 +308,34 @@ mod tests {
             "user",
             "pass",
         )));
+        test_success_repo.set_reload_result(Ok(ReloadSettings { secs: 120 }));
+        test_success_repo.set_scrub_result(Ok(ScrubSettings {
+            patterns: vec!["secret-[0-9]+".to_owned()],
+        }));
+        test_success_repo.set_sink_result(Ok(SinkSettings {
+            archive: Some(ArchiveSinkSettings {
+                path: Some("/tmp/events.jsonl".to_owned()),
+                ..ArchiveSinkSettings::default()
+            }),
+            webhook: None,
+        }));
+        test_success_repo.set_synthetic_result(Ok(SyntheticSettings::new("secret")));
         test_success_repo.set_timeout_result(Ok(TimeoutSettings { millis: 5599 }));
         test_success_repo.set_tracing_result(Ok(TracingSettings {
             directive: "trace".to_owned(),
         }));
 
         let test_success_service = ConfService::new(test_success_repo);
+        assert!(
+            test_success_service.try_batch_limits().is_ok(),
+            "Expected to create valid batch limits"
+        );
+
+        assert!(
+            test_success_service.try_body_limit_layer().is_ok(),
+            "Expected to create valid body limit layer"
+        );
+
         assert!(
             test_success_service.try_compression_layer().is_ok(),
             "Expected to create valid compression layer"
@@ -180,6 +346,26 @@ mod tests {
             "Expected to create valid CORS layer"
         );
 
+        assert!(
+            test_success_service.try_dead_letters().is_ok(),
+            "Expected to create valid dead-letter config"
+        );
+
+        assert!(
+            test_success_service.try_decompression_layer().is_ok(),
+            "Expected to create valid decompression layer"
+        );
+
+        assert!(
+            test_success_service.try_decompression_limit().is_ok(),
+            "Expected to create valid decompression limit"
+        );
+
+        assert!(
+            test_success_service.try_dedupe_horizon().is_ok(),
+            "Expected to create valid dedupe horizon"
+        );
+
         assert!(
             test_success_service.try_ip_source().is_ok(),
             "Expected a valid ClientIpSource"
@@ -190,11 +376,38 @@ mod tests {
             "Expected to create valid listener soccet address"
         );
 
+        assert_eq!(
+            test_success_service.try_metrics_backend().unwrap(),
+            MetricsBackend::Clickhouse,
+            "Expected ClickHouse as the default metrics backend"
+        );
+
         assert!(
             test_success_service.try_metrics_db_client().is_ok(),
             "Expected to create valid metrics db client"
         );
 
+        assert!(
+            test_success_service.try_reload_interval().is_ok(),
+            "Expected to create valid reload interval"
+        );
+
+        assert!(
+            test_success_service.try_scrub_patterns().is_ok(),
+            "Expected to compile valid scrub patterns"
+        );
+
+        assert_eq!(
+            test_success_service.try_sinks().unwrap().len(),
+            1,
+            "Expected to create valid sinks"
+        );
+
+        assert!(
+            test_success_service.try_synthetic_visitor_key().is_ok(),
+            "Expected to create valid synthetic visitor key"
+        );
+
         assert!(
             test_success_service.try_timeout_layer().is_ok(),
             "Expected to create valid timeout layer"
@@ -210,14 +423,51 @@ mod tests {
         // Negative test cases
         let mut test_failure_repo = MockConfigurationRepository::default();
 
+        test_failure_repo.set_backend_result(Ok(BackendSettings {
+            kind: Some(BackendKind::Postgres),
+            ..BackendSettings::default()
+        }));
+        test_failure_repo.set_dev_result(Ok(DevSettings::default()));
         test_failure_repo.set_compression_result(Err(ConfigurationRepositoryError::Repository));
         test_failure_repo.set_cors_result(Err(ConfigurationRepositoryError::Missing));
+        test_failure_repo.set_dead_letter_result(Ok(DeadLetterSettings::default()));
+        test_failure_repo.set_decompression_result(Ok(DecompressionSettings {
+            limit: Some(0),
+            ..DecompressionSettings::default()
+        }));
+        test_failure_repo.set_dedupe_result(Err(ConfigurationRepositoryError::Missing));
+        test_failure_repo.set_limit_result(Ok(LimitSettings {
+            body: Some(0),
+            events: Some(0),
+            ..LimitSettings::default()
+        }));
         test_failure_repo.set_listener_result(Err(ConfigurationRepositoryError::Missing));
         test_failure_repo.set_metrics_db(Err(ConfigurationRepositoryError::Missing));
+        test_failure_repo.set_reload_result(Err(ConfigurationRepositoryError::Missing));
+        test_failure_repo.set_scrub_result(Err(ConfigurationRepositoryError::Missing));
+        test_failure_repo.set_sink_result(Ok(SinkSettings {
+            archive: Some(ArchiveSinkSettings {
+                path: Some(String::new()),
+                ..ArchiveSinkSettings::default()
+            }),
+            webhook: None,
+        }));
+        test_failure_repo.set_synthetic_result(Ok(SyntheticSettings::new(" ")));
         test_failure_repo.set_timeout_result(Err(ConfigurationRepositoryError::Repository));
         test_failure_repo.set_tracing_result(Err(ConfigurationRepositoryError::Repository));
 
         let test_failure_service = ConfService::new(test_failure_repo);
+        assert_eq!(
+            test_failure_service.try_batch_limits().unwrap_err(),
+            ConfigurationServiceError::Invalid,
+            "Expected invalid error for zero batch limits"
+        );
+
+        assert!(
+            test_failure_service.try_body_limit_layer().is_err(),
+            "Expected error for body limit layer"
+        );
+
         assert!(
             test_failure_service.try_compression_layer().is_err(),
             "Expected error for compression layer"
@@ -233,11 +483,61 @@ mod tests {
             "Expected error for listener soccet address"
         );
 
+        assert_eq!(
+            test_failure_service.try_metrics_backend().unwrap_err(),
+            ConfigurationServiceError::Invalid,
+            "Expected invalid error for PostgreSQL backend without url"
+        );
+
         assert!(
             test_failure_service.try_metrics_db_client().is_err(),
             "Expected error for metrics db client"
         );
 
+        assert_eq!(
+            test_failure_service.try_decompression_limit().unwrap_err(),
+            ConfigurationServiceError::Invalid,
+            "Expected invalid error for zero decompression limit"
+        );
+
+        assert_eq!(
+            test_failure_service.try_dead_letters().unwrap_err(),
+            ConfigurationServiceError::Invalid,
+            "Expected invalid error for dead letters without an admin key"
+        );
+
+        assert_eq!(
+            test_failure_service.try_dedupe_horizon().unwrap_err(),
+            ConfigurationServiceError::Missing,
+            "Expected missing error for dedupe horizon"
+        );
+
+        assert_eq!(
+            test_failure_service.try_reload_interval().unwrap_err(),
+            ConfigurationServiceError::Missing,
+            "Expected missing error for reload interval"
+        );
+
+        assert_eq!(
+            test_failure_service.try_scrub_patterns().unwrap_err(),
+            ConfigurationServiceError::Missing,
+            "Expected missing error for scrub patterns"
+        );
+
+        assert_eq!(
+            test_failure_service.try_sinks().unwrap_err(),
+            ConfigurationServiceError::Invalid,
+            "Expected invalid error for empty archive path"
+        );
+
+        assert_eq!(
+            test_failure_service
+                .try_synthetic_visitor_key()
+                .unwrap_err(),
+            ConfigurationServiceError::Invalid,
+            "Expected invalid error for blank synthetic visitor key"
+        );
+
         assert!(
             test_failure_service.try_timeout_layer().is_err(),
             "Expected error for timeout layer"
@@ -248,4 +548,40 @@ mod tests {
             "Expected error for tracing settings"
         );
     }
+
+    #[test]
+    fn test_dev_mode_metrics_backend() {
+        // Dev mode takes precedence over the configured backend
+        let mut test_dev_repo = MockConfigurationRepository::default();
+        test_dev_repo.set_backend_result(Ok(BackendSettings {
+            kind: Some(BackendKind::Postgres),
+            ..BackendSettings::default()
+        }));
+        test_dev_repo.set_dev_result(Ok(DevSettings {
+            mode: Some(true),
+            capacity: Some(50),
+            ..DevSettings::default()
+        }));
+        let test_dev_service = ConfService::new(test_dev_repo);
+        let Ok(MetricsBackend::Memory(memory_backend)) = test_dev_service.try_metrics_backend()
+        else {
+            panic!("Expected the in-memory backend in dev mode");
+        };
+        assert_eq!(memory_backend.capacity, 50);
+
+        // Negative test case
+        let mut test_invalid_repo = MockConfigurationRepository::default();
+        test_invalid_repo.set_dev_result(Ok(DevSettings {
+            mode: Some(true),
+            sources: Some("abc-123".to_owned()),
+            ..DevSettings::default()
+        }));
+        assert_eq!(
+            ConfService::new(test_invalid_repo)
+                .try_metrics_backend()
+                .unwrap_err(),
+            ConfigurationServiceError::Invalid,
+            "Expected invalid error for a dev source without a site"
+        );
+    }
 }
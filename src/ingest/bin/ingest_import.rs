@@ -0,0 +1,112 @@
+use conf::domain::service::configuration_service::{
+    ConfigurationService, ConfigurationServiceError,
+};
+use conf::env_conf::env_conf;
+use ingest::domain::model::event_scrubber::EventScrubber;
+use ingest::domain::model::ingest_event::{ApiKey, Site};
+use ingest::domain::model::server_key::ServerKey;
+use ingest::import::event_importer::{DEFAULT_IMPORT_BATCH_SIZE, EventImporter};
+use ingest::import::import_checkpoint::ImportCheckpoint;
+use ingest::repositories::backend_ingest_repository::BackendIngestRepository;
+use ingest::services::ingest_service::IngestService;
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+
+/// APP_NAME is used to resolve configuration parameters from ENV
+pub const APP_NAME: &str = "SALUS_INGEST";
+
+/// ENV variable holding the `ServerKey` that authorizes the import
+pub const SERVER_KEY_ENV: &str = "SALUS_INGEST_IMPORT_SERVER_KEY";
+
+const USAGE: &str = "Usage: ingest_import --api-key <API_KEY> --site <SITE> \
+[--batch-size <N>] [--checkpoint <PATH>] [--report <PATH>] <FILE>";
+
+/// Command line arguments for a single import run
+struct ImportArgs {
+    api_key: String,
+    site: String,
+    batch_size: usize,
+    checkpoint: String,
+    report: Option<String>,
+    input: String,
+}
+
+impl ImportArgs {
+    fn try_parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut api_key = None;
+        let mut site = None;
+        let mut batch_size = DEFAULT_IMPORT_BATCH_SIZE;
+        let mut checkpoint = None;
+        let mut report = None;
+        let mut input = None;
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().ok_or(format!("Missing value for {name}"));
+            match arg.as_str() {
+                "--api-key" => api_key = Some(value("--api-key")?),
+                "--site" => site = Some(value("--site")?),
+                "--batch-size" => {
+                    batch_size = value("--batch-size")?
+                        .parse()
+                        .map_err(|_| "Invalid value for --batch-size".to_owned())?
+                }
+                "--checkpoint" => checkpoint = Some(value("--checkpoint")?),
+                "--report" => report = Some(value("--report")?),
+                _ if arg.starts_with("--") => return Err(format!("Unknown option {arg}")),
+                _ if input.is_none() => input = Some(arg),
+                _ => return Err("Only a single input file is accepted".to_owned()),
+            }
+        }
+        let input = input.ok_or("Missing input file")?;
+        Ok(Self {
+            api_key: api_key.ok_or("Missing --api-key")?,
+            site: site.ok_or("Missing --site")?,
+            batch_size,
+            checkpoint: checkpoint.unwrap_or_else(|| format!("{input}.checkpoint")),
+            report,
+            input,
+        })
+    }
+}
+
+/// Import historical events from a JSON Lines file for a single source. The
+/// run is resumable through the checkpoint file and prints a JSON summary of
+/// accepted, duplicate and rejected rows when done.
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error + 'static>> {
+    let args =
+        ImportArgs::try_parse(std::env::args().skip(1)).map_err(|e| format!("{e}\n{USAGE}"))?;
+    let server_key = ServerKey::new(
+        std::env::var(SERVER_KEY_ENV).map_err(|_| format!("{SERVER_KEY_ENV} must be set"))?,
+    );
+
+    let conf_service = env_conf(APP_NAME)?;
+    conf_service.try_tracing_subscriber_setup()?;
+    let event_scrubber = match conf_service.try_scrub_patterns() {
+        Ok(patterns) => EventScrubber::new(patterns),
+        Err(ConfigurationServiceError::Missing) => EventScrubber::default(),
+        Err(e) => return Err(e.into()),
+    };
+
+    let ingest_repository = BackendIngestRepository::try_from_conf(&conf_service).await?;
+    let ingest_service = IngestService::new(ingest_repository).with_event_scrubber(event_scrubber);
+    let importer = EventImporter::try_new(
+        ingest_service,
+        ApiKey::new(&args.api_key),
+        Site::new(&args.site),
+        &server_key,
+    )
+    .await?
+    .with_batch_size(args.batch_size);
+
+    let mut checkpoint = ImportCheckpoint::try_load(&args.checkpoint)?;
+    let reader = BufReader::new(File::open(&args.input)?);
+    let summary = importer.import(reader, &mut checkpoint).await?;
+
+    let report = serde_json::to_string_pretty(&summary)?;
+    if let Some(report_path) = &args.report {
+        std::fs::write(report_path, &report)?;
+    }
+    println!("{report}");
+    Ok(())
+}
@@ -0,0 +1,150 @@
+use conf::domain::service::configuration_service::{
+    ConfigurationService, ConfigurationServiceError,
+};
+use conf::env_conf::env_conf;
+use flate2::read::MultiGzDecoder;
+use ingest::domain::model::event_scrubber::EventScrubber;
+use ingest::domain::model::ingest_event::{ApiKey, Site};
+use ingest::domain::model::server_key::ServerKey;
+use ingest::import::access_log_parser::{AccessLogParser, DEFAULT_SESSION_GAP};
+use ingest::import::event_importer::{DEFAULT_IMPORT_BATCH_SIZE, EventImporter};
+use ingest::import::import_checkpoint::ImportCheckpoint;
+use ingest::repositories::backend_ingest_repository::BackendIngestRepository;
+use ingest::services::ingest_service::IngestService;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use time::Duration;
+
+/// APP_NAME is used to resolve configuration parameters from ENV
+pub const APP_NAME: &str = "SALUS_INGEST";
+
+/// ENV variable holding the `ServerKey` that authorizes the import
+pub const SERVER_KEY_ENV: &str = "SALUS_INGEST_IMPORT_SERVER_KEY";
+
+const USAGE: &str = "Usage: ingest_import_access_log --api-key <API_KEY> --site <SITE> \
+[--scheme <SCHEME>] [--session-gap-mins <N>] [--batch-size <N>] [--checkpoint <PATH>] \
+[--report <PATH>] <FILE>...";
+
+/// Command line arguments for a single access log import run
+struct AccessLogImportArgs {
+    api_key: String,
+    site: String,
+    scheme: String,
+    session_gap: Duration,
+    batch_size: usize,
+    checkpoint: String,
+    report: Option<String>,
+    inputs: Vec<String>,
+}
+
+impl AccessLogImportArgs {
+    fn try_parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut api_key = None;
+        let mut site = None;
+        let mut scheme = "https".to_owned();
+        let mut session_gap = DEFAULT_SESSION_GAP;
+        let mut batch_size = DEFAULT_IMPORT_BATCH_SIZE;
+        let mut checkpoint = None;
+        let mut report = None;
+        let mut inputs = Vec::new();
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().ok_or(format!("Missing value for {name}"));
+            match arg.as_str() {
+                "--api-key" => api_key = Some(value("--api-key")?),
+                "--site" => site = Some(value("--site")?),
+                "--scheme" => scheme = value("--scheme")?,
+                "--session-gap-mins" => {
+                    session_gap = Duration::minutes(
+                        value("--session-gap-mins")?
+                            .parse()
+                            .map_err(|_| "Invalid value for --session-gap-mins".to_owned())?,
+                    )
+                }
+                "--batch-size" => {
+                    batch_size = value("--batch-size")?
+                        .parse()
+                        .map_err(|_| "Invalid value for --batch-size".to_owned())?
+                }
+                "--checkpoint" => checkpoint = Some(value("--checkpoint")?),
+                "--report" => report = Some(value("--report")?),
+                _ if arg.starts_with("--") => return Err(format!("Unknown option {arg}")),
+                _ => inputs.push(arg),
+            }
+        }
+        let first_input = inputs.first().ok_or("Missing input file")?;
+        Ok(Self {
+            api_key: api_key.ok_or("Missing --api-key")?,
+            site: site.ok_or("Missing --site")?,
+            scheme,
+            session_gap,
+            batch_size,
+            checkpoint: checkpoint.unwrap_or_else(|| format!("{first_input}.checkpoint")),
+            report,
+            inputs,
+        })
+    }
+}
+
+/// Open a plain or gzipped access log
+fn open_log(path: &str) -> Result<Box<dyn Read>, std::io::Error> {
+    let file = File::open(path)?;
+    if path.ends_with(".gz") {
+        Ok(Box::new(MultiGzDecoder::new(file)))
+    } else {
+        Ok(Box::new(file))
+    }
+}
+
+/// Import page views from nginx or Apache access logs in combined log format
+/// for a single source. Multiple files are read in the order given as one
+/// continuous log, so rotated logs must be passed oldest first. The run is
+/// resumable through the checkpoint file as long as the same files are given,
+/// and prints a JSON summary when done.
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error + 'static>> {
+    let args = AccessLogImportArgs::try_parse(std::env::args().skip(1))
+        .map_err(|e| format!("{e}\n{USAGE}"))?;
+    let server_key = ServerKey::new(
+        std::env::var(SERVER_KEY_ENV).map_err(|_| format!("{SERVER_KEY_ENV} must be set"))?,
+    );
+
+    let conf_service = env_conf(APP_NAME)?;
+    conf_service.try_tracing_subscriber_setup()?;
+    let event_scrubber = match conf_service.try_scrub_patterns() {
+        Ok(patterns) => EventScrubber::new(patterns),
+        Err(ConfigurationServiceError::Missing) => EventScrubber::default(),
+        Err(e) => return Err(e.into()),
+    };
+
+    let ingest_repository = BackendIngestRepository::try_from_conf(&conf_service).await?;
+    let ingest_service = IngestService::new(ingest_repository).with_event_scrubber(event_scrubber);
+    let importer = EventImporter::try_new(
+        ingest_service,
+        ApiKey::new(&args.api_key),
+        Site::new(&args.site),
+        &server_key,
+    )
+    .await?
+    .with_batch_size(args.batch_size);
+    let mut parser = AccessLogParser::new(importer.api_key().clone(), importer.site().clone())
+        .with_scheme(&args.scheme)
+        .with_session_gap(args.session_gap);
+
+    let mut input: Box<dyn Read> = Box::new(std::io::empty());
+    for path in args.inputs.iter() {
+        // Files that do not end in a newline must not run into the next one
+        input = Box::new(input.chain(open_log(path)?).chain(&b"\n"[..]));
+    }
+    let mut checkpoint = ImportCheckpoint::try_load(&args.checkpoint)?;
+    let summary = importer
+        .import_with(BufReader::new(input), &mut parser, &mut checkpoint)
+        .await?;
+
+    let report = serde_json::to_string_pretty(&summary)?;
+    if let Some(report_path) = &args.report {
+        std::fs::write(report_path, &report)?;
+    }
+    println!("{report}");
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use conf::domain::service::configuration_service::ConfigurationService;
+use conf::env_conf::env_conf;
+use ingest::import::archive_replay::{
+    ArchiveReplayFilter, ArchiveReplayer, DEFAULT_REPLAY_BATCH_SIZE,
+};
+use ingest::repositories::backend_ingest_repository::BackendIngestRepository;
+use std::error::Error;
+use std::path::Path;
+use time::Date;
+use time::format_description::well_known::Iso8601;
+
+/// APP_NAME is used to resolve configuration parameters from ENV
+pub const APP_NAME: &str = "SALUS_INGEST";
+
+const USAGE: &str = "Usage: ingest_replay [--site <SITE>] [--from <YYYY-MM-DD>] \
+[--to <YYYY-MM-DD>] [--batch-size <N>] [--report <PATH>] <ARCHIVE>";
+
+/// Command line arguments for a single replay run
+struct ReplayArgs {
+    filter: ArchiveReplayFilter,
+    batch_size: usize,
+    report: Option<String>,
+    archive: String,
+}
+
+impl ReplayArgs {
+    fn try_parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut filter = ArchiveReplayFilter::default();
+        let mut batch_size = DEFAULT_REPLAY_BATCH_SIZE;
+        let mut report = None;
+        let mut archive = None;
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().ok_or(format!("Missing value for {name}"));
+            let date = |name: &str, value: String| {
+                Date::parse(&value, &Iso8601::DATE).map_err(|_| format!("Invalid value for {name}"))
+            };
+            match arg.as_str() {
+                "--site" => filter.site = Some(value("--site")?),
+                "--from" => filter.from = Some(date("--from", value("--from")?)?),
+                "--to" => filter.to = Some(date("--to", value("--to")?)?),
+                "--batch-size" => {
+                    batch_size = value("--batch-size")?
+                        .parse()
+                        .map_err(|_| "Invalid value for --batch-size".to_owned())?
+                }
+                "--report" => report = Some(value("--report")?),
+                _ if arg.starts_with("--") => return Err(format!("Unknown option {arg}")),
+                _ if archive.is_none() => archive = Some(arg),
+                _ => return Err("Only a single archive is accepted".to_owned()),
+            }
+        }
+        Ok(Self {
+            filter,
+            batch_size,
+            report,
+            archive: archive.ok_or("Missing archive")?,
+        })
+    }
+}
+
+/// Re-ingest events from an archive written by the archive sink, which is
+/// either the directory of a rotating archive or a single JSON Lines file.
+/// Events are saved to the configured backend as they were archived and a
+/// JSON summary of replayed, corrupt and unsealed files is printed when done.
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error + 'static>> {
+    let args =
+        ReplayArgs::try_parse(std::env::args().skip(1)).map_err(|e| format!("{e}\n{USAGE}"))?;
+
+    let conf_service = env_conf(APP_NAME)?;
+    conf_service.try_tracing_subscriber_setup()?;
+
+    let ingest_repository = BackendIngestRepository::try_from_conf(&conf_service).await?;
+    let summary = ArchiveReplayer::new(ingest_repository)
+        .with_filter(args.filter)
+        .with_batch_size(args.batch_size)
+        .replay(Path::new(&args.archive))
+        .await?;
+
+    let report = serde_json::to_string_pretty(&summary)?;
+    if let Some(report_path) = &args.report {
+        std::fs::write(report_path, &report)?;
+    }
+    println!("{report}");
+    Ok(())
+}
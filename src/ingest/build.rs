@@ -0,0 +1,14 @@
+/// Generate the gRPC service and messages from the protobuf definitions. A
+/// vendored `protoc` is used unless `PROTOC` is set, so that no system
+/// installation is required.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    if std::env::var_os("PROTOC").is_none() {
+        // Safety: build scripts are single threaded
+        unsafe {
+            std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+        }
+    }
+    tonic_prost_build::configure()
+        .compile_protos(&["proto/salus/ingest/v1/ingest.proto"], &["proto"])?;
+    Ok(())
+}
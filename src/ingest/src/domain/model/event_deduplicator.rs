@@ -0,0 +1,232 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Mutex,
+};
+
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+use crate::domain::model::ingest_event::{IngestEvent, IngestEventSource};
+
+/// Default period during which a saved event id is recognized as a duplicate
+pub const DEFAULT_DEDUPE_HORIZON: Duration = Duration::hours(1);
+
+/// Default number of saved event ids that are remembered per source
+pub const DEFAULT_DEDUPE_CAPACITY: usize = 500_000;
+
+#[derive(Debug, Default)]
+struct SourceWindow {
+    ids: HashSet<Uuid>,
+    order: VecDeque<(OffsetDateTime, Uuid)>,
+    reserved: HashMap<Uuid, OffsetDateTime>,
+}
+
+/// `EventDeduplicator` drops events whose `(api_key, site, id)` was already
+/// saved within the horizon, such as those of a batch that a client retried
+/// after a network failure. Ids are kept in a sliding window in memory, so
+/// duplicates are only recognized by the server that saved the original and
+/// not across restarts. Each source keeps at most `capacity` ids, evicting the
+/// oldest first.
+///
+/// Ids that are being saved are reserved, so that concurrent retries of the
+/// same batch count each other as duplicates. Reservations are either turned
+/// into saved ids with `record` or dropped with `release`, and expire with the
+/// horizon should neither happen, such as when a save is cancelled.
+#[derive(Debug)]
+pub struct EventDeduplicator {
+    horizon: Duration,
+    capacity: usize,
+    sources: Mutex<HashMap<IngestEventSource, SourceWindow>>,
+}
+
+impl Default for EventDeduplicator {
+    /// Uses `DEFAULT_DEDUPE_HORIZON` and `DEFAULT_DEDUPE_CAPACITY`
+    fn default() -> Self {
+        Self {
+            horizon: DEFAULT_DEDUPE_HORIZON,
+            capacity: DEFAULT_DEDUPE_CAPACITY,
+            sources: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl EventDeduplicator {
+    /// Replace the period during which saved ids are recognized
+    pub fn with_horizon(mut self, horizon: Duration) -> Self {
+        self.horizon = horizon;
+        self
+    }
+
+    /// Replace the number of ids that are remembered per source
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Remove events that were saved or reserved within the horizon or that
+    /// repeat an earlier event of the same batch. Returns the number of events
+    /// removed. The ids of the remaining events are reserved until `record`
+    /// or `release` is called, so that a batch which fails to save can be
+    /// retried.
+    pub fn retain_unsaved(&self, events: &mut Vec<IngestEvent>) -> usize {
+        let now = OffsetDateTime::now_utc();
+        let mut sources = self
+            .sources
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let before = events.len();
+        events.retain(|event| {
+            let window = self.expire(
+                sources.entry(IngestEventSource::from(event)).or_default(),
+                now,
+            );
+            let id = event.id();
+            if window.ids.contains(&id) || window.reserved.contains_key(&id) {
+                return false;
+            }
+            window.reserved.insert(id, now);
+            true
+        });
+        before - events.len()
+    }
+
+    /// Drop the reservations of events that failed to save, so that they are
+    /// not counted as duplicates when retried
+    pub fn release(&self, reserved: &[(IngestEventSource, Uuid)]) {
+        let mut sources = self
+            .sources
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for (source, id) in reserved.iter() {
+            let Some(window) = sources.get_mut(source) else {
+                continue;
+            };
+            window.reserved.remove(id);
+            if window.ids.is_empty() && window.reserved.is_empty() {
+                sources.remove(source);
+            }
+        }
+    }
+
+    /// Remember the ids of events that were saved, replacing their
+    /// reservations
+    pub fn record(&self, saved: &[(IngestEventSource, Uuid)]) {
+        let now = OffsetDateTime::now_utc();
+        let mut sources = self
+            .sources
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for (source, id) in saved.iter() {
+            let window = sources.entry(source.clone()).or_default();
+            window.reserved.remove(id);
+            if window.ids.insert(*id) {
+                window.order.push_back((now, *id));
+            }
+            while window.order.len() > self.capacity {
+                if let Some((_, evicted)) = window.order.pop_front() {
+                    window.ids.remove(&evicted);
+                }
+            }
+        }
+    }
+
+    /// Forget the ids and reservations of the window that are past the
+    /// horizon
+    fn expire<'a>(
+        &self,
+        window: &'a mut SourceWindow,
+        now: OffsetDateTime,
+    ) -> &'a mut SourceWindow {
+        while let Some((saved_at, id)) = window.order.front() {
+            if now - *saved_at <= self.horizon {
+                break;
+            }
+            window.ids.remove(id);
+            window.order.pop_front();
+        }
+        window
+            .reserved
+            .retain(|_, reserved_at| now - *reserved_at <= self.horizon);
+        window
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::model::ingest_event::{ApiKey, Site, VisitorEvent};
+
+    fn visitor(site: &str, id: Uuid) -> IngestEvent {
+        IngestEvent::Visitor(
+            VisitorEvent::try_new(ApiKey::new("abc_123"), Site::new(site), id).unwrap(),
+        )
+    }
+
+    fn keys(events: &[IngestEvent]) -> Vec<(IngestEventSource, Uuid)> {
+        events
+            .iter()
+            .map(|event| (IngestEventSource::from(event), event.id()))
+            .collect()
+    }
+
+    #[test]
+    fn test_retain_unsaved() {
+        let deduplicator = EventDeduplicator::default();
+        let (first, second) = (Uuid::now_v7(), Uuid::now_v7());
+
+        let mut events = vec![
+            visitor("test.com", first),
+            visitor("test.com", first),
+            visitor("other.com", first),
+        ];
+        assert_eq!(
+            deduplicator.retain_unsaved(&mut events),
+            1,
+            "Expected repeated ids within a batch to be removed per source"
+        );
+        assert_eq!(
+            deduplicator.retain_unsaved(&mut events.clone()),
+            2,
+            "Expected ids being saved to be reserved"
+        );
+        deduplicator.release(&keys(&events));
+        assert_eq!(
+            deduplicator.retain_unsaved(&mut events.clone()),
+            0,
+            "Expected released ids to be retried"
+        );
+        deduplicator.record(&keys(&events));
+
+        let mut retried = vec![visitor("test.com", first), visitor("test.com", second)];
+        assert_eq!(deduplicator.retain_unsaved(&mut retried), 1);
+        assert_eq!(keys(&retried), keys(&[visitor("test.com", second)]));
+    }
+
+    #[test]
+    fn test_horizon_and_capacity() {
+        let expired = EventDeduplicator::default().with_horizon(Duration::ZERO);
+        let id = Uuid::now_v7();
+        expired.record(&keys(&[visitor("test.com", id)]));
+        assert_eq!(
+            expired.retain_unsaved(&mut vec![visitor("test.com", id)]),
+            0,
+            "Expected ids past the horizon to be forgotten"
+        );
+
+        let bounded = EventDeduplicator::default().with_capacity(1);
+        let (first, second) = (Uuid::now_v7(), Uuid::now_v7());
+        bounded.record(&keys(&[
+            visitor("test.com", first),
+            visitor("test.com", second),
+        ]));
+        assert_eq!(
+            bounded.retain_unsaved(&mut vec![visitor("test.com", first)]),
+            0,
+            "Expected the oldest id to be evicted"
+        );
+        assert_eq!(
+            bounded.retain_unsaved(&mut vec![visitor("test.com", second)]),
+            1
+        );
+    }
+}
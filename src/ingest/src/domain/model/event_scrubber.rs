@@ -0,0 +1,318 @@
+use regex::{NoExpand, Regex};
+
+use crate::domain::model::{
+    ingest_event::IngestEvent,
+    ingest_source_rules::{IngestSourceRules, QueryParamPolicy},
+};
+
+/// `REDACTED` is the replacement value for any text matched by a scrub pattern
+pub const REDACTED: &str = "[REDACTED]";
+
+/// `DEFAULT_SCRUB_PATTERNS` are the patterns applied when no other patterns
+/// have been configured. In order they match email addresses (including the
+/// percent encoded form found in query strings), card-like numbers of 13 to 19
+/// digits, long hex tokens and long base64 tokens. The base64 pattern
+/// intentionally excludes `/`, `-` and `_` so that ordinary paths and slugs are
+/// not mistaken for tokens.
+pub const DEFAULT_SCRUB_PATTERNS: &[&str] = &[
+    r"[A-Za-z0-9._%+\-]+(?:@|%40)[A-Za-z0-9.\-]+\.[A-Za-z]{2,}",
+    r"\b(?:\d[ \-]?){12,18}\d\b",
+    r"\b[0-9A-Fa-f]{32,}\b",
+    r"[A-Za-z0-9+]{32,}={0,2}",
+];
+
+/// `EventScrubber` removes personally identifiable information from the free
+/// text fields of an `IngestEvent` before it is handed to any repository.
+///
+/// Query parameters of a location are first filtered using the
+/// `QueryParamPolicy` of the event's source, then every pattern is redacted
+/// from what remains. This applies to section and custom event locations.
+/// Titles, session referrers, custom event props and identify traits only have
+/// patterns redacted.
+#[derive(Debug, Clone)]
+pub struct EventScrubber {
+    patterns: Vec<Regex>,
+}
+
+impl EventScrubber {
+    /// `EventScrubber` constructor
+    pub fn new(patterns: Vec<Regex>) -> Self {
+        Self { patterns }
+    }
+
+    /// Scrub the fields of the supplied event in place using the global
+    /// patterns and the rules that apply to the event's source
+    pub fn scrub(&self, event: &mut IngestEvent, rules: &IngestSourceRules) {
+        match event {
+            IngestEvent::Section(section) => {
+                if let Some(location) = &section.location {
+                    section.location = Some(self.scrub_location(location, &rules.query_params));
+                }
+                if let Some(title) = &section.title {
+                    section.title = Some(self.scrub_text(title));
+                }
+            }
+            IngestEvent::Session(session) => {
+                if let Some(referrer) = &session.referrer {
+                    session.referrer = Some(self.scrub_text(referrer));
+                }
+            }
+            IngestEvent::Custom(custom) => {
+                if let Some(location) = &custom.location {
+                    custom.location = Some(self.scrub_location(location, &rules.query_params));
+                }
+                for value in custom.props.values_mut() {
+                    *value = self.scrub_text(value);
+                }
+            }
+            IngestEvent::Identify(identify) => {
+                for value in identify.traits.values_mut() {
+                    *value = self.scrub_text(value);
+                }
+            }
+            IngestEvent::Visitor(_) | IngestEvent::Click(_) => {}
+        }
+    }
+
+    /// Apply the `QueryParamPolicy` to the query string of a location and then
+    /// redact all patterns from the result
+    pub fn scrub_location(&self, location: &str, policy: &QueryParamPolicy) -> String {
+        self.scrub_text(&apply_query_param_policy(location, policy))
+    }
+
+    /// Redact every match of every pattern from the supplied text
+    pub fn scrub_text(&self, text: &str) -> String {
+        let mut scrubbed = text.to_owned();
+        for pattern in self.patterns.iter() {
+            if pattern.is_match(&scrubbed) {
+                scrubbed = pattern
+                    .replace_all(&scrubbed, NoExpand(REDACTED))
+                    .into_owned();
+            }
+        }
+        scrubbed
+    }
+}
+
+impl Default for EventScrubber {
+    /// Defaults to redacting `DEFAULT_SCRUB_PATTERNS`
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_SCRUB_PATTERNS
+                .iter()
+                .map(|p| Regex::new(p).expect("Default scrub patterns must be valid"))
+                .collect(),
+        )
+    }
+}
+
+/// Remove any query parameters from the location that the policy does not
+/// keep. The fragment, if any, is left untouched and the `?` is dropped when
+/// no parameters remain.
+fn apply_query_param_policy(location: &str, policy: &QueryParamPolicy) -> String {
+    if policy.is_empty() {
+        return location.to_owned();
+    }
+    let (before_fragment, fragment) = match location.find('#') {
+        Some(idx) => location.split_at(idx),
+        None => (location, ""),
+    };
+    let Some((base, query)) = before_fragment.split_once('?') else {
+        return location.to_owned();
+    };
+    let kept: Vec<&str> = query
+        .split('&')
+        .filter(|pair| {
+            let name = pair.split_once('=').map_or(*pair, |(name, _)| name);
+            !name.is_empty() && policy.is_kept(name)
+        })
+        .collect();
+    if kept.is_empty() {
+        format!("{base}{fragment}")
+    } else {
+        format!("{base}?{}{fragment}", kept.join("&"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::domain::model::ingest_event::{ApiKey, CustomEvent, SectionEvent, Site};
+
+    /// Corpus of `(input, expected)` locations for the default patterns with
+    /// no query parameter policy in place
+    const LOCATION_CORPUS: &[(&str, &str)] = &[
+        (
+            "https://test.com/path/to/section?foo=bar#last",
+            "https://test.com/path/to/section?foo=bar#last",
+        ),
+        (
+            "/how-to-choose-the-best-running-shoes-for-beginners",
+            "/how-to-choose-the-best-running-shoes-for-beginners",
+        ),
+        (
+            "/users/123/orders/456?page=2",
+            "/users/123/orders/456?page=2",
+        ),
+        (
+            "/account?email=jane.doe@example.com",
+            "/account?email=[REDACTED]",
+        ),
+        (
+            "/account?email=jane.doe%40example.co.uk&tab=profile",
+            "/account?email=[REDACTED]&tab=profile",
+        ),
+        (
+            "/reset-password?token=9f86d081884c7d659a2feaa0c55ad015a3bf4f1b",
+            "/reset-password?token=[REDACTED]",
+        ),
+        (
+            "/session/5E884898DA28047151D0E56F8DC6292773603D0D/view",
+            "/session/[REDACTED]/view",
+        ),
+        (
+            "/callback?code=eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9",
+            "/callback?code=[REDACTED]",
+        ),
+        (
+            "/callback?sig=dGhpcyBpcyBhIHNlY3JldCBzaWduYXR1cmU=&next=/home",
+            "/callback?sig=[REDACTED]&next=/home",
+        ),
+        (
+            "/checkout?card=4111111111111111",
+            "/checkout?card=[REDACTED]",
+        ),
+        (
+            "/checkout?card=4111-1111-1111-1111&step=2",
+            "/checkout?card=[REDACTED]&step=2",
+        ),
+        ("/orders/1234567890", "/orders/1234567890"),
+    ];
+
+    /// Corpus of `(input, expected)` titles for the default patterns
+    const TITLE_CORPUS: &[(&str, &str)] = &[
+        ("Section Title", "Section Title"),
+        (
+            "Welcome back, jane.doe@example.com",
+            "Welcome back, [REDACTED]",
+        ),
+        (
+            "Payment for 4111 1111 1111 1111 received",
+            "Payment for [REDACTED] received",
+        ),
+    ];
+
+    #[test]
+    fn test_default_patterns() {
+        let scrubber = EventScrubber::default();
+        let no_policy = QueryParamPolicy::default();
+        for (input, expected) in LOCATION_CORPUS {
+            assert_eq!(
+                scrubber.scrub_location(input, &no_policy),
+                *expected,
+                "Unexpected scrub result for location {input}"
+            );
+        }
+        for (input, expected) in TITLE_CORPUS {
+            assert_eq!(
+                scrubber.scrub_text(input),
+                *expected,
+                "Unexpected scrub result for title {input}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_query_param_policy() {
+        let scrubber = EventScrubber::default();
+
+        let mut deny_policy = QueryParamPolicy::default();
+        deny_policy.deny("sid");
+        assert_eq!(
+            scrubber.scrub_location("/search?q=shoes&sid=abc123&page=2#results", &deny_policy),
+            "/search?q=shoes&page=2#results",
+            "Expected denied param to be removed"
+        );
+
+        let mut allow_policy = QueryParamPolicy::default();
+        allow_policy.allow("q");
+        assert_eq!(
+            scrubber.scrub_location(
+                "https://test.com/search?q=shoes&sid=abc123&email=a@b.com",
+                &allow_policy
+            ),
+            "https://test.com/search?q=shoes",
+            "Expected only allowed params to be kept"
+        );
+        assert_eq!(
+            scrubber.scrub_location("/search?sid=abc123#top", &allow_policy),
+            "/search#top",
+            "Expected query separator to be dropped when no params remain"
+        );
+        assert_eq!(
+            scrubber.scrub_location("/search#top?sid=abc123", &allow_policy),
+            "/search#top?sid=abc123",
+            "Expected fragment to be left untouched"
+        );
+    }
+
+    #[test]
+    fn test_custom_patterns() {
+        let scrubber = EventScrubber::new(vec![Regex::new("secret-[0-9]+").unwrap()]);
+        assert_eq!(
+            scrubber.scrub_text("/docs/secret-42?email=jane@example.com"),
+            "/docs/[REDACTED]?email=jane@example.com",
+            "Expected only configured patterns to be applied"
+        );
+    }
+
+    #[test]
+    fn test_scrub_event() {
+        let scrubber = EventScrubber::default();
+        let mut rules = IngestSourceRules::default();
+        rules.query_params.deny("utm_source");
+        let mut event = IngestEvent::Section(
+            SectionEvent::try_new(
+                ApiKey::new("abc-123"),
+                Site::new("test.com"),
+                Uuid::now_v7(),
+                Uuid::now_v7(),
+                Some("/welcome?utm_source=mail&email=jane@example.com".to_owned()),
+                Some("Welcome jane@example.com".to_owned()),
+            )
+            .unwrap(),
+        );
+        scrubber.scrub(&mut event, &rules);
+        let IngestEvent::Section(section) = event else {
+            panic!("Expected scrubbing to preserve the event type");
+        };
+        assert_eq!(
+            section.location.as_deref(),
+            Some("/welcome?email=[REDACTED]")
+        );
+        assert_eq!(section.title.as_deref(), Some("Welcome [REDACTED]"));
+
+        let mut custom_event = IngestEvent::Custom(
+            CustomEvent::try_new(
+                ApiKey::new("abc-123"),
+                Site::new("test.com"),
+                Uuid::now_v7(),
+                Uuid::now_v7(),
+                "signup".to_owned(),
+                Some("/join?utm_source=mail".to_owned()),
+                HashMap::from([("contact".to_owned(), "jane@example.com".to_owned())]),
+            )
+            .unwrap(),
+        );
+        scrubber.scrub(&mut custom_event, &rules);
+        let IngestEvent::Custom(custom) = custom_event else {
+            panic!("Expected scrubbing to preserve the event type");
+        };
+        assert_eq!(custom.location.as_deref(), Some("/join"));
+        assert_eq!(custom.props["contact"], REDACTED);
+    }
+}
@@ -0,0 +1,450 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+use crate::domain::model::ingest_event::{CommonEvent, IngestEvent, IngestEventSource};
+
+/// Default number of recent ids that are remembered per source
+pub const DEFAULT_HIERARCHY_CACHE_CAPACITY: usize = 100_000;
+
+/// Default period that an unknown parent may arrive late in before the events
+/// referencing it are counted as orphans
+pub const DEFAULT_HIERARCHY_GRACE_PERIOD: Duration = Duration::minutes(5);
+
+/// Maximum number of sources that ids are remembered for. Events of further
+/// sources are not checked, so that the cache stays bounded even if events of
+/// sources that were never configured reach it.
+pub const HIERARCHY_CACHE_MAX_SOURCES: usize = 1024;
+
+/// `HierarchyLevel` is the position of an event in the `Visitor`, `Session`,
+/// `Section` hierarchy that other events can reference as their parent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HierarchyLevel {
+    Visitor,
+    Session,
+    Section,
+}
+
+impl HierarchyLevel {
+    /// Level and id of the event when it can be referenced as a parent
+    pub fn of(event: &IngestEvent) -> Option<(Self, Uuid)> {
+        match event {
+            IngestEvent::Visitor(e) => Some((Self::Visitor, e.id())),
+            IngestEvent::Session(e) => Some((Self::Session, e.id())),
+            IngestEvent::Section(e) => Some((Self::Section, e.id())),
+            IngestEvent::Click(_) | IngestEvent::Custom(_) | IngestEvent::Identify(_) => None,
+        }
+    }
+
+    /// Level and id of the parent that the event references
+    pub fn parent_of(event: &IngestEvent) -> Option<(Self, Uuid)> {
+        match event {
+            IngestEvent::Visitor(_) => None,
+            IngestEvent::Session(e) => Some((Self::Visitor, e.parent)),
+            IngestEvent::Section(e) => Some((Self::Session, e.parent)),
+            IngestEvent::Click(e) => Some((Self::Section, e.parent)),
+            IngestEvent::Custom(e) => Some((Self::Session, e.parent)),
+            IngestEvent::Identify(e) => Some((Self::Visitor, e.parent)),
+        }
+    }
+}
+
+/// `HierarchyStats` counts the parent references of the events of a source
+/// since the last report
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HierarchyStats {
+    /// Events that reference a parent
+    pub checked: u64,
+    /// Events whose parent was unknown but arrived within the grace period
+    pub late: u64,
+    /// Events whose parent did not arrive within the grace period
+    pub orphaned: u64,
+    /// Events whose parent is an event of the wrong type
+    pub mismatched: u64,
+}
+
+impl HierarchyStats {
+    /// Share of checked events that were orphaned or had a mismatched parent
+    pub fn orphan_rate(&self) -> f64 {
+        if self.checked == 0 {
+            return 0.0;
+        }
+        (self.orphaned + self.mismatched) as f64 / self.checked as f64
+    }
+}
+
+/// `HierarchyReport` holds the `HierarchyStats` of a single source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HierarchyReport {
+    pub source: IngestEventSource,
+    pub stats: HierarchyStats,
+}
+
+/// `HierarchyCheck` is the outcome of checking the parent of a single event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HierarchyCheck {
+    /// The event has no parent or its parent is known and of the right type
+    Valid,
+    /// The parent is not known yet and may still arrive late
+    Unknown,
+    /// The parent is known to be an event of the wrong type
+    Mismatched,
+}
+
+#[derive(Debug)]
+struct PendingParent {
+    level: HierarchyLevel,
+    since: OffsetDateTime,
+    events: u64,
+}
+
+#[derive(Debug, Default)]
+struct SourceHierarchy {
+    levels: HashMap<Uuid, HierarchyLevel>,
+    order: VecDeque<Uuid>,
+    pending: HashMap<Uuid, PendingParent>,
+    stats: HierarchyStats,
+}
+
+/// `HierarchyCache` remembers the ids of recently seen visitors, sessions and
+/// sections per source in order to validate the parents of incoming events.
+/// Each source keeps at most `capacity` ids, evicting the oldest first, and
+/// at most `HIERARCHY_CACHE_MAX_SOURCES` sources are kept.
+///
+/// As the cache is neither shared between servers nor kept across restarts,
+/// an unknown parent does not prove an orphan. Events with unknown parents
+/// are therefore still saved, while their parent is held as pending for the
+/// grace period and only counted as an orphan if it does not arrive in time.
+#[derive(Debug)]
+pub struct HierarchyCache {
+    capacity: usize,
+    grace_period: Duration,
+    sources: Mutex<HashMap<IngestEventSource, SourceHierarchy>>,
+}
+
+impl Default for HierarchyCache {
+    /// Uses `DEFAULT_HIERARCHY_CACHE_CAPACITY` and
+    /// `DEFAULT_HIERARCHY_GRACE_PERIOD`
+    fn default() -> Self {
+        Self {
+            capacity: DEFAULT_HIERARCHY_CACHE_CAPACITY,
+            grace_period: DEFAULT_HIERARCHY_GRACE_PERIOD,
+            sources: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl HierarchyCache {
+    /// Replace the number of ids that are remembered per source
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Replace the period that unknown parents may arrive late in
+    pub fn with_grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period = grace_period;
+        self
+    }
+
+    /// Record the events and check their parents. Parents within the same
+    /// batch are recognized regardless of order. Returns the `HierarchyCheck`
+    /// of each event in order, where events of sources beyond
+    /// `HIERARCHY_CACHE_MAX_SOURCES` are `Valid` as they are not checked.
+    pub fn observe(&self, events: &[IngestEvent]) -> Vec<HierarchyCheck> {
+        let now = OffsetDateTime::now_utc();
+        let mut sources = self
+            .sources
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        for event in events.iter() {
+            if let Some((level, id)) = HierarchyLevel::of(event)
+                && let Some(source) = Self::source_of(&mut sources, event)
+            {
+                self.remember(source, id, level);
+            }
+        }
+
+        events
+            .iter()
+            .map(|event| {
+                let Some((level, parent)) = HierarchyLevel::parent_of(event) else {
+                    return HierarchyCheck::Valid;
+                };
+                let Some(source) = Self::source_of(&mut sources, event) else {
+                    return HierarchyCheck::Valid;
+                };
+                source.stats.checked += 1;
+                match source.levels.get(&parent) {
+                    Some(known) if *known == level => HierarchyCheck::Valid,
+                    Some(_) => {
+                        source.stats.mismatched += 1;
+                        HierarchyCheck::Mismatched
+                    }
+                    None => {
+                        self.hold(source, parent, level, now);
+                        HierarchyCheck::Unknown
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// `HierarchyReport` of every source with checked events since the last
+    /// report, after which the stats are reset. Pending parents that are past
+    /// the grace period are counted as orphans first.
+    pub fn report(&self) -> Vec<HierarchyReport> {
+        let now = OffsetDateTime::now_utc();
+        let mut sources = self
+            .sources
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        sources
+            .iter_mut()
+            .filter_map(|(source, hierarchy)| {
+                hierarchy.pending.retain(|_, pending| {
+                    if now - pending.since <= self.grace_period {
+                        return true;
+                    }
+                    hierarchy.stats.orphaned += pending.events;
+                    false
+                });
+                if hierarchy.stats.checked == 0 {
+                    return None;
+                }
+                Some(HierarchyReport {
+                    source: source.clone(),
+                    stats: std::mem::take(&mut hierarchy.stats),
+                })
+            })
+            .collect()
+    }
+
+    /// `SourceHierarchy` of the event's source, which is only added while
+    /// fewer than `HIERARCHY_CACHE_MAX_SOURCES` sources are kept
+    fn source_of<'a>(
+        sources: &'a mut HashMap<IngestEventSource, SourceHierarchy>,
+        event: &IngestEvent,
+    ) -> Option<&'a mut SourceHierarchy> {
+        let source = IngestEventSource::from(event);
+        if !sources.contains_key(&source) && sources.len() >= HIERARCHY_CACHE_MAX_SOURCES {
+            return None;
+        }
+        Some(sources.entry(source).or_default())
+    }
+
+    /// Add the id to the source, resolving any pending references to it
+    fn remember(&self, source: &mut SourceHierarchy, id: Uuid, level: HierarchyLevel) {
+        if let Some(pending) = source.pending.remove(&id) {
+            if pending.level == level {
+                source.stats.late += pending.events;
+            } else {
+                source.stats.mismatched += pending.events;
+            }
+        }
+        if source.levels.insert(id, level).is_none() {
+            source.order.push_back(id);
+        }
+        while source.order.len() > self.capacity {
+            if let Some(evicted) = source.order.pop_front() {
+                source.levels.remove(&evicted);
+            }
+        }
+    }
+
+    /// Hold an unknown parent as pending. Once as many parents are pending as
+    /// ids are remembered, further references are counted as orphans.
+    fn hold(
+        &self,
+        source: &mut SourceHierarchy,
+        parent: Uuid,
+        level: HierarchyLevel,
+        now: OffsetDateTime,
+    ) {
+        if let Some(pending) = source.pending.get_mut(&parent) {
+            pending.events += 1;
+        } else if source.pending.len() < self.capacity {
+            source.pending.insert(
+                parent,
+                PendingParent {
+                    level,
+                    since: now,
+                    events: 1,
+                },
+            );
+        } else {
+            source.stats.orphaned += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::model::ingest_event::{
+        ApiKey, ClickEvent, SectionEvent, SessionEvent, Site, VisitorEvent,
+    };
+
+    const API_KEY: &str = "abc_123";
+    const SITE: &str = "test.com";
+
+    fn visitor(id: Uuid) -> IngestEvent {
+        IngestEvent::Visitor(
+            VisitorEvent::try_new(ApiKey::new(API_KEY), Site::new(SITE), id).unwrap(),
+        )
+    }
+
+    fn session_of(site: &str, parent: Uuid) -> IngestEvent {
+        IngestEvent::Session(
+            SessionEvent::try_new(
+                ApiKey::new(API_KEY),
+                Site::new(site),
+                Uuid::now_v7(),
+                parent,
+                "Mozilla/5.0".to_owned(),
+                "127.0.0.1".parse().unwrap(),
+            )
+            .unwrap(),
+        )
+    }
+
+    fn session(id: Uuid, parent: Uuid) -> IngestEvent {
+        IngestEvent::Session(
+            SessionEvent::try_new(
+                ApiKey::new(API_KEY),
+                Site::new(SITE),
+                id,
+                parent,
+                "Mozilla/5.0".to_owned(),
+                "127.0.0.1".parse().unwrap(),
+            )
+            .unwrap(),
+        )
+    }
+
+    fn section(id: Uuid, parent: Uuid) -> IngestEvent {
+        IngestEvent::Section(
+            SectionEvent::try_new(
+                ApiKey::new(API_KEY),
+                Site::new(SITE),
+                id,
+                parent,
+                None,
+                None,
+            )
+            .unwrap(),
+        )
+    }
+
+    fn click(id: Uuid, parent: Uuid) -> IngestEvent {
+        IngestEvent::Click(
+            ClickEvent::try_new(ApiKey::new(API_KEY), Site::new(SITE), id, parent).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_observe() {
+        let cache = HierarchyCache::default();
+        let (visitor_id, session_id, section_id) = (Uuid::now_v7(), Uuid::now_v7(), Uuid::now_v7());
+
+        assert_eq!(
+            cache.observe(&[
+                section(section_id, session_id),
+                session(session_id, visitor_id),
+                visitor(visitor_id),
+            ]),
+            vec![
+                HierarchyCheck::Valid,
+                HierarchyCheck::Valid,
+                HierarchyCheck::Valid
+            ],
+            "Expected parents within the batch to be recognized in any order"
+        );
+        assert_eq!(
+            cache.observe(&[click(Uuid::now_v7(), section_id)]),
+            vec![HierarchyCheck::Valid]
+        );
+        assert_eq!(
+            cache.observe(&[click(Uuid::now_v7(), session_id)]),
+            vec![HierarchyCheck::Mismatched],
+            "Expected a click under a session to be mismatched"
+        );
+
+        let late_session = Uuid::now_v7();
+        assert_eq!(
+            cache.observe(&[section(Uuid::now_v7(), late_session)]),
+            vec![HierarchyCheck::Unknown]
+        );
+        cache.observe(&[session(late_session, visitor_id)]);
+
+        let [report] = &cache.report()[..] else {
+            panic!("Expected a single report for the source");
+        };
+        assert_eq!(
+            report.stats,
+            HierarchyStats {
+                checked: 6,
+                late: 1,
+                orphaned: 0,
+                mismatched: 1,
+            }
+        );
+        assert_eq!(report.stats.orphan_rate(), 1.0 / 6.0);
+        assert!(cache.report().is_empty(), "Expected stats to be reset");
+    }
+
+    #[test]
+    fn test_orphans_and_eviction() {
+        let cache = HierarchyCache::default()
+            .with_capacity(1)
+            .with_grace_period(Duration::ZERO);
+        let (first, second) = (Uuid::now_v7(), Uuid::now_v7());
+        cache.observe(&[visitor(first), visitor(second)]);
+        assert_eq!(
+            cache.observe(&[session(Uuid::now_v7(), first)]),
+            vec![HierarchyCheck::Unknown],
+            "Expected the oldest id to be evicted"
+        );
+        cache.observe(&[session(Uuid::now_v7(), Uuid::now_v7())]);
+
+        let [report] = &cache.report()[..] else {
+            panic!("Expected a single report for the source");
+        };
+        assert_eq!(
+            report.stats,
+            HierarchyStats {
+                checked: 2,
+                late: 0,
+                orphaned: 2,
+                mismatched: 0,
+            },
+            "Expected pending parents past the grace period and overflow to be orphans"
+        );
+    }
+
+    #[test]
+    fn test_max_sources() {
+        let cache = HierarchyCache::default();
+        for site in 0..HIERARCHY_CACHE_MAX_SOURCES {
+            cache.observe(&[session_of(&format!("{site}.test.com"), Uuid::now_v7())]);
+        }
+        assert_eq!(
+            cache.observe(&[session_of("overflow.test.com", Uuid::now_v7())]),
+            vec![HierarchyCheck::Valid],
+            "Expected events of sources beyond the maximum to not be checked"
+        );
+        let reports = cache.report();
+        assert_eq!(reports.len(), HIERARCHY_CACHE_MAX_SOURCES);
+        assert!(
+            reports
+                .iter()
+                .all(|report| report.source.site().value() != "overflow.test.com"),
+            "Expected sources beyond the maximum to not be kept"
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// `IdentityKey` is the per-source secret that user ids of `IdentifyEvent`s
+/// are hashed with before they are stored. Using a keyed HMAC rather than a
+/// plain hash means that a known user id cannot be looked up in the stored
+/// data without the key, and that the same user id yields unrelated hashes for
+/// sources with different keys. The same holds for the visitor keys from which
+/// the `SessionSynthesizer` derives visitor ids.
+#[derive(Clone, PartialEq, Eq)]
+pub struct IdentityKey {
+    secret: String,
+}
+
+impl IdentityKey {
+    /// `IdentityKey` constructor
+    pub fn new(secret: impl AsRef<str>) -> Self {
+        Self {
+            secret: secret.as_ref().trim().to_owned(),
+        }
+    }
+
+    /// `true` when no secret was supplied
+    pub fn is_empty(&self) -> bool {
+        self.secret.is_empty()
+    }
+
+    /// Lowercase hex encoded HMAC-SHA256 of the user id
+    pub fn pseudonymize(&self, user_id: &str) -> String {
+        self.digest(user_id)
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+
+    /// HMAC-SHA256 of the value
+    pub fn digest(&self, value: &str) -> [u8; 32] {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(value.as_bytes());
+        mac.finalize().into_bytes().into()
+    }
+}
+
+/// The secret is deliberately left out so that it never ends up in logs
+impl std::fmt::Debug for IdentityKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IdentityKey").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pseudonymize() {
+        let identity_key = IdentityKey::new(" key ");
+        assert_eq!(
+            identity_key.pseudonymize("The quick brown fox jumps over the lazy dog"),
+            "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8",
+            "Expected trimmed key to be used for HMAC-SHA256"
+        );
+        assert_ne!(
+            IdentityKey::new("other").pseudonymize("user-1"),
+            identity_key.pseudonymize("user-1"),
+            "Expected different keys to yield different hashes"
+        );
+        assert!(
+            !format!("{:?}", IdentityKey::new("hunter2")).contains("hunter2"),
+            "Expected secret to be omitted from debug output"
+        );
+        assert!(IdentityKey::new("  ").is_empty());
+    }
+}
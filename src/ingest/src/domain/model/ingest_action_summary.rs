@@ -13,11 +13,17 @@ pub enum IngestActionSummary {
 pub struct IngestEventSaveSummary {
     /// `event_count` is the number of events that were saved in this call.
     pub event_count: usize,
+    /// `duplicate_count` is the number of events that were skipped because
+    /// they had already been saved.
+    pub duplicate_count: usize,
 }
 
 impl IngestEventSaveSummary {
     /// `IngestEventSaveSummary` constructor
     pub fn new(event_count: usize) -> Self {
-        Self { event_count }
+        Self {
+            event_count,
+            duplicate_count: 0,
+        }
     }
 }
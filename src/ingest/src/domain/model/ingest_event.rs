@@ -1,10 +1,13 @@
-use std::net::IpAddr;
+use std::{collections::HashMap, net::IpAddr};
 
 use thiserror::Error;
-use time::OffsetDateTime;
+use time::{Duration, OffsetDateTime};
 use uuid::Uuid;
 
-use crate::domain::model::util::{is_ts_within_ingest_range, try_uuid_datetime};
+use crate::domain::model::identity_key::IdentityKey;
+use crate::domain::model::util::{
+    MAX_DURATION_AFTER_PRESENT, MAX_DURATION_BEFORE_PRESENT, is_ts_within_range, try_uuid_datetime,
+};
 
 /// `IngestEventError` represents the  potential domain error cases for
 /// `IngestEvent`. This is strictly due to domain rules, not infrastructure
@@ -21,6 +24,10 @@ pub enum IngestEventError {
     UuidVersion,
     #[error("UUID timestamp conversion error")]
     UuidTimestampConversion,
+    #[error("custom event name was empty")]
+    CustomEventName,
+    #[error("identify user id was empty")]
+    UserId,
 }
 
 /// `IngestEvent` is the domain model for all metrics that the system is able
@@ -32,6 +39,92 @@ pub enum IngestEvent {
     Session(SessionEvent),
     Section(SectionEvent),
     Click(ClickEvent),
+    Custom(CustomEvent),
+    Identify(IdentifyEvent),
+}
+
+impl IngestEvent {
+    /// Id of the wrapped event
+    pub fn id(&self) -> Uuid {
+        match self {
+            IngestEvent::Visitor(event) => event.id(),
+            IngestEvent::Session(event) => event.id(),
+            IngestEvent::Section(event) => event.id(),
+            IngestEvent::Click(event) => event.id(),
+            IngestEvent::Custom(event) => event.id(),
+            IngestEvent::Identify(event) => event.id(),
+        }
+    }
+}
+
+/// `IngestEventOrigin` records which pathway an event arrived through. Events
+/// from untrusted browser clients are held to a narrow timestamp window, while
+/// authenticated server-to-server events are allowed a wider one and imported
+/// historical events may be arbitrarily old.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum IngestEventOrigin {
+    /// Untrusted client such as the browser script
+    #[default]
+    Client,
+    /// Trusted server-to-server request authenticated with a `ServerKey`
+    Server,
+    /// Historical event backfilled from another analytics tool or log
+    Import,
+}
+
+impl IngestEventOrigin {
+    /// `IngestEventWindow` that an event from this origin must fall within
+    pub fn window(&self) -> IngestEventWindow {
+        match self {
+            IngestEventOrigin::Client => IngestEventWindow::CLIENT,
+            IngestEventOrigin::Server => IngestEventWindow::SERVER,
+            IngestEventOrigin::Import => IngestEventWindow::IMPORT,
+        }
+    }
+
+    /// Name of the origin as recorded alongside persisted events
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IngestEventOrigin::Client => "client",
+            IngestEventOrigin::Server => "server",
+            IngestEventOrigin::Import => "import",
+        }
+    }
+}
+
+/// `IngestEventWindow` bounds how far before and after the present the
+/// timestamp of an event may be in order to be accepted for ingestion
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IngestEventWindow {
+    /// Earliest accepted timestamp relative to now
+    pub before: Duration,
+    /// Latest accepted timestamp relative to now
+    pub after: Duration,
+}
+
+impl IngestEventWindow {
+    /// Window for events from untrusted clients
+    pub const CLIENT: Self = Self {
+        before: MAX_DURATION_BEFORE_PRESENT,
+        after: MAX_DURATION_AFTER_PRESENT,
+    };
+    /// Window for events from trusted servers, which may queue or retry
+    /// events for some time before delivering them
+    pub const SERVER: Self = Self {
+        before: Duration::days(3),
+        after: MAX_DURATION_AFTER_PRESENT,
+    };
+    /// Window for imported historical events. A century reaches back past the
+    /// Unix epoch, which is the earliest timestamp a UUIDv7 can carry.
+    pub const IMPORT: Self = Self {
+        before: Duration::days(36_525),
+        after: MAX_DURATION_AFTER_PRESENT,
+    };
+
+    /// Determine whether the supplied timestamp falls within this window
+    pub fn contains(&self, ts: &OffsetDateTime) -> bool {
+        is_ts_within_range(ts, self.before, self.after)
+    }
 }
 
 /// `ApiKey` newtype wrapper for the api_key string
@@ -93,10 +186,33 @@ where
     }
 }
 
+impl From<&IngestEvent> for IngestEventSource {
+    fn from(value: &IngestEvent) -> Self {
+        match value {
+            IngestEvent::Visitor(event) => Self::from(&event),
+            IngestEvent::Session(event) => Self::from(&event),
+            IngestEvent::Section(event) => Self::from(&event),
+            IngestEvent::Click(event) => Self::from(&event),
+            IngestEvent::Custom(event) => Self::from(&event),
+            IngestEvent::Identify(event) => Self::from(&event),
+        }
+    }
+}
+
 impl IngestEventSource {
     pub fn new(api_key: ApiKey, site: Site) -> Self {
         Self { api_key, site }
     }
+
+    /// `ApiKey` of this source
+    pub fn api_key(&self) -> &ApiKey {
+        &self.api_key
+    }
+
+    /// `Site` of this source
+    pub fn site(&self) -> &Site {
+        &self.site
+    }
 }
 
 /// `CommonEvent` trait is used to represent the common attributes that all
@@ -110,6 +226,8 @@ pub trait CommonEvent {
     fn id(&self) -> Uuid;
     /// Retrieve the `OffsetDateTime` timestamp for this event
     fn ts(&self) -> &OffsetDateTime;
+    /// Retrieve the `IngestEventOrigin` for this event
+    fn origin(&self) -> IngestEventOrigin;
 }
 
 /// `VisitorEvent` represents a an event where an unrecognized user begins to
@@ -126,9 +244,11 @@ pub struct VisitorEvent {
     /// for ingestion.
     id: Uuid,
     /// `ts` is the timestamp, represented as a
-    /// `time::offset_date_time::OffsetDateTime` value. This is strictly derived
-    /// from the `id` field above
+    /// `time::offset_date_time::OffsetDateTime` value. This is derived from
+    /// the `id` field above unless the event was created with a given time
     ts: OffsetDateTime,
+    /// `origin` records which pathway this event arrived through
+    origin: IngestEventOrigin,
 }
 
 impl CommonEvent for &VisitorEvent {
@@ -144,6 +264,9 @@ impl CommonEvent for &VisitorEvent {
     fn ts(&self) -> &OffsetDateTime {
         &self.ts
     }
+    fn origin(&self) -> IngestEventOrigin {
+        self.origin
+    }
 }
 
 impl VisitorEvent {
@@ -154,12 +277,13 @@ impl VisitorEvent {
 
     /// `VisitorEvent` constructor with `IngestEventCore` already created for
     /// convenience or ergonomics
-    fn try_new_with_core_event(core: IngestEventCore) -> Result<Self, IngestEventError> {
+    pub fn try_new_with_core_event(core: IngestEventCore) -> Result<Self, IngestEventError> {
         Ok(Self {
             api_key: core.api_key,
             id: core.id,
             site: core.site,
             ts: core.ts,
+            origin: core.origin,
         })
     }
 }
@@ -176,15 +300,19 @@ pub struct SessionEvent {
     /// for ingestion.
     id: Uuid,
     /// `ts` is the timestamp, represented as a
-    /// `time::offset_date_time::OffsetDateTime` value. This is strictly derived
-    /// from the `id` field above
+    /// `time::offset_date_time::OffsetDateTime` value. This is derived from
+    /// the `id` field above unless the event was created with a given time
     ts: OffsetDateTime,
+    /// `origin` records which pathway this event arrived through
+    origin: IngestEventOrigin,
     /// `parent` identifies the `Visitor` which this session is associated with
     pub parent: Uuid,
     /// `user_agent` records the user agent/system on which the event originated
     pub user_agent: String,
     /// `ip` records the ip address that this event originated from
     pub ip: IpAddr,
+    /// `referrer` is the page that led to the start of this session, if known
+    pub referrer: Option<String>,
 }
 
 impl CommonEvent for &SessionEvent {
@@ -200,6 +328,9 @@ impl CommonEvent for &SessionEvent {
     fn ts(&self) -> &OffsetDateTime {
         &self.ts
     }
+    fn origin(&self) -> IngestEventOrigin {
+        self.origin
+    }
 }
 
 impl SessionEvent {
@@ -222,7 +353,7 @@ impl SessionEvent {
 
     /// `SessionEvent` constructor with `IngestEventCore` already created for
     /// convenience or ergonomics
-    fn try_new_with_core_event(
+    pub fn try_new_with_core_event(
         core: IngestEventCore,
         parent: Uuid,
         user_agent: String,
@@ -233,11 +364,19 @@ impl SessionEvent {
             id: core.id,
             site: core.site,
             ts: core.ts,
+            origin: core.origin,
             parent,
             user_agent,
             ip,
+            referrer: None,
         })
     }
+
+    /// Set the `referrer` that led to this session
+    pub fn with_referrer(mut self, referrer: Option<String>) -> Self {
+        self.referrer = referrer;
+        self
+    }
 }
 
 /// `SectionEvent` represents an event for which the associated Visitor in a
@@ -256,9 +395,11 @@ pub struct SectionEvent {
     /// for ingestion.
     pub id: Uuid,
     /// `ts` is the timestamp, represented as a
-    /// `time::offset_date_time::OffsetDateTime` value. This is strictly derived
-    /// from the `id` field above
+    /// `time::offset_date_time::OffsetDateTime` value. This is derived from
+    /// the `id` field above unless the event was created with a given time
     ts: OffsetDateTime,
+    /// `origin` records which pathway this event arrived through
+    origin: IngestEventOrigin,
     /// `parent` identifies the `Session` which this section is associated with
     pub parent: Uuid,
     /// `location` specifies the full location string portion of the URI
@@ -266,6 +407,10 @@ pub struct SectionEvent {
     pub location: Option<String>,
     /// `title` identifies the title of the section, if it exists
     pub title: Option<String>,
+    /// `path` is the normalized path derived from `location` according to the
+    /// `PathNormalizationRules` of the source. It is populated during
+    /// ingestion rather than supplied by the client
+    pub path: Option<String>,
 }
 
 impl CommonEvent for &SectionEvent {
@@ -281,6 +426,9 @@ impl CommonEvent for &SectionEvent {
     fn ts(&self) -> &OffsetDateTime {
         &self.ts
     }
+    fn origin(&self) -> IngestEventOrigin {
+        self.origin
+    }
 }
 
 impl SectionEvent {
@@ -303,7 +451,7 @@ impl SectionEvent {
 
     /// `SectionEvent` constructor with `IngestEventCore` already created for
     /// convenience or ergonomics
-    fn try_new_with_core_event(
+    pub fn try_new_with_core_event(
         core: IngestEventCore,
         parent: Uuid,
         location: Option<String>,
@@ -314,9 +462,11 @@ impl SectionEvent {
             id: core.id,
             site: core.site,
             ts: core.ts,
+            origin: core.origin,
             parent,
             location,
             title,
+            path: None,
         })
     }
 }
@@ -334,9 +484,11 @@ pub struct ClickEvent {
     /// for ingestion.
     id: Uuid,
     /// `ts` is the timestamp, represented as a
-    /// `time::offset_date_time::OffsetDateTime` value. This is strictly derived
-    /// from the `id` field above
+    /// `time::offset_date_time::OffsetDateTime` value. This is derived from
+    /// the `id` field above unless the event was created with a given time
     ts: OffsetDateTime,
+    /// `origin` records which pathway this event arrived through
+    origin: IngestEventOrigin,
     /// `parent` identifies the `Section` which this click is associated with
     pub parent: Uuid,
 }
@@ -354,6 +506,9 @@ impl CommonEvent for &ClickEvent {
     fn ts(&self) -> &OffsetDateTime {
         &self.ts
     }
+    fn origin(&self) -> IngestEventOrigin {
+        self.origin
+    }
 }
 
 impl ClickEvent {
@@ -369,20 +524,251 @@ impl ClickEvent {
 
     /// `ClickEvent` constructor with `IngestEventCore` already created for
     /// convenience or ergonomics
-    fn try_new_with_core_event(
+    pub fn try_new_with_core_event(
+        core: IngestEventCore,
+        parent: Uuid,
+    ) -> Result<Self, IngestEventError> {
+        Ok(Self {
+            api_key: core.api_key,
+            id: core.id,
+            site: core.site,
+            ts: core.ts,
+            origin: core.origin,
+            parent,
+        })
+    }
+}
+
+/// `CustomEvent` represents a named occurrence within a `Session` that is not
+/// covered by the other event types, such as a signup or a download. Custom
+/// events are mostly received through the compatibility APIs of other
+/// analytics tools, which is why the free form `props` are kept as strings.
+#[derive(Debug, Clone)]
+pub struct CustomEvent {
+    /// `api_key` that ties this event to a particular client and site
+    api_key: ApiKey,
+    /// `site` is the site from which this event is coming. i.e. www.test.com
+    site: Site,
+    /// `id` is a `Uuid` that must be a UUIDv7 and must have an associated
+    /// timestamp within a certain range of now in order to be considered valid
+    /// for ingestion.
+    id: Uuid,
+    /// `ts` is the timestamp, represented as a
+    /// `time::offset_date_time::OffsetDateTime` value. This is derived from
+    /// the `id` field above unless the event was created with a given time
+    ts: OffsetDateTime,
+    /// `origin` records which pathway this event arrived through
+    origin: IngestEventOrigin,
+    /// `parent` identifies the `Session` which this event is associated with
+    pub parent: Uuid,
+    /// `name` of the event, which must not be empty
+    pub name: String,
+    /// `location` of the section on which the event happened, if it exists
+    pub location: Option<String>,
+    /// `props` are the free form properties of the event
+    pub props: HashMap<String, String>,
+}
+
+impl CommonEvent for &CustomEvent {
+    fn api_key(&self) -> &ApiKey {
+        &self.api_key
+    }
+    fn id(&self) -> Uuid {
+        self.id
+    }
+    fn site(&self) -> &Site {
+        &self.site
+    }
+    fn ts(&self) -> &OffsetDateTime {
+        &self.ts
+    }
+    fn origin(&self) -> IngestEventOrigin {
+        self.origin
+    }
+}
+
+impl CustomEvent {
+    /// `CustomEvent` all field constructor
+    pub fn try_new(
+        api_key: ApiKey,
+        site: Site,
+        id: Uuid,
+        parent: Uuid,
+        name: String,
+        location: Option<String>,
+        props: HashMap<String, String>,
+    ) -> Result<Self, IngestEventError> {
+        Self::try_new_with_core_event(
+            IngestEventCore::try_new(api_key, site, id)?,
+            parent,
+            name,
+            location,
+            props,
+        )
+    }
+
+    /// `CustomEvent` constructor with `IngestEventCore` already created for
+    /// convenience or ergonomics
+    pub fn try_new_with_core_event(
         core: IngestEventCore,
         parent: Uuid,
+        name: String,
+        location: Option<String>,
+        props: HashMap<String, String>,
     ) -> Result<Self, IngestEventError> {
+        if name.trim().is_empty() {
+            return Err(IngestEventError::CustomEventName);
+        }
         Ok(Self {
             api_key: core.api_key,
             id: core.id,
             site: core.site,
             ts: core.ts,
+            origin: core.origin,
             parent,
+            name,
+            location,
+            props,
         })
     }
 }
 
+/// `IdentifyEvent` associates a `Visitor` with a known user of the site, for
+/// example after logging in, so that the same user can be recognized across
+/// devices. The `user_id` is only held in clear until the event is
+/// pseudonymized with the `IdentityKey` of its source, which must happen
+/// before it is stored.
+#[derive(Clone)]
+pub struct IdentifyEvent {
+    /// `api_key` that ties this event to a particular client and site
+    api_key: ApiKey,
+    /// `site` is the site from which this event is coming. i.e. www.test.com
+    site: Site,
+    /// `id` is a `Uuid` that must be a UUIDv7 and must have an associated
+    /// timestamp within a certain range of now in order to be considered valid
+    /// for ingestion.
+    id: Uuid,
+    /// `ts` is the timestamp, represented as a
+    /// `time::offset_date_time::OffsetDateTime` value. This is derived from
+    /// the `id` field above unless the event was created with a given time
+    ts: OffsetDateTime,
+    /// `origin` records which pathway this event arrived through
+    origin: IngestEventOrigin,
+    /// `parent` identifies the `Visitor` which this event is associated with
+    pub parent: Uuid,
+    /// `user_id` as received or, once pseudonymized, its hash
+    user_id: String,
+    /// `pseudonymized` is `true` once `user_id` holds the hash
+    pseudonymized: bool,
+    /// `traits` are the free form attributes of the user
+    pub traits: HashMap<String, String>,
+}
+
+/// `IdentifyEvent` only shows the `user_id` once it was pseudonymized, so
+/// that the user id never reaches spans or logs in clear
+impl std::fmt::Debug for IdentifyEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IdentifyEvent")
+            .field("api_key", &self.api_key)
+            .field("site", &self.site)
+            .field("id", &self.id)
+            .field("ts", &self.ts)
+            .field("origin", &self.origin)
+            .field("parent", &self.parent)
+            .field("user_hash", &self.user_hash().unwrap_or("[REDACTED]"))
+            .field("traits", &self.traits)
+            .finish()
+    }
+}
+
+impl CommonEvent for &IdentifyEvent {
+    fn api_key(&self) -> &ApiKey {
+        &self.api_key
+    }
+    fn id(&self) -> Uuid {
+        self.id
+    }
+    fn site(&self) -> &Site {
+        &self.site
+    }
+    fn ts(&self) -> &OffsetDateTime {
+        &self.ts
+    }
+    fn origin(&self) -> IngestEventOrigin {
+        self.origin
+    }
+}
+
+impl IdentifyEvent {
+    /// `IdentifyEvent` all field constructor
+    pub fn try_new(
+        api_key: ApiKey,
+        site: Site,
+        id: Uuid,
+        parent: Uuid,
+        user_id: String,
+        traits: HashMap<String, String>,
+    ) -> Result<Self, IngestEventError> {
+        Self::try_new_with_core_event(
+            IngestEventCore::try_new(api_key, site, id)?,
+            parent,
+            user_id,
+            traits,
+        )
+    }
+
+    /// `IdentifyEvent` constructor with `IngestEventCore` already created for
+    /// convenience or ergonomics
+    pub fn try_new_with_core_event(
+        core: IngestEventCore,
+        parent: Uuid,
+        user_id: String,
+        traits: HashMap<String, String>,
+    ) -> Result<Self, IngestEventError> {
+        if user_id.trim().is_empty() {
+            return Err(IngestEventError::UserId);
+        }
+        Ok(Self {
+            api_key: core.api_key,
+            id: core.id,
+            site: core.site,
+            ts: core.ts,
+            origin: core.origin,
+            parent,
+            user_id,
+            pseudonymized: false,
+            traits,
+        })
+    }
+
+    /// `IdentifyEvent` constructor for an event whose user id was already
+    /// pseudonymized, such as one restored from an archive
+    pub fn try_new_pseudonymized(
+        core: IngestEventCore,
+        parent: Uuid,
+        user_hash: String,
+        traits: HashMap<String, String>,
+    ) -> Result<Self, IngestEventError> {
+        let mut event = Self::try_new_with_core_event(core, parent, user_hash, traits)?;
+        event.pseudonymized = true;
+        Ok(event)
+    }
+
+    /// Replace the `user_id` with its hash under the `IdentityKey`. Has no
+    /// effect once the event was pseudonymized.
+    pub fn pseudonymize(&mut self, identity_key: &IdentityKey) {
+        if !self.pseudonymized {
+            self.user_id = identity_key.pseudonymize(&self.user_id);
+            self.pseudonymized = true;
+        }
+    }
+
+    /// Hash of the user id, which is only available once pseudonymized
+    pub fn user_hash(&self) -> Option<&str> {
+        self.pseudonymized.then_some(self.user_id.as_str())
+    }
+}
+
 /// `IngestEventCore` represents the common fields that all events have like
 /// `api_key`, `id` and `ts`.
 ///
@@ -392,7 +778,7 @@ impl ClickEvent {
 /// given ingestion event must be within a specified duration of now, or else
 /// an error will be returned during attempted construction.
 #[derive(Debug, Clone)]
-struct IngestEventCore {
+pub struct IngestEventCore {
     /// `api_key` that ties this event to a particular client and site
     api_key: ApiKey,
     /// `site` is the site from which this event is coming. i.e. www.test.com
@@ -402,15 +788,75 @@ struct IngestEventCore {
     /// for ingestion.
     id: Uuid,
     /// `ts` is the timestamp, represented as a
-    /// `time::offset_date_time::OffsetDateTime` value. This is strictly derived
-    /// from the `id` field above
+    /// `time::offset_date_time::OffsetDateTime` value. This is derived from
+    /// the `id` field above unless the event was created with a given time
     ts: OffsetDateTime,
+    /// `origin` records which pathway this event arrived through
+    origin: IngestEventOrigin,
 }
 
 impl IngestEventCore {
-    /// `IngestEventCore` constructor. Enforces domain rules with regard to
-    /// `id` UUID type as well as the allowed range of times for events.
+    /// `IngestEventCore` constructor for events from untrusted clients.
+    /// Enforces domain rules with regard to `id` UUID type as well as the
+    /// allowed range of times for events.
     pub fn try_new(api_key: ApiKey, site: Site, id: Uuid) -> Result<Self, IngestEventError> {
+        Self::try_new_with_origin(api_key, site, id, IngestEventOrigin::Client)
+    }
+
+    /// `IngestEventCore` constructor for events from the given
+    /// `IngestEventOrigin`. The timestamp of the `id` must fall within the
+    /// `IngestEventWindow` of the origin.
+    pub fn try_new_with_origin(
+        api_key: ApiKey,
+        site: Site,
+        id: Uuid,
+        origin: IngestEventOrigin,
+    ) -> Result<Self, IngestEventError> {
+        Self::try_new_checked(api_key, site, id, None, origin)
+    }
+
+    /// `IngestEventCore` constructor for an event whose `id` is derived from
+    /// something other than the time it happened, such as a visitor that is
+    /// synthesized for a client id. The `id` must still be a UUIDv7, but the
+    /// timestamp is given as `ts` and must fall within the
+    /// `IngestEventWindow` of the origin.
+    pub fn try_new_at(
+        api_key: ApiKey,
+        site: Site,
+        id: Uuid,
+        ts: OffsetDateTime,
+        origin: IngestEventOrigin,
+    ) -> Result<Self, IngestEventError> {
+        Self::try_new_checked(api_key, site, id, Some(ts), origin)
+    }
+
+    /// `IngestEventCore` constructor for an event that was accepted before
+    /// and is restored from an archive along with its `ts`. The event keeps
+    /// its `origin`, but as it may be replayed long after it arrived, its
+    /// timestamp only needs to fall within the `IngestEventWindow` of
+    /// imported events.
+    pub fn try_new_archived(
+        api_key: ApiKey,
+        site: Site,
+        id: Uuid,
+        ts: OffsetDateTime,
+        origin: IngestEventOrigin,
+    ) -> Result<Self, IngestEventError> {
+        Ok(Self {
+            origin,
+            ..Self::try_new_at(api_key, site, id, ts, IngestEventOrigin::Import)?
+        })
+    }
+
+    /// Checks the domain rules of every constructor, deriving the timestamp
+    /// from the `id` unless one is given
+    fn try_new_checked(
+        api_key: ApiKey,
+        site: Site,
+        id: Uuid,
+        ts: Option<OffsetDateTime>,
+        origin: IngestEventOrigin,
+    ) -> Result<Self, IngestEventError> {
         if api_key.value().trim().is_empty() {
             return Err(IngestEventError::ApiKey);
         }
@@ -418,14 +864,16 @@ impl IngestEventCore {
             return Err(IngestEventError::Site);
         }
 
-        let ts = try_uuid_datetime(id)?;
+        let id_ts = try_uuid_datetime(id)?;
+        let ts = ts.unwrap_or(id_ts);
 
-        if is_ts_within_ingest_range(&ts) {
+        if origin.window().contains(&ts) {
             Ok(Self {
                 api_key,
                 site,
                 id,
                 ts,
+                origin,
             })
         } else {
             Err(IngestEventError::TimestampOutOfRange)
@@ -472,6 +920,128 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_core_event_origin_window() {
+        let (ts_now, _) = Uuid::now_v7().get_timestamp().unwrap().to_unix();
+        let two_hours_ago = Uuid::new_v7(Timestamp::from_unix_time(ts_now - 7200, 0, 0, 8));
+        assert_eq!(
+            IngestEventCore::try_new(ApiKey::new(API_KEY_STR), Site::new(SITE), two_hours_ago)
+                .unwrap_err(),
+            IngestEventError::TimestampOutOfRange,
+            "Expected client window to reject a two hour old event"
+        );
+        let server_core = IngestEventCore::try_new_with_origin(
+            ApiKey::new(API_KEY_STR),
+            Site::new(SITE),
+            two_hours_ago,
+            IngestEventOrigin::Server,
+        )
+        .unwrap();
+        let visitor = VisitorEvent::try_new_with_core_event(server_core).unwrap();
+        assert_eq!(
+            (&visitor).origin(),
+            IngestEventOrigin::Server,
+            "Expected origin to be carried from the core to the event"
+        );
+
+        let four_days_ago = Uuid::new_v7(Timestamp::from_unix_time(ts_now - 345600, 0, 0, 8));
+        assert_eq!(
+            IngestEventCore::try_new_with_origin(
+                ApiKey::new(API_KEY_STR),
+                Site::new(SITE),
+                four_days_ago,
+                IngestEventOrigin::Server,
+            )
+            .unwrap_err(),
+            IngestEventError::TimestampOutOfRange,
+            "Expected server window to reject a four day old event"
+        );
+
+        let five_years_ago =
+            Uuid::new_v7(Timestamp::from_unix_time(ts_now - 5 * 365 * 86400, 0, 0, 8));
+        let import_core = IngestEventCore::try_new_with_origin(
+            ApiKey::new(API_KEY_STR),
+            Site::new(SITE),
+            five_years_ago,
+            IngestEventOrigin::Import,
+        )
+        .unwrap();
+        let imported_visitor = VisitorEvent::try_new_with_core_event(import_core).unwrap();
+        assert_eq!(
+            (&imported_visitor).origin(),
+            IngestEventOrigin::Import,
+            "Expected import window to accept a five year old event"
+        );
+        let archived_core = IngestEventCore::try_new_archived(
+            ApiKey::new(API_KEY_STR),
+            Site::new(SITE),
+            five_years_ago,
+            try_uuid_datetime(five_years_ago).unwrap(),
+            IngestEventOrigin::Client,
+        )
+        .unwrap();
+        assert_eq!(
+            archived_core.origin,
+            IngestEventOrigin::Client,
+            "Expected an archived event to keep its origin"
+        );
+        let tomorrow = Uuid::new_v7(Timestamp::from_unix_time(ts_now + 86400, 0, 0, 8));
+        assert_eq!(
+            IngestEventCore::try_new_with_origin(
+                ApiKey::new(API_KEY_STR),
+                Site::new(SITE),
+                tomorrow,
+                IngestEventOrigin::Import,
+            )
+            .unwrap_err(),
+            IngestEventError::TimestampOutOfRange,
+            "Expected import window to reject a future event"
+        );
+    }
+
+    #[test]
+    fn test_core_event_at() {
+        // An id far outside the window is accepted along with a recent time
+        let keyed_id = uuid::Builder::from_unix_timestamp_millis(0, &[7; 10]).into_uuid();
+        let now = OffsetDateTime::now_utc();
+        let keyed_core = IngestEventCore::try_new_at(
+            ApiKey::new(API_KEY_STR),
+            Site::new(SITE),
+            keyed_id,
+            now,
+            IngestEventOrigin::Client,
+        )
+        .unwrap();
+        assert_eq!(keyed_core.id, keyed_id);
+        assert_eq!(keyed_core.ts, now, "Expected the given time to be kept");
+
+        // Negative test cases
+        assert_eq!(
+            IngestEventCore::try_new_at(
+                ApiKey::new(API_KEY_STR),
+                Site::new(SITE),
+                keyed_id,
+                now - Duration::hours(2),
+                IngestEventOrigin::Client,
+            )
+            .unwrap_err(),
+            IngestEventError::TimestampOutOfRange,
+            "Expected the given time to be checked against the window"
+        );
+        assert_eq!(
+            IngestEventCore::try_new_at(
+                ApiKey::new(API_KEY_STR),
+                Site::new(SITE),
+                Uuid::parse_str(UUID_V4_STR).unwrap(),
+                now,
+                IngestEventOrigin::Client,
+            )
+            .unwrap_err(),
+            IngestEventError::UuidVersion,
+            "Expected a UUIDv7 to still be required"
+        );
+    }
+
     #[test]
     fn test_try_new_events() {
         let uuid_now = Uuid::now_v7();
@@ -553,5 +1123,73 @@ mod tests {
         ) else {
             panic!("Expected valid ClickEvent");
         };
+
+        let Ok(_) = CustomEvent::try_new(
+            ApiKey::new(API_KEY_STR),
+            Site::new(SITE),
+            Uuid::now_v7(),
+            Uuid::now_v7(),
+            "signup".to_owned(),
+            Some("https://test.com/join".to_owned()),
+            HashMap::from([("plan".to_owned(), "pro".to_owned())]),
+        ) else {
+            panic!("Expected valid CustomEvent");
+        };
+        assert_eq!(
+            CustomEvent::try_new(
+                ApiKey::new(API_KEY_STR),
+                Site::new(SITE),
+                Uuid::now_v7(),
+                Uuid::now_v7(),
+                " ".to_owned(),
+                None,
+                HashMap::new(),
+            )
+            .unwrap_err(),
+            IngestEventError::CustomEventName,
+            "Expected CustomEventName error"
+        );
+
+        let Ok(mut identify) = IdentifyEvent::try_new(
+            ApiKey::new(API_KEY_STR),
+            Site::new(SITE),
+            Uuid::now_v7(),
+            Uuid::now_v7(),
+            "user-1".to_owned(),
+            HashMap::from([("plan".to_owned(), "pro".to_owned())]),
+        ) else {
+            panic!("Expected valid IdentifyEvent");
+        };
+        assert_eq!(
+            identify.user_hash(),
+            None,
+            "Expected no hash before pseudonymizing"
+        );
+        assert!(
+            !format!("{identify:?}").contains("user-1"),
+            "Expected the user id to not be shown in clear"
+        );
+        let identity_key = IdentityKey::new("key");
+        identify.pseudonymize(&identity_key);
+        identify.pseudonymize(&identity_key);
+        assert_eq!(
+            identify.user_hash(),
+            Some(identity_key.pseudonymize("user-1").as_str()),
+            "Expected user id to be hashed exactly once"
+        );
+        assert!(format!("{identify:?}").contains(identify.user_hash().unwrap()));
+        assert_eq!(
+            IdentifyEvent::try_new(
+                ApiKey::new(API_KEY_STR),
+                Site::new(SITE),
+                Uuid::now_v7(),
+                Uuid::now_v7(),
+                "".to_owned(),
+                HashMap::new(),
+            )
+            .unwrap_err(),
+            IngestEventError::UserId,
+            "Expected UserId error"
+        );
     }
 }
@@ -0,0 +1,109 @@
+use std::collections::HashSet;
+
+use crate::domain::model::{identity_key::IdentityKey, path_normalizer::PathNormalizationRules};
+
+/// `QueryParamPolicy` determines which query parameters of a location are
+/// kept for a given `IngestEventSource`. When an `allow` list is present, only
+/// the listed parameters survive. Any parameter in the `deny` list is always
+/// removed, even if it is also allowed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryParamPolicy {
+    allow: Option<HashSet<String>>,
+    deny: HashSet<String>,
+}
+
+impl QueryParamPolicy {
+    /// `QueryParamPolicy` constructor
+    pub fn new(allow: Option<HashSet<String>>, deny: HashSet<String>) -> Self {
+        Self { allow, deny }
+    }
+
+    /// Add a single parameter name to the allow list, creating the list if it
+    /// does not yet exist
+    pub fn allow(&mut self, param: impl AsRef<str>) {
+        self.allow
+            .get_or_insert_with(HashSet::new)
+            .insert(param.as_ref().to_owned());
+    }
+
+    /// Add a single parameter name to the deny list
+    pub fn deny(&mut self, param: impl AsRef<str>) {
+        self.deny.insert(param.as_ref().to_owned());
+    }
+
+    /// Determine whether a query parameter with the given name should be kept
+    pub fn is_kept(&self, param: &str) -> bool {
+        if self.deny.contains(param) {
+            return false;
+        }
+        match &self.allow {
+            Some(allow) => allow.contains(param),
+            None => true,
+        }
+    }
+
+    /// `true` when this policy would keep every parameter
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_none() && self.deny.is_empty()
+    }
+}
+
+/// `IngestSourceRules` represents the per-source processing rules that the
+/// underlying data source has configured for a given `IngestEventSource`.
+/// Sources without any configured rules use the `Default` value, which leaves
+/// events untouched beyond the global rules of the system.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IngestSourceRules {
+    /// `query_params` determines which query parameters are retained in the
+    /// `location` of `SectionEvent`s for this source
+    pub query_params: QueryParamPolicy,
+    /// `path_normalization` determines how the `path` of `SectionEvent`s for
+    /// this source is derived from the `location`
+    pub path_normalization: PathNormalizationRules,
+    /// `identity_key` pseudonymizes the user ids of `IdentifyEvent`s for this
+    /// source. Sources without one cannot accept `IdentifyEvent`s.
+    pub identity_key: Option<IdentityKey>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_param_policy() {
+        // Empty policy keeps everything
+        let empty_policy = QueryParamPolicy::default();
+        assert!(
+            empty_policy.is_empty(),
+            "Expected default policy to be empty"
+        );
+        assert!(
+            empty_policy.is_kept("utm_source"),
+            "Expected empty policy to keep all params"
+        );
+
+        // Deny list only removes listed params
+        let mut deny_policy = QueryParamPolicy::default();
+        deny_policy.deny("token");
+        assert!(!deny_policy.is_kept("token"), "Expected token to be denied");
+        assert!(
+            deny_policy.is_kept("page"),
+            "Expected params not on deny list to be kept"
+        );
+
+        // Allow list only keeps listed params and deny wins over allow
+        let mut allow_policy = QueryParamPolicy::default();
+        allow_policy.allow("page");
+        allow_policy.allow("token");
+        allow_policy.deny("token");
+        assert!(allow_policy.is_kept("page"), "Expected page to be allowed");
+        assert!(
+            !allow_policy.is_kept("token"),
+            "Expected deny list to take precedence over allow list"
+        );
+        assert!(
+            !allow_policy.is_kept("email"),
+            "Expected params not on allow list to be removed"
+        );
+    }
+}
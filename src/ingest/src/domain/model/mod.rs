@@ -1,4 +1,12 @@
 mod util;
 
+pub mod event_deduplicator;
+pub mod event_scrubber;
+pub mod hierarchy_cache;
+pub mod identity_key;
 pub mod ingest_action_summary;
 pub mod ingest_event;
+pub mod ingest_source_rules;
+pub mod path_normalizer;
+pub mod server_key;
+pub mod session_synthesizer;
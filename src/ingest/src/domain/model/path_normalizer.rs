@@ -0,0 +1,229 @@
+use regex::Regex;
+use uuid::Uuid;
+
+use crate::domain::model::ingest_event::IngestEvent;
+
+/// `NUMERIC_SEGMENT_PLACEHOLDER` replaces path segments made up of digits only
+pub const NUMERIC_SEGMENT_PLACEHOLDER: &str = ":id";
+/// `UUID_SEGMENT_PLACEHOLDER` replaces path segments that are hyphenated UUIDs
+pub const UUID_SEGMENT_PLACEHOLDER: &str = ":uuid";
+
+/// `PathRewrite` is a single regex rewrite applied to a path. The
+/// `replacement` may reference capture groups of the `pattern` using the
+/// usual `$1` or `${name}` syntax.
+#[derive(Debug, Clone)]
+pub struct PathRewrite {
+    pattern: Regex,
+    replacement: String,
+}
+
+impl PathRewrite {
+    /// `PathRewrite` constructor
+    pub fn new(pattern: Regex, replacement: impl AsRef<str>) -> Self {
+        Self {
+            pattern,
+            replacement: replacement.as_ref().to_owned(),
+        }
+    }
+}
+
+impl PartialEq for PathRewrite {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern.as_str() == other.pattern.as_str() && self.replacement == other.replacement
+    }
+}
+
+impl Eq for PathRewrite {}
+
+/// `PathNormalizationRules` describe how the path of a `SectionEvent` location
+/// is normalized so that reports group equivalent pages together, i.e.
+/// `/users/123/orders/456` and `/users/789/orders/1` both become
+/// `/users/:id/orders/:id`.
+///
+/// Steps are applied in a fixed order: case folding, regex rewrites in the
+/// order given, UUID segment collapsing, numeric segment collapsing and
+/// finally trailing slash removal. The default rules leave paths untouched.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PathNormalizationRules {
+    pub lowercase: bool,
+    pub rewrites: Vec<PathRewrite>,
+    pub collapse_uuid: bool,
+    pub collapse_numeric: bool,
+    pub trim_trailing_slash: bool,
+}
+
+impl PathNormalizationRules {
+    /// Normalize the supplied path according to these rules
+    pub fn normalize(&self, path: &str) -> String {
+        let mut normalized = if self.lowercase {
+            path.to_lowercase()
+        } else {
+            path.to_owned()
+        };
+        for rewrite in self.rewrites.iter() {
+            normalized = rewrite
+                .pattern
+                .replace_all(&normalized, rewrite.replacement.as_str())
+                .into_owned();
+        }
+        if self.collapse_uuid || self.collapse_numeric {
+            normalized = normalized
+                .split('/')
+                .map(|segment| self.collapse_segment(segment))
+                .collect::<Vec<&str>>()
+                .join("/");
+        }
+        if self.trim_trailing_slash {
+            while normalized.len() > 1 && normalized.ends_with('/') {
+                normalized.pop();
+            }
+        }
+        normalized
+    }
+
+    /// Set the normalized `path` of a `SectionEvent` from its `location`.
+    /// Other event types are left untouched.
+    pub fn normalize_event(&self, event: &mut IngestEvent) {
+        if let IngestEvent::Section(section) = event {
+            section.path = section
+                .location
+                .as_deref()
+                .map(|location| self.normalize(location_path(location)));
+        }
+    }
+
+    /// Replace a single path segment with a placeholder if it should be
+    /// collapsed
+    fn collapse_segment<'a>(&self, segment: &'a str) -> &'a str {
+        if self.collapse_numeric
+            && !segment.is_empty()
+            && segment.bytes().all(|b| b.is_ascii_digit())
+        {
+            NUMERIC_SEGMENT_PLACEHOLDER
+        } else if self.collapse_uuid && segment.len() == 36 && Uuid::try_parse(segment).is_ok() {
+            UUID_SEGMENT_PLACEHOLDER
+        } else {
+            segment
+        }
+    }
+}
+
+/// Extract the path portion of a location, which may be either a full URL or
+/// a relative reference. Any scheme, authority, query and fragment are removed.
+pub fn location_path(location: &str) -> &str {
+    let without_authority = match location.find("://") {
+        Some(idx) => {
+            let rest = &location[idx + 3..];
+            match rest.find(['/', '?', '#']) {
+                Some(path_start) => &rest[path_start..],
+                None => "",
+            }
+        }
+        None => location,
+    };
+    let path_end = without_authority
+        .find(['?', '#'])
+        .unwrap_or(without_authority.len());
+    &without_authority[..path_end]
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::domain::model::ingest_event::{ApiKey, SectionEvent, Site};
+
+    #[test]
+    fn test_location_path() {
+        assert_eq!(
+            location_path("https://test.com/path/to/section?foo=bar#last"),
+            "/path/to/section"
+        );
+        assert_eq!(location_path("https://test.com?foo=bar"), "");
+        assert_eq!(location_path("https://test.com"), "");
+        assert_eq!(location_path("/path/to/section#last"), "/path/to/section");
+        assert_eq!(location_path("/path/to/section"), "/path/to/section");
+    }
+
+    #[test]
+    fn test_default_rules() {
+        let rules = PathNormalizationRules::default();
+        assert_eq!(
+            rules.normalize("/Users/123/Orders/"),
+            "/Users/123/Orders/",
+            "Expected default rules to leave path untouched"
+        );
+    }
+
+    #[test]
+    fn test_normalize() {
+        let rules = PathNormalizationRules {
+            lowercase: true,
+            rewrites: vec![PathRewrite::new(Regex::new(r"^/(en|fr|de)/").unwrap(), "/")],
+            collapse_uuid: true,
+            collapse_numeric: true,
+            trim_trailing_slash: true,
+        };
+        assert_eq!(
+            rules.normalize("/users/123/orders/456"),
+            "/users/:id/orders/:id"
+        );
+        assert_eq!(
+            rules.normalize("/users/789/orders/1"),
+            "/users/:id/orders/:id"
+        );
+        assert_eq!(
+            rules.normalize("/FR/Docs/0195a0b4-5e86-7023-9f8b-34eba8d2cc59/"),
+            "/docs/:uuid"
+        );
+        assert_eq!(
+            rules.normalize("/docs/v2/page-10/"),
+            "/docs/v2/page-10",
+            "Expected mixed segments to be left alone"
+        );
+        assert_eq!(rules.normalize("/"), "/", "Expected root to be preserved");
+
+        let capture_rules = PathNormalizationRules {
+            rewrites: vec![PathRewrite::new(
+                Regex::new(r"^/blog/(\d{4})/\d{2}/").unwrap(),
+                "/blog/$1/",
+            )],
+            ..Default::default()
+        };
+        assert_eq!(
+            capture_rules.normalize("/blog/2025/07/launch"),
+            "/blog/2025/launch",
+            "Expected capture groups to be expanded in rewrites"
+        );
+    }
+
+    #[test]
+    fn test_normalize_event() {
+        let rules = PathNormalizationRules {
+            collapse_numeric: true,
+            ..Default::default()
+        };
+        let mut event = IngestEvent::Section(
+            SectionEvent::try_new(
+                ApiKey::new("abc-123"),
+                Site::new("test.com"),
+                Uuid::now_v7(),
+                Uuid::now_v7(),
+                Some("https://test.com/users/123?tab=orders".to_owned()),
+                None,
+            )
+            .unwrap(),
+        );
+        rules.normalize_event(&mut event);
+        let IngestEvent::Section(section) = event else {
+            panic!("Expected normalization to preserve the event type");
+        };
+        assert_eq!(section.path.as_deref(), Some("/users/:id"));
+        assert_eq!(
+            section.location.as_deref(),
+            Some("https://test.com/users/123?tab=orders"),
+            "Expected raw location to be preserved"
+        );
+    }
+}
@@ -0,0 +1,62 @@
+use sha2::{Digest, Sha256};
+
+/// `ServerKey` is the secret that a trusted backend presents in order to submit
+/// events through the server-to-server API. Unlike the `ApiKey`, which is
+/// embedded in public pages, the `ServerKey` must never be exposed to browsers.
+/// Only the SHA-256 digest of the key is stored by the underlying data source.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ServerKey {
+    secret: String,
+}
+
+impl ServerKey {
+    /// `ServerKey` constructor
+    pub fn new(secret: impl AsRef<str>) -> Self {
+        Self {
+            secret: secret.as_ref().trim().to_owned(),
+        }
+    }
+
+    /// `true` when no secret was supplied
+    pub fn is_empty(&self) -> bool {
+        self.secret.is_empty()
+    }
+
+    /// Lowercase hex encoded SHA-256 digest of the secret
+    pub fn digest(&self) -> String {
+        Sha256::digest(self.secret.as_bytes())
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+}
+
+/// The secret is deliberately left out so that it never ends up in logs
+impl std::fmt::Debug for ServerKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ServerKey").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_key() {
+        let server_key = ServerKey::new(" secret ");
+        assert_eq!(
+            server_key.digest(),
+            "2bb80d537b1da3e38bd30361aa855686bde0eacd7162fef6a25fe97bf527a25b",
+            "Expected trimmed secret to be hashed with SHA-256"
+        );
+        assert!(
+            !format!("{server_key:?}").contains("secret"),
+            "Expected secret to be omitted from debug output"
+        );
+        assert!(
+            ServerKey::new("  ").is_empty(),
+            "Expected blank key to be empty"
+        );
+    }
+}
@@ -0,0 +1,530 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    net::IpAddr,
+    sync::Mutex,
+};
+
+use time::{Duration, OffsetDateTime};
+use uuid::{Builder, Timestamp, Uuid};
+
+use crate::domain::model::{
+    identity_key::IdentityKey,
+    ingest_event::{
+        IngestEvent, IngestEventCore, IngestEventError, IngestEventOrigin, IngestEventSource,
+        SessionEvent, VisitorEvent,
+    },
+};
+
+/// Default period of inactivity after which a visitor starts a new session
+pub const DEFAULT_SYNTHETIC_SESSION_GAP: Duration = Duration::minutes(30);
+
+/// Default period of inactivity after which a visitor is forgotten
+pub const DEFAULT_SYNTHETIC_VISITOR_TTL: Duration = Duration::days(1);
+
+/// Default number of visitors that are remembered at once
+pub const DEFAULT_SYNTHETIC_VISITOR_CAPACITY: usize = 100_000;
+
+/// `SyntheticSessionRequest` describes the visitor behind an event that
+/// arrived without `Visitor` and `Session` parents
+#[derive(Debug, Clone)]
+pub struct SyntheticSessionRequest {
+    /// Source the event belongs to
+    pub source: IngestEventSource,
+    /// Identifies the visitor within the source, such as a client id or a
+    /// hash of the ip and user agent. It is only used to derive the visitor
+    /// id and is not remembered.
+    pub visitor_key: String,
+    pub ip: IpAddr,
+    pub user_agent: String,
+    /// Recorded on the session when a new one is started
+    pub referrer: Option<String>,
+    /// Time of the event, which is also used for any new parents
+    pub ts: OffsetDateTime,
+    /// Origin of the event, which is also used for any new parents
+    pub origin: IngestEventOrigin,
+}
+
+/// `SyntheticParents` are the ids of the `Visitor` and `Session` that an event
+/// described by a `SyntheticSessionRequest` belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyntheticParents {
+    pub visitor_id: Uuid,
+    pub session_id: Uuid,
+}
+
+#[derive(Debug, Clone)]
+struct SyntheticVisitor {
+    session_id: Uuid,
+    last_seen: OffsetDateTime,
+}
+
+#[derive(Debug)]
+struct SyntheticState {
+    visitors: HashMap<Uuid, SyntheticVisitor>,
+    order: VecDeque<Uuid>,
+    last_sweep: OffsetDateTime,
+}
+
+/// `SyntheticVisits` are the visitors and sessions that the events of a
+/// single request were placed in. They are only remembered by the
+/// `SessionSynthesizer` once the request was saved and they are committed, so
+/// that a failed save does not leave behind parents that later events would
+/// reference although they were never stored.
+#[derive(Debug, Default)]
+pub struct SyntheticVisits {
+    visitors: HashMap<Uuid, SyntheticVisitor>,
+}
+
+/// `SessionSynthesizer` creates the `Visitor` and `Session` parents for events
+/// from clients that do not track them, such as the compatibility APIs for
+/// other analytics tools. Visitors are remembered in memory until they have
+/// been inactive for the visitor TTL, and a new session is started after the
+/// session gap. As visitor keys are chosen by
+/// clients, at most `capacity` visitors are remembered, forgetting the
+/// earliest first. Sessions are not recognized across restarts of the server,
+/// but visitor ids are derived from an HMAC of the source and visitor key
+/// with the `visitor_key` secret, so that a visitor keeps its id across
+/// restarts and on every server sharing the secret. Visitors are remembered by
+/// that id, so visitor keys such as user ids are never held in memory.
+#[derive(Debug)]
+pub struct SessionSynthesizer {
+    session_gap: Duration,
+    visitor_ttl: Duration,
+    capacity: usize,
+    visitor_key: IdentityKey,
+    state: Mutex<SyntheticState>,
+}
+
+impl Default for SessionSynthesizer {
+    /// Uses `DEFAULT_SYNTHETIC_SESSION_GAP`, `DEFAULT_SYNTHETIC_VISITOR_TTL`
+    /// and `DEFAULT_SYNTHETIC_VISITOR_CAPACITY`, deriving visitor ids without
+    /// a secret
+    fn default() -> Self {
+        Self {
+            session_gap: DEFAULT_SYNTHETIC_SESSION_GAP,
+            visitor_ttl: DEFAULT_SYNTHETIC_VISITOR_TTL,
+            capacity: DEFAULT_SYNTHETIC_VISITOR_CAPACITY,
+            visitor_key: IdentityKey::new(""),
+            state: Mutex::new(SyntheticState {
+                visitors: HashMap::new(),
+                order: VecDeque::new(),
+                last_sweep: OffsetDateTime::now_utc(),
+            }),
+        }
+    }
+}
+
+impl SessionSynthesizer {
+    /// Replace the period of inactivity that starts a new session
+    pub fn with_session_gap(mut self, session_gap: Duration) -> Self {
+        self.session_gap = session_gap;
+        self
+    }
+
+    /// Replace the period of inactivity after which a visitor is forgotten
+    pub fn with_visitor_ttl(mut self, visitor_ttl: Duration) -> Self {
+        self.visitor_ttl = visitor_ttl;
+        self
+    }
+
+    /// Replace the number of visitors that are remembered at once
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Replace the secret from which visitor ids are derived
+    pub fn with_visitor_key(mut self, visitor_key: IdentityKey) -> Self {
+        self.visitor_key = visitor_key;
+        self
+    }
+
+    /// Id of the visitor described by `request`, which only depends on its
+    /// source and visitor key. It is a UUIDv7 whose random bits are an HMAC
+    /// and whose timestamp is left at the Unix epoch, as the time the visitor
+    /// was first seen is not known again.
+    pub fn visitor_id(&self, request: &SyntheticSessionRequest) -> Uuid {
+        let digest = self.visitor_key.digest(&format!(
+            "{}|{}|{}",
+            request.source.api_key().value(),
+            request.source.site().value(),
+            request.visitor_key
+        ));
+        let mut counter_random_bytes = [0u8; 10];
+        counter_random_bytes.copy_from_slice(&digest[..10]);
+        Builder::from_unix_timestamp_millis(0, &counter_random_bytes).into_uuid()
+    }
+
+    /// Id of the session that an event described by `request` belongs to.
+    /// When a new visitor or session had to be started, the corresponding
+    /// events are pushed onto `events` and must be saved along with the event.
+    /// The visit is recorded in `visits`, which must be committed once the
+    /// events were saved.
+    pub fn try_session(
+        &self,
+        request: SyntheticSessionRequest,
+        visits: &mut SyntheticVisits,
+        events: &mut Vec<IngestEvent>,
+    ) -> Result<Uuid, IngestEventError> {
+        Ok(self.try_parents(request, visits, events)?.session_id)
+    }
+
+    /// Ids of the visitor and session that an event described by `request`
+    /// belongs to, for events such as `IdentifyEvent` whose parent is the
+    /// visitor. New parents are pushed onto `events` and the visit recorded
+    /// in `visits` as with `try_session`.
+    pub fn try_parents(
+        &self,
+        request: SyntheticSessionRequest,
+        visits: &mut SyntheticVisits,
+        events: &mut Vec<IngestEvent>,
+    ) -> Result<SyntheticParents, IngestEventError> {
+        let visitor_id = self.visitor_id(&request);
+        // Visits of the same request take precedence, as they are newer
+        let known = match visits.visitors.get(&visitor_id) {
+            Some(visitor) => Some(visitor.clone()),
+            None => {
+                let mut state = self
+                    .state
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+                self.sweep(&mut state);
+                state.visitors.get(&visitor_id).cloned()
+            }
+        };
+        let session_id = match &known {
+            Some(visitor) if request.ts - visitor.last_seen <= self.session_gap => {
+                visitor.session_id
+            }
+            Some(_) => push_session(&request, visitor_id, events)?,
+            None => {
+                events.push(IngestEvent::Visitor(VisitorEvent::try_new_with_core_event(
+                    IngestEventCore::try_new_at(
+                        request.source.api_key().clone(),
+                        request.source.site().clone(),
+                        visitor_id,
+                        request.ts,
+                        request.origin,
+                    )?,
+                )?));
+                push_session(&request, visitor_id, events)?
+            }
+        };
+        let last_seen = known.map_or(request.ts, |visitor| visitor.last_seen.max(request.ts));
+        visits.visitors.insert(
+            visitor_id,
+            SyntheticVisitor {
+                session_id,
+                last_seen,
+            },
+        );
+        Ok(SyntheticParents {
+            visitor_id,
+            session_id,
+        })
+    }
+
+    /// Remember the visits of a request once its events were saved. Once
+    /// more than `capacity` visitors are remembered, the earliest are
+    /// forgotten.
+    pub fn commit(&self, visits: SyntheticVisits) {
+        let mut state = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for (visitor_id, mut visitor) in visits.visitors {
+            match state.visitors.get_mut(&visitor_id) {
+                Some(known) => {
+                    visitor.last_seen = visitor.last_seen.max(known.last_seen);
+                    *known = visitor;
+                }
+                None => {
+                    state.order.push_back(visitor_id);
+                    state.visitors.insert(visitor_id, visitor);
+                }
+            }
+        }
+        while state.visitors.len() > self.capacity {
+            let Some(evicted) = state.order.pop_front() else {
+                break;
+            };
+            state.visitors.remove(&evicted);
+        }
+    }
+
+    /// Forget visitors that have been inactive for longer than the visitor
+    /// TTL. Runs at most once per session gap.
+    fn sweep(&self, state: &mut SyntheticState) {
+        let now = OffsetDateTime::now_utc();
+        if now - state.last_sweep < self.session_gap {
+            return;
+        }
+        let SyntheticState {
+            visitors, order, ..
+        } = state;
+        visitors.retain(|_, visitor| now - visitor.last_seen <= self.visitor_ttl);
+        order.retain(|visitor_id| visitors.contains_key(visitor_id));
+        state.last_sweep = now;
+    }
+}
+
+/// UUIDv7 for an event that happened at `ts`
+pub fn uuid_v7_at(ts: &OffsetDateTime) -> Uuid {
+    let nanos = ts.unix_timestamp_nanos().max(0);
+    Uuid::new_v7(Timestamp::from_unix_time(
+        (nanos / 1_000_000_000) as u64,
+        (nanos % 1_000_000_000) as u32,
+        0,
+        0,
+    ))
+}
+
+fn core(request: &SyntheticSessionRequest, id: Uuid) -> Result<IngestEventCore, IngestEventError> {
+    IngestEventCore::try_new_with_origin(
+        request.source.api_key().clone(),
+        request.source.site().clone(),
+        id,
+        request.origin,
+    )
+}
+
+fn push_session(
+    request: &SyntheticSessionRequest,
+    visitor_id: Uuid,
+    events: &mut Vec<IngestEvent>,
+) -> Result<Uuid, IngestEventError> {
+    let session_id = uuid_v7_at(&request.ts);
+    events.push(IngestEvent::Session(
+        SessionEvent::try_new_with_core_event(
+            core(request, session_id)?,
+            visitor_id,
+            request.user_agent.clone(),
+            request.ip,
+        )?
+        .with_referrer(request.referrer.clone()),
+    ));
+    Ok(session_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+    use crate::domain::model::ingest_event::{ApiKey, CommonEvent, Site};
+
+    fn request(site: &str, ts: OffsetDateTime) -> SyntheticSessionRequest {
+        SyntheticSessionRequest {
+            source: IngestEventSource::new(ApiKey::new("abc_123"), Site::new(site)),
+            visitor_key: "visitor".to_owned(),
+            ip: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            user_agent: "Mozilla/5.0".to_owned(),
+            referrer: Some("https://search.example/".to_owned()),
+            ts,
+            origin: IngestEventOrigin::Client,
+        }
+    }
+
+    /// Parents of a single request whose events were saved
+    fn saved_parents(
+        synthesizer: &SessionSynthesizer,
+        request: SyntheticSessionRequest,
+        events: &mut Vec<IngestEvent>,
+    ) -> SyntheticParents {
+        let mut visits = SyntheticVisits::default();
+        let parents = synthesizer
+            .try_parents(request, &mut visits, events)
+            .unwrap();
+        synthesizer.commit(visits);
+        parents
+    }
+
+    #[test]
+    fn test_try_session() {
+        let synthesizer = SessionSynthesizer::default();
+        let now = OffsetDateTime::now_utc();
+        let start = now - Duration::minutes(50);
+
+        let mut events = Vec::new();
+        let first_session =
+            saved_parents(&synthesizer, request("test.com", start), &mut events).session_id;
+        let [IngestEvent::Visitor(visitor), IngestEvent::Session(session)] = &events[..] else {
+            panic!("Expected a new visitor and session");
+        };
+        assert_eq!(session.parent, visitor.id());
+        assert_eq!(session.id(), first_session);
+        assert_eq!(session.referrer.as_deref(), Some("https://search.example/"));
+        let visitor_id = visitor.id();
+
+        let mut events = Vec::new();
+        let same_session = saved_parents(
+            &synthesizer,
+            request("test.com", start + Duration::minutes(10)),
+            &mut events,
+        )
+        .session_id;
+        assert!(events.is_empty(), "Expected no new parents within the gap");
+        assert_eq!(same_session, first_session);
+
+        let mut events = Vec::new();
+        let mut visits = SyntheticVisits::default();
+        let next_session = synthesizer
+            .try_session(request("test.com", now), &mut visits, &mut events)
+            .unwrap();
+        let [IngestEvent::Session(session)] = &events[..] else {
+            panic!("Expected only a new session after the gap");
+        };
+        assert_ne!(next_session, first_session);
+        assert_eq!(session.parent, visitor_id);
+        assert_eq!(
+            synthesizer
+                .try_parents(request("test.com", now), &mut visits, &mut events)
+                .unwrap(),
+            SyntheticParents {
+                visitor_id,
+                session_id: next_session,
+            },
+            "Expected the visitor to be given along with the session"
+        );
+        assert_eq!(events.len(), 1, "Expected visits of a request to be reused");
+        synthesizer.commit(visits);
+
+        let mut events = Vec::new();
+        saved_parents(&synthesizer, request("other.com", now), &mut events);
+        assert_eq!(events.len(), 2, "Expected visitors to be kept per source");
+    }
+
+    #[test]
+    fn test_uncommitted_visits() {
+        let synthesizer = SessionSynthesizer::default();
+        let now = OffsetDateTime::now_utc();
+
+        let mut events = Vec::new();
+        let mut visits = SyntheticVisits::default();
+        let failed = synthesizer
+            .try_session(request("test.com", now), &mut visits, &mut events)
+            .unwrap();
+        // The events of the request are not saved, so the visits are dropped
+        drop(visits);
+
+        let mut events = Vec::new();
+        let session_id =
+            saved_parents(&synthesizer, request("test.com", now), &mut events).session_id;
+        assert_eq!(
+            events.len(),
+            2,
+            "Expected parents of a failed request to be started again"
+        );
+        assert_ne!(session_id, failed);
+    }
+
+    #[test]
+    fn test_capacity() {
+        let synthesizer = SessionSynthesizer::default().with_capacity(1);
+        let now = OffsetDateTime::now_utc();
+
+        let mut events = Vec::new();
+        saved_parents(&synthesizer, request("test.com", now), &mut events);
+        saved_parents(&synthesizer, request("other.com", now), &mut events);
+        let mut events = Vec::new();
+        saved_parents(&synthesizer, request("other.com", now), &mut events);
+        assert!(events.is_empty(), "Expected the latest visitor to be kept");
+        saved_parents(&synthesizer, request("test.com", now), &mut events);
+        assert_eq!(
+            events.len(),
+            2,
+            "Expected the earliest visitor to be forgotten beyond the capacity"
+        );
+    }
+
+    #[test]
+    fn test_forgotten_visitor() {
+        let synthesizer = SessionSynthesizer::default().with_capacity(1);
+        let now = OffsetDateTime::now_utc();
+
+        let mut events = Vec::new();
+        let first = saved_parents(&synthesizer, request("test.com", now), &mut events);
+        saved_parents(&synthesizer, request("other.com", now), &mut events);
+        let mut events = Vec::new();
+        let later = now + Duration::minutes(1);
+        let second = saved_parents(&synthesizer, request("test.com", later), &mut events);
+        let [IngestEvent::Visitor(visitor), IngestEvent::Session(_)] = &events[..] else {
+            panic!("Expected the forgotten visitor to be emitted again");
+        };
+        assert_eq!(
+            (visitor.id(), second.visitor_id),
+            (first.visitor_id, first.visitor_id),
+            "Expected a forgotten visitor to keep its id, so that storage keeps it once"
+        );
+        assert_eq!(visitor.ts(), &later);
+        assert_ne!(first.session_id, second.session_id);
+    }
+
+    #[test]
+    fn test_visitor_id() {
+        let now = OffsetDateTime::now_utc();
+        let keyed = || SessionSynthesizer::default().with_visitor_key(IdentityKey::new("secret"));
+
+        let mut first_events = Vec::new();
+        let first = saved_parents(&keyed(), request("test.com", now), &mut first_events);
+        let mut second_events = Vec::new();
+        let second = saved_parents(
+            &keyed(),
+            request("test.com", now + Duration::minutes(1)),
+            &mut second_events,
+        );
+        assert_eq!(
+            first.visitor_id, second.visitor_id,
+            "Expected separate synthesizers to derive the same visitor id"
+        );
+        assert_ne!(first.session_id, second.session_id);
+        let IngestEvent::Visitor(visitor) = &second_events[0] else {
+            panic!("Expected a new visitor");
+        };
+        assert_eq!(visitor.id(), second.visitor_id);
+        assert_eq!(
+            visitor.ts(),
+            &(now + Duration::minutes(1)),
+            "Expected the visitor to be recorded at the time of the event"
+        );
+
+        // Negative test cases
+        let mut events = Vec::new();
+        let other_key = SessionSynthesizer::default().with_visitor_key(IdentityKey::new("other"));
+        assert_ne!(
+            saved_parents(&other_key, request("test.com", now), &mut events).visitor_id,
+            first.visitor_id,
+            "Expected visitor ids to depend on the secret"
+        );
+        assert_ne!(
+            saved_parents(&keyed(), request("other.com", now), &mut events).visitor_id,
+            first.visitor_id,
+            "Expected visitor ids to depend on the source"
+        );
+    }
+
+    #[test]
+    fn test_visitor_key_not_remembered() {
+        let synthesizer =
+            SessionSynthesizer::default().with_visitor_key(IdentityKey::new("secret"));
+        let user_request = SyntheticSessionRequest {
+            visitor_key: "jane@example.com".to_owned(),
+            ..request("test.com", OffsetDateTime::now_utc())
+        };
+
+        let mut events = Vec::new();
+        let parents = saved_parents(&synthesizer, user_request.clone(), &mut events);
+        let state = synthesizer.state.lock().unwrap();
+        assert_eq!(
+            state.visitors.keys().collect::<Vec<&Uuid>>(),
+            vec![&parents.visitor_id],
+            "Expected visitors to be remembered by their derived id only"
+        );
+        assert_eq!(state.order, VecDeque::from([parents.visitor_id]));
+        assert_ne!(
+            parents.visitor_id,
+            SessionSynthesizer::default().visitor_id(&user_request),
+            "Expected the derived id to depend on the secret"
+        );
+    }
+}
@@ -4,9 +4,9 @@ use uuid::Uuid;
 use crate::domain::model::ingest_event::IngestEventError;
 
 /// Earliest event the system treats as valid for ingestion relative to now
-const MAX_DURATION_BEFORE_PRESENT: Duration = Duration::HOUR;
+pub(crate) const MAX_DURATION_BEFORE_PRESENT: Duration = Duration::HOUR;
 /// Latest event the system treats as valid for ingestion relative to now
-const MAX_DURATION_AFTER_PRESENT: Duration = Duration::minutes(5);
+pub(crate) const MAX_DURATION_AFTER_PRESENT: Duration = Duration::minutes(5);
 
 /// Domain functions for Ingest
 /// Function that attempts to derive a datetime from the supplied UUID and
@@ -30,11 +30,12 @@ pub(crate) fn try_uuid_datetime(uuid: Uuid) -> Result<OffsetDateTime, IngestEven
 /// Function to check whether the submitted even has a timestamp which falls
 /// within the max and min duration from now
 ///
-/// All events must be no earlier than now - MAX_BEFORE_PRESENT and no later
-/// than now + MAX_AFTER_PRESENT.
-pub(crate) fn is_ts_within_ingest_range(ts: &OffsetDateTime) -> bool {
+/// All events must be no earlier than now - `before` and no later than
+/// now + `after`. Client events use MAX_DURATION_BEFORE_PRESENT and
+/// MAX_DURATION_AFTER_PRESENT respectively.
+pub(crate) fn is_ts_within_range(ts: &OffsetDateTime, before: Duration, after: Duration) -> bool {
     let now = OffsetDateTime::now_utc();
-    (ts > &(now - MAX_DURATION_BEFORE_PRESENT)) && (ts < &(now + MAX_DURATION_AFTER_PRESENT))
+    (ts > &(now - before)) && (ts < &(now + after))
 }
 
 #[cfg(test)]
@@ -63,7 +64,7 @@ mod tests {
     }
 
     #[test]
-    fn test_is_ts_within_ingest_range() {
+    fn test_is_ts_within_range() {
         let valid_now = OffsetDateTime::now_utc();
         let valid_early = valid_now - Duration::minutes(30);
         let valid_late = valid_now + Duration::minutes(2);
@@ -72,24 +73,44 @@ mod tests {
 
         // Should succeed with no panic
         assert!(
-            is_ts_within_ingest_range(&valid_now),
+            is_ts_within_range(
+                &valid_now,
+                MAX_DURATION_BEFORE_PRESENT,
+                MAX_DURATION_AFTER_PRESENT
+            ),
             "Current ts should be valid ingest OffsetDateTime"
         );
         assert!(
-            is_ts_within_ingest_range(&valid_early),
+            is_ts_within_range(
+                &valid_early,
+                MAX_DURATION_BEFORE_PRESENT,
+                MAX_DURATION_AFTER_PRESENT
+            ),
             "30 minutes prior should be valid ingest OffsetDateTime"
         );
         assert!(
-            is_ts_within_ingest_range(&valid_late),
+            is_ts_within_range(
+                &valid_late,
+                MAX_DURATION_BEFORE_PRESENT,
+                MAX_DURATION_AFTER_PRESENT
+            ),
             "2 minutes after should be vaid ingest OffsetDateTime"
         );
         // Should return an Err of type IngestError::TimestampOutOfRange
         assert!(
-            !is_ts_within_ingest_range(&invalid_early),
+            !is_ts_within_range(
+                &invalid_early,
+                MAX_DURATION_BEFORE_PRESENT,
+                MAX_DURATION_AFTER_PRESENT
+            ),
             "70 minutes prior should be invalid ingest OffsetDateTime"
         );
         assert!(
-            !is_ts_within_ingest_range(&invalid_late),
+            !is_ts_within_range(
+                &invalid_late,
+                MAX_DURATION_BEFORE_PRESENT,
+                MAX_DURATION_AFTER_PRESENT
+            ),
             "20 minutes prior should be invalid ingest OffsetDateTime"
         );
     }
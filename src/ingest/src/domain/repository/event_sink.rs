@@ -0,0 +1,36 @@
+use std::fmt::Debug;
+
+use futures_util::future::BoxFuture;
+
+use crate::domain::{
+    model::ingest_event::IngestEvent, repository::ingest_event_repository::IngestRepositoryError,
+};
+
+/// `SinkPolicy` determines how the failure of an `EventSink` affects a save
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SinkPolicy {
+    /// The save fails unless the sink accepts the events, so that the client
+    /// retries the whole batch
+    Required,
+    /// The sink is written before the save completes, but its failures are
+    /// only logged and counted
+    BestEffort,
+    /// The sink is written in the background once the save has completed, so
+    /// that a slow destination never delays a response
+    Async,
+}
+
+/// `EventSink` is a destination to which saved `IngestEvent`s are mirrored in
+/// addition to the `IngestEventRepository`. Unlike repositories, sinks of
+/// different types are configured side by side at runtime, so `write` returns
+/// a boxed future in order that the trait can be used as `dyn EventSink`.
+pub trait EventSink: 'static + Debug + Send + Sync {
+    /// `name` identifies the sink in logs and reports
+    fn name(&self) -> &str;
+
+    /// `write` the given batch of `IngestEvent`s to the sink
+    fn write<'a>(
+        &'a self,
+        events: &'a [IngestEvent],
+    ) -> BoxFuture<'a, Result<(), IngestRepositoryError>>;
+}
@@ -4,7 +4,9 @@ use thiserror::Error;
 
 use crate::domain::model::{
     ingest_action_summary::IngestActionSummary,
-    ingest_event::{IngestEvent, IngestEventSource},
+    ingest_event::{ApiKey, IngestEvent, IngestEventSource},
+    ingest_source_rules::IngestSourceRules,
+    server_key::ServerKey,
 };
 
 /// `IngestRepositoryError` represents potential error cases for an
@@ -39,16 +41,39 @@ pub trait IngestEventRepository: 'static + Clone + Send + Sync {
     fn event_sources(
         &self,
     ) -> impl Future<Output = Result<HashSet<IngestEventSource>, IngestRepositoryError>> + Send;
+
+    /// `source_rules` attempts to return the `IngestSourceRules` that the
+    /// underlying data source has configured for the given
+    /// `IngestEventSource`. Sources without any configured rules receive the
+    /// default rules.
+    fn source_rules(
+        &self,
+        source: &IngestEventSource,
+    ) -> impl Future<Output = Result<IngestSourceRules, IngestRepositoryError>> + Send;
+
+    /// `is_server_key_valid` determines whether the supplied `ServerKey` has
+    /// been issued for the given `ApiKey` in the underlying data source
+    fn is_server_key_valid(
+        &self,
+        api_key: &ApiKey,
+        server_key: &ServerKey,
+    ) -> impl Future<Output = Result<bool, IngestRepositoryError>> + Send;
+
+    /// `reload_event_sources` refreshes the `IngestEventSource` structs and
+    /// their `IngestSourceRules` from the underlying data source. Repositories
+    /// that do not cache sources need not override the default no-op.
+    fn reload_event_sources(
+        &self,
+    ) -> impl Future<Output = Result<(), IngestRepositoryError>> + Send {
+        async { Ok(()) }
+    }
 }
 
 /// Provide a mock for the `IngestEventRepository` trait to be used in other
 /// code that needs to unit test
 #[cfg(test)]
 pub(crate) mod test {
-    use crate::domain::model::{
-        ingest_action_summary::IngestEventSaveSummary,
-        ingest_event::{ApiKey, Site},
-    };
+    use crate::domain::model::{ingest_action_summary::IngestEventSaveSummary, ingest_event::Site};
 
     use super::*;
 
@@ -56,6 +81,8 @@ pub(crate) mod test {
     pub(crate) struct MockIngestEventRepository {
         pub(crate) save_result: Result<IngestActionSummary, IngestRepositoryError>,
         pub(crate) event_source_result: Result<HashSet<IngestEventSource>, IngestRepositoryError>,
+        pub(crate) source_rules_result: Result<IngestSourceRules, IngestRepositoryError>,
+        pub(crate) server_key_result: Result<bool, IngestRepositoryError>,
     }
 
     impl IngestEventRepository for MockIngestEventRepository {
@@ -68,6 +95,19 @@ pub(crate) mod test {
         async fn event_sources(&self) -> Result<HashSet<IngestEventSource>, IngestRepositoryError> {
             self.event_source_result.clone()
         }
+        async fn source_rules(
+            &self,
+            _: &IngestEventSource,
+        ) -> Result<IngestSourceRules, IngestRepositoryError> {
+            self.source_rules_result.clone()
+        }
+        async fn is_server_key_valid(
+            &self,
+            _: &ApiKey,
+            _: &ServerKey,
+        ) -> Result<bool, IngestRepositoryError> {
+            self.server_key_result.clone()
+        }
     }
 
     #[tokio::test(flavor = "multi_thread")]
@@ -75,11 +115,14 @@ pub(crate) mod test {
         let mock_success_repo = MockIngestEventRepository {
             save_result: Ok(IngestActionSummary::Save(IngestEventSaveSummary {
                 event_count: 5,
+                duplicate_count: 0,
             })),
             event_source_result: Ok(HashSet::from([IngestEventSource::new(
                 ApiKey::new("abc-123"),
                 Site::new("test.com"),
             )])),
+            source_rules_result: Ok(IngestSourceRules::default()),
+            server_key_result: Ok(true),
         };
         let mock_save_success_result = mock_success_repo.save(Vec::new()).await.unwrap();
         match mock_save_success_result {
@@ -97,10 +140,31 @@ pub(crate) mod test {
             )),
             "Expected IngestEventSource to be in returned result for this repo mock"
         );
+        let mock_rules_success_result = mock_success_repo
+            .source_rules(&IngestEventSource::new(
+                ApiKey::new("abc-123"),
+                Site::new("test.com"),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(
+            mock_rules_success_result,
+            IngestSourceRules::default(),
+            "Expected default IngestSourceRules for this repo mock"
+        );
+        assert!(
+            mock_success_repo
+                .is_server_key_valid(&ApiKey::new("abc-123"), &ServerKey::new("secret"))
+                .await
+                .unwrap(),
+            "Expected server key to be valid for this repo mock"
+        );
 
         let mock_failure_repo = MockIngestEventRepository {
             save_result: Err(IngestRepositoryError::Repository),
             event_source_result: Err(IngestRepositoryError::Repository),
+            source_rules_result: Err(IngestRepositoryError::Repository),
+            server_key_result: Ok(false),
         };
         let mock_save_failure_result = mock_failure_repo.save(Vec::new()).await.unwrap_err();
         assert_eq!(mock_save_failure_result, IngestRepositoryError::Repository);
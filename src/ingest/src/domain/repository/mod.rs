@@ -1 +1,2 @@
+pub mod event_sink;
 pub mod ingest_event_repository;
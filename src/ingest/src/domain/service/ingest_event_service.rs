@@ -4,8 +4,10 @@ use thiserror::Error;
 
 use crate::domain::{
     model::{
+        hierarchy_cache::HierarchyReport,
         ingest_action_summary::IngestActionSummary,
-        ingest_event::{IngestEvent, IngestEventSource},
+        ingest_event::{ApiKey, IngestEvent, IngestEventSource},
+        server_key::ServerKey,
     },
     repository::ingest_event_repository::IngestRepositoryError,
 };
@@ -18,6 +20,10 @@ pub enum IngestServiceError {
     /// like saving an empty `Vec` of `IngestEvent`
     #[error("Invalid request")]
     InvalidRequest,
+    /// `IdentityKey` represents an `IdentifyEvent` for a source that has no
+    /// `IdentityKey` configured, so its user id cannot be pseudonymized
+    #[error("No identity key configured for source")]
+    IdentityKey,
     /// `Repository` allows underlying `IngestRepositoryError` errors to be
     /// handled at the service level
     #[error("Error handling IngestEvent")]
@@ -34,10 +40,34 @@ pub trait IngestEventService: 'static + Send + Sync {
         events: Vec<IngestEvent>,
     ) -> impl Future<Output = Result<IngestActionSummary, IngestServiceError>> + Send;
 
+    /// `validate` checks a slice of `IngestEvents` against the rules that
+    /// `save` applies without saving them. Returns the violation of each
+    /// event in order, or `None` for events that would be accepted
+    fn validate(
+        &self,
+        events: &[IngestEvent],
+    ) -> impl Future<Output = Result<Vec<Option<IngestServiceError>>, IngestServiceError>> + Send;
+
     /// `event_sources` returns a HashSet of `IngestEventSource` structs that
     /// are configured in the underlying datasource. This represents the full
     /// set of `api_key` / `site` combinations that this server will accept
     fn event_sources(
         &self,
     ) -> impl Future<Output = Result<HashSet<IngestEventSource>, IngestServiceError>> + Send;
+
+    /// `is_server_key_valid` determines whether the supplied `ServerKey` may
+    /// be used to submit server-to-server events for the given `ApiKey`
+    fn is_server_key_valid(
+        &self,
+        api_key: &ApiKey,
+        server_key: &ServerKey,
+    ) -> impl Future<Output = Result<bool, IngestServiceError>> + Send;
+
+    /// `reload_event_sources` refreshes the event sources and their rules
+    /// from the associated `IngestEventRepository`
+    fn reload_event_sources(&self) -> impl Future<Output = Result<(), IngestServiceError>> + Send;
+
+    /// `hierarchy_report` returns the `HierarchyReport` of every source that
+    /// saved events with parents since the previous call
+    fn hierarchy_report(&self) -> Vec<HierarchyReport>;
 }
@@ -0,0 +1,417 @@
+use std::{collections::HashMap, net::IpAddr};
+
+use thiserror::Error;
+use tonic::{Code, Status, metadata::MetadataMap};
+use uuid::Uuid;
+
+use crate::{
+    domain::{
+        model::{
+            ingest_event::{
+                ApiKey, ClickEvent, CustomEvent, IdentifyEvent, IngestEvent, IngestEventCore,
+                IngestEventError, IngestEventOrigin, SectionEvent, SessionEvent, Site,
+                VisitorEvent,
+            },
+            server_key::ServerKey,
+        },
+        service::ingest_event_service::IngestServiceError,
+    },
+    grpc_api::proto::{Event, event::Kind},
+    http_api::model::{
+        client_event_request::TRAIT_ATTR_PREFIX,
+        client_event_request_components::API_KEY_HTTP_HEADER,
+        request_limiter::{LOCATION_ATTR, LimitViolation, PROP_ATTR_PREFIX, TITLE_ATTR},
+        server_event_request_components::SERVER_KEY_AUTH_SCHEME,
+    },
+};
+
+/// Metadata key that carries the `ServerKey` as a bearer token
+const AUTHORIZATION_METADATA: &str = "authorization";
+
+/// `GrpcEventRequestError` encapsulates the error types that can occur in the
+/// gRPC tier, which are mapped onto gRPC status codes. As the callers are
+/// trusted backends, validation errors describe the offending field or rule,
+/// while errors of the underlying store do not reveal any internals.
+#[derive(Clone, Error, Debug, PartialEq, Eq)]
+pub enum GrpcEventRequestError {
+    #[error("API KEY missing from request metadata")]
+    ApiKey,
+    #[error("Missing or invalid server key")]
+    Unauthorized,
+    #[error("Invalid or missing event field: {0}")]
+    InvalidField(&'static str),
+    #[error("Invalid event: {0}")]
+    IngestEvent(#[from] IngestEventError),
+    #[error("Error returned from IngestEventService")]
+    IngestService(#[from] IngestServiceError),
+    #[error("Request exceeds a limit: {0}")]
+    Limit(#[from] LimitViolation),
+}
+
+/// `GrpcEventRequestError` mapped to the gRPC `Status` returned to the caller.
+/// Only failures of the underlying store are worth retrying, which is
+/// signalled with `Unavailable`.
+impl From<GrpcEventRequestError> for Status {
+    fn from(value: GrpcEventRequestError) -> Self {
+        let code = match &value {
+            GrpcEventRequestError::ApiKey | GrpcEventRequestError::Unauthorized => {
+                Code::Unauthenticated
+            }
+            GrpcEventRequestError::InvalidField(_) | GrpcEventRequestError::IngestEvent(_) => {
+                Code::InvalidArgument
+            }
+            GrpcEventRequestError::Limit(LimitViolation::Events) => Code::ResourceExhausted,
+            GrpcEventRequestError::Limit(_) => Code::InvalidArgument,
+            GrpcEventRequestError::IngestService(IngestServiceError::InvalidRequest) => {
+                Code::InvalidArgument
+            }
+            // Identify events require a source that was configured to
+            // pseudonymize user ids
+            GrpcEventRequestError::IngestService(IngestServiceError::IdentityKey) => {
+                Code::FailedPrecondition
+            }
+            GrpcEventRequestError::IngestService(IngestServiceError::Repository(e)) => {
+                tracing::error!("{}", e);
+                return Status::unavailable("Events could not be saved");
+            }
+        };
+        Status::new(code, value.to_string())
+    }
+}
+
+/// `GrpcRequestMetadata` represents the authentication information of a gRPC
+/// call, which is given in the same way as for server-to-server HTTP requests
+#[derive(Debug, Clone)]
+pub struct GrpcRequestMetadata {
+    pub api_key: String,
+    pub server_key: ServerKey,
+}
+
+impl TryFrom<&MetadataMap> for GrpcRequestMetadata {
+    type Error = GrpcEventRequestError;
+
+    fn try_from(value: &MetadataMap) -> Result<Self, Self::Error> {
+        let api_key = value
+            .get(API_KEY_HTTP_HEADER)
+            .ok_or(GrpcEventRequestError::ApiKey)?
+            .to_str()
+            .map_err(|_| GrpcEventRequestError::ApiKey)?
+            .to_string();
+        let authorization = value
+            .get(AUTHORIZATION_METADATA)
+            .ok_or(GrpcEventRequestError::Unauthorized)?
+            .to_str()
+            .map_err(|_| GrpcEventRequestError::Unauthorized)?;
+        let (scheme, secret) = authorization
+            .split_once(' ')
+            .ok_or(GrpcEventRequestError::Unauthorized)?;
+        if !scheme.eq_ignore_ascii_case(SERVER_KEY_AUTH_SCHEME) {
+            return Err(GrpcEventRequestError::Unauthorized);
+        }
+        Ok(GrpcRequestMetadata {
+            api_key,
+            server_key: ServerKey::new(secret),
+        })
+    }
+}
+
+/// `GrpcEventRequest` represents a single protobuf `Event` from a trusted
+/// backend that authenticated with a `ServerKey`. The same `IngestEvent`
+/// domain rules apply as for `ServerEventRequest`, including the
+/// `IngestEventOrigin::Server` timestamp window.
+#[derive(Debug)]
+pub struct GrpcEventRequest {
+    pub api_key: String,
+    pub event: Event,
+}
+
+impl GrpcEventRequest {
+    /// Fields of the event as the attrs of a `ServerEventRequest`, so that
+    /// the `RequestLimiter` holds gRPC events to the same limits on their
+    /// number of attrs, title and location as HTTP events
+    pub fn attrs(&self) -> HashMap<String, String> {
+        let mut attrs = HashMap::new();
+        let mut insert = |key: &str, value: Option<&String>| {
+            if let Some(value) = value {
+                attrs.insert(key.to_owned(), value.to_owned());
+            }
+        };
+        match &self.event.kind {
+            None | Some(Kind::Visitor(_)) => {}
+            Some(Kind::Session(session)) => insert("p", Some(&session.parent)),
+            Some(Kind::Section(section)) => {
+                insert("p", Some(&section.parent));
+                insert(LOCATION_ATTR, section.location.as_ref());
+                insert(TITLE_ATTR, section.title.as_ref());
+            }
+            Some(Kind::Click(click)) => insert("p", Some(&click.parent)),
+            Some(Kind::Custom(custom)) => {
+                insert("p", Some(&custom.parent));
+                insert(LOCATION_ATTR, custom.location.as_ref());
+                for (key, value) in &custom.props {
+                    insert(&format!("{PROP_ATTR_PREFIX}{key}"), Some(value));
+                }
+            }
+            Some(Kind::Identify(identify)) => {
+                insert("p", Some(&identify.parent));
+                insert("u", Some(&identify.user_id));
+                for (key, value) in &identify.traits {
+                    insert(&format!("{TRAIT_ATTR_PREFIX}{key}"), Some(value));
+                }
+            }
+        }
+        attrs
+    }
+}
+
+/// Parse a required id field of an `Event`
+fn try_uuid(value: &str, field: &'static str) -> Result<Uuid, GrpcEventRequestError> {
+    Uuid::parse_str(value).map_err(|_| GrpcEventRequestError::InvalidField(field))
+}
+
+/// `GrpcEventRequest` translated into the domain object of `IngestEvent`
+impl TryFrom<&GrpcEventRequest> for IngestEvent {
+    type Error = GrpcEventRequestError;
+
+    fn try_from(value: &GrpcEventRequest) -> Result<Self, Self::Error> {
+        let event = &value.event;
+        let core = IngestEventCore::try_new_with_origin(
+            ApiKey::new(&value.api_key),
+            Site::new(&event.site),
+            try_uuid(&event.id, "id")?,
+            IngestEventOrigin::Server,
+        )?;
+        let kind = event
+            .kind
+            .as_ref()
+            .ok_or(GrpcEventRequestError::InvalidField("kind"))?;
+        match kind {
+            Kind::Visitor(_) => Ok(IngestEvent::Visitor(VisitorEvent::try_new_with_core_event(
+                core,
+            )?)),
+            Kind::Session(session) => {
+                let ip: IpAddr = session
+                    .ip
+                    .parse()
+                    .map_err(|_| GrpcEventRequestError::InvalidField("ip"))?;
+                Ok(IngestEvent::Session(
+                    SessionEvent::try_new_with_core_event(
+                        core,
+                        try_uuid(&session.parent, "parent")?,
+                        session.user_agent.to_owned(),
+                        ip,
+                    )?
+                    .with_referrer(session.referrer.to_owned()),
+                ))
+            }
+            Kind::Section(section) => {
+                Ok(IngestEvent::Section(SectionEvent::try_new_with_core_event(
+                    core,
+                    try_uuid(&section.parent, "parent")?,
+                    section.location.to_owned(),
+                    section.title.to_owned(),
+                )?))
+            }
+            Kind::Click(click) => Ok(IngestEvent::Click(ClickEvent::try_new_with_core_event(
+                core,
+                try_uuid(&click.parent, "parent")?,
+            )?)),
+            Kind::Custom(custom) => Ok(IngestEvent::Custom(CustomEvent::try_new_with_core_event(
+                core,
+                try_uuid(&custom.parent, "parent")?,
+                custom.name.to_owned(),
+                custom.location.to_owned(),
+                custom.props.to_owned(),
+            )?)),
+            Kind::Identify(identify) => Ok(IngestEvent::Identify(
+                IdentifyEvent::try_new_with_core_event(
+                    core,
+                    try_uuid(&identify.parent, "parent")?,
+                    identify.user_id.to_owned(),
+                    identify.traits.to_owned(),
+                )?,
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use uuid::Timestamp;
+
+    use super::*;
+    use crate::{
+        domain::{
+            model::ingest_event::CommonEvent,
+            repository::ingest_event_repository::IngestRepositoryError,
+        },
+        grpc_api::proto,
+    };
+
+    fn grpc_request(id: Uuid, kind: Option<Kind>) -> GrpcEventRequest {
+        GrpcEventRequest {
+            api_key: "abc-123".to_owned(),
+            event: Event {
+                id: id.to_string(),
+                site: "test.com".to_owned(),
+                kind,
+            },
+        }
+    }
+
+    #[test]
+    fn test_try_from_grpc_request() {
+        let (ts_now, _) = Uuid::now_v7().get_timestamp().unwrap().to_unix();
+        let two_hours_ago = Uuid::new_v7(Timestamp::from_unix_time(ts_now - 7200, 0, 0, 8));
+        let parent = Uuid::now_v7();
+
+        // Events are held to the server window
+        let visitor = IngestEvent::try_from(&grpc_request(
+            two_hours_ago,
+            Some(Kind::Visitor(proto::VisitorEvent {})),
+        ))
+        .unwrap();
+        let IngestEvent::Visitor(ref visitor) = visitor else {
+            panic!("Expected valid visitor event to be generated");
+        };
+        assert_eq!(visitor.origin(), IngestEventOrigin::Server);
+
+        let session = IngestEvent::try_from(&grpc_request(
+            Uuid::now_v7(),
+            Some(Kind::Session(proto::SessionEvent {
+                parent: parent.to_string(),
+                user_agent: "backend/1.0".to_owned(),
+                ip: "2001:db8::1".to_owned(),
+                referrer: Some("https://search.example".to_owned()),
+            })),
+        ))
+        .unwrap();
+        let IngestEvent::Session(session) = session else {
+            panic!("Expected valid session event to be generated");
+        };
+        assert_eq!(session.parent, parent);
+        assert_eq!(session.ip, "2001:db8::1".parse::<IpAddr>().unwrap());
+        assert_eq!(session.referrer.as_deref(), Some("https://search.example"));
+
+        let custom = IngestEvent::try_from(&grpc_request(
+            Uuid::now_v7(),
+            Some(Kind::Custom(proto::CustomEvent {
+                parent: parent.to_string(),
+                name: "signup".to_owned(),
+                location: None,
+                props: HashMap::from([("plan".to_owned(), "team".to_owned())]),
+            })),
+        ))
+        .unwrap();
+        let IngestEvent::Custom(custom) = custom else {
+            panic!("Expected valid custom event to be generated");
+        };
+        assert_eq!(custom.props.get("plan").map(String::as_str), Some("team"));
+
+        // Negative test cases
+        assert_eq!(
+            IngestEvent::try_from(&grpc_request(Uuid::now_v7(), None)).unwrap_err(),
+            GrpcEventRequestError::InvalidField("kind")
+        );
+        assert_eq!(
+            IngestEvent::try_from(&grpc_request(
+                Uuid::now_v7(),
+                Some(Kind::Click(proto::ClickEvent {
+                    parent: "not-a-uuid".to_owned(),
+                })),
+            ))
+            .unwrap_err(),
+            GrpcEventRequestError::InvalidField("parent")
+        );
+        assert_eq!(
+            IngestEvent::try_from(&grpc_request(
+                Uuid::nil(),
+                Some(Kind::Visitor(proto::VisitorEvent {})),
+            ))
+            .unwrap_err(),
+            GrpcEventRequestError::IngestEvent(IngestEventError::UuidVersion)
+        );
+    }
+
+    #[test]
+    fn test_status_codes() {
+        let code = |e: GrpcEventRequestError| Status::from(e).code();
+        assert_eq!(code(GrpcEventRequestError::ApiKey), Code::Unauthenticated);
+        assert_eq!(
+            code(GrpcEventRequestError::IngestEvent(
+                IngestEventError::TimestampOutOfRange
+            )),
+            Code::InvalidArgument
+        );
+        assert_eq!(
+            code(GrpcEventRequestError::Limit(LimitViolation::Events)),
+            Code::ResourceExhausted
+        );
+        assert_eq!(
+            code(GrpcEventRequestError::IngestService(
+                IngestServiceError::IdentityKey
+            )),
+            Code::FailedPrecondition
+        );
+        assert_eq!(
+            code(GrpcEventRequestError::IngestService(
+                IngestServiceError::Repository(IngestRepositoryError::Repository)
+            )),
+            Code::Unavailable
+        );
+
+        let mut metadata = MetadataMap::new();
+        assert_eq!(
+            GrpcRequestMetadata::try_from(&metadata).unwrap_err(),
+            GrpcEventRequestError::ApiKey
+        );
+        metadata.insert(API_KEY_HTTP_HEADER, "abc-123".parse().unwrap());
+        metadata.insert(AUTHORIZATION_METADATA, "Basic secret".parse().unwrap());
+        assert_eq!(
+            GrpcRequestMetadata::try_from(&metadata).unwrap_err(),
+            GrpcEventRequestError::Unauthorized
+        );
+        metadata.insert(AUTHORIZATION_METADATA, "Bearer secret".parse().unwrap());
+        assert!(GrpcRequestMetadata::try_from(&metadata).is_ok());
+    }
+
+    #[test]
+    fn test_attrs() {
+        let parent = Uuid::now_v7();
+        let section = grpc_request(
+            Uuid::now_v7(),
+            Some(Kind::Section(proto::SectionEvent {
+                parent: parent.to_string(),
+                location: Some("/path".to_owned()),
+                title: None,
+            })),
+        );
+        assert_eq!(
+            section.attrs(),
+            HashMap::from([
+                ("p".to_owned(), parent.to_string()),
+                ("l".to_owned(), "/path".to_owned()),
+            ]),
+            "Expected fields under the attr keys of HTTP events"
+        );
+
+        let identify = grpc_request(
+            Uuid::now_v7(),
+            Some(Kind::Identify(proto::IdentifyEvent {
+                parent: parent.to_string(),
+                user_id: "user-1".to_owned(),
+                traits: HashMap::from([("t".to_owned(), "trait".to_owned())]),
+            })),
+        );
+        let attrs = identify.attrs();
+        assert_eq!(attrs.len(), 3, "Expected traits to be counted as attrs");
+        assert_eq!(
+            attrs.get("t"),
+            None,
+            "Expected traits to not be taken for the title"
+        );
+        assert!(grpc_request(Uuid::now_v7(), None).attrs().is_empty());
+    }
+}
@@ -0,0 +1 @@
+pub mod grpc_event_request;
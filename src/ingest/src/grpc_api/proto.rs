@@ -0,0 +1,4 @@
+//! Messages and service definitions generated from
+//! `proto/salus/ingest/v1/ingest.proto`
+
+tonic::include_proto!("salus.ingest.v1");
@@ -0,0 +1,290 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::Router;
+use tonic::{Request, Response, Status, Streaming, metadata::MetadataMap};
+
+use crate::{
+    domain::{
+        model::{
+            ingest_action_summary::{IngestActionSummary, IngestEventSaveSummary},
+            ingest_event::{ApiKey, IngestEvent},
+        },
+        service::ingest_event_service::IngestEventService,
+    },
+    grpc_api::{
+        model::grpc_event_request::{GrpcEventRequest, GrpcEventRequestError, GrpcRequestMetadata},
+        proto::{
+            Event, SendEventsRequest, SendEventsResponse,
+            ingest_service_server::{
+                IngestService as IngestRpc, IngestServiceServer, SERVICE_NAME,
+            },
+        },
+    },
+    http_api::model::request_limiter::RequestLimiter,
+};
+
+/// `IngestGrpcService` implements the gRPC `IngestService` for trusted
+/// backends on top of the same `IngestEventService` as the HTTP handlers.
+/// Every call is authenticated with a `ServerKey` and every batch is bounded
+/// by the limits of the `RequestLimiter` on events and their attrs.
+#[derive(Debug)]
+pub struct IngestGrpcService<I: IngestEventService> {
+    ingest_service: Arc<I>,
+    request_limiter: Arc<RequestLimiter>,
+}
+
+impl<I: IngestEventService> IngestGrpcService<I> {
+    /// `IngestGrpcService` constructor that shares the given
+    /// `IngestEventService`
+    pub fn new(ingest_service: Arc<I>) -> Self {
+        Self {
+            ingest_service,
+            request_limiter: Arc::new(RequestLimiter::default()),
+        }
+    }
+
+    /// Replace the `RequestLimiter` used to bound incoming batches
+    pub fn with_request_limiter(mut self, request_limiter: Arc<RequestLimiter>) -> Self {
+        self.request_limiter = request_limiter;
+        self
+    }
+
+    /// Router that serves this service alongside the HTTP routes. Unlike the
+    /// routes of a tonic server, it has no fallback of its own, so that other
+    /// paths are left to the router it is merged into.
+    pub fn into_router<S: Clone + Send + Sync + 'static>(self) -> Router<S> {
+        Router::new().route_service(
+            &format!("/{SERVICE_NAME}/{{*rest}}"),
+            IngestServiceServer::new(self),
+        )
+    }
+
+    /// Check the `ServerKey` of a call for its api key, returning the api key
+    async fn try_api_key(&self, metadata: &MetadataMap) -> Result<String, GrpcEventRequestError> {
+        let metadata = GrpcRequestMetadata::try_from(metadata)?;
+        if !self
+            .ingest_service
+            .is_server_key_valid(&ApiKey::new(&metadata.api_key), &metadata.server_key)
+            .await?
+        {
+            return Err(GrpcEventRequestError::Unauthorized);
+        }
+        Ok(metadata.api_key)
+    }
+
+    /// Convert and save a batch of events, none of which are saved when any
+    /// of them is invalid
+    async fn save_batch(
+        &self,
+        api_key: &str,
+        events: Vec<Event>,
+    ) -> Result<IngestEventSaveSummary, GrpcEventRequestError> {
+        let requests: Vec<GrpcEventRequest> = events
+            .into_iter()
+            .map(|event| GrpcEventRequest {
+                api_key: api_key.to_owned(),
+                event,
+            })
+            .collect();
+        let attrs: Vec<HashMap<String, String>> =
+            requests.iter().map(GrpcEventRequest::attrs).collect();
+        self.request_limiter.check(attrs.iter().map(Some))?;
+        let events = requests
+            .iter()
+            .map(IngestEvent::try_from)
+            .collect::<Result<Vec<IngestEvent>, GrpcEventRequestError>>()?;
+        let IngestActionSummary::Save(summary) = self.ingest_service.save(events).await?;
+        Ok(summary)
+    }
+}
+
+impl From<IngestEventSaveSummary> for SendEventsResponse {
+    fn from(value: IngestEventSaveSummary) -> Self {
+        Self {
+            event_count: value.event_count as u64,
+            duplicate_count: value.duplicate_count as u64,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl<I: IngestEventService> IngestRpc for IngestGrpcService<I> {
+    async fn send_events(
+        &self,
+        request: Request<SendEventsRequest>,
+    ) -> Result<Response<SendEventsResponse>, Status> {
+        let api_key = self.try_api_key(request.metadata()).await?;
+        let summary = self
+            .save_batch(&api_key, request.into_inner().events)
+            .await?;
+        Ok(Response::new(summary.into()))
+    }
+
+    async fn stream_events(
+        &self,
+        request: Request<Streaming<SendEventsRequest>>,
+    ) -> Result<Response<SendEventsResponse>, Status> {
+        let api_key = self.try_api_key(request.metadata()).await?;
+        let mut stream = request.into_inner();
+        let mut response = SendEventsResponse::default();
+        while let Some(batch) = stream.message().await? {
+            if batch.events.is_empty() {
+                continue;
+            }
+            let summary = self.save_batch(&api_key, batch.events).await?;
+            response.event_count += summary.event_count as u64;
+            response.duplicate_count += summary.duplicate_count as u64;
+        }
+        Ok(Response::new(response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use tokio::net::TcpListener;
+    use tonic::Code;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{
+        domain::{
+            model::ingest_source_rules::IngestSourceRules,
+            repository::ingest_event_repository::test::MockIngestEventRepository,
+        },
+        grpc_api::proto::{
+            SectionEvent, VisitorEvent, event::Kind, ingest_service_client::IngestServiceClient,
+        },
+        services::ingest_service::IngestService,
+    };
+
+    fn visitor(id: Uuid) -> Event {
+        Event {
+            id: id.to_string(),
+            site: "test.com".to_owned(),
+            kind: Some(Kind::Visitor(VisitorEvent {})),
+        }
+    }
+
+    fn authorized<T>(message: T) -> Request<T> {
+        let mut request = Request::new(message);
+        request
+            .metadata_mut()
+            .insert("api-key", "abc-123".parse().unwrap());
+        request
+            .metadata_mut()
+            .insert("authorization", "Bearer secret".parse().unwrap());
+        request
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_ingest_grpc_service() {
+        let mock_repo = MockIngestEventRepository {
+            save_result: Ok(IngestActionSummary::Save(IngestEventSaveSummary {
+                event_count: 2,
+                duplicate_count: 0,
+            })),
+            event_source_result: Ok(HashSet::new()),
+            source_rules_result: Ok(IngestSourceRules::default()),
+            server_key_result: Ok(true),
+        };
+        let grpc_service = IngestGrpcService::new(Arc::new(IngestService::new(mock_repo)))
+            .with_request_limiter(Arc::new(
+                RequestLimiter::default()
+                    .with_max_events(2)
+                    .with_max_title(5),
+            ));
+
+        // Serve in process and call through a real client
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router: Router = grpc_service.into_router();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        let mut client = IngestServiceClient::connect(format!("http://{addr}"))
+            .await
+            .unwrap();
+
+        let sent = client
+            .send_events(authorized(SendEventsRequest {
+                events: vec![visitor(Uuid::now_v7()), visitor(Uuid::now_v7())],
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(
+            sent,
+            SendEventsResponse {
+                event_count: 2,
+                duplicate_count: 0
+            }
+        );
+
+        let streamed = client
+            .stream_events(authorized(futures_util::stream::iter(vec![
+                SendEventsRequest {
+                    events: vec![visitor(Uuid::now_v7())],
+                },
+                SendEventsRequest { events: Vec::new() },
+                SendEventsRequest {
+                    events: vec![visitor(Uuid::now_v7())],
+                },
+            ])))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(
+            streamed.event_count, 4,
+            "Expected the counts of every non-empty batch to be summed"
+        );
+
+        // Negative test cases
+        let unauthenticated = client
+            .send_events(SendEventsRequest {
+                events: vec![visitor(Uuid::now_v7())],
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(unauthenticated.code(), Code::Unauthenticated);
+
+        let limited = client
+            .send_events(authorized(SendEventsRequest {
+                events: vec![
+                    visitor(Uuid::now_v7()),
+                    visitor(Uuid::now_v7()),
+                    visitor(Uuid::now_v7()),
+                ],
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(limited.code(), Code::ResourceExhausted);
+
+        let long_title = client
+            .send_events(authorized(SendEventsRequest {
+                events: vec![Event {
+                    id: Uuid::now_v7().to_string(),
+                    site: "test.com".to_owned(),
+                    kind: Some(Kind::Section(SectionEvent {
+                        parent: Uuid::now_v7().to_string(),
+                        location: None,
+                        title: Some("Long title".to_owned()),
+                    })),
+                }],
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            long_title.code(),
+            Code::InvalidArgument,
+            "Expected the title of a gRPC event to be limited as for HTTP"
+        );
+
+        let invalid = client
+            .send_events(authorized(SendEventsRequest {
+                events: vec![visitor(Uuid::nil())],
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(invalid.code(), Code::InvalidArgument);
+    }
+}
@@ -0,0 +1,268 @@
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    body::{Body, Bytes, HttpBody, to_bytes},
+    extract::{Query, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use futures_util::StreamExt;
+use http::{HeaderMap, StatusCode, Uri, header};
+use http_body_util::{BodyExt, BodyStream, StreamBody};
+use time::OffsetDateTime;
+use tracing::instrument;
+
+use crate::{
+    domain::model::server_key::ServerKey,
+    http_api::model::{
+        dead_letter_store::{
+            DeadLetter, DeadLetterQuery, DeadLetterState, DeadLetterStore, RejectionReason,
+        },
+        server_event_request_components::SERVER_KEY_AUTH_SCHEME,
+    },
+};
+
+/// `record_dead_letters` is a middleware that keeps the requests rejected by
+/// the wrapped routes in the `DeadLetterStore`. The start of each body is
+/// copied as it is read, up to the maximum body size of the store, while the
+/// handler still receives the whole body. Requests count as rejected when
+/// the response carries a `RejectionReason` or has a client error status,
+/// such as when the body could not be parsed.
+pub async fn record_dead_letters(
+    State(dead_letter_store): State<Arc<DeadLetterStore>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let (parts, body) = request.into_parts();
+    let (body, body_start, truncated) = read_body_start(body, dead_letter_store.max_body()).await;
+    let site = origin_site(&parts.headers);
+    let method = parts.method.to_string();
+    let path = parts.uri.path().to_owned();
+    let headers = parts.headers.clone();
+
+    let response = next.run(Request::from_parts(parts, body)).await;
+    let reason = response
+        .extensions()
+        .get::<RejectionReason>()
+        .map(|reason| reason.0.clone());
+    if reason.is_none() && !response.status().is_client_error() {
+        return response;
+    }
+    if !dead_letter_store.sample(site.as_deref()) {
+        return response;
+    }
+    let status = response.status();
+    let (response, reason) = match reason {
+        Some(reason) => (response, reason),
+        None => response_reason(response).await,
+    };
+    let (body, base64) = DeadLetter::encoded_body(&body_start);
+    dead_letter_store.push(DeadLetter {
+        site,
+        method,
+        path,
+        status: status.as_u16(),
+        reason,
+        headers: DeadLetter::redacted_headers(&headers),
+        body,
+        base64,
+        truncated,
+        received_at: OffsetDateTime::now_utc(),
+    });
+    response
+}
+
+/// `list_dead_letters` returns the rejected requests kept in the
+/// `DeadLetterStore`, newest first. The query string selects them by `site`
+/// and bounds them with `limit`. Callers must present the admin key as a
+/// bearer token.
+#[instrument(skip_all, fields(site = ?query.site))]
+pub async fn list_dead_letters(
+    State(state): State<DeadLetterState>,
+    headers: HeaderMap,
+    Query(query): Query<DeadLetterQuery>,
+) -> Result<Json<Vec<DeadLetter>>, StatusCode> {
+    if !is_admin(&headers, &state.admin_key) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    Ok(Json(state.dead_letter_store.dead_letters(&query)))
+}
+
+/// Read the frames of `body` until `max_body` bytes have been copied or the
+/// body ends. Returns a body that yields the frames that were read followed
+/// by the rest, the copied bytes and whether the copy is incomplete.
+async fn read_body_start(mut body: Body, max_body: usize) -> (Body, Vec<u8>, bool) {
+    let mut frames = Vec::new();
+    let mut body_start = Vec::new();
+    let mut truncated = false;
+    while body_start.len() < max_body {
+        let Some(frame) = body.frame().await else {
+            break;
+        };
+        let failed = frame.is_err();
+        if let Ok(data) = frame.as_ref().map(|frame| frame.data_ref()) {
+            let data = data.map(Bytes::as_ref).unwrap_or_default();
+            let remaining = max_body - body_start.len();
+            truncated = data.len() > remaining;
+            body_start.extend_from_slice(&data[..data.len().min(remaining)]);
+        }
+        frames.push(frame);
+        // Errors such as an exceeded body limit are left to the handler
+        if failed {
+            break;
+        }
+    }
+    if body_start.len() >= max_body && !body.is_end_stream() {
+        truncated = true;
+    }
+    let rest = BodyStream::new(body);
+    let body = Body::new(StreamBody::new(
+        futures_util::stream::iter(frames).chain(rest),
+    ));
+    (body, body_start, truncated)
+}
+
+/// Reason of a response without a `RejectionReason`, which is the text that
+/// Axum responds with when an extractor rejects a request, or the canonical
+/// reason of the status otherwise. These responses are short, so the body is
+/// read whole and handed on.
+async fn response_reason(response: Response) -> (Response, String) {
+    let (parts, body) = response.into_parts();
+    let body = to_bytes(body, usize::MAX).await.unwrap_or_default();
+    let reason = match std::str::from_utf8(&body) {
+        Ok(text) if !text.trim().is_empty() => text.trim().to_owned(),
+        _ => parts
+            .status
+            .canonical_reason()
+            .unwrap_or_default()
+            .to_owned(),
+    };
+    (Response::from_parts(parts, Body::from(body)), reason)
+}
+
+/// Host of the `Origin` header, which is the site of a client request
+fn origin_site(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(header::ORIGIN)?
+        .to_str()
+        .ok()?
+        .parse::<Uri>()
+        .ok()?
+        .host()
+        .map(str::to_owned)
+}
+
+/// Whether the headers carry the admin key as a bearer token. Digests are
+/// compared so that the time taken does not depend on the key.
+fn is_admin(headers: &HeaderMap, admin_key: &ServerKey) -> bool {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|authorization| authorization.to_str().ok())
+        .and_then(|authorization| authorization.split_once(' '))
+        .filter(|(scheme, _)| scheme.eq_ignore_ascii_case(SERVER_KEY_AUTH_SCHEME))
+        .is_some_and(|(_, secret)| {
+            let server_key = ServerKey::new(secret);
+            !server_key.is_empty() && server_key.digest() == admin_key.digest()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, middleware::from_fn_with_state, response::IntoResponse, routing::post};
+    use tower::ServiceExt;
+
+    use crate::http_api::model::{
+        client_event_request::ClientEventRequestError,
+        client_event_request_components::API_KEY_HTTP_HEADER,
+    };
+
+    use super::*;
+
+    async fn reject(body: Bytes) -> Response {
+        assert_eq!(body.as_ref(), b"[{\"event_type\":1}]");
+        ClientEventRequestError::InvalidRequestBody.into_response()
+    }
+
+    #[tokio::test]
+    async fn test_record_dead_letters() {
+        let dead_letter_store = Arc::new(DeadLetterStore::new(10, 1, 8));
+        let app = Router::new()
+            .route("/multi", post(reject))
+            .route("/ok", post(|| async { StatusCode::CREATED }))
+            .layer(from_fn_with_state(
+                dead_letter_store.clone(),
+                record_dead_letters,
+            ));
+        let request = |path: &str| {
+            Request::post(path)
+                .header(header::ORIGIN, "http://test.com")
+                .header(API_KEY_HTTP_HEADER, "abc-123")
+                .body(Body::from("[{\"event_type\":1}]"))
+                .unwrap()
+        };
+        let response = app.clone().oneshot(request("/multi")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = app.oneshot(request("/ok")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let dead_letters = dead_letter_store.dead_letters(&DeadLetterQuery::default());
+        assert_eq!(dead_letters.len(), 1, "Expected only the rejected request");
+        let dead_letter = &dead_letters[0];
+        assert_eq!(dead_letter.site.as_deref(), Some("test.com"));
+        assert_eq!(dead_letter.path, "/multi");
+        assert_eq!(dead_letter.reason, "Invalid request body");
+        assert_eq!(dead_letter.body, "[{\"event");
+        assert!(dead_letter.truncated, "Expected the body to be cut off");
+        assert!(
+            !dead_letter
+                .headers
+                .iter()
+                .any(|(_, value)| value == "abc-123"),
+            "Expected the api key to be redacted"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_dead_letters() {
+        let state = DeadLetterState {
+            dead_letter_store: Arc::new(DeadLetterStore::new(10, 1, 1024)),
+            admin_key: ServerKey::new("secret"),
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, "Bearer secret".parse().unwrap());
+        assert!(
+            list_dead_letters(
+                State(state.clone()),
+                headers,
+                Query(DeadLetterQuery::default())
+            )
+            .await
+            .is_ok()
+        );
+
+        // Negative test cases
+        let mut wrong_key = HeaderMap::new();
+        wrong_key.insert(header::AUTHORIZATION, "Bearer other".parse().unwrap());
+        assert_eq!(
+            list_dead_letters(
+                State(state.clone()),
+                wrong_key,
+                Query(DeadLetterQuery::default())
+            )
+            .await
+            .unwrap_err(),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            list_dead_letters(
+                State(state),
+                HeaderMap::new(),
+                Query(DeadLetterQuery::default())
+            )
+            .await
+            .unwrap_err(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+}
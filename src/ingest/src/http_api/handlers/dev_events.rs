@@ -0,0 +1,85 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+use http::StatusCode;
+use tracing::instrument;
+
+use crate::repositories::{
+    memory_ingest_repository::{MemoryEventQuery, MemoryIngestRepository},
+    sink_event_record::SinkEventRecord,
+};
+
+/// `list_dev_events` returns the events kept by the `MemoryIngestRepository`
+/// of dev mode, newest first, so that developers can inspect what their
+/// instrumentation sends. The query string selects events by `api_key`,
+/// `site` and `event_type` and bounds them with `limit`.
+#[instrument(skip_all)]
+pub async fn list_dev_events(
+    State(memory_repository): State<MemoryIngestRepository>,
+    Query(query): Query<MemoryEventQuery>,
+) -> Json<Vec<SinkEventRecord>> {
+    Json(memory_repository.events(&query))
+}
+
+/// `clear_dev_events` drops every event kept by the `MemoryIngestRepository`
+/// of dev mode
+#[instrument(skip_all)]
+pub async fn clear_dev_events(
+    State(memory_repository): State<MemoryIngestRepository>,
+) -> StatusCode {
+    memory_repository.clear();
+    StatusCode::NO_CONTENT
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::domain::{
+        model::ingest_event::{ApiKey, IngestEvent, Site, VisitorEvent},
+        repository::ingest_event_repository::IngestEventRepository,
+    };
+    use crate::repositories::sink_event_record::SinkEventRecordType;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dev_events() {
+        let memory_repository = MemoryIngestRepository::new(10);
+        let event = IngestEvent::Visitor(
+            VisitorEvent::try_new(
+                ApiKey::new("abc-123"),
+                Site::new("localhost"),
+                Uuid::now_v7(),
+            )
+            .unwrap(),
+        );
+        memory_repository.save(vec![event.clone()]).await.unwrap();
+
+        let Json(records) = list_dev_events(
+            State(memory_repository.clone()),
+            Query(MemoryEventQuery::default()),
+        )
+        .await;
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].id, event.id());
+        assert_eq!(records[0].event_type, SinkEventRecordType::Visitor);
+
+        let Json(records) = list_dev_events(
+            State(memory_repository.clone()),
+            Query(MemoryEventQuery {
+                event_type: Some(SinkEventRecordType::Click),
+                ..MemoryEventQuery::default()
+            }),
+        )
+        .await;
+        assert!(records.is_empty(), "Expected no click events");
+
+        assert_eq!(
+            clear_dev_events(State(memory_repository.clone())).await,
+            StatusCode::NO_CONTENT
+        );
+        assert!(memory_repository.is_empty());
+    }
+}
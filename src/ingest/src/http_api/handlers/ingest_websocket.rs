@@ -0,0 +1,192 @@
+use std::net::IpAddr;
+
+use axum::{
+    extract::{
+        Query, State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    response::Response,
+};
+use axum_client_ip::ClientIp;
+use http::HeaderMap;
+use tracing::instrument;
+
+use crate::{
+    domain::{
+        model::ingest_event::{ApiKey, IngestEventSource, Site},
+        service::ingest_event_service::IngestEventService,
+    },
+    http_api::{
+        handlers::save_client_events::save_client_bodies,
+        model::{
+            client_event_request::ClientEventRequestError,
+            client_event_request_components::ClientEventRequestHeaders,
+            ingest_application_state::IngestApplicationState,
+            websocket_request::{
+                MAX_WEBSOCKET_MESSAGE_BYTES, WebSocketAck, WebSocketFrame, WebSocketQuery,
+            },
+        },
+    },
+};
+
+/// `ingest_websocket` upgrades a connection from a long lived page to a
+/// WebSocket over which it streams client events. The connection is
+/// authenticated once, before the upgrade, using the same api key and origin
+/// rules as `save_client_events`, and only for a configured source. Each text
+/// message then carries the JSON of one `ClientEventRequestBody` or a list of
+/// them, which is saved as a batch of `save_client_events` would be and
+/// answered with a `WebSocketAck`. Binary messages are not accepted and are
+/// acknowledged as unsupported.
+#[instrument(skip(ws))]
+pub async fn ingest_websocket<I: IngestEventService + std::fmt::Debug>(
+    State(state): State<IngestApplicationState<I>>,
+    Query(query): Query<WebSocketQuery>,
+    headers: HeaderMap,
+    client_ip: ClientIp,
+    ws: WebSocketUpgrade,
+) -> Result<Response, ClientEventRequestError> {
+    let client_request_headers = query.try_headers(&headers)?;
+    let source = IngestEventSource::new(
+        ApiKey::new(&client_request_headers.api_key),
+        Site::new(&client_request_headers.site),
+    );
+    if !state
+        .ingest_service
+        .event_sources()
+        .await?
+        .contains(&source)
+    {
+        return Err(ClientEventRequestError::ApiKey);
+    }
+    Ok(ws
+        .max_message_size(MAX_WEBSOCKET_MESSAGE_BYTES)
+        .on_upgrade(move |socket| {
+            stream_events(socket, state, client_request_headers, client_ip.0)
+        }))
+}
+
+/// Save the events of every message of an upgraded connection in order and
+/// acknowledge each, until the client closes the connection
+async fn stream_events<I: IngestEventService>(
+    mut socket: WebSocket,
+    state: IngestApplicationState<I>,
+    client_request_headers: ClientEventRequestHeaders,
+    ip: IpAddr,
+) {
+    let mut frame: u64 = 0;
+    while let Some(message) = socket.recv().await {
+        let ack = match message {
+            Ok(Message::Text(text)) => {
+                frame += 1;
+                save_frame(&state, &client_request_headers, ip, frame, text.as_str()).await
+            }
+            Ok(Message::Binary(_)) => {
+                frame += 1;
+                WebSocketAck::new(frame, Err(ClientEventRequestError::UnsupportedMediaType))
+            }
+            Ok(Message::Close(_)) => break,
+            // Pings are answered by axum
+            Ok(_) => continue,
+            Err(e) => {
+                tracing::debug!("WebSocket connection failed: {e}");
+                break;
+            }
+        };
+        let ack = match serde_json::to_string(&ack) {
+            Ok(ack) => ack,
+            Err(e) => {
+                tracing::error!("Failed to serialize WebSocketAck: {e}");
+                break;
+            }
+        };
+        if socket.send(Message::Text(ack.into())).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Decode and save the events of a single text message
+async fn save_frame<I: IngestEventService>(
+    state: &IngestApplicationState<I>,
+    client_request_headers: &ClientEventRequestHeaders,
+    ip: IpAddr,
+    frame: u64,
+    text: &str,
+) -> WebSocketAck {
+    let result = match serde_json::from_str::<WebSocketFrame>(text) {
+        Ok(event_frame) => {
+            save_client_bodies(state, client_request_headers, ip, event_frame.into_bodies()).await
+        }
+        Err(e) => {
+            tracing::debug!("Failed to decode WebSocket message: {e}");
+            Err(ClientEventRequestError::InvalidRequestBody)
+        }
+    };
+    WebSocketAck::new(frame, result)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, net::Ipv4Addr};
+
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{
+        domain::{
+            model::{
+                ingest_action_summary::{IngestActionSummary, IngestEventSaveSummary},
+                ingest_source_rules::IngestSourceRules,
+            },
+            repository::ingest_event_repository::test::MockIngestEventRepository,
+        },
+        http_api::model::request_limiter::RequestLimiter,
+        services::ingest_service::IngestService,
+    };
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_save_frame() {
+        let mock_repo = MockIngestEventRepository {
+            save_result: Ok(IngestActionSummary::Save(IngestEventSaveSummary {
+                event_count: 1,
+                duplicate_count: 0,
+            })),
+            event_source_result: Ok(HashSet::from([IngestEventSource::new(
+                ApiKey::new("abc-123"),
+                Site::new("test.com"),
+            )])),
+            source_rules_result: Ok(IngestSourceRules::default()),
+            server_key_result: Ok(false),
+        };
+        let state = IngestApplicationState::new(IngestService::new(mock_repo))
+            .with_request_limiter(RequestLimiter::default().with_max_events(1));
+        let client_request_headers = ClientEventRequestHeaders::new(
+            "abc-123",
+            "test.com",
+            "Mozilla/5.0 (X11; Linux x86_64; rv:135.0) Gecko/20100101 Firefox/135.0",
+        );
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let visitor = format!(r#"{{"t":1,"i":"{}"}}"#, Uuid::now_v7());
+
+        let saved = save_frame(&state, &client_request_headers, ip, 1, &visitor).await;
+        assert_eq!((saved.frame, saved.status), (1, 201));
+        assert_eq!(saved.event_count, 1);
+
+        let invalid = save_frame(&state, &client_request_headers, ip, 2, "{\"t\":1}").await;
+        assert_eq!((invalid.frame, invalid.status), (2, 400));
+
+        let limited = save_frame(
+            &state,
+            &client_request_headers,
+            ip,
+            3,
+            &format!("[{visitor},{visitor}]"),
+        )
+        .await;
+        assert_eq!(
+            (limited.status, limited.retry),
+            (413, false),
+            "Expected a batch over the limit to be rejected as it would be over HTTP"
+        );
+    }
+}
@@ -1 +1,11 @@
+pub mod dead_letters;
+pub mod dev_events;
+pub mod ingest_websocket;
 pub mod save_client_events;
+pub mod save_measurement_protocol_events;
+pub mod save_ndjson_events;
+pub mod save_plausible_event;
+pub mod save_segment_events;
+pub mod save_server_events;
+pub mod tail_events;
+pub mod validate_client_events;
@@ -1,36 +1,125 @@
-use axum::{Json, extract::State};
+use std::net::IpAddr;
+
+use axum::extract::State;
 use axum_client_ip::ClientIp;
+use time::OffsetDateTime;
 use tracing::instrument;
 
 use crate::{
-    domain::{model::ingest_event::IngestEvent, service::ingest_event_service::IngestEventService},
+    domain::{
+        model::{ingest_event::IngestEvent, session_synthesizer::SyntheticVisits},
+        service::ingest_event_service::IngestEventService,
+    },
     http_api::model::{
         client_event_action_summary::ClientEventActionSummary,
         client_event_request::{ClientEventRequest, ClientEventRequestError},
         client_event_request_components::{ClientEventRequestBody, ClientEventRequestHeaders},
         ingest_application_state::IngestApplicationState,
+        live_tail::{TailEvent, TailStatus},
+        wire_format::Negotiated,
     },
 };
 
-/// `save_client_events` expects POST data in JSON format that consists of
-/// a list of `ClientEventRequestBody` structs as well as information in the
+/// `save_client_events` expects POST data in JSON, MessagePack or CBOR format,
+/// as given by the `Content-Type` header, that consists of a list of
+/// `ClientEventRequestBody` structs as well as information in the
 /// HTTP headers which can be used to determine the `api_key` and the `site`
 /// for the incoming request. `site` is determined in a simple fashion by
 /// examining the referrer attribute, whereas the api_key uses a custom header
-/// as specified in `client_event_request_components::API_KEY_HTTP_HEADER`
-#[instrument]
+/// as specified in `client_event_request_components::API_KEY_HTTP_HEADER`.
+/// Requests that exceed the limits of the `RequestLimiter` are rejected before
+/// any event is converted.
+#[instrument(skip(event_bodies))]
 pub async fn save_client_events<I: IngestEventService + std::fmt::Debug>(
     State(state): State<IngestApplicationState<I>>,
     client_request_headers: ClientEventRequestHeaders,
     client_ip: ClientIp,
-    Json(event_bodies): Json<Vec<ClientEventRequestBody>>,
+    Negotiated(event_bodies): Negotiated<Vec<ClientEventRequestBody>>,
+) -> Result<ClientEventActionSummary, ClientEventRequestError> {
+    save_client_bodies(&state, &client_request_headers, client_ip.0, event_bodies).await
+}
+
+/// Check a batch of `ClientEventRequestBody` structs against the limits of the
+/// `RequestLimiter`, convert them with the shared `ClientEventRequestHeaders`
+/// and save them. Shared by every handler that accepts client events. When
+/// the source is being tailed, every event is published to the `LiveTail`
+/// along with whether the batch was accepted.
+pub(crate) async fn save_client_bodies<I: IngestEventService>(
+    state: &IngestApplicationState<I>,
+    client_request_headers: &ClientEventRequestHeaders,
+    ip: IpAddr,
+    event_bodies: Vec<ClientEventRequestBody>,
+) -> Result<ClientEventActionSummary, ClientEventRequestError> {
+    let tailed_bodies = state
+        .live_tail
+        .is_tailed(
+            &client_request_headers.api_key,
+            &client_request_headers.site,
+        )
+        .then(|| event_bodies.clone());
+    let result = save_untailed_bodies(state, client_request_headers, ip, event_bodies).await;
+    if let Some(tailed_bodies) = tailed_bodies {
+        let (status, reason) = match &result {
+            Ok(_) => (TailStatus::Accepted, None),
+            Err(e) => (
+                TailStatus::Rejected,
+                Some(e.rejection_reason().unwrap_or_else(|| e.to_string())),
+            ),
+        };
+        let received_at = OffsetDateTime::now_utc();
+        for event in tailed_bodies {
+            state.live_tail.publish(
+                &client_request_headers.api_key,
+                &client_request_headers.site,
+                TailEvent {
+                    status,
+                    reason: reason.clone(),
+                    event,
+                    received_at,
+                },
+            );
+        }
+    }
+    result
+}
+
+/// Check the events converted from a request to a compatibility API against
+/// the limits of the `RequestLimiter` and save them together with the
+/// `Visitor` and `Session` parents synthesized for them, which the client did
+/// not send and are not counted. The `SyntheticVisits` are only committed once
+/// the events were saved, so that later events never reference parents that
+/// were not stored. Shared by the Plausible, Measurement Protocol and Segment
+/// handlers.
+pub(crate) async fn save_synthesized_events<I: IngestEventService>(
+    state: &IngestApplicationState<I>,
+    events: Vec<IngestEvent>,
+    visits: SyntheticVisits,
+) -> Result<(), ClientEventRequestError> {
+    state.request_limiter.check_events(
+        events
+            .iter()
+            .filter(|event| !matches!(event, IngestEvent::Visitor(_) | IngestEvent::Session(_))),
+    )?;
+    state.ingest_service.save(events).await?;
+    state.session_synthesizer.commit(visits);
+    Ok(())
+}
+
+async fn save_untailed_bodies<I: IngestEventService>(
+    state: &IngestApplicationState<I>,
+    client_request_headers: &ClientEventRequestHeaders,
+    ip: IpAddr,
+    event_bodies: Vec<ClientEventRequestBody>,
 ) -> Result<ClientEventActionSummary, ClientEventRequestError> {
+    state
+        .request_limiter
+        .check(event_bodies.iter().map(|eb| eb.attrs.as_ref()))?;
     let requests: Vec<ClientEventRequest> = event_bodies
-        .iter()
-        .map(|eb| ClientEventRequest {
-            body: eb.to_owned(),
+        .into_iter()
+        .map(|body| ClientEventRequest {
+            body,
             headers: client_request_headers.clone(),
-            ip: client_ip.0,
+            ip,
         })
         .collect();
     let mut events: Vec<IngestEvent> = Vec::with_capacity(requests.len());
@@ -49,11 +138,13 @@ pub async fn save_client_events<I: IngestEventService + std::fmt::Debug>(
 #[cfg(test)]
 mod tests {
     use std::{
-        collections::HashSet,
+        collections::{HashMap, HashSet},
         net::{IpAddr, Ipv4Addr},
     };
 
     use super::*;
+    use axum::response::IntoResponse;
+    use http::StatusCode;
     use uuid::Uuid;
 
     use crate::{
@@ -61,10 +152,14 @@ mod tests {
             model::{
                 ingest_action_summary::{IngestActionSummary, IngestEventSaveSummary},
                 ingest_event::{ApiKey, IngestEventSource, Site},
+                ingest_source_rules::IngestSourceRules,
             },
             repository::ingest_event_repository::test::MockIngestEventRepository,
         },
-        http_api::model::client_event_request::ClientEventRequestType,
+        http_api::model::{
+            client_event_request::ClientEventRequestType, live_tail::TailMessage,
+            request_limiter::RequestLimiter,
+        },
         services::ingest_service::IngestService,
     };
 
@@ -77,11 +172,14 @@ mod tests {
         let mock_success_repo = MockIngestEventRepository {
             save_result: Ok(IngestActionSummary::Save(IngestEventSaveSummary {
                 event_count: 1,
+                duplicate_count: 0,
             })),
             event_source_result: Ok(HashSet::from([IngestEventSource::new(
                 ApiKey::new("abc-123"),
                 Site::new("test.com"),
             )])),
+            source_rules_result: Ok(IngestSourceRules::default()),
+            server_key_result: Ok(false),
         };
         let test_success_service = IngestService::new(mock_success_repo);
         let test_success_state = IngestApplicationState::new(test_success_service);
@@ -98,7 +196,7 @@ mod tests {
                 user_agent: "Mozilla/5.0 (Macintosh; Intel Mac OS X 10.15; rv:135.0) Gecko/20100101 Firefox/135.0".to_owned(),
             },
             test_client_ip,
-            Json(valid_request_bodies),
+            Negotiated(valid_request_bodies),
         )
         .await;
         let Ok(ClientEventActionSummary::Save(save_summary)) = save_client_events_success else {
@@ -118,11 +216,126 @@ mod tests {
                 user_agent: "Mozilla/5.0 (Macintosh; Intel Mac OS X 10.15; rv:135.0) Gecko/20100101 Firefox/135.0".to_owned(),
             },
             test_client_ip,
-            Json(invalid_request_bodies),
+            Negotiated(invalid_request_bodies),
         )
         .await;
         let Err(ClientEventRequestError::IngestService(_)) = save_client_events_invalid else {
             panic!("Expected error from HTTP mock");
         };
+
+        // Functional repo, but too many events for the limiter
+        let test_limited_state = test_success_state
+            .clone()
+            .with_request_limiter(RequestLimiter::default().with_max_events(1));
+        let save_client_events_limited = save_client_events(
+            State(test_limited_state.clone()),
+            ClientEventRequestHeaders {
+                api_key: "abc-123".to_owned(),
+                site: "test.com".to_owned(),
+                user_agent: "Mozilla/5.0 (Macintosh; Intel Mac OS X 10.15; rv:135.0) Gecko/20100101 Firefox/135.0".to_owned(),
+            },
+            test_client_ip,
+            Negotiated(vec![
+                ClientEventRequestBody::new(ClientEventRequestType::Visitor, Uuid::now_v7(), None),
+                ClientEventRequestBody::new(ClientEventRequestType::Visitor, Uuid::now_v7(), None),
+            ]),
+        )
+        .await;
+        let Err(limit_error) = save_client_events_limited else {
+            panic!("Expected limit error from HTTP mock");
+        };
+        assert_eq!(
+            limit_error.into_response().status(),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+        assert_eq!(test_limited_state.request_limiter.report().events, 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_save_client_events_tail() {
+        let test_state =
+            IngestApplicationState::new(IngestService::new(MockIngestEventRepository {
+                save_result: Ok(IngestActionSummary::Save(IngestEventSaveSummary {
+                    event_count: 1,
+                    duplicate_count: 0,
+                })),
+                event_source_result: Ok(HashSet::new()),
+                source_rules_result: Ok(IngestSourceRules::default()),
+                server_key_result: Ok(false),
+            }))
+            .with_request_limiter(RequestLimiter::default().with_max_events(1));
+        let mut subscription = test_state
+            .live_tail
+            .subscribe("abc-123", "test.com", None)
+            .unwrap();
+        let headers = ClientEventRequestHeaders::new("abc-123", "test.com", "test-agent");
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let accepted =
+            ClientEventRequestBody::new(ClientEventRequestType::Visitor, Uuid::now_v7(), None);
+        save_client_bodies(&test_state, &headers, ip, vec![accepted.clone()])
+            .await
+            .unwrap();
+        save_client_bodies(
+            &test_state,
+            &headers,
+            ip,
+            vec![accepted.clone(), accepted.clone()],
+        )
+        .await
+        .unwrap_err();
+
+        let Some(TailMessage::Event(event)) = subscription.next().await else {
+            panic!("Expected the accepted event to be tailed");
+        };
+        assert_eq!(event.status, TailStatus::Accepted);
+        assert_eq!(event.event.id, accepted.id);
+        let Some(TailMessage::Event(event)) = subscription.next().await else {
+            panic!("Expected the rejected events to be tailed");
+        };
+        assert_eq!(event.status, TailStatus::Rejected);
+        assert_eq!(
+            event.reason.as_deref(),
+            Some("Request exceeds a limit: Request contains too many events")
+        );
+
+        assert!(matches!(
+            subscription.next().await,
+            Some(TailMessage::Event(_))
+        ));
+
+        // Negative test case
+        let other_headers = ClientEventRequestHeaders::new("abc-123", "other.com", "test-agent");
+        save_client_bodies(&test_state, &other_headers, ip, vec![accepted])
+            .await
+            .unwrap();
+        assert!(
+            tokio::time::timeout(std::time::Duration::from_millis(100), subscription.next())
+                .await
+                .is_err(),
+            "Expected events of other sites not to be tailed"
+        );
+
+        let identify = ClientEventRequestBody::new(
+            ClientEventRequestType::Identify,
+            Uuid::now_v7(),
+            Some(HashMap::from([
+                ("p".to_owned(), Uuid::now_v7().to_string()),
+                ("u".to_owned(), "user-1234".to_owned()),
+            ])),
+        );
+        // Rejected as the source has no identity key, but tailed regardless
+        save_client_bodies(&test_state, &headers, ip, vec![identify])
+            .await
+            .unwrap_err();
+        let Some(TailMessage::Event(event)) = subscription.next().await else {
+            panic!("Expected the identify event to be tailed");
+        };
+        assert_eq!(event.event.event_type, ClientEventRequestType::Identify);
+        assert!(
+            !serde_json::to_string(&*event)
+                .unwrap()
+                .contains("user-1234"),
+            "Expected the user id to not be tailed in clear"
+        );
     }
 }
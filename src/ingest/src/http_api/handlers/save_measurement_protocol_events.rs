@@ -0,0 +1,225 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+use axum_client_ip::ClientIp;
+use http::{HeaderMap, StatusCode, header};
+use time::OffsetDateTime;
+use tracing::instrument;
+
+use crate::{
+    domain::{
+        model::{
+            ingest_event::{IngestEvent, IngestEventOrigin},
+            server_key::ServerKey,
+            session_synthesizer::{SyntheticSessionRequest, SyntheticVisits, uuid_v7_at},
+        },
+        service::ingest_event_service::IngestEventService,
+    },
+    http_api::{
+        handlers::save_client_events::save_synthesized_events,
+        model::{
+            client_event_request::ClientEventRequestError,
+            ingest_application_state::IngestApplicationState,
+            measurement_protocol_request::{
+                MeasurementProtocolQuery, MeasurementProtocolRequest,
+                MeasurementProtocolRequestBody,
+            },
+        },
+    },
+};
+
+/// `save_measurement_protocol_events` accepts requests in the format of the
+/// GA4 Measurement Protocol so that apps and backends already emitting it can
+/// send their events here instead. The `measurement_id` is the `ApiKey` and
+/// the `api_secret` must be a valid `ServerKey` for it, so like the other
+/// server-to-server route this refuses requests issued by a browser. The
+/// `client_id` identifies the visitor, for which the `Visitor` and `Session`
+/// parents are synthesized, and events are held to the timestamp window of
+/// `IngestEventOrigin::Server`. As with GA4 the response is `204 No Content`.
+#[instrument(skip(query))]
+pub async fn save_measurement_protocol_events<I: IngestEventService + std::fmt::Debug>(
+    State(state): State<IngestApplicationState<I>>,
+    Query(query): Query<MeasurementProtocolQuery>,
+    headers: HeaderMap,
+    client_ip: ClientIp,
+    Json(body): Json<MeasurementProtocolRequestBody>,
+) -> Result<StatusCode, ClientEventRequestError> {
+    if headers.contains_key(header::ORIGIN) {
+        return Err(ClientEventRequestError::Forbidden);
+    }
+    let request = MeasurementProtocolRequest {
+        user_agent: headers
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_owned(),
+        ip: client_ip.0,
+        query,
+        body,
+    };
+    if !state
+        .ingest_service
+        .is_server_key_valid(
+            &request.api_key(),
+            &ServerKey::new(&request.query.api_secret),
+        )
+        .await?
+    {
+        return Err(ClientEventRequestError::Unauthorized);
+    }
+    let source = request.try_source(&state.ingest_service.event_sources().await?)?;
+
+    let now = OffsetDateTime::now_utc();
+    let mut visits = SyntheticVisits::default();
+    let mut events: Vec<IngestEvent> = Vec::with_capacity(request.body.events.len() + 2);
+    for event in request.body.events.iter() {
+        let ts = request.try_event_ts(event, now)?;
+        let session_id = state.session_synthesizer.try_session(
+            SyntheticSessionRequest {
+                source: source.clone(),
+                visitor_key: request.body.client_id.to_owned(),
+                ip: request.end_user_ip(),
+                user_agent: request.end_user_agent().to_owned(),
+                referrer: event.referrer(),
+                ts,
+                origin: IngestEventOrigin::Server,
+            },
+            &mut visits,
+            &mut events,
+        )?;
+        events.push(request.try_into_event(event, &source, uuid_v7_at(&ts), session_id)?);
+    }
+
+    save_synthesized_events(&state, events, visits).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashSet,
+        net::{IpAddr, Ipv4Addr},
+    };
+
+    use axum::response::IntoResponse;
+
+    use super::*;
+
+    use crate::{
+        domain::{
+            model::{
+                ingest_action_summary::{IngestActionSummary, IngestEventSaveSummary},
+                ingest_event::{ApiKey, IngestEventSource, Site},
+                ingest_source_rules::IngestSourceRules,
+            },
+            repository::ingest_event_repository::test::MockIngestEventRepository,
+        },
+        http_api::model::request_limiter::RequestLimiter,
+        services::ingest_service::IngestService,
+    };
+
+    fn measurement_state(
+        server_key_valid: bool,
+    ) -> IngestApplicationState<IngestService<MockIngestEventRepository>> {
+        IngestApplicationState::new(IngestService::new(MockIngestEventRepository {
+            save_result: Ok(IngestActionSummary::Save(IngestEventSaveSummary {
+                event_count: 3,
+                duplicate_count: 0,
+            })),
+            event_source_result: Ok(HashSet::from([IngestEventSource::new(
+                ApiKey::new("G-ABC"),
+                Site::new("test.com"),
+            )])),
+            source_rules_result: Ok(IngestSourceRules::default()),
+            server_key_result: Ok(server_key_valid),
+        }))
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_save_measurement_protocol_events() {
+        let query = MeasurementProtocolQuery {
+            measurement_id: "G-ABC".to_owned(),
+            api_secret: "secret".to_owned(),
+            site: None,
+        };
+        let body: MeasurementProtocolRequestBody = serde_json::from_str(
+            r#"{"client_id":"123.456","events":[{"name":"page_view","params":{"page_location":"https://test.com/"}}]}"#,
+        )
+        .unwrap();
+        let client_ip = || ClientIp(IpAddr::V4(Ipv4Addr::LOCALHOST));
+
+        let Ok(StatusCode::NO_CONTENT) = save_measurement_protocol_events(
+            State(measurement_state(true)),
+            Query(query.clone()),
+            HeaderMap::new(),
+            client_ip(),
+            Json(body.clone()),
+        )
+        .await
+        else {
+            panic!("Expected successful save from HTTP mock");
+        };
+
+        let Err(ClientEventRequestError::Unauthorized) = save_measurement_protocol_events(
+            State(measurement_state(false)),
+            Query(query.clone()),
+            HeaderMap::new(),
+            client_ip(),
+            Json(body.clone()),
+        )
+        .await
+        else {
+            panic!("Expected unauthorized error for an invalid api secret");
+        };
+
+        let mut browser_headers = HeaderMap::new();
+        browser_headers.insert(header::ORIGIN, "https://test.com".parse().unwrap());
+        let Err(ClientEventRequestError::Forbidden) = save_measurement_protocol_events(
+            State(measurement_state(true)),
+            Query(query),
+            browser_headers,
+            client_ip(),
+            Json(body),
+        )
+        .await
+        else {
+            panic!("Expected requests from a browser to be refused");
+        };
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_save_measurement_protocol_events_limits() {
+        let state = measurement_state(true)
+            .with_request_limiter(RequestLimiter::default().with_max_events(1));
+        let body: MeasurementProtocolRequestBody = serde_json::from_str(
+            r#"{"client_id":"123.456","events":[{"name":"page_view"},{"name":"purchase"}]}"#,
+        )
+        .unwrap();
+
+        let Err(limit_error) = save_measurement_protocol_events(
+            State(state.clone()),
+            Query(MeasurementProtocolQuery {
+                measurement_id: "G-ABC".to_owned(),
+                api_secret: "secret".to_owned(),
+                site: None,
+            }),
+            HeaderMap::new(),
+            ClientIp(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            Json(body),
+        )
+        .await
+        else {
+            panic!("Expected a batch beyond the limit to be rejected");
+        };
+        assert_eq!(
+            limit_error.into_response().status(),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+        assert_eq!(
+            state.request_limiter.report().events,
+            1,
+            "Expected synthesized parents to not count as events"
+        );
+    }
+}
@@ -0,0 +1,274 @@
+use axum::{body::Body, extract::State};
+use futures_util::StreamExt;
+use http::HeaderMap;
+use tracing::instrument;
+
+use crate::{
+    domain::{
+        model::{
+            ingest_action_summary::IngestActionSummary,
+            ingest_event::{ApiKey, IngestEvent},
+        },
+        service::ingest_event_service::{IngestEventService, IngestServiceError},
+    },
+    http_api::model::{
+        client_event_request::ClientEventRequestError,
+        ingest_application_state::IngestApplicationState,
+        ndjson_request::{NdjsonLine, NdjsonSaveSummary, NdjsonSplitter, is_ndjson},
+        server_event_request::ServerEventRequest,
+        server_event_request_components::{ServerEventRequestBody, ServerEventRequestHeaders},
+    },
+};
+
+/// `save_ndjson_events` reads a stream of newline delimited JSON with one
+/// `ServerEventRequestBody` per line from a trusted backend, authenticated in
+/// the same way as `save_server_events`. The body is read incrementally and
+/// events are saved in chunks of at most the maximum events of the
+/// `RequestLimiter`, so that memory use does not grow with the size of the
+/// stream. Lines that cannot be converted into an `IngestEvent`, or whose
+/// events fail the rules of the `IngestEventService` such as being for a site
+/// that is not configured, are skipped and reported by line number in the
+/// `NdjsonSaveSummary`. A failure to save a chunk ends the request, and as
+/// the earlier chunks are remembered as saved, the whole stream can be sent
+/// again.
+#[instrument(skip(body))]
+pub async fn save_ndjson_events<I: IngestEventService + std::fmt::Debug>(
+    State(state): State<IngestApplicationState<I>>,
+    server_request_headers: ServerEventRequestHeaders,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<NdjsonSaveSummary, ClientEventRequestError> {
+    if !is_ndjson(&headers) {
+        return Err(ClientEventRequestError::UnsupportedMediaType);
+    }
+    if !state
+        .ingest_service
+        .is_server_key_valid(
+            &ApiKey::new(&server_request_headers.api_key),
+            &server_request_headers.server_key,
+        )
+        .await?
+    {
+        return Err(ClientEventRequestError::Unauthorized);
+    }
+
+    let chunk_size = state.request_limiter.max_events();
+    let mut summary = NdjsonSaveSummary::default();
+    let mut splitter = NdjsonSplitter::default();
+    // Events are kept along with their line number until they are validated
+    let mut events: Vec<(usize, IngestEvent)> = Vec::with_capacity(chunk_size);
+    let mut line_number = 0;
+    let mut stream = body.into_data_stream();
+    loop {
+        let lines = match stream.next().await {
+            Some(Ok(bytes)) => splitter.push(&bytes),
+            Some(Err(e)) => {
+                tracing::error!("Failed to read NDJSON body: {e}");
+                return Err(ClientEventRequestError::InvalidRequestBody);
+            }
+            None => {
+                let lines: Vec<NdjsonLine> = splitter.finish().into_iter().collect();
+                if lines.is_empty() {
+                    break;
+                }
+                lines
+            }
+        };
+        for line in lines {
+            line_number += 1;
+            match try_line_event(&state, &server_request_headers, line) {
+                Ok(Some(event)) => events.push((line_number, event)),
+                Ok(None) => {}
+                Err(error) => summary.add_error(line_number, error),
+            }
+            if events.len() >= chunk_size {
+                save_chunk(&state, &mut events, &mut summary).await?;
+            }
+        }
+    }
+    if !events.is_empty() {
+        save_chunk(&state, &mut events, &mut summary).await?;
+    }
+    Ok(summary)
+}
+
+/// Convert a line into an `IngestEvent`. Blank lines are skipped, and errors
+/// are described for the `NdjsonSaveSummary`
+fn try_line_event<I: IngestEventService>(
+    state: &IngestApplicationState<I>,
+    server_request_headers: &ServerEventRequestHeaders,
+    line: NdjsonLine,
+) -> Result<Option<IngestEvent>, String> {
+    let NdjsonLine::Complete(line) = line else {
+        return Err("Line is too long".to_owned());
+    };
+    if line.trim_ascii().is_empty() {
+        return Ok(None);
+    }
+    let body: ServerEventRequestBody = serde_json::from_slice(&line).map_err(|e| e.to_string())?;
+    state
+        .request_limiter
+        .check(std::iter::once(body.attrs.as_ref()))
+        .map_err(|violation| {
+            let e = ClientEventRequestError::Limit(violation);
+            e.rejection_reason().unwrap_or_else(|| e.to_string())
+        })?;
+    let request = ServerEventRequest {
+        api_key: server_request_headers.api_key.to_owned(),
+        body,
+    };
+    IngestEvent::try_from(&request)
+        .map(Some)
+        .map_err(|e| match e {
+            ClientEventRequestError::IngestEvent(e) => e.to_string(),
+            e => e.to_string(),
+        })
+}
+
+/// Validate, save and clear a chunk of events, adding the counts to the
+/// summary. Events that fail validation are reported as errors of their line
+/// rather than failing the save of the whole chunk.
+async fn save_chunk<I: IngestEventService>(
+    state: &IngestApplicationState<I>,
+    events: &mut Vec<(usize, IngestEvent)>,
+    summary: &mut NdjsonSaveSummary,
+) -> Result<(), ClientEventRequestError> {
+    let (line_numbers, chunk): (Vec<usize>, Vec<IngestEvent>) =
+        std::mem::take(events).into_iter().unzip();
+    let violations = state.ingest_service.validate(&chunk).await?;
+    let mut valid = Vec::with_capacity(chunk.len());
+    for ((line_number, event), violation) in line_numbers.into_iter().zip(chunk).zip(violations) {
+        match violation {
+            None => valid.push(event),
+            Some(IngestServiceError::InvalidRequest) => summary.add_error(
+                line_number,
+                "No event source is configured for the api key and site",
+            ),
+            Some(violation) => summary.add_error(line_number, violation),
+        }
+    }
+    if valid.is_empty() {
+        return Ok(());
+    }
+    let IngestActionSummary::Save(saved) = state.ingest_service.save(valid).await?;
+    summary.add_saved(saved.into());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use http::{HeaderValue, header};
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{
+        domain::{
+            model::{
+                ingest_action_summary::IngestEventSaveSummary,
+                ingest_event::{IngestEventSource, Site},
+                ingest_source_rules::IngestSourceRules,
+                server_key::ServerKey,
+            },
+            repository::ingest_event_repository::test::MockIngestEventRepository,
+        },
+        http_api::model::request_limiter::RequestLimiter,
+        services::ingest_service::IngestService,
+    };
+
+    fn visitor_line(id: Uuid) -> String {
+        format!(r#"{{"t":1,"i":"{id}","site":"test.com"}}"#)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_save_ndjson_events() {
+        let mock_repo = MockIngestEventRepository {
+            save_result: Ok(IngestActionSummary::Save(IngestEventSaveSummary {
+                event_count: 1,
+                duplicate_count: 0,
+            })),
+            event_source_result: Ok(HashSet::from([IngestEventSource::new(
+                ApiKey::new("abc-123"),
+                Site::new("test.com"),
+            )])),
+            source_rules_result: Ok(IngestSourceRules::default()),
+            server_key_result: Ok(true),
+        };
+        // Chunks of a single event, so that every event is saved separately
+        let state = IngestApplicationState::new(IngestService::new(mock_repo))
+            .with_request_limiter(RequestLimiter::default().with_max_events(1));
+        let server_request_headers = ServerEventRequestHeaders {
+            api_key: "abc-123".to_owned(),
+            server_key: ServerKey::new("secret"),
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/x-ndjson"),
+        );
+        let body = [
+            visitor_line(Uuid::now_v7()),
+            "{\"t\":1}".to_owned(),
+            String::new(),
+            format!(r#"{{"t":1,"i":"{}","site":"test.com"}}"#, Uuid::nil()),
+            visitor_line(Uuid::now_v7()),
+            format!(r#"{{"t":1,"i":"{}","site":"other.com"}}"#, Uuid::now_v7()),
+        ]
+        .join("\n");
+
+        let summary = save_ndjson_events(
+            State(state.clone()),
+            server_request_headers.clone(),
+            headers.clone(),
+            Body::from(body),
+        )
+        .await
+        .unwrap();
+        assert_eq!(summary.event_count, 2);
+        assert_eq!(summary.error_count, 3);
+        assert_eq!(
+            summary
+                .errors
+                .iter()
+                .map(|error| error.line)
+                .collect::<Vec<usize>>(),
+            vec![2, 4, 6],
+            "Expected errors to be reported by line number"
+        );
+        assert_eq!(
+            summary.errors[2].error, "No event source is configured for the api key and site",
+            "Expected a line for an unconfigured site to not fail the stream"
+        );
+
+        let limited_state = state
+            .clone()
+            .with_request_limiter(RequestLimiter::default().with_max_attrs(0));
+        let limited = save_ndjson_events(
+            State(limited_state),
+            server_request_headers.clone(),
+            headers,
+            Body::from(format!(
+                r#"{{"t":1,"i":"{}","site":"test.com","a":{{"x":"1"}}}}"#,
+                Uuid::now_v7()
+            )),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            limited.errors[0].error, "Request exceeds a limit: Event contains too many attrs",
+            "Expected the error of a line to name the exceeded limit"
+        );
+
+        let unsupported = save_ndjson_events(
+            State(state),
+            server_request_headers,
+            HeaderMap::new(),
+            Body::from(visitor_line(Uuid::now_v7())),
+        )
+        .await;
+        let Err(ClientEventRequestError::UnsupportedMediaType) = unsupported else {
+            panic!("Expected unsupported media type without a NDJSON content type");
+        };
+    }
+}
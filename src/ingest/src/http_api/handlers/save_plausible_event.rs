@@ -0,0 +1,257 @@
+use axum::extract::State;
+use axum_client_ip::ClientIp;
+use http::{HeaderMap, StatusCode, Uri, header};
+use time::OffsetDateTime;
+use tracing::instrument;
+
+use crate::{
+    domain::{
+        model::{
+            ingest_event::{IngestEvent, IngestEventOrigin},
+            session_synthesizer::{SyntheticSessionRequest, SyntheticVisits, uuid_v7_at},
+        },
+        service::ingest_event_service::IngestEventService,
+    },
+    http_api::{
+        handlers::save_client_events::save_synthesized_events,
+        model::{
+            client_event_request::ClientEventRequestError,
+            client_event_request_components::API_KEY_HTTP_HEADER,
+            ingest_application_state::IngestApplicationState,
+            plausible_event_request::{PlausibleEventRequest, PlausibleEventRequestBody},
+        },
+    },
+};
+
+/// `save_plausible_event` accepts a single event in the format of the
+/// Plausible Events API so that sites already using the Plausible script or
+/// its server side API can send events without changes. The body is parsed as
+/// JSON regardless of the content type, since the Plausible script sends
+/// `text/plain`, which also spares the script a CORS preflight. Requests from
+/// a browser are therefore only saved for the domain matching their `Origin`,
+/// while requests without one need the api key header. Each domain of the
+/// event is resolved to a configured source, see
+/// `PlausibleEventRequest::sources`, and the visitor and session parents
+/// are synthesized from the client ip and user agent. As with Plausible the
+/// response is `202 Accepted` with a body of `ok`.
+#[instrument(skip(body))]
+pub async fn save_plausible_event<I: IngestEventService + std::fmt::Debug>(
+    State(state): State<IngestApplicationState<I>>,
+    headers: HeaderMap,
+    client_ip: ClientIp,
+    body: String,
+) -> Result<(StatusCode, &'static str), ClientEventRequestError> {
+    let body: PlausibleEventRequestBody =
+        serde_json::from_str(&body).map_err(|_| ClientEventRequestError::InvalidRequestBody)?;
+    let header_value = |name| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_owned())
+    };
+    let origin = header_value(header::ORIGIN.as_str())
+        .map(|origin| {
+            origin
+                .parse::<Uri>()
+                .ok()
+                .and_then(|origin| origin.host().map(str::to_owned))
+                .ok_or(ClientEventRequestError::InvalidRequestHeaders)
+        })
+        .transpose()?;
+    let request = PlausibleEventRequest {
+        body,
+        api_key: header_value(API_KEY_HTTP_HEADER),
+        origin,
+        user_agent: header_value(header::USER_AGENT.as_str())
+            .ok_or(ClientEventRequestError::InvalidRequestHeaders)?,
+        ip: client_ip.0,
+    };
+    if request.is_ignored() {
+        return Ok((StatusCode::ACCEPTED, "ok"));
+    }
+
+    let sources = request.sources(&state.ingest_service.event_sources().await?);
+    if sources.is_empty() {
+        return Err(ClientEventRequestError::ApiKey);
+    }
+    let ts = OffsetDateTime::now_utc();
+    let mut visits = SyntheticVisits::default();
+    let mut events: Vec<IngestEvent> = Vec::with_capacity(sources.len() * 3);
+    for source in sources.iter() {
+        let session_id = state.session_synthesizer.try_session(
+            SyntheticSessionRequest {
+                source: source.clone(),
+                visitor_key: request.visitor_key(source, &ts),
+                ip: request.ip,
+                user_agent: request.user_agent.to_owned(),
+                referrer: request.body.referrer.to_owned(),
+                ts,
+                origin: IngestEventOrigin::Client,
+            },
+            &mut visits,
+            &mut events,
+        )?;
+        events.push(request.try_into_event(source, uuid_v7_at(&ts), session_id)?);
+    }
+
+    save_synthesized_events(&state, events, visits).await?;
+    Ok((StatusCode::ACCEPTED, "ok"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashSet,
+        net::{IpAddr, Ipv4Addr},
+    };
+
+    use axum::response::IntoResponse;
+
+    use super::*;
+
+    use crate::{
+        domain::{
+            model::{
+                ingest_action_summary::{IngestActionSummary, IngestEventSaveSummary},
+                ingest_event::{ApiKey, IngestEventSource, Site},
+                ingest_source_rules::IngestSourceRules,
+            },
+            repository::ingest_event_repository::test::MockIngestEventRepository,
+        },
+        http_api::model::request_limiter::RequestLimiter,
+        services::ingest_service::IngestService,
+    };
+
+    fn plausible_state(
+        sources: HashSet<IngestEventSource>,
+    ) -> IngestApplicationState<IngestService<MockIngestEventRepository>> {
+        IngestApplicationState::new(IngestService::new(MockIngestEventRepository {
+            save_result: Ok(IngestActionSummary::Save(IngestEventSaveSummary {
+                event_count: 3,
+                duplicate_count: 0,
+            })),
+            event_source_result: Ok(sources),
+            source_rules_result: Ok(IngestSourceRules::default()),
+            server_key_result: Ok(false),
+        }))
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_save_plausible_event() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::USER_AGENT, "Mozilla/5.0".parse().unwrap());
+        headers.insert(header::ORIGIN, "https://test.com".parse().unwrap());
+        let client_ip = || ClientIp(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        let body = r#"{"n":"pageview","u":"https://test.com/","d":"test.com","r":null}"#;
+
+        let state = plausible_state(HashSet::from([IngestEventSource::new(
+            ApiKey::new("abc_123"),
+            Site::new("test.com"),
+        )]));
+        let Ok((status, "ok")) = save_plausible_event(
+            State(state.clone()),
+            headers.clone(),
+            client_ip(),
+            body.to_owned(),
+        )
+        .await
+        else {
+            panic!("Expected pageview for a configured domain to be accepted");
+        };
+        assert_eq!(status, StatusCode::ACCEPTED);
+
+        let mut mismatched_headers = headers.clone();
+        mismatched_headers.insert(header::ORIGIN, "https://attacker.com".parse().unwrap());
+        let Err(ClientEventRequestError::ApiKey) = save_plausible_event(
+            State(state.clone()),
+            mismatched_headers,
+            client_ip(),
+            body.to_owned(),
+        )
+        .await
+        else {
+            panic!("Expected a domain not matching the origin to be rejected");
+        };
+
+        let mut server_headers = headers.clone();
+        server_headers.remove(header::ORIGIN);
+        let Err(ClientEventRequestError::ApiKey) = save_plausible_event(
+            State(state.clone()),
+            server_headers.clone(),
+            client_ip(),
+            body.to_owned(),
+        )
+        .await
+        else {
+            panic!("Expected a request without an origin to require an api key");
+        };
+        server_headers.insert(API_KEY_HTTP_HEADER, "abc_123".parse().unwrap());
+        let Ok((StatusCode::ACCEPTED, "ok")) =
+            save_plausible_event(State(state), server_headers, client_ip(), body.to_owned()).await
+        else {
+            panic!("Expected a request with an api key to be accepted");
+        };
+
+        let unknown_state = plausible_state(HashSet::new());
+        let Err(ClientEventRequestError::ApiKey) = save_plausible_event(
+            State(unknown_state.clone()),
+            headers.clone(),
+            client_ip(),
+            body.to_owned(),
+        )
+        .await
+        else {
+            panic!("Expected an unknown domain to be rejected");
+        };
+
+        let Err(ClientEventRequestError::InvalidRequestBody) = save_plausible_event(
+            State(unknown_state.clone()),
+            headers,
+            client_ip(),
+            "not json".to_owned(),
+        )
+        .await
+        else {
+            panic!("Expected an invalid body to be rejected");
+        };
+
+        let Err(ClientEventRequestError::InvalidRequestHeaders) = save_plausible_event(
+            State(unknown_state),
+            HeaderMap::new(),
+            client_ip(),
+            body.to_owned(),
+        )
+        .await
+        else {
+            panic!("Expected a missing user agent to be rejected");
+        };
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_save_plausible_event_limits() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::USER_AGENT, "Mozilla/5.0".parse().unwrap());
+        headers.insert(header::ORIGIN, "https://test.com".parse().unwrap());
+        let state = plausible_state(HashSet::from([IngestEventSource::new(
+            ApiKey::new("abc_123"),
+            Site::new("test.com"),
+        )]))
+        .with_request_limiter(RequestLimiter::default().with_max_location(10));
+
+        let Err(limit_error) = save_plausible_event(
+            State(state.clone()),
+            headers,
+            ClientIp(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            r#"{"n":"pageview","u":"https://test.com/a/long/location","d":"test.com"}"#.to_owned(),
+        )
+        .await
+        else {
+            panic!("Expected a location beyond the limit to be rejected");
+        };
+        assert_eq!(
+            limit_error.into_response().status(),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(state.request_limiter.report().location, 1);
+    }
+}
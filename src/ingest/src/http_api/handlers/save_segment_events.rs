@@ -0,0 +1,332 @@
+use axum::{Json, extract::State};
+use axum_client_ip::ClientIp;
+use time::OffsetDateTime;
+use tracing::instrument;
+
+use crate::{
+    domain::{
+        model::{
+            ingest_event::{IngestEvent, IngestEventOrigin},
+            session_synthesizer::{SyntheticSessionRequest, SyntheticVisits, uuid_v7_at},
+        },
+        service::ingest_event_service::IngestEventService,
+    },
+    http_api::{
+        handlers::save_client_events::save_synthesized_events,
+        model::{
+            client_event_request::ClientEventRequestError,
+            ingest_application_state::IngestApplicationState,
+            segment_request::SegmentRequest,
+            segment_request_components::{
+                SegmentBatch, SegmentMessage, SegmentMessageType, SegmentRequestHeaders,
+                SegmentResponse,
+            },
+        },
+    },
+};
+
+/// `save_segment_track` accepts a single Segment `track` message
+#[instrument(skip(message))]
+pub async fn save_segment_track<I: IngestEventService + std::fmt::Debug>(
+    State(state): State<IngestApplicationState<I>>,
+    segment_request_headers: SegmentRequestHeaders,
+    client_ip: ClientIp,
+    Json(message): Json<SegmentMessage>,
+) -> Result<Json<SegmentResponse>, ClientEventRequestError> {
+    save_segment_messages(
+        state,
+        segment_request_headers,
+        client_ip,
+        vec![(SegmentMessageType::Track, message)],
+        None,
+    )
+    .await
+}
+
+/// `save_segment_page` accepts a single Segment `page` message
+#[instrument(skip(message))]
+pub async fn save_segment_page<I: IngestEventService + std::fmt::Debug>(
+    State(state): State<IngestApplicationState<I>>,
+    segment_request_headers: SegmentRequestHeaders,
+    client_ip: ClientIp,
+    Json(message): Json<SegmentMessage>,
+) -> Result<Json<SegmentResponse>, ClientEventRequestError> {
+    save_segment_messages(
+        state,
+        segment_request_headers,
+        client_ip,
+        vec![(SegmentMessageType::Page, message)],
+        None,
+    )
+    .await
+}
+
+/// `save_segment_identify` accepts a single Segment `identify` message
+#[instrument(skip(message))]
+pub async fn save_segment_identify<I: IngestEventService + std::fmt::Debug>(
+    State(state): State<IngestApplicationState<I>>,
+    segment_request_headers: SegmentRequestHeaders,
+    client_ip: ClientIp,
+    Json(message): Json<SegmentMessage>,
+) -> Result<Json<SegmentResponse>, ClientEventRequestError> {
+    save_segment_messages(
+        state,
+        segment_request_headers,
+        client_ip,
+        vec![(SegmentMessageType::Identify, message)],
+        None,
+    )
+    .await
+}
+
+/// `save_segment_batch` accepts a Segment batch, in which every message must
+/// name its `type`. The batch is saved or rejected as a whole.
+#[instrument(skip(batch))]
+pub async fn save_segment_batch<I: IngestEventService + std::fmt::Debug>(
+    State(state): State<IngestApplicationState<I>>,
+    mut segment_request_headers: SegmentRequestHeaders,
+    client_ip: ClientIp,
+    Json(batch): Json<SegmentBatch>,
+) -> Result<Json<SegmentResponse>, ClientEventRequestError> {
+    let mut messages = Vec::with_capacity(batch.batch.len());
+    for message in batch.batch.into_iter() {
+        let message_type = message
+            .message_type
+            .ok_or(ClientEventRequestError::InvalidRequestBody)?;
+        messages.push((message_type, message));
+    }
+    if segment_request_headers.write_key.is_none() {
+        segment_request_headers.write_key = batch.write_key;
+    }
+    save_segment_messages(
+        state,
+        segment_request_headers,
+        client_ip,
+        messages,
+        batch.sent_at,
+    )
+    .await
+}
+
+/// Translate Segment messages into `IngestEvent`s and save them. The write
+/// key is taken from basic authorization or otherwise from each message, and
+/// is used as the `ApiKey`. The `anonymousId` of a message identifies the
+/// visitor, for which the `Visitor` and `Session` parents are synthesized, so
+/// that an `identify` links the `userId` to that visitor.
+async fn save_segment_messages<I: IngestEventService>(
+    state: IngestApplicationState<I>,
+    segment_request_headers: SegmentRequestHeaders,
+    client_ip: ClientIp,
+    messages: Vec<(SegmentMessageType, SegmentMessage)>,
+    batch_sent_at: Option<String>,
+) -> Result<Json<SegmentResponse>, ClientEventRequestError> {
+    let received_at = OffsetDateTime::now_utc();
+    let configured = state.ingest_service.event_sources().await?;
+    let mut visits = SyntheticVisits::default();
+    let mut events: Vec<IngestEvent> = Vec::with_capacity(messages.len() + 2);
+    for (message_type, message) in messages.into_iter() {
+        let request = SegmentRequest {
+            write_key: segment_request_headers
+                .write_key
+                .clone()
+                .or(message.write_key.clone())
+                .ok_or(ClientEventRequestError::ApiKey)?,
+            message_type,
+            user_agent: message
+                .context
+                .user_agent
+                .clone()
+                .or(segment_request_headers.user_agent.clone())
+                .unwrap_or_default(),
+            ip: message.context.ip.unwrap_or(client_ip.0),
+            message,
+        };
+        if request.is_ignored() {
+            continue;
+        }
+        let source = request.try_source(&configured)?;
+        let ts = request.try_ts(received_at, batch_sent_at.as_deref())?;
+        let parents = state.session_synthesizer.try_parents(
+            SyntheticSessionRequest {
+                source: source.clone(),
+                visitor_key: request.try_visitor_key()?.to_owned(),
+                ip: request.ip,
+                user_agent: request.user_agent.to_owned(),
+                referrer: request.referrer(),
+                ts,
+                origin: IngestEventOrigin::Client,
+            },
+            &mut visits,
+            &mut events,
+        )?;
+        if let Some(event) = request.try_into_event(&source, uuid_v7_at(&ts), &parents)? {
+            events.push(event);
+        }
+    }
+
+    if !events.is_empty() {
+        save_synthesized_events(&state, events, visits).await?;
+    }
+    Ok(Json(SegmentResponse { success: true }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashSet,
+        net::{IpAddr, Ipv4Addr},
+    };
+
+    use axum::response::IntoResponse;
+    use http::StatusCode;
+
+    use super::*;
+
+    use crate::{
+        domain::{
+            model::{
+                ingest_action_summary::{IngestActionSummary, IngestEventSaveSummary},
+                ingest_event::{ApiKey, IngestEventSource, Site},
+                ingest_source_rules::IngestSourceRules,
+            },
+            repository::ingest_event_repository::test::MockIngestEventRepository,
+        },
+        http_api::model::request_limiter::RequestLimiter,
+        services::ingest_service::IngestService,
+    };
+
+    fn segment_state() -> IngestApplicationState<IngestService<MockIngestEventRepository>> {
+        IngestApplicationState::new(IngestService::new(MockIngestEventRepository {
+            save_result: Ok(IngestActionSummary::Save(IngestEventSaveSummary {
+                event_count: 3,
+                duplicate_count: 0,
+            })),
+            event_source_result: Ok(HashSet::from([IngestEventSource::new(
+                ApiKey::new("write_key_123"),
+                Site::new("test.com"),
+            )])),
+            source_rules_result: Ok(IngestSourceRules::default()),
+            server_key_result: Ok(false),
+        }))
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_save_segment_events() {
+        let headers = SegmentRequestHeaders {
+            write_key: Some("write_key_123".to_owned()),
+            user_agent: Some("analytics-node/1.0".to_owned()),
+        };
+        let client_ip = || ClientIp(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        let track: SegmentMessage =
+            serde_json::from_str(r#"{"anonymousId":"a","event":"Signup"}"#).unwrap();
+
+        let Ok(Json(SegmentResponse { success: true })) = save_segment_track(
+            State(segment_state()),
+            headers.clone(),
+            client_ip(),
+            Json(track.clone()),
+        )
+        .await
+        else {
+            panic!("Expected successful track from HTTP mock");
+        };
+
+        let Err(ClientEventRequestError::ApiKey) = save_segment_track(
+            State(segment_state()),
+            SegmentRequestHeaders::default(),
+            client_ip(),
+            Json(track),
+        )
+        .await
+        else {
+            panic!("Expected a missing write key to be rejected");
+        };
+
+        let batch: SegmentBatch = serde_json::from_str(
+            r#"{"writeKey":"write_key_123","batch":[
+                {"type":"page","anonymousId":"a","properties":{"url":"https://test.com/"}},
+                {"type":"group","anonymousId":"a","groupId":"g"},
+                {"type":"track","anonymousId":"a","event":"Signup"}
+            ]}"#,
+        )
+        .unwrap();
+        let Ok(Json(SegmentResponse { success: true })) = save_segment_batch(
+            State(segment_state()),
+            SegmentRequestHeaders::default(),
+            client_ip(),
+            Json(batch),
+        )
+        .await
+        else {
+            panic!("Expected successful batch with the write key in the body");
+        };
+
+        let untyped: SegmentBatch = serde_json::from_str(
+            r#"{"writeKey":"write_key_123","batch":[{"anonymousId":"a","event":"Signup"}]}"#,
+        )
+        .unwrap();
+        let Err(ClientEventRequestError::InvalidRequestBody) = save_segment_batch(
+            State(segment_state()),
+            SegmentRequestHeaders::default(),
+            client_ip(),
+            Json(untyped),
+        )
+        .await
+        else {
+            panic!("Expected batch messages without a type to be rejected");
+        };
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_save_segment_events_limits() {
+        let batch: SegmentBatch = serde_json::from_str(
+            r#"{"writeKey":"write_key_123","batch":[
+                {"type":"track","anonymousId":"a","event":"Signup"},
+                {"type":"track","anonymousId":"a","event":"Upgrade"}
+            ]}"#,
+        )
+        .unwrap();
+        let events_state =
+            segment_state().with_request_limiter(RequestLimiter::default().with_max_events(1));
+        let Err(events_error) = save_segment_batch(
+            State(events_state.clone()),
+            SegmentRequestHeaders::default(),
+            ClientIp(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            Json(batch),
+        )
+        .await
+        else {
+            panic!("Expected a batch beyond the limit to be rejected");
+        };
+        assert_eq!(
+            events_error.into_response().status(),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+        assert_eq!(events_state.request_limiter.report().events, 1);
+
+        let track: SegmentMessage = serde_json::from_str(
+            r#"{"anonymousId":"a","event":"Signup","properties":{"plan":"pro","seats":"3"}}"#,
+        )
+        .unwrap();
+        let attrs_state =
+            segment_state().with_request_limiter(RequestLimiter::default().with_max_attrs(1));
+        let Err(attrs_error) = save_segment_track(
+            State(attrs_state.clone()),
+            SegmentRequestHeaders {
+                write_key: Some("write_key_123".to_owned()),
+                user_agent: None,
+            },
+            ClientIp(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            Json(track),
+        )
+        .await
+        else {
+            panic!("Expected a message with too many properties to be rejected");
+        };
+        assert_eq!(
+            attrs_error.into_response().status(),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(attrs_state.request_limiter.report().attrs, 1);
+    }
+}
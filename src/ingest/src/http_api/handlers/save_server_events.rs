@@ -0,0 +1,144 @@
+use axum::{Json, extract::State};
+use tracing::instrument;
+
+use crate::{
+    domain::{
+        model::ingest_event::{ApiKey, IngestEvent},
+        service::ingest_event_service::IngestEventService,
+    },
+    http_api::model::{
+        client_event_action_summary::ClientEventActionSummary,
+        client_event_request::ClientEventRequestError,
+        ingest_application_state::IngestApplicationState,
+        server_event_request::ServerEventRequest,
+        server_event_request_components::{ServerEventRequestBody, ServerEventRequestHeaders},
+    },
+};
+
+/// `save_server_events` expects POST data in JSON format that consists of a
+/// list of `ServerEventRequestBody` structs from a trusted backend. The
+/// `api_key` header together with a bearer `ServerKey` authenticate the
+/// request, after which the same domain rules as for client events apply. The
+/// `site` of each event is still checked against the configured sources for
+/// the `api_key` when the events are saved. The same `RequestLimiter` limits
+/// as for client events apply.
+#[instrument(skip(event_bodies))]
+pub async fn save_server_events<I: IngestEventService + std::fmt::Debug>(
+    State(state): State<IngestApplicationState<I>>,
+    server_request_headers: ServerEventRequestHeaders,
+    Json(event_bodies): Json<Vec<ServerEventRequestBody>>,
+) -> Result<ClientEventActionSummary, ClientEventRequestError> {
+    if !state
+        .ingest_service
+        .is_server_key_valid(
+            &ApiKey::new(&server_request_headers.api_key),
+            &server_request_headers.server_key,
+        )
+        .await?
+    {
+        return Err(ClientEventRequestError::Unauthorized);
+    }
+    state
+        .request_limiter
+        .check(event_bodies.iter().map(|body| body.attrs.as_ref()))?;
+
+    let mut events: Vec<IngestEvent> = Vec::with_capacity(event_bodies.len());
+    for body in event_bodies.into_iter() {
+        let request = ServerEventRequest {
+            api_key: server_request_headers.api_key.to_owned(),
+            body,
+        };
+        events.push((&request).try_into()?);
+    }
+
+    state
+        .ingest_service
+        .save(events)
+        .await
+        .map_err(|e| e.into())
+        .map(|s| s.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use uuid::Uuid;
+
+    use crate::{
+        domain::{
+            model::{
+                ingest_action_summary::{IngestActionSummary, IngestEventSaveSummary},
+                ingest_source_rules::IngestSourceRules,
+                server_key::ServerKey,
+            },
+            repository::ingest_event_repository::test::MockIngestEventRepository,
+        },
+        http_api::model::client_event_request::ClientEventRequestType,
+        services::ingest_service::IngestService,
+    };
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_save_server_events() {
+        let valid_request_bodies: Vec<ServerEventRequestBody> = vec![ServerEventRequestBody {
+            event_type: ClientEventRequestType::Visitor,
+            id: Uuid::now_v7(),
+            site: "test.com".to_owned(),
+            attrs: None,
+            ip: None,
+            user_agent: None,
+        }];
+        let headers = ServerEventRequestHeaders {
+            api_key: "abc-123".to_owned(),
+            server_key: ServerKey::new("secret"),
+        };
+
+        // Valid server key
+        let mock_success_repo = MockIngestEventRepository {
+            save_result: Ok(IngestActionSummary::Save(IngestEventSaveSummary {
+                event_count: 1,
+                duplicate_count: 0,
+            })),
+            event_source_result: Ok(HashSet::new()),
+            source_rules_result: Ok(IngestSourceRules::default()),
+            server_key_result: Ok(true),
+        };
+        let test_success_state = IngestApplicationState::new(IngestService::new(mock_success_repo));
+        let save_server_events_success = save_server_events(
+            State(test_success_state),
+            headers.clone(),
+            Json(valid_request_bodies.clone()),
+        )
+        .await;
+        let Ok(ClientEventActionSummary::Save(save_summary)) = save_server_events_success else {
+            panic!("Expected successful save from HTTP mock");
+        };
+        assert_eq!(
+            save_summary.event_count, 1,
+            "Expected to have 1 save event count"
+        );
+
+        // Unknown server key
+        let mock_unauthorized_repo = MockIngestEventRepository {
+            save_result: Ok(IngestActionSummary::Save(IngestEventSaveSummary {
+                event_count: 1,
+                duplicate_count: 0,
+            })),
+            event_source_result: Ok(HashSet::new()),
+            source_rules_result: Ok(IngestSourceRules::default()),
+            server_key_result: Ok(false),
+        };
+        let test_unauthorized_state =
+            IngestApplicationState::new(IngestService::new(mock_unauthorized_repo));
+        let save_server_events_unauthorized = save_server_events(
+            State(test_unauthorized_state),
+            headers,
+            Json(valid_request_bodies),
+        )
+        .await;
+        let Err(ClientEventRequestError::Unauthorized) = save_server_events_unauthorized else {
+            panic!("Expected unauthorized error from HTTP mock");
+        };
+    }
+}
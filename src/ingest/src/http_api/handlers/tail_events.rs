@@ -0,0 +1,190 @@
+use std::{convert::Infallible, time::Duration};
+
+use axum::{
+    extract::{Query, State},
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures_util::Stream;
+use tracing::instrument;
+
+use crate::{
+    domain::{model::ingest_event::ApiKey, service::ingest_event_service::IngestEventService},
+    http_api::model::{
+        client_event_request::ClientEventRequestError,
+        ingest_application_state::IngestApplicationState,
+        live_tail::{TailMessage, TailQuery},
+        server_event_request_components::ServerEventRequestHeaders,
+    },
+};
+
+/// `tail_events` streams the client events of the `api_key` and the `site` of
+/// the query string as Server-Sent Events while they are ingested. Each event
+/// is sent as an `accepted` or `rejected` message holding the event as it was
+/// sent along with the reason for a rejection. `dropped` messages count the
+/// events left out for exceeding the rate of the tail and an `expired`
+/// message ends the tail. Like the server-to-server routes, the `api_key`
+/// header and a bearer `ServerKey` authenticate the request. The site is not
+/// checked against the configured sources, so that events rejected for an
+/// unknown source can be watched as well.
+#[instrument]
+pub async fn tail_events<I: IngestEventService + std::fmt::Debug>(
+    State(state): State<IngestApplicationState<I>>,
+    server_request_headers: ServerEventRequestHeaders,
+    Query(query): Query<TailQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ClientEventRequestError> {
+    if !state
+        .ingest_service
+        .is_server_key_valid(
+            &ApiKey::new(&server_request_headers.api_key),
+            &server_request_headers.server_key,
+        )
+        .await?
+    {
+        return Err(ClientEventRequestError::Unauthorized);
+    }
+    if query.site.trim().is_empty() {
+        return Err(ClientEventRequestError::InvalidRequestHeaders);
+    }
+    let subscription = state
+        .live_tail
+        .subscribe(
+            &server_request_headers.api_key,
+            &query.site,
+            query.secs.map(Duration::from_secs),
+        )
+        .map_err(|_| ClientEventRequestError::TooManyRequests)?;
+    let stream = futures_util::stream::unfold(subscription, |mut subscription| async move {
+        let message = subscription.next().await?;
+        Some((Ok(sse_event(message)), subscription))
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// Server-Sent Event of a single `TailMessage`
+fn sse_event(message: TailMessage) -> Event {
+    match message {
+        TailMessage::Event(event) => Event::default()
+            .event(event.status.as_str())
+            .json_data(&*event)
+            .unwrap_or_default(),
+        TailMessage::Dropped(count) => Event::default().event("dropped").data(count.to_string()),
+        TailMessage::Expired => Event::default().event("expired").data(""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use axum::response::IntoResponse;
+    use futures_util::StreamExt;
+    use http::{StatusCode, header};
+    use http_body_util::BodyExt;
+    use uuid::Uuid;
+
+    use crate::{
+        domain::{
+            model::{
+                ingest_action_summary::{IngestActionSummary, IngestEventSaveSummary},
+                ingest_source_rules::IngestSourceRules,
+                server_key::ServerKey,
+            },
+            repository::ingest_event_repository::test::MockIngestEventRepository,
+        },
+        http_api::model::{
+            client_event_request::ClientEventRequestType,
+            client_event_request_components::ClientEventRequestBody,
+            live_tail::{TailEvent, TailStatus},
+        },
+        services::ingest_service::IngestService,
+    };
+
+    use super::*;
+
+    fn test_state(
+        server_key_valid: bool,
+    ) -> IngestApplicationState<IngestService<MockIngestEventRepository>> {
+        IngestApplicationState::new(IngestService::new(MockIngestEventRepository {
+            save_result: Ok(IngestActionSummary::Save(IngestEventSaveSummary {
+                event_count: 1,
+                duplicate_count: 0,
+            })),
+            event_source_result: Ok(HashSet::new()),
+            source_rules_result: Ok(IngestSourceRules::default()),
+            server_key_result: Ok(server_key_valid),
+        }))
+    }
+
+    fn headers() -> ServerEventRequestHeaders {
+        ServerEventRequestHeaders {
+            api_key: "abc-123".to_owned(),
+            server_key: ServerKey::new("secret"),
+        }
+    }
+
+    fn query(secs: Option<u64>) -> Query<TailQuery> {
+        Query(TailQuery {
+            site: "test.com".to_owned(),
+            secs,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_tail_events() {
+        let state = test_state(true);
+        let response = tail_events(State(state.clone()), headers(), query(Some(1)))
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/event-stream",
+            "Expected a Server-Sent Events stream"
+        );
+        state.live_tail.publish(
+            "abc-123",
+            "test.com",
+            TailEvent {
+                status: TailStatus::Rejected,
+                reason: Some("Invalid request body".to_owned()),
+                event: ClientEventRequestBody::new(
+                    ClientEventRequestType::Visitor,
+                    Uuid::now_v7(),
+                    None,
+                ),
+                received_at: time::OffsetDateTime::now_utc(),
+            },
+        );
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.starts_with("event: rejected\ndata: {\"status\":\"rejected\""));
+        assert!(body.contains("\"reason\":\"Invalid request body\""));
+        assert!(
+            body.contains("event: expired\n"),
+            "Expected the tail to expire"
+        );
+        assert!(!state.live_tail.is_tailed("abc-123", "test.com"));
+
+        // Negative test cases
+        let Err(unauthorized) = tail_events(State(test_state(false)), headers(), query(None)).await
+        else {
+            panic!("Expected an invalid server key to be refused");
+        };
+        assert_eq!(
+            unauthorized.into_response().status(),
+            StatusCode::UNAUTHORIZED
+        );
+        let tails: Vec<_> = futures_util::stream::iter(0..2)
+            .then(|_| tail_events(State(state.clone()), headers(), query(None)))
+            .collect()
+            .await;
+        assert!(tails.iter().all(Result::is_ok));
+        let Err(too_many) = tail_events(State(state), headers(), query(None)).await else {
+            panic!("Expected the number of tails to be limited");
+        };
+        assert_eq!(
+            too_many.into_response().status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+    }
+}
@@ -0,0 +1,266 @@
+use axum::extract::State;
+use axum_client_ip::ClientIp;
+use http::HeaderMap;
+use tracing::instrument;
+
+use crate::{
+    domain::{model::ingest_event::IngestEvent, service::ingest_event_service::IngestEventService},
+    http_api::model::{
+        client_event_request::{ClientEventRequest, ClientEventRequestError},
+        client_event_request_components::{ClientEventRequestBody, ClientEventRequestHeaders},
+        ingest_application_state::IngestApplicationState,
+        request_limiter::LimitViolation,
+        validation_report::{EventValidation, ValidationIssue, ValidationReport},
+        wire_format::Negotiated,
+    },
+};
+
+/// `validate_client_events` accepts the same requests as `save_client_events`
+/// and runs them through the same pipeline, from the headers and every limit
+/// of the `RequestLimiter` through to the conversion into `IngestEvent`s and
+/// the rules of the `IngestEventService`, without saving them. Rather than
+/// stopping at the first failure, it responds with a `ValidationReport` that
+/// names every failed rule and field of the request and of each event, so
+/// that SDKs and the tests of customer sites can check their payloads.
+/// Violations are not counted by the `RequestLimiter` and validated events
+/// are neither tailed nor recorded as saved.
+#[instrument(skip(event_bodies))]
+pub async fn validate_client_events<I: IngestEventService + std::fmt::Debug>(
+    State(state): State<IngestApplicationState<I>>,
+    headers: HeaderMap,
+    client_ip: ClientIp,
+    Negotiated(event_bodies): Negotiated<Vec<ClientEventRequestBody>>,
+) -> Result<ValidationReport, ClientEventRequestError> {
+    let mut issues = Vec::new();
+    if event_bodies.is_empty() {
+        issues.push(ValidationIssue::no_events());
+    }
+    if event_bodies.len() > state.request_limiter.max_events() {
+        issues.push(ValidationIssue::from_limit(LimitViolation::Events));
+    }
+    let client_request_headers = ClientEventRequestHeaders::try_from(&headers)
+        .inspect_err(|e| issues.push(ValidationIssue::from_headers_error(e, &headers)))
+        .ok();
+
+    let mut validations = Vec::with_capacity(event_bodies.len());
+    let mut events: Vec<(usize, IngestEvent)> = Vec::new();
+    for (index, body) in event_bodies.into_iter().enumerate() {
+        let id = body.id;
+        let mut event_issues: Vec<ValidationIssue> = body
+            .attrs
+            .iter()
+            .flat_map(|attrs| state.request_limiter.attrs_violations(attrs))
+            .map(ValidationIssue::from_limit)
+            .collect();
+        // Events can only be converted once the headers are valid
+        if let Some(client_request_headers) = &client_request_headers {
+            let request = ClientEventRequest {
+                body,
+                headers: client_request_headers.clone(),
+                ip: client_ip.0,
+            };
+            match IngestEvent::try_from(&request) {
+                Ok(event) => events.push((index, event)),
+                Err(e) => event_issues.push(ValidationIssue::from_request_error(&e, &request)),
+            }
+        }
+        validations.push((id, event_issues));
+    }
+
+    if !events.is_empty() {
+        let (indexes, events): (Vec<usize>, Vec<IngestEvent>) = events.into_iter().unzip();
+        let violations = state.ingest_service.validate(&events).await?;
+        for (index, violation) in indexes.into_iter().zip(violations) {
+            if let Some(violation) = violation {
+                validations[index]
+                    .1
+                    .push(ValidationIssue::from_service_error(&violation));
+            }
+        }
+    }
+    let events = validations
+        .into_iter()
+        .enumerate()
+        .map(|(index, (id, event_issues))| EventValidation::new(index, id, event_issues))
+        .collect();
+    Ok(ValidationReport::new(issues, events))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{HashMap, HashSet},
+        net::{IpAddr, Ipv4Addr},
+    };
+
+    use http::header;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{
+        domain::{
+            model::{
+                ingest_event::{ApiKey, IngestEventSource, Site},
+                ingest_source_rules::IngestSourceRules,
+            },
+            repository::ingest_event_repository::{
+                IngestRepositoryError, test::MockIngestEventRepository,
+            },
+        },
+        http_api::model::{
+            client_event_request::ClientEventRequestType,
+            client_event_request_components::API_KEY_HTTP_HEADER,
+            request_limiter::{LimitViolationCounts, RequestLimiter},
+            validation_report::ValidationRule,
+        },
+        services::ingest_service::IngestService,
+    };
+
+    fn request_headers(site: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ORIGIN, format!("http://{site}").parse().unwrap());
+        headers.insert(API_KEY_HTTP_HEADER, "abc-123".parse().unwrap());
+        headers.insert(header::USER_AGENT, "test-agent".parse().unwrap());
+        headers
+    }
+
+    fn event_body(
+        event_type: ClientEventRequestType,
+        id: Uuid,
+        attrs: &[(&str, &str)],
+    ) -> ClientEventRequestBody {
+        ClientEventRequestBody::new(
+            event_type,
+            id,
+            (!attrs.is_empty()).then(|| {
+                attrs
+                    .iter()
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect::<HashMap<String, String>>()
+            }),
+        )
+    }
+
+    fn rules(event: &EventValidation) -> Vec<(ValidationRule, Option<&str>)> {
+        event
+            .issues
+            .iter()
+            .map(|issue| (issue.rule, issue.field.as_deref()))
+            .collect()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_validate_client_events() {
+        let mock_repo = |event_source_result| MockIngestEventRepository {
+            save_result: Err(IngestRepositoryError::Repository),
+            event_source_result,
+            source_rules_result: Ok(IngestSourceRules::default()),
+            server_key_result: Ok(false),
+        };
+        let state =
+            IngestApplicationState::new(IngestService::new(mock_repo(Ok(HashSet::from([
+                IngestEventSource::new(ApiKey::new("abc-123"), Site::new("test.com")),
+            ])))))
+            .with_request_limiter(RequestLimiter::default().with_max_title(5));
+        let client_ip = ClientIp(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        let visitor_id = Uuid::now_v7().to_string();
+        let bodies = || {
+            vec![
+                event_body(ClientEventRequestType::Visitor, Uuid::now_v7(), &[]),
+                event_body(ClientEventRequestType::Session, Uuid::now_v7(), &[]),
+                event_body(ClientEventRequestType::Visitor, Uuid::nil(), &[]),
+                event_body(
+                    ClientEventRequestType::Section,
+                    Uuid::now_v7(),
+                    &[("p", &visitor_id), ("t", "Long title")],
+                ),
+                event_body(
+                    ClientEventRequestType::Identify,
+                    Uuid::now_v7(),
+                    &[("p", &visitor_id), ("u", "user-1")],
+                ),
+            ]
+        };
+
+        let report = validate_client_events(
+            State(state.clone()),
+            request_headers("test.com"),
+            client_ip,
+            Negotiated(bodies()),
+        )
+        .await
+        .unwrap();
+        assert!(!report.valid);
+        assert!(report.issues.is_empty());
+        assert_eq!(
+            report
+                .events
+                .iter()
+                .map(rules)
+                .collect::<Vec<Vec<(ValidationRule, Option<&str>)>>>(),
+            vec![
+                vec![],
+                vec![(ValidationRule::Parent, Some("attrs.p"))],
+                vec![(ValidationRule::UuidVersion, Some("id"))],
+                vec![(ValidationRule::Title, Some("attrs.t"))],
+                vec![(ValidationRule::IdentityKey, Some("attrs.u"))],
+            ],
+            "Expected every event to be reported with its failed rules"
+        );
+        assert!(report.events[0].valid);
+        assert_eq!(
+            state.request_limiter.report(),
+            LimitViolationCounts::default(),
+            "Expected validation to not count limit violations"
+        );
+
+        let report = validate_client_events(
+            State(state.clone()),
+            request_headers("other.com"),
+            client_ip,
+            Negotiated(bodies()[..1].to_vec()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            rules(&report.events[0]),
+            vec![(ValidationRule::Source, None)],
+            "Expected an unknown source to be reported"
+        );
+
+        let mut missing_api_key = request_headers("test.com");
+        missing_api_key.remove(API_KEY_HTTP_HEADER);
+        let report = validate_client_events(
+            State(state.clone()),
+            missing_api_key,
+            client_ip,
+            Negotiated(Vec::new()),
+        )
+        .await
+        .unwrap();
+        assert!(!report.valid);
+        assert_eq!(
+            report
+                .issues
+                .iter()
+                .map(|issue| issue.rule)
+                .collect::<Vec<ValidationRule>>(),
+            vec![ValidationRule::Events, ValidationRule::ApiKey]
+        );
+
+        // Negative test case
+        let failing_state = IngestApplicationState::new(IngestService::new(mock_repo(Err(
+            IngestRepositoryError::Repository,
+        ))));
+        assert!(matches!(
+            validate_client_events(
+                State(failing_state),
+                request_headers("test.com"),
+                client_ip,
+                Negotiated(bodies()),
+            )
+            .await,
+            Err(ClientEventRequestError::IngestService(_))
+        ));
+    }
+}
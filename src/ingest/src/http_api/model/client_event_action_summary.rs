@@ -1,19 +1,22 @@
-use axum::response::IntoResponse;
+use axum::{Json, response::IntoResponse};
 use http::StatusCode;
+use serde::Serialize;
 
 use crate::domain::model::ingest_action_summary::{IngestActionSummary, IngestEventSaveSummary};
 
 /// `ClientEventSaveSummary` provides a struct outside of the domain to
 /// encapsulate the results from the service layer.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ClientEventSaveSummary {
     pub event_count: usize,
+    pub duplicate_count: usize,
 }
 
 impl From<IngestEventSaveSummary> for ClientEventSaveSummary {
     fn from(value: IngestEventSaveSummary) -> Self {
         Self {
             event_count: value.event_count,
+            duplicate_count: value.duplicate_count,
         }
     }
 }
@@ -36,12 +39,15 @@ impl From<IngestActionSummary> for ClientEventActionSummary {
 /// `ClientEventActionSummary` should be able to be returned from handler
 /// functions so that there is a clean
 /// `Result<ClientEventActionSummary, ClientEventRequestError>` return
-/// signature for the handlers. But, we don't need to actually provide data
-/// back to the client, so this type simply maps to a HTTP 201 Created response
+/// signature for the handlers. Saves map to a HTTP 201 Created response with
+/// the counts of saved and duplicate events, so that a client which retried a
+/// batch can tell that it was already saved.
 impl IntoResponse for ClientEventActionSummary {
     fn into_response(self) -> axum::response::Response {
         match self {
-            ClientEventActionSummary::Save(_) => StatusCode::CREATED.into_response(),
+            ClientEventActionSummary::Save(summary) => {
+                (StatusCode::CREATED, Json(summary)).into_response()
+            }
         }
     }
 }
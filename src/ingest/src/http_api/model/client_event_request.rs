@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::net::IpAddr;
 
 use axum::response::IntoResponse;
@@ -9,6 +10,7 @@ use uuid::Uuid;
 
 use crate::domain::model::ingest_event::ApiKey;
 use crate::domain::model::ingest_event::ClickEvent;
+use crate::domain::model::ingest_event::IdentifyEvent;
 use crate::domain::model::ingest_event::IngestEvent;
 use crate::domain::model::ingest_event::IngestEventError;
 use crate::domain::model::ingest_event::SectionEvent;
@@ -19,6 +21,8 @@ use crate::domain::service::ingest_event_service::IngestServiceError;
 
 use super::client_event_request_components::ClientEventRequestBody;
 use super::client_event_request_components::ClientEventRequestHeaders;
+use super::dead_letter_store::RejectionReason;
+use super::request_limiter::LimitViolation;
 
 /// `ClientEventRequestType` represents the type of analytics event submitted by
 /// client. This enum must match up with the `event_record`'s
@@ -30,6 +34,29 @@ pub enum ClientEventRequestType {
     Session = 2,
     Section = 3,
     Click = 4,
+    Identify = 6,
+}
+
+/// `USER_ID_ATTR` holds the user id of an `Identify` request, which must
+/// never leave ingestion in clear
+pub const USER_ID_ATTR: &str = "u";
+
+/// `TRAIT_ATTR_PREFIX` marks the attributes of an `Identify` request that are
+/// traits of the user rather than attributes of the event
+pub const TRAIT_ATTR_PREFIX: &str = "trait.";
+
+/// Traits of an `Identify` request, which are the attributes carrying the
+/// `TRAIT_ATTR_PREFIX` with that prefix removed
+pub fn traits_from_attrs(attrs: Option<&HashMap<String, String>>) -> HashMap<String, String> {
+    attrs
+        .into_iter()
+        .flatten()
+        .filter_map(|(key, value)| {
+            key.strip_prefix(TRAIT_ATTR_PREFIX)
+                .filter(|name| !name.is_empty())
+                .map(|name| (name.to_owned(), value.to_owned()))
+        })
+        .collect()
 }
 
 /// `ClientEventRequestError` encapsulates the error types that can occur
@@ -39,6 +66,8 @@ pub enum ClientEventRequestType {
 pub enum ClientEventRequestError {
     #[error("API KEY missing from request header")]
     ApiKey,
+    #[error("Request is not allowed from a browser")]
+    Forbidden,
     #[error("Error converting client request into ingest event")]
     IngestEvent(#[from] IngestEventError),
     #[error("Error returned from IngestEventService")]
@@ -47,10 +76,18 @@ pub enum ClientEventRequestError {
     InvalidRequestBody,
     #[error("Invalid request headers")]
     InvalidRequestHeaders,
+    #[error("Request exceeds a limit")]
+    Limit(#[from] LimitViolation),
     #[error(
         "Somehow ended up trying to create event of one type with input for another - this should never happen"
     )]
     TypeMismatch,
+    #[error("Too many requests")]
+    TooManyRequests,
+    #[error("Missing or invalid server key")]
+    Unauthorized,
+    #[error("Unsupported request content type")]
+    UnsupportedMediaType,
 }
 
 /// `ClientEventRequestError` needs to implement `IntoResponse` in order to
@@ -59,19 +96,32 @@ pub enum ClientEventRequestError {
 /// portions of the `Result` properly implement `IntoResponse`
 /// Note that this response is meant to not reveal any internal error
 /// information to the client for the sake of security. Instead, all responses
-/// are merely HTTP response codes with no accompanying body.
+/// are merely HTTP response codes with no accompanying body. The reason for a
+/// rejection is only added to the response extensions as a `RejectionReason`
+/// for the `DeadLetterStore`.
 impl IntoResponse for ClientEventRequestError {
     fn into_response(self) -> axum::response::Response {
-        match self {
+        let rejection_reason = self.rejection_reason();
+        let mut response = match self {
             ClientEventRequestError::ApiKey => StatusCode::BAD_REQUEST.into_response(),
+            ClientEventRequestError::Forbidden => StatusCode::FORBIDDEN.into_response(),
             ClientEventRequestError::InvalidRequestBody => StatusCode::BAD_REQUEST.into_response(),
             ClientEventRequestError::InvalidRequestHeaders => {
                 StatusCode::BAD_REQUEST.into_response()
             }
+            ClientEventRequestError::Limit(LimitViolation::Events) => {
+                StatusCode::PAYLOAD_TOO_LARGE.into_response()
+            }
+            ClientEventRequestError::Limit(_) => StatusCode::BAD_REQUEST.into_response(),
             ClientEventRequestError::IngestEvent(e) => {
                 tracing::error!("{}", e);
                 StatusCode::BAD_REQUEST.into_response()
             }
+            // Identify events are a client error for sources that were not
+            // configured to pseudonymize user ids
+            ClientEventRequestError::IngestService(IngestServiceError::IdentityKey) => {
+                StatusCode::BAD_REQUEST.into_response()
+            }
             ClientEventRequestError::IngestService(e) => {
                 tracing::error!("{}", e);
                 StatusCode::INTERNAL_SERVER_ERROR.into_response()
@@ -80,6 +130,32 @@ impl IntoResponse for ClientEventRequestError {
                 tracing::error!("Encounterd TypeMismatch, which shoule never happen");
                 StatusCode::INTERNAL_SERVER_ERROR.into_response()
             }
+            ClientEventRequestError::TooManyRequests => {
+                StatusCode::TOO_MANY_REQUESTS.into_response()
+            }
+            ClientEventRequestError::Unauthorized => StatusCode::UNAUTHORIZED.into_response(),
+            ClientEventRequestError::UnsupportedMediaType => {
+                StatusCode::UNSUPPORTED_MEDIA_TYPE.into_response()
+            }
+        };
+        if let Some(reason) = rejection_reason {
+            response.extensions_mut().insert(RejectionReason(reason));
+        }
+        response
+    }
+}
+
+impl ClientEventRequestError {
+    /// Reason kept with the dead letter of a request rejected with this
+    /// error, or `None` when the request failed on the side of the server
+    pub(crate) fn rejection_reason(&self) -> Option<String> {
+        match self {
+            ClientEventRequestError::IngestService(IngestServiceError::Repository(_))
+            | ClientEventRequestError::TypeMismatch => None,
+            ClientEventRequestError::IngestEvent(e) => Some(format!("{self}: {e}")),
+            ClientEventRequestError::IngestService(e) => Some(format!("{self}: {e}")),
+            ClientEventRequestError::Limit(e) => Some(format!("{self}: {e}")),
+            _ => Some(self.to_string()),
         }
     }
 }
@@ -120,6 +196,7 @@ impl TryFrom<&ClientEventRequest> for IngestEvent {
             ClientEventRequestType::Session => Ok(IngestEvent::Session(value.try_into()?)),
             ClientEventRequestType::Section => Ok(IngestEvent::Section(value.try_into()?)),
             ClientEventRequestType::Click => Ok(IngestEvent::Click(value.try_into()?)),
+            ClientEventRequestType::Identify => Ok(IngestEvent::Identify(value.try_into()?)),
         }
     }
 }
@@ -220,6 +297,36 @@ impl TryFrom<&ClientEventRequest> for ClickEvent {
     }
 }
 
+/// `ClientEventRequest` to the discriminant for `IngestEvent::Identify`. The
+/// user id is given in the `u` attribute and traits with `TRAIT_ATTR_PREFIX`.
+impl TryFrom<&ClientEventRequest> for IdentifyEvent {
+    type Error = ClientEventRequestError;
+    fn try_from(value: &ClientEventRequest) -> Result<Self, Self::Error> {
+        assert!(
+            value.body.event_type == ClientEventRequestType::Identify,
+            "Attempted to build Identify event from other type"
+        );
+
+        let parent = value
+            .attr("p")
+            .ok_or(ClientEventRequestError::InvalidRequestBody)?;
+        let parent_uuid =
+            Uuid::parse_str(parent).map_err(|_| ClientEventRequestError::InvalidRequestBody)?;
+        let user_id = value
+            .attr(USER_ID_ATTR)
+            .ok_or(ClientEventRequestError::InvalidRequestBody)?;
+        IdentifyEvent::try_new(
+            ApiKey::new(&value.headers.api_key),
+            Site::new(&value.headers.site),
+            value.body.id,
+            parent_uuid,
+            user_id.to_owned(),
+            traits_from_attrs(value.body.attrs.as_ref()),
+        )
+        .map_err(|e| e.into())
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -260,6 +367,12 @@ mod tests {
             click_discriminant, 4,
             "ClientEventRequestType::Click discriminant does not match expected value"
         );
+
+        let identify_discriminant = ClientEventRequestType::Identify as u32;
+        assert_eq!(
+            identify_discriminant, 6,
+            "ClientEventRequestType::Identify discriminant does not match expected value"
+        );
     }
 
     #[test]
@@ -372,5 +485,38 @@ mod tests {
             }
             _ => panic!("Expected valid section event to be generated"),
         }
+
+        // Identify
+        let identify_attrs: HashMap<String, String> = HashMap::from([
+            ("p".to_owned(), parent_id.to_string()),
+            ("u".to_owned(), "user-42".to_owned()),
+            ("trait.plan".to_owned(), "pro".to_owned()),
+        ]);
+        let valid_identify_request = ClientEventRequest {
+            body: ClientEventRequestBody {
+                id: uuid_now,
+                event_type: ClientEventRequestType::Identify,
+                attrs: Some(identify_attrs),
+            },
+            headers: ClientEventRequestHeaders {
+                api_key: API_KEY.to_owned(),
+                site: SITE.to_owned(),
+                user_agent: USER_AGENT.to_owned(),
+            },
+            ip: client_ip,
+        };
+        let identify_ingest_event: IngestEvent = (&valid_identify_request).try_into().unwrap();
+        match identify_ingest_event {
+            IngestEvent::Identify(ref identify_event) => {
+                assert_eq!(identify_event.id(), uuid_now);
+                assert_eq!(identify_event.parent, parent_id);
+                assert_eq!(identify_event.user_hash(), None);
+                assert_eq!(
+                    identify_event.traits,
+                    HashMap::from([("plan".to_owned(), "pro".to_owned())])
+                );
+            }
+            _ => panic!("Expected valid identify event to be generated"),
+        }
     }
 }
@@ -38,12 +38,48 @@ pub struct ClientEventRequestBody {
     #[serde(alias = "t")]
     pub event_type: ClientEventRequestType,
     #[serde(alias = "i")]
-    #[serde(with = "clickhouse::serde::uuid")]
+    #[serde(with = "request_uuid")]
     pub id: Uuid,
     #[serde(alias = "a")]
     pub attrs: Option<HashMap<String, String>>,
 }
 
+/// `request_uuid` reads the id of a `ClientEventRequestBody` from either its
+/// string form or its 16 bytes, so that binary `WireFormat`s can send ids
+/// compactly. Ids are always written in their string form.
+mod request_uuid {
+    use std::fmt;
+
+    use serde::{Deserializer, Serializer, de};
+    use uuid::Uuid;
+
+    pub fn serialize<S: Serializer>(id: &Uuid, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(id)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Uuid, D::Error> {
+        deserializer.deserialize_any(UuidVisitor)
+    }
+
+    struct UuidVisitor;
+
+    impl de::Visitor<'_> for UuidVisitor {
+        type Value = Uuid;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a UUID string or 16 bytes")
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<Uuid, E> {
+            Uuid::parse_str(value).map_err(E::custom)
+        }
+
+        fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<Uuid, E> {
+            Uuid::from_slice(value).map_err(E::custom)
+        }
+    }
+}
+
 impl ClientEventRequestBody {
     /// `ClientEventRequestBody` constructor
     pub fn new(
@@ -0,0 +1,318 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use http::HeaderMap;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::domain::model::{event_scrubber::REDACTED, server_key::ServerKey};
+
+use super::client_event_request_components::API_KEY_HTTP_HEADER;
+
+/// Maximum number of sites that rejected requests are kept for. Sites are
+/// taken from the `Origin` header, which any client can set, so requests for
+/// further sites are only counted.
+pub const DEAD_LETTER_MAX_SITES: usize = 1024;
+
+/// Default number of dead letters returned by a `DeadLetterQuery`
+pub const DEFAULT_DEAD_LETTER_QUERY_LIMIT: usize = 100;
+
+/// Headers whose values are credentials and are never kept
+const REDACTED_HEADERS: &[&str] = &[
+    API_KEY_HTTP_HEADER,
+    "authorization",
+    "cookie",
+    "proxy-authorization",
+];
+
+/// `RejectionReason` is added to the extensions of the response to a rejected
+/// request, so that the reason for the rejection is kept with its dead letter
+/// even though it is not revealed to the client
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RejectionReason(pub String);
+
+/// `DeadLetter` is a rejected request kept for review. The `body` is kept as
+/// text when it is valid UTF-8 and base64 encoded otherwise, such as for the
+/// binary `WireFormat`s, and is cut off at the maximum body size of the store.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeadLetter {
+    /// Host of the `Origin` header, which is absent for server-to-server
+    /// requests
+    pub site: Option<String>,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    pub reason: String,
+    /// Request headers with credentials replaced by `[REDACTED]`
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+    pub base64: bool,
+    pub truncated: bool,
+    #[serde(with = "time::serde::rfc3339")]
+    pub received_at: OffsetDateTime,
+}
+
+impl DeadLetter {
+    /// Keep the headers of a rejected request with credentials redacted
+    pub fn redacted_headers(headers: &HeaderMap) -> Vec<(String, String)> {
+        headers
+            .iter()
+            .map(|(name, value)| {
+                let value = if REDACTED_HEADERS.contains(&name.as_str()) {
+                    REDACTED.to_owned()
+                } else {
+                    String::from_utf8_lossy(value.as_bytes()).into_owned()
+                };
+                (name.as_str().to_owned(), value)
+            })
+            .collect()
+    }
+
+    /// Keep the body of a rejected request as text, or as base64 if it is not
+    /// valid UTF-8. Returns the kept body and whether it is base64 encoded.
+    /// A character cut off at the end of a truncated body is left out.
+    pub fn encoded_body(body: &[u8]) -> (String, bool) {
+        match std::str::from_utf8(body) {
+            Ok(text) => (text.to_owned(), false),
+            Err(e) if e.error_len().is_none() => (
+                String::from_utf8_lossy(&body[..e.valid_up_to()]).into_owned(),
+                false,
+            ),
+            Err(_) => (STANDARD.encode(body), true),
+        }
+    }
+}
+
+/// `DeadLetterQuery` selects the dead letters of a single `site`, or of every
+/// site when absent, and bounds them with `limit`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct DeadLetterQuery {
+    pub site: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// Dead letters of a single site along with the number of rejected requests
+/// seen for it, which determines the requests that are sampled
+#[derive(Debug, Default)]
+struct SiteDeadLetters {
+    seen: u64,
+    letters: VecDeque<DeadLetter>,
+}
+
+/// `DeadLetterStore` keeps the most recent rejected requests of every site in
+/// memory, so that broken integrations can be debugged from what they
+/// actually sent. One in every `sample` rejected requests of a site is kept,
+/// up to `capacity` per site, dropping the oldest first.
+#[derive(Debug)]
+pub struct DeadLetterStore {
+    capacity: usize,
+    sample: u64,
+    max_body: usize,
+    sites: Mutex<HashMap<Option<String>, SiteDeadLetters>>,
+}
+
+impl DeadLetterStore {
+    /// `DeadLetterStore` constructor that keeps up to `capacity` dead letters
+    /// per site, one in every `sample` and at most `max_body` bytes of each
+    /// body
+    pub fn new(capacity: usize, sample: u64, max_body: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            sample: sample.max(1),
+            max_body,
+            sites: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Maximum number of bytes kept of each body
+    pub fn max_body(&self) -> usize {
+        self.max_body
+    }
+
+    /// The dead letters of every site. A panic while the lock was held
+    /// leaves them consistent, so a poisoned lock is recovered.
+    fn sites(&self) -> MutexGuard<'_, HashMap<Option<String>, SiteDeadLetters>> {
+        self.sites
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Whether the next rejected request of `site` is sampled. Every call
+    /// counts as a rejected request of the site.
+    pub fn sample(&self, site: Option<&str>) -> bool {
+        let mut sites = self.sites();
+        let key = site.map(str::to_owned);
+        if !sites.contains_key(&key) && sites.len() >= DEAD_LETTER_MAX_SITES {
+            return false;
+        }
+        let site_letters = sites.entry(key).or_default();
+        site_letters.seen += 1;
+        (site_letters.seen - 1).is_multiple_of(self.sample)
+    }
+
+    /// Keep a sampled `DeadLetter`, dropping the oldest of its site once the
+    /// capacity is reached
+    pub fn push(&self, dead_letter: DeadLetter) {
+        let mut sites = self.sites();
+        if !sites.contains_key(&dead_letter.site) && sites.len() >= DEAD_LETTER_MAX_SITES {
+            return;
+        }
+        let site_letters = sites.entry(dead_letter.site.clone()).or_default();
+        if site_letters.letters.len() >= self.capacity {
+            site_letters.letters.pop_front();
+        }
+        site_letters.letters.push_back(dead_letter);
+    }
+
+    /// Dead letters matching the query, newest first
+    pub fn dead_letters(&self, query: &DeadLetterQuery) -> Vec<DeadLetter> {
+        let sites = self.sites();
+        let mut dead_letters: Vec<DeadLetter> = sites
+            .iter()
+            .filter(|(site, _)| {
+                query
+                    .site
+                    .as_ref()
+                    .is_none_or(|filter| site.as_ref() == Some(filter))
+            })
+            .flat_map(|(_, site_letters)| site_letters.letters.iter().cloned())
+            .collect();
+        dead_letters.sort_by_key(|dead_letter| std::cmp::Reverse(dead_letter.received_at));
+        dead_letters.truncate(query.limit.unwrap_or(DEFAULT_DEAD_LETTER_QUERY_LIMIT));
+        dead_letters
+    }
+}
+
+/// `DeadLetterState` is the Axum state of the admin routes for reviewing the
+/// `DeadLetterStore`, which are only served to callers presenting the
+/// `admin_key`
+#[derive(Debug, Clone)]
+pub struct DeadLetterState {
+    pub dead_letter_store: Arc<DeadLetterStore>,
+    pub admin_key: ServerKey,
+}
+
+#[cfg(test)]
+mod tests {
+    use http::header;
+
+    use super::*;
+
+    fn dead_letter(site: Option<&str>, body: &str) -> DeadLetter {
+        DeadLetter {
+            site: site.map(str::to_owned),
+            method: "POST".to_owned(),
+            path: "/multi".to_owned(),
+            status: 400,
+            reason: "Invalid request body".to_owned(),
+            headers: Vec::new(),
+            body: body.to_owned(),
+            base64: false,
+            truncated: false,
+            received_at: OffsetDateTime::now_utc(),
+        }
+    }
+
+    #[test]
+    fn test_dead_letter_store() {
+        let dead_letter_store = DeadLetterStore::new(2, 2, 1024);
+        let sampled: Vec<bool> = (0..4)
+            .map(|_| dead_letter_store.sample(Some("test.com")))
+            .collect();
+        assert_eq!(
+            sampled,
+            vec![true, false, true, false],
+            "Expected one in every two requests to be sampled"
+        );
+        assert!(
+            dead_letter_store.sample(Some("other.com")),
+            "Expected sampling to be counted per site"
+        );
+
+        for body in ["first", "second", "third"] {
+            dead_letter_store.push(dead_letter(Some("test.com"), body));
+        }
+        dead_letter_store.push(dead_letter(None, "server"));
+        let dead_letters = dead_letter_store.dead_letters(&DeadLetterQuery {
+            site: Some("test.com".to_owned()),
+            limit: None,
+        });
+        assert_eq!(
+            dead_letters
+                .iter()
+                .map(|dead_letter| dead_letter.body.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["third", "second"],
+            "Expected the newest dead letters up to the capacity"
+        );
+        assert_eq!(
+            dead_letter_store
+                .dead_letters(&DeadLetterQuery::default())
+                .len(),
+            3
+        );
+        assert_eq!(
+            dead_letter_store
+                .dead_letters(&DeadLetterQuery {
+                    site: None,
+                    limit: Some(1),
+                })
+                .len(),
+            1
+        );
+
+        // Negative test case
+        assert!(
+            dead_letter_store
+                .dead_letters(&DeadLetterQuery {
+                    site: Some("unknown.com".to_owned()),
+                    limit: None,
+                })
+                .is_empty()
+        );
+
+        let poisoned = std::panic::catch_unwind(|| {
+            let _sites = dead_letter_store.sites.lock().unwrap();
+            panic!("poison the lock");
+        });
+        assert!(poisoned.is_err() && dead_letter_store.sites.is_poisoned());
+        dead_letter_store.push(dead_letter(Some("test.com"), "fourth"));
+        assert_eq!(
+            dead_letter_store
+                .dead_letters(&DeadLetterQuery::default())
+                .len(),
+            3,
+            "Expected the store to keep working after a panic while it was locked"
+        );
+    }
+
+    #[test]
+    fn test_dead_letter_encoding() {
+        let mut headers = HeaderMap::new();
+        headers.insert(API_KEY_HTTP_HEADER, "abc-123".parse().unwrap());
+        headers.insert(header::AUTHORIZATION, "Bearer secret".parse().unwrap());
+        headers.insert(header::USER_AGENT, "test-agent".parse().unwrap());
+        let redacted = DeadLetter::redacted_headers(&headers);
+        assert!(redacted.contains(&(API_KEY_HTTP_HEADER.to_owned(), REDACTED.to_owned())));
+        assert!(redacted.contains(&("authorization".to_owned(), REDACTED.to_owned())));
+        assert!(redacted.contains(&("user-agent".to_owned(), "test-agent".to_owned())));
+
+        assert_eq!(
+            DeadLetter::encoded_body(b"[{\"event_type\":1}]"),
+            ("[{\"event_type\":1}]".to_owned(), false)
+        );
+        assert_eq!(
+            DeadLetter::encoded_body(&[0x91, 0xff]),
+            ("kf8=".to_owned(), true),
+            "Expected a binary body to be base64 encoded"
+        );
+        assert_eq!(
+            DeadLetter::encoded_body(&"café".as_bytes()[..4]),
+            ("caf".to_owned(), false),
+            "Expected a character cut off by truncation to be left out"
+        );
+    }
+}
@@ -1,14 +1,26 @@
 use std::sync::Arc;
 
-use crate::domain::service::ingest_event_service::IngestEventService;
+use crate::{
+    domain::{
+        model::session_synthesizer::SessionSynthesizer,
+        service::ingest_event_service::IngestEventService,
+    },
+    http_api::model::{live_tail::LiveTail, request_limiter::RequestLimiter},
+};
 
 /// `IngestApplicationState` is the Axum state that is required for all
 /// handlers for the HTTP API for Ingestion. This generic implementation
 /// requires an `IngestEventService` that is used for saving incoming events
-/// to the data store.
+/// to the data store. The `SessionSynthesizer` is shared by the handlers for
+/// clients that do not send `Visitor` and `Session` events themselves, the
+/// `RequestLimiter` by the handlers that accept batches of events and the
+/// `LiveTail` by the handlers of client events and the tails watching them.
 #[derive(Debug, Clone)]
 pub struct IngestApplicationState<I: IngestEventService> {
     pub ingest_service: Arc<I>,
+    pub session_synthesizer: Arc<SessionSynthesizer>,
+    pub request_limiter: Arc<RequestLimiter>,
+    pub live_tail: Arc<LiveTail>,
 }
 
 impl<I: IngestEventService> IngestApplicationState<I> {
@@ -17,6 +29,28 @@ impl<I: IngestEventService> IngestApplicationState<I> {
     pub fn new(ingest_service: I) -> Self {
         Self {
             ingest_service: Arc::new(ingest_service),
+            session_synthesizer: Arc::new(SessionSynthesizer::default()),
+            request_limiter: Arc::new(RequestLimiter::default()),
+            live_tail: Arc::new(LiveTail::default()),
         }
     }
+
+    /// Replace the `SessionSynthesizer` used for clients that do not track
+    /// visitors and sessions
+    pub fn with_session_synthesizer(mut self, session_synthesizer: SessionSynthesizer) -> Self {
+        self.session_synthesizer = Arc::new(session_synthesizer);
+        self
+    }
+
+    /// Replace the `RequestLimiter` used to bound incoming batches
+    pub fn with_request_limiter(mut self, request_limiter: RequestLimiter) -> Self {
+        self.request_limiter = Arc::new(request_limiter);
+        self
+    }
+
+    /// Replace the `LiveTail` that streams client events to their tails
+    pub fn with_live_tail(mut self, live_tail: LiveTail) -> Self {
+        self.live_tail = Arc::new(live_tail);
+        self
+    }
 }
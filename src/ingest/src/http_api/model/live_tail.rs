@@ -0,0 +1,460 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use time::OffsetDateTime;
+use tokio::{
+    sync::broadcast::{self, error::RecvError},
+    time::Instant,
+};
+
+use crate::domain::model::{
+    event_scrubber::{EventScrubber, REDACTED},
+    ingest_source_rules::QueryParamPolicy,
+};
+
+use super::{
+    client_event_request::{ClientEventRequestType, USER_ID_ATTR},
+    client_event_request_components::ClientEventRequestBody,
+    request_limiter::LOCATION_ATTR,
+};
+
+/// Default maximum time for which a single tail streams events
+pub const DEFAULT_TAIL_MAX_DURATION: Duration = Duration::from_secs(600);
+
+/// Default maximum number of events streamed to a single tail per second
+pub const DEFAULT_TAIL_MAX_RATE: u32 = 50;
+
+/// Default maximum number of tails open at once for a single api key
+pub const DEFAULT_TAIL_MAX_PER_API_KEY: usize = 2;
+
+/// Number of published events of a source buffered for its tails that fall
+/// behind
+const TAIL_CHANNEL_CAPACITY: usize = 1024;
+
+/// `TailError` is the reason a tail could not be opened
+#[derive(Clone, Copy, Error, Debug, PartialEq, Eq)]
+pub enum TailError {
+    #[error("Too many tails open for api key")]
+    TooManyTails,
+}
+
+/// `TailStatus` is whether a tailed event was accepted or rejected
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TailStatus {
+    Accepted,
+    Rejected,
+}
+
+impl TailStatus {
+    /// Name of the status, which is the name of its Server-Sent Events
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TailStatus::Accepted => "accepted",
+            TailStatus::Rejected => "rejected",
+        }
+    }
+}
+
+/// `TailQuery` selects the `site` to tail and optionally closes the tail after
+/// `secs` rather than after the maximum duration
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct TailQuery {
+    pub site: String,
+    pub secs: Option<u64>,
+}
+
+/// `TailEvent` is a client event as it passed through ingestion, along with
+/// whether it was accepted and the reason it was rejected if not. Events are
+/// scrubbed by `LiveTail::publish` before any tail receives them.
+#[derive(Clone, Debug, Serialize)]
+pub struct TailEvent {
+    pub status: TailStatus,
+    pub reason: Option<String>,
+    #[serde(flatten)]
+    pub event: ClientEventRequestBody,
+    #[serde(with = "time::serde::rfc3339")]
+    pub received_at: OffsetDateTime,
+}
+
+/// `TailMessage` is a single item streamed to a tail
+#[derive(Clone, Debug)]
+pub enum TailMessage {
+    /// An event of the tailed api key and site
+    Event(Arc<TailEvent>),
+    /// Number of events left out since the last message, as the tail exceeded
+    /// its rate or fell behind
+    Dropped(u64),
+    /// The tail reached its maximum duration and is closed
+    Expired,
+}
+
+/// `TailChannel` carries the events of a single api key and site to its open
+/// tails
+#[derive(Debug)]
+struct TailChannel {
+    sender: broadcast::Sender<Arc<TailEvent>>,
+    tails: usize,
+}
+
+/// `LiveTail` streams the client events of a single api key and site to
+/// anyone watching them, so that customers can see whether their
+/// instrumentation works while they set it up. Each tailed source has its own
+/// channel, which exists while any tail of the source is open, so that events
+/// are only copied for sources that are being tailed and a busy source cannot
+/// crowd out the events of another. Events are scrubbed with the
+/// `EventScrubber` before they are streamed. Tails are bounded in number per
+/// api key, in the rate at which they receive events and in how long they
+/// stay open.
+#[derive(Debug)]
+pub struct LiveTail {
+    channels: Mutex<HashMap<(String, String), TailChannel>>,
+    event_scrubber: EventScrubber,
+    max_duration: Duration,
+    max_rate: u32,
+    max_per_api_key: usize,
+}
+
+impl Default for LiveTail {
+    /// Uses the default `EventScrubber`, `DEFAULT_TAIL_MAX_DURATION`,
+    /// `DEFAULT_TAIL_MAX_RATE` and `DEFAULT_TAIL_MAX_PER_API_KEY`
+    fn default() -> Self {
+        Self {
+            channels: Mutex::new(HashMap::new()),
+            event_scrubber: EventScrubber::default(),
+            max_duration: DEFAULT_TAIL_MAX_DURATION,
+            max_rate: DEFAULT_TAIL_MAX_RATE,
+            max_per_api_key: DEFAULT_TAIL_MAX_PER_API_KEY,
+        }
+    }
+}
+
+impl LiveTail {
+    /// Replace the `EventScrubber` applied to events before they are streamed
+    pub fn with_event_scrubber(mut self, event_scrubber: EventScrubber) -> Self {
+        self.event_scrubber = event_scrubber;
+        self
+    }
+
+    /// Replace the maximum time for which a single tail streams events
+    pub fn with_max_duration(mut self, max_duration: Duration) -> Self {
+        self.max_duration = max_duration;
+        self
+    }
+
+    /// Replace the maximum number of events streamed to a tail per second
+    pub fn with_max_rate(mut self, max_rate: u32) -> Self {
+        self.max_rate = max_rate.max(1);
+        self
+    }
+
+    /// Replace the maximum number of tails open at once for an api key
+    pub fn with_max_per_api_key(mut self, max_per_api_key: usize) -> Self {
+        self.max_per_api_key = max_per_api_key;
+        self
+    }
+
+    /// Whether any tail is open for the api key and site
+    pub fn is_tailed(&self, api_key: &str, site: &str) -> bool {
+        self.channels()
+            .contains_key(&(api_key.to_owned(), site.to_owned()))
+    }
+
+    /// Scrub `event` and stream it to the tails of the api key and site
+    pub fn publish(&self, api_key: &str, site: &str, mut event: TailEvent) {
+        let Some(sender) = self
+            .channels()
+            .get(&(api_key.to_owned(), site.to_owned()))
+            .map(|channel| channel.sender.clone())
+        else {
+            return;
+        };
+        self.scrub(&mut event.event);
+        // Sending only fails when the last tail was just closed
+        let _ = sender.send(Arc::new(event));
+    }
+
+    /// Scrub every attr of a tailed event. The query parameter rules of its
+    /// source are not at hand, so locations lose their whole query string.
+    /// The user id of an `Identify` event is replaced entirely, as it is only
+    /// ever stored as a hash.
+    fn scrub(&self, event: &mut ClientEventRequestBody) {
+        let Some(attrs) = event.attrs.as_mut() else {
+            return;
+        };
+        let no_query_params = QueryParamPolicy::new(Some(HashSet::new()), HashSet::new());
+        for (name, value) in attrs.iter_mut() {
+            *value = if event.event_type == ClientEventRequestType::Identify && name == USER_ID_ATTR
+            {
+                REDACTED.to_owned()
+            } else if name == LOCATION_ATTR {
+                self.event_scrubber.scrub_location(value, &no_query_params)
+            } else {
+                self.event_scrubber.scrub_text(value)
+            };
+        }
+    }
+
+    /// Open a tail of the api key and site that closes after `duration`, or
+    /// after the maximum duration if that is shorter
+    pub fn subscribe(
+        self: &Arc<Self>,
+        api_key: &str,
+        site: &str,
+        duration: Option<Duration>,
+    ) -> Result<TailSubscription, TailError> {
+        let mut channels = self.channels();
+        let open_for_api_key: usize = channels
+            .iter()
+            .filter(|((tailed_api_key, _), _)| tailed_api_key == api_key)
+            .map(|(_, channel)| channel.tails)
+            .sum();
+        if open_for_api_key >= self.max_per_api_key {
+            return Err(TailError::TooManyTails);
+        }
+        let channel = channels
+            .entry((api_key.to_owned(), site.to_owned()))
+            .or_insert_with(|| TailChannel {
+                sender: broadcast::channel(TAIL_CHANNEL_CAPACITY).0,
+                tails: 0,
+            });
+        channel.tails += 1;
+        let duration = duration.map_or(self.max_duration, |d| d.min(self.max_duration));
+        Ok(TailSubscription {
+            live_tail: self.clone(),
+            api_key: api_key.to_owned(),
+            site: site.to_owned(),
+            receiver: channel.sender.subscribe(),
+            deadline: Instant::now() + duration,
+            window_start: Instant::now(),
+            window_count: 0,
+            dropped: 0,
+            pending: None,
+            expired: false,
+        })
+    }
+
+    /// The channels of the tailed sources. A panic while the lock was held
+    /// leaves them consistent, so a poisoned lock is recovered.
+    fn channels(&self) -> MutexGuard<'_, HashMap<(String, String), TailChannel>> {
+        self.channels
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// `TailSubscription` is an open tail, which is closed when dropped
+#[derive(Debug)]
+pub struct TailSubscription {
+    live_tail: Arc<LiveTail>,
+    api_key: String,
+    site: String,
+    receiver: broadcast::Receiver<Arc<TailEvent>>,
+    deadline: Instant,
+    window_start: Instant,
+    window_count: u32,
+    dropped: u64,
+    pending: Option<Arc<TailEvent>>,
+    expired: bool,
+}
+
+impl TailSubscription {
+    /// Wait for the next `TailMessage`, or `None` once the tail is closed.
+    /// Events beyond the maximum rate are counted and reported as dropped
+    /// before the next event that is streamed.
+    pub async fn next(&mut self) -> Option<TailMessage> {
+        if self.expired {
+            return None;
+        }
+        if let Some(event) = self.pending.take() {
+            return Some(TailMessage::Event(event));
+        }
+        loop {
+            let received = tokio::select! {
+                received = self.receiver.recv() => received,
+                _ = tokio::time::sleep_until(self.deadline) => {
+                    self.expired = true;
+                    return Some(TailMessage::Expired);
+                }
+            };
+            let event = match received {
+                Ok(event) => event,
+                Err(RecvError::Lagged(count)) => {
+                    self.dropped += count;
+                    continue;
+                }
+                Err(RecvError::Closed) => return None,
+            };
+            let now = Instant::now();
+            if now.duration_since(self.window_start) >= Duration::from_secs(1) {
+                self.window_start = now;
+                self.window_count = 0;
+            }
+            if self.window_count >= self.live_tail.max_rate {
+                self.dropped += 1;
+                continue;
+            }
+            self.window_count += 1;
+            if self.dropped > 0 {
+                self.pending = Some(event);
+                return Some(TailMessage::Dropped(std::mem::take(&mut self.dropped)));
+            }
+            return Some(TailMessage::Event(event));
+        }
+    }
+}
+
+impl Drop for TailSubscription {
+    /// Close the tail, removing the channel of its source with the last tail
+    fn drop(&mut self) {
+        let mut channels = self.live_tail.channels();
+        let key = (
+            std::mem::take(&mut self.api_key),
+            std::mem::take(&mut self.site),
+        );
+        if let Some(channel) = channels.get_mut(&key) {
+            channel.tails -= 1;
+            if channel.tails == 0 {
+                channels.remove(&key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::http_api::model::request_limiter::TITLE_ATTR;
+
+    use super::*;
+
+    fn tail_event(status: TailStatus) -> TailEvent {
+        TailEvent {
+            status,
+            reason: None,
+            event: ClientEventRequestBody::new(
+                ClientEventRequestType::Visitor,
+                Uuid::now_v7(),
+                None,
+            ),
+            received_at: OffsetDateTime::now_utc(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_live_tail() {
+        let live_tail = Arc::new(LiveTail::default().with_max_rate(2));
+        assert!(!live_tail.is_tailed("abc-123", "test.com"));
+        let mut subscription = live_tail.subscribe("abc-123", "test.com", None).unwrap();
+        assert!(live_tail.is_tailed("abc-123", "test.com"));
+
+        live_tail.publish("abc-123", "other.com", tail_event(TailStatus::Accepted));
+        for _ in 0..3 {
+            live_tail.publish("abc-123", "test.com", tail_event(TailStatus::Accepted));
+        }
+        live_tail.publish("abc-123", "test.com", tail_event(TailStatus::Rejected));
+        let mut messages = Vec::new();
+        for _ in 0..2 {
+            messages.push(subscription.next().await.unwrap());
+        }
+        assert!(
+            messages
+                .iter()
+                .all(|message| matches!(message, TailMessage::Event(event) if event.status == TailStatus::Accepted)),
+            "Expected only the events of the tailed site up to the rate"
+        );
+
+        // Negative test cases
+        let _second = live_tail.subscribe("abc-123", "other.com", None).unwrap();
+        assert_eq!(
+            live_tail
+                .subscribe("abc-123", "test.com", None)
+                .unwrap_err(),
+            TailError::TooManyTails
+        );
+        drop(subscription);
+        assert!(
+            !live_tail.is_tailed("abc-123", "test.com"),
+            "Expected the tail to be closed when dropped"
+        );
+        let mut expiring = live_tail
+            .subscribe("abc-123", "test.com", Some(Duration::from_millis(10)))
+            .unwrap();
+        assert!(matches!(expiring.next().await, Some(TailMessage::Expired)));
+        assert!(expiring.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_live_tail_scrubbed() {
+        let live_tail = Arc::new(LiveTail::default());
+        let mut subscription = live_tail.subscribe("abc-123", "test.com", None).unwrap();
+        let mut other = live_tail.subscribe("xyz-789", "test.com", None).unwrap();
+        let mut event = tail_event(TailStatus::Accepted);
+        event.event.attrs = Some(HashMap::from([
+            (TITLE_ATTR.to_owned(), "Hello a@b.com".to_owned()),
+            (
+                LOCATION_ATTR.to_owned(),
+                "/search?q=shoes&email=a@b.com#results".to_owned(),
+            ),
+        ]));
+        live_tail.publish("abc-123", "test.com", event);
+        let Some(TailMessage::Event(event)) = subscription.next().await else {
+            panic!("Expected the published event");
+        };
+        let attrs = event.event.attrs.as_ref().unwrap();
+        assert_eq!(attrs[TITLE_ATTR], format!("Hello {REDACTED}"));
+        assert_eq!(
+            attrs[LOCATION_ATTR], "/search#results",
+            "Expected the query string to be removed from a tailed location"
+        );
+
+        // Negative test cases
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), other.next())
+                .await
+                .is_err(),
+            "Expected no events of another api key"
+        );
+        drop(subscription);
+        assert!(live_tail.is_tailed("xyz-789", "test.com"));
+        drop(other);
+        assert!(
+            live_tail.channels().is_empty(),
+            "Expected the channels to be removed with their last tail"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_live_tail_dropped() {
+        let live_tail = Arc::new(LiveTail::default().with_max_rate(1));
+        let mut subscription = live_tail.subscribe("abc-123", "test.com", None).unwrap();
+        for _ in 0..3 {
+            live_tail.publish("abc-123", "test.com", tail_event(TailStatus::Accepted));
+        }
+        assert!(matches!(
+            subscription.next().await,
+            Some(TailMessage::Event(_))
+        ));
+        // The remaining events exceed the rate, after which the tail waits
+        assert!(
+            tokio::time::timeout(Duration::from_secs(1), subscription.next())
+                .await
+                .is_err()
+        );
+        live_tail.publish("abc-123", "test.com", tail_event(TailStatus::Rejected));
+        assert!(
+            matches!(subscription.next().await, Some(TailMessage::Dropped(2))),
+            "Expected the events beyond the rate to be reported"
+        );
+        assert!(matches!(
+            subscription.next().await,
+            Some(TailMessage::Event(event)) if event.status == TailStatus::Rejected
+        ));
+    }
+}
@@ -0,0 +1,301 @@
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::domain::model::ingest_event::{
+    ApiKey, CustomEvent, IngestEvent, IngestEventCore, IngestEventOrigin, IngestEventSource,
+    SectionEvent, Site,
+};
+
+use super::client_event_request::ClientEventRequestError;
+
+/// `MEASUREMENT_PROTOCOL_PAGE_VIEW` is the GA4 event name for page views
+pub const MEASUREMENT_PROTOCOL_PAGE_VIEW: &str = "page_view";
+
+/// GA4 parameter holding the full URL of the page
+const PAGE_LOCATION_PARAM: &str = "page_location";
+/// GA4 parameter holding the title of the page
+const PAGE_TITLE_PARAM: &str = "page_title";
+/// GA4 parameter holding the referrer of the page
+const PAGE_REFERRER_PARAM: &str = "page_referrer";
+
+/// `MeasurementProtocolQuery` holds the credentials that GA4 Measurement
+/// Protocol requests carry in the query string. The `measurement_id` is used
+/// as the `ApiKey` and the `api_secret` must be a `ServerKey` for it. `site`
+/// is an extension for api keys that are configured for several sites.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct MeasurementProtocolQuery {
+    pub measurement_id: String,
+    pub api_secret: String,
+    pub site: Option<String>,
+}
+
+/// `MeasurementProtocolEventBody` is a single entry of the `events` of a
+/// Measurement Protocol request
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct MeasurementProtocolEventBody {
+    pub name: String,
+    #[serde(default)]
+    pub params: HashMap<String, serde_json::Value>,
+    /// Overrides the `timestamp_micros` of the request for this event
+    pub timestamp_micros: Option<i64>,
+}
+
+/// `MeasurementProtocolRequestBody` is the JSON body of a GA4 Measurement
+/// Protocol request. Fields that have no equivalent in the event model, such
+/// as `user_properties` or `consent`, are ignored.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct MeasurementProtocolRequestBody {
+    pub client_id: String,
+    /// Time of the events in microseconds since the epoch, defaulting to now
+    pub timestamp_micros: Option<i64>,
+    /// Ip of the end user, defaulting to the ip of the request
+    pub ip_override: Option<IpAddr>,
+    /// User agent of the end user, defaulting to the `User-Agent` header
+    pub user_agent: Option<String>,
+    pub events: Vec<MeasurementProtocolEventBody>,
+}
+
+/// `MeasurementProtocolRequest` combines the body of a Measurement Protocol
+/// request with its credentials and the details of the HTTP request
+#[derive(Debug)]
+pub struct MeasurementProtocolRequest {
+    pub query: MeasurementProtocolQuery,
+    pub body: MeasurementProtocolRequestBody,
+    pub user_agent: String,
+    pub ip: IpAddr,
+}
+
+impl MeasurementProtocolRequest {
+    /// `ApiKey` given by the `measurement_id`
+    pub fn api_key(&self) -> ApiKey {
+        ApiKey::new(&self.query.measurement_id)
+    }
+
+    /// Configured source for the request. Without a `site` in the query, the
+    /// `measurement_id` must be configured for exactly one site.
+    pub fn try_source(
+        &self,
+        configured: &HashSet<IngestEventSource>,
+    ) -> Result<IngestEventSource, ClientEventRequestError> {
+        let api_key = self.api_key();
+        match &self.query.site {
+            Some(site) => {
+                let source = IngestEventSource::new(api_key, Site::new(site));
+                configured
+                    .contains(&source)
+                    .then_some(source)
+                    .ok_or(ClientEventRequestError::ApiKey)
+            }
+            None => {
+                let mut matching = configured.iter().filter(|s| s.api_key() == &api_key);
+                match (matching.next(), matching.next()) {
+                    (Some(source), None) => Ok(source.clone()),
+                    _ => Err(ClientEventRequestError::ApiKey),
+                }
+            }
+        }
+    }
+
+    /// Ip of the end user
+    pub fn end_user_ip(&self) -> IpAddr {
+        self.body.ip_override.unwrap_or(self.ip)
+    }
+
+    /// User agent of the end user
+    pub fn end_user_agent(&self) -> &str {
+        self.body.user_agent.as_deref().unwrap_or(&self.user_agent)
+    }
+
+    /// Time of the event, taken from the event, then from the request and
+    /// otherwise `now`
+    pub fn try_event_ts(
+        &self,
+        event: &MeasurementProtocolEventBody,
+        now: OffsetDateTime,
+    ) -> Result<OffsetDateTime, ClientEventRequestError> {
+        match event.timestamp_micros.or(self.body.timestamp_micros) {
+            Some(micros) => OffsetDateTime::from_unix_timestamp_nanos(micros as i128 * 1_000)
+                .map_err(|_| ClientEventRequestError::InvalidRequestBody),
+            None => Ok(now),
+        }
+    }
+
+    /// Translate an entry of `events` into an `IngestEvent` within the
+    /// synthesized session. `page_view` becomes a `SectionEvent` and all other
+    /// names become `CustomEvent`s with the remaining params as props. The
+    /// `id` must carry the time of the event, which is held to the
+    /// `IngestEventOrigin::Server` window.
+    pub fn try_into_event(
+        &self,
+        event: &MeasurementProtocolEventBody,
+        source: &IngestEventSource,
+        id: Uuid,
+        session_id: Uuid,
+    ) -> Result<IngestEvent, ClientEventRequestError> {
+        let core = IngestEventCore::try_new_with_origin(
+            source.api_key().clone(),
+            source.site().clone(),
+            id,
+            IngestEventOrigin::Server,
+        )?;
+        let mut params = params_as_strings(&event.params);
+        let location = params.remove(PAGE_LOCATION_PARAM);
+        if event.name == MEASUREMENT_PROTOCOL_PAGE_VIEW {
+            Ok(IngestEvent::Section(SectionEvent::try_new_with_core_event(
+                core,
+                session_id,
+                location,
+                params.remove(PAGE_TITLE_PARAM),
+            )?))
+        } else {
+            Ok(IngestEvent::Custom(CustomEvent::try_new_with_core_event(
+                core,
+                session_id,
+                event.name.to_owned(),
+                location,
+                params,
+            )?))
+        }
+    }
+}
+
+impl MeasurementProtocolEventBody {
+    /// `page_referrer` param, which is recorded on new sessions
+    pub fn referrer(&self) -> Option<String> {
+        match self.params.get(PAGE_REFERRER_PARAM) {
+            Some(serde_json::Value::String(referrer)) if !referrer.is_empty() => {
+                Some(referrer.to_owned())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Params as strings, keeping non string values in their JSON representation
+/// and dropping nulls
+fn params_as_strings(params: &HashMap<String, serde_json::Value>) -> HashMap<String, String> {
+    params
+        .iter()
+        .filter_map(|(key, value)| match value {
+            serde_json::Value::Null => None,
+            serde_json::Value::String(value) => Some((key.to_owned(), value.to_owned())),
+            value => Some((key.to_owned(), value.to_string())),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    fn measurement_request(site: Option<&str>, body: &str) -> MeasurementProtocolRequest {
+        MeasurementProtocolRequest {
+            query: MeasurementProtocolQuery {
+                measurement_id: "G-ABC".to_owned(),
+                api_secret: "secret".to_owned(),
+                site: site.map(|s| s.to_owned()),
+            },
+            body: serde_json::from_str(body).unwrap(),
+            user_agent: "backend/1.0".to_owned(),
+            ip: IpAddr::V4(Ipv4Addr::LOCALHOST),
+        }
+    }
+
+    #[test]
+    fn test_try_source() {
+        let body = r#"{"client_id":"123.456","events":[]}"#;
+        let test_source = IngestEventSource::new(ApiKey::new("G-ABC"), Site::new("test.com"));
+        let other_source = IngestEventSource::new(ApiKey::new("G-ABC"), Site::new("other.com"));
+        let single = HashSet::from([test_source.clone()]);
+        let multiple = HashSet::from([test_source.clone(), other_source]);
+
+        assert_eq!(
+            measurement_request(None, body).try_source(&single),
+            Ok(test_source.clone())
+        );
+        assert_eq!(
+            measurement_request(None, body).try_source(&multiple),
+            Err(ClientEventRequestError::ApiKey),
+            "Expected a site to be required for api keys with several sites"
+        );
+        assert_eq!(
+            measurement_request(Some("test.com"), body).try_source(&multiple),
+            Ok(test_source)
+        );
+        assert_eq!(
+            measurement_request(Some("unknown.com"), body).try_source(&multiple),
+            Err(ClientEventRequestError::ApiKey)
+        );
+    }
+
+    #[test]
+    fn test_try_into_event() {
+        let source = IngestEventSource::new(ApiKey::new("G-ABC"), Site::new("test.com"));
+        let now = OffsetDateTime::now_utc();
+        let hour_ago_micros = (now.unix_timestamp() - 3_600) * 1_000_000;
+        let request = measurement_request(
+            None,
+            &format!(
+                r#"{{"client_id":"123.456","timestamp_micros":{hour_ago_micros},"ip_override":"203.0.113.7","events":[
+                    {{"name":"page_view","params":{{"page_location":"https://test.com/docs","page_title":"Docs","page_referrer":"https://search.example/"}}}},
+                    {{"name":"purchase","params":{{"page_location":"https://test.com/cart","value":9.99,"currency":"EUR","coupon":null}},"timestamp_micros":{}}}
+                ]}}"#,
+                hour_ago_micros + 1_000_000
+            ),
+        );
+        assert_eq!(request.end_user_ip().to_string(), "203.0.113.7");
+        assert_eq!(request.end_user_agent(), "backend/1.0");
+        let [page_view, purchase] = &request.body.events[..] else {
+            panic!("Expected two events");
+        };
+        assert_eq!(
+            request
+                .try_event_ts(page_view, now)
+                .unwrap()
+                .unix_timestamp(),
+            now.unix_timestamp() - 3_600
+        );
+        assert_eq!(
+            request
+                .try_event_ts(purchase, now)
+                .unwrap()
+                .unix_timestamp(),
+            now.unix_timestamp() - 3_599,
+            "Expected the event timestamp to override the request timestamp"
+        );
+        assert_eq!(
+            page_view.referrer().as_deref(),
+            Some("https://search.example/")
+        );
+
+        let session_id = Uuid::now_v7();
+        let Ok(IngestEvent::Section(section)) =
+            request.try_into_event(page_view, &source, Uuid::now_v7(), session_id)
+        else {
+            panic!("Expected page_view to become a section event");
+        };
+        assert_eq!(section.location.as_deref(), Some("https://test.com/docs"));
+        assert_eq!(section.title.as_deref(), Some("Docs"));
+
+        let Ok(IngestEvent::Custom(custom)) =
+            request.try_into_event(purchase, &source, Uuid::now_v7(), session_id)
+        else {
+            panic!("Expected other names to become custom events");
+        };
+        assert_eq!(custom.name, "purchase");
+        assert_eq!(custom.location.as_deref(), Some("https://test.com/cart"));
+        assert_eq!(
+            custom.props,
+            HashMap::from([
+                ("value".to_owned(), "9.99".to_owned()),
+                ("currency".to_owned(), "EUR".to_owned()),
+            ])
+        );
+    }
+}
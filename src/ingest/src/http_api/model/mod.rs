@@ -1,4 +1,17 @@
 pub mod client_event_action_summary;
 pub mod client_event_request;
 pub mod client_event_request_components;
+pub mod dead_letter_store;
 pub mod ingest_application_state;
+pub mod live_tail;
+pub mod measurement_protocol_request;
+pub mod ndjson_request;
+pub mod plausible_event_request;
+pub mod request_limiter;
+pub mod segment_request;
+pub mod segment_request_components;
+pub mod server_event_request;
+pub mod server_event_request_components;
+pub mod validation_report;
+pub mod websocket_request;
+pub mod wire_format;
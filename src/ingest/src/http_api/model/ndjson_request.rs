@@ -0,0 +1,211 @@
+use std::mem;
+
+use axum::{Json, response::IntoResponse};
+use http::{HeaderMap, StatusCode, header};
+use serde::Serialize;
+
+use super::client_event_action_summary::ClientEventSaveSummary;
+
+/// Content types accepted for a stream of newline delimited JSON events
+pub const NDJSON_CONTENT_TYPES: &[&str] = &["application/x-ndjson", "application/ndjson"];
+
+/// Maximum number of bytes of a single line of a NDJSON stream
+pub const MAX_NDJSON_LINE_BYTES: usize = 64 * 1024;
+
+/// Maximum number of line errors that are returned in a `NdjsonSaveSummary`
+pub const MAX_NDJSON_LINE_ERRORS: usize = 100;
+
+/// Whether the `Content-Type` of a request is one of `NDJSON_CONTENT_TYPES`
+pub fn is_ndjson(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|essence| {
+            NDJSON_CONTENT_TYPES
+                .iter()
+                .any(|content_type| essence.trim().eq_ignore_ascii_case(content_type))
+        })
+}
+
+/// `NdjsonLine` is a single line of a NDJSON stream
+#[derive(Debug, PartialEq, Eq)]
+pub enum NdjsonLine {
+    /// A line without its line ending
+    Complete(Vec<u8>),
+    /// A line that exceeded `MAX_NDJSON_LINE_BYTES` and was discarded
+    TooLong,
+}
+
+/// `NdjsonSplitter` splits the chunks of a request body into lines as they
+/// arrive, so that at most one line is held in memory regardless of the size
+/// of the body. Lines longer than the maximum are discarded rather than
+/// buffered.
+#[derive(Debug)]
+pub struct NdjsonSplitter {
+    buffer: Vec<u8>,
+    max_line_bytes: usize,
+    overflow: bool,
+}
+
+impl Default for NdjsonSplitter {
+    /// Uses `MAX_NDJSON_LINE_BYTES`
+    fn default() -> Self {
+        Self {
+            buffer: Vec::new(),
+            max_line_bytes: MAX_NDJSON_LINE_BYTES,
+            overflow: false,
+        }
+    }
+}
+
+impl NdjsonSplitter {
+    /// Replace the maximum number of bytes of a single line
+    pub fn with_max_line_bytes(mut self, max_line_bytes: usize) -> Self {
+        self.max_line_bytes = max_line_bytes;
+        self
+    }
+
+    /// Add the next chunk of the body, returning the lines it completed
+    pub fn push(&mut self, mut chunk: &[u8]) -> Vec<NdjsonLine> {
+        let mut lines = Vec::new();
+        while let Some(end) = chunk.iter().position(|byte| *byte == b'\n') {
+            self.extend(&chunk[..end]);
+            lines.push(self.take());
+            chunk = &chunk[end + 1..];
+        }
+        self.extend(chunk);
+        lines
+    }
+
+    /// Return the last line of a body that does not end with a line ending
+    pub fn finish(&mut self) -> Option<NdjsonLine> {
+        (self.overflow || !self.buffer.is_empty()).then(|| self.take())
+    }
+
+    fn extend(&mut self, bytes: &[u8]) {
+        if self.overflow {
+            return;
+        }
+        if self.buffer.len() + bytes.len() > self.max_line_bytes {
+            self.overflow = true;
+            self.buffer = Vec::new();
+        } else {
+            self.buffer.extend_from_slice(bytes);
+        }
+    }
+
+    fn take(&mut self) -> NdjsonLine {
+        if mem::take(&mut self.overflow) {
+            return NdjsonLine::TooLong;
+        }
+        let mut line = mem::take(&mut self.buffer);
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        NdjsonLine::Complete(line)
+    }
+}
+
+/// `NdjsonLineError` is the reason that the event on a given line, counting
+/// from 1, was not saved
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct NdjsonLineError {
+    pub line: usize,
+    pub error: String,
+}
+
+/// `NdjsonSaveSummary` is the result of saving a NDJSON stream of events. It
+/// counts the saved and duplicate events of every chunk, together with the
+/// number of lines that could not be saved and the first
+/// `MAX_NDJSON_LINE_ERRORS` of their errors.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct NdjsonSaveSummary {
+    pub event_count: usize,
+    pub duplicate_count: usize,
+    pub error_count: usize,
+    pub errors: Vec<NdjsonLineError>,
+}
+
+impl NdjsonSaveSummary {
+    /// Add the counts of a saved chunk of events
+    pub fn add_saved(&mut self, saved: ClientEventSaveSummary) {
+        self.event_count += saved.event_count;
+        self.duplicate_count += saved.duplicate_count;
+    }
+
+    /// Add the error of a line that could not be saved
+    pub fn add_error(&mut self, line: usize, error: impl ToString) {
+        self.error_count += 1;
+        if self.errors.len() < MAX_NDJSON_LINE_ERRORS {
+            self.errors.push(NdjsonLineError {
+                line,
+                error: error.to_string(),
+            });
+        }
+    }
+}
+
+/// A NDJSON stream responds with 201 Created and the summary, even when some
+/// lines had errors, as the events of all other lines were saved
+impl IntoResponse for NdjsonSaveSummary {
+    fn into_response(self) -> axum::response::Response {
+        (StatusCode::CREATED, Json(self)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_splitter() {
+        let mut splitter = NdjsonSplitter::default().with_max_line_bytes(8);
+        assert_eq!(
+            splitter.push(b"{\"a\":1}\r\n{\"b\""),
+            vec![NdjsonLine::Complete(b"{\"a\":1}".to_vec())]
+        );
+        assert_eq!(
+            splitter.push(b":2}\n\n0123456"),
+            vec![
+                NdjsonLine::Complete(b"{\"b\":2}".to_vec()),
+                NdjsonLine::Complete(Vec::new()),
+            ]
+        );
+        assert_eq!(
+            splitter.push(b"789\n{}"),
+            vec![NdjsonLine::TooLong],
+            "Expected lines over the maximum to be discarded"
+        );
+        assert_eq!(
+            splitter.finish(),
+            Some(NdjsonLine::Complete(b"{}".to_vec()))
+        );
+        assert_eq!(splitter.finish(), None);
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut summary = NdjsonSaveSummary::default();
+        summary.add_saved(ClientEventSaveSummary {
+            event_count: 3,
+            duplicate_count: 1,
+        });
+        for line in 0..MAX_NDJSON_LINE_ERRORS + 1 {
+            summary.add_error(line + 1, "Invalid");
+        }
+        assert_eq!(summary.event_count, 3);
+        assert_eq!(summary.error_count, MAX_NDJSON_LINE_ERRORS + 1);
+        assert_eq!(summary.errors.len(), MAX_NDJSON_LINE_ERRORS);
+
+        let mut headers = HeaderMap::new();
+        assert!(!is_ndjson(&headers));
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/x-ndjson; charset=utf-8"),
+        );
+        assert!(is_ndjson(&headers));
+    }
+}
@@ -0,0 +1,293 @@
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+
+use serde::{Deserialize, Deserializer, Serialize};
+use sha2::{Digest, Sha256};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::domain::model::ingest_event::{
+    ApiKey, CustomEvent, IngestEvent, IngestEventCore, IngestEventOrigin, IngestEventSource,
+    SectionEvent, Site,
+};
+
+use super::client_event_request::ClientEventRequestError;
+
+/// `PLAUSIBLE_PAGEVIEW` is the event name Plausible uses for page views
+pub const PLAUSIBLE_PAGEVIEW: &str = "pageview";
+
+/// `PLAUSIBLE_ENGAGEMENT` is the event name newer Plausible scripts use to
+/// report time on page. These are acknowledged but not stored.
+pub const PLAUSIBLE_ENGAGEMENT: &str = "engagement";
+
+/// `PlausibleEventRequestBody` is the JSON body of a request to the Plausible
+/// Events API. Both the full field names and the single letter names used by
+/// the Plausible script are accepted.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PlausibleEventRequestBody {
+    #[serde(alias = "n")]
+    pub name: String,
+    #[serde(alias = "u")]
+    pub url: String,
+    /// One or more comma separated domains, each of which is a `Site`
+    #[serde(alias = "d")]
+    pub domain: String,
+    #[serde(alias = "r", default)]
+    pub referrer: Option<String>,
+    /// Custom properties, sent either as an object or as a JSON encoded string
+    #[serde(alias = "p", default, deserialize_with = "deserialize_props")]
+    pub props: HashMap<String, String>,
+}
+
+/// `PlausibleEventRequest` combines the body of a Plausible event with the
+/// details of the HTTP request that sent it. Plausible has no api key, so the
+/// `api_key` header is optional for requests from a browser, whose `origin`
+/// must then match the domain. Requests without an `Origin` header must carry
+/// the `api_key` header instead.
+#[derive(Debug)]
+pub struct PlausibleEventRequest {
+    pub body: PlausibleEventRequestBody,
+    pub api_key: Option<String>,
+    /// Host of the `Origin` header, which is absent for server side requests
+    pub origin: Option<String>,
+    pub user_agent: String,
+    pub ip: IpAddr,
+}
+
+impl PlausibleEventRequest {
+    /// `true` for events that are acknowledged without being stored
+    pub fn is_ignored(&self) -> bool {
+        self.body.name == PLAUSIBLE_ENGAGEMENT
+    }
+
+    /// Configured sources for the domains of the event. Requests from a
+    /// browser only resolve the domain that matches their `origin`, and
+    /// requests without one only resolve with an `api_key`. With an `api_key`
+    /// header the source must match exactly, otherwise a domain only resolves
+    /// when exactly one api key is configured for it.
+    pub fn sources(&self, configured: &HashSet<IngestEventSource>) -> Vec<IngestEventSource> {
+        if self.origin.is_none() && self.api_key.is_none() {
+            return Vec::new();
+        }
+        self.body
+            .domain
+            .split(',')
+            .map(str::trim)
+            .filter(|domain| !domain.is_empty())
+            .filter(|domain| {
+                self.origin
+                    .as_deref()
+                    .is_none_or(|origin| origin == *domain)
+            })
+            .filter_map(|domain| {
+                let site = Site::new(domain);
+                match &self.api_key {
+                    Some(api_key) => {
+                        let source = IngestEventSource::new(ApiKey::new(api_key), site);
+                        configured.contains(&source).then_some(source)
+                    }
+                    None => {
+                        let mut matching = configured.iter().filter(|s| s.site() == &site);
+                        match (matching.next(), matching.next()) {
+                            (Some(source), None) => Some(source.clone()),
+                            _ => None,
+                        }
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Key identifying the visitor within a source. Like Plausible, the ip and
+    /// user agent are hashed together with the date so that visitors cannot be
+    /// followed from one day to the next and the raw ip is never kept.
+    pub fn visitor_key(&self, source: &IngestEventSource, ts: &OffsetDateTime) -> String {
+        Sha256::digest(format!(
+            "{}|{}|{}|{}",
+            ts.date(),
+            source.site().value(),
+            self.ip,
+            self.user_agent
+        ))
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+    }
+
+    /// Translate into an `IngestEvent` for the given source within the
+    /// synthesized session. Page views become `SectionEvent`s and all other
+    /// names become `CustomEvent`s.
+    pub fn try_into_event(
+        &self,
+        source: &IngestEventSource,
+        id: Uuid,
+        session_id: Uuid,
+    ) -> Result<IngestEvent, ClientEventRequestError> {
+        let core = IngestEventCore::try_new_with_origin(
+            source.api_key().clone(),
+            source.site().clone(),
+            id,
+            IngestEventOrigin::Client,
+        )?;
+        if self.body.name == PLAUSIBLE_PAGEVIEW {
+            Ok(IngestEvent::Section(SectionEvent::try_new_with_core_event(
+                core,
+                session_id,
+                Some(self.body.url.to_owned()),
+                None,
+            )?))
+        } else {
+            Ok(IngestEvent::Custom(CustomEvent::try_new_with_core_event(
+                core,
+                session_id,
+                self.body.name.to_owned(),
+                Some(self.body.url.to_owned()),
+                self.body.props.clone(),
+            )?))
+        }
+    }
+}
+
+/// Props may be an object or, for older scripts, a JSON encoded object. Non
+/// string values are kept in their JSON representation and nulls are dropped.
+fn deserialize_props<'de, D>(deserializer: D) -> Result<HashMap<String, String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Option::<serde_json::Value>::deserialize(deserializer)?;
+    let object = match value {
+        Some(serde_json::Value::Object(object)) => object,
+        Some(serde_json::Value::String(encoded)) => serde_json::from_str(&encoded)
+            .map_err(|_| serde::de::Error::custom("props must be a JSON object"))?,
+        Some(serde_json::Value::Null) | None => return Ok(HashMap::new()),
+        Some(_) => return Err(serde::de::Error::custom("props must be a JSON object")),
+    };
+    Ok(object
+        .into_iter()
+        .filter_map(|(key, value)| match value {
+            serde_json::Value::Null => None,
+            serde_json::Value::String(value) => Some((key, value)),
+            value => Some((key, value.to_string())),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    fn plausible_request(body: &str, api_key: Option<&str>) -> PlausibleEventRequest {
+        PlausibleEventRequest {
+            body: serde_json::from_str(body).unwrap(),
+            api_key: api_key.map(|k| k.to_owned()),
+            origin: None,
+            user_agent: "Mozilla/5.0".to_owned(),
+            ip: IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7)),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_body() {
+        let short = plausible_request(
+            r#"{"n":"Signup","u":"https://test.com/join","d":"test.com","r":null,"p":"{\"plan\":\"pro\",\"seats\":3}"}"#,
+            None,
+        );
+        assert_eq!(short.body.name, "Signup");
+        assert_eq!(short.body.props["plan"], "pro");
+        assert_eq!(short.body.props["seats"], "3");
+
+        let full = plausible_request(
+            r#"{"name":"pageview","url":"https://test.com/","domain":"test.com","props":{"x":null}}"#,
+            None,
+        );
+        assert_eq!(full.body.url, "https://test.com/");
+        assert!(
+            full.body.props.is_empty(),
+            "Expected null props to be dropped"
+        );
+    }
+
+    #[test]
+    fn test_sources() {
+        let test_source = IngestEventSource::new(ApiKey::new("abc"), Site::new("test.com"));
+        let shared_a = IngestEventSource::new(ApiKey::new("abc"), Site::new("shared.com"));
+        let shared_b = IngestEventSource::new(ApiKey::new("def"), Site::new("shared.com"));
+        let configured = HashSet::from([test_source.clone(), shared_a.clone(), shared_b]);
+
+        let body =
+            r#"{"n":"pageview","u":"https://test.com/","d":"test.com, shared.com,unknown.com"}"#;
+        let browser_request = |origin: &str| PlausibleEventRequest {
+            origin: Some(origin.to_owned()),
+            ..plausible_request(body, None)
+        };
+        assert_eq!(
+            browser_request("test.com").sources(&configured),
+            vec![test_source.clone()],
+            "Expected only the domain of the origin to resolve"
+        );
+        assert!(
+            browser_request("shared.com")
+                .sources(&configured)
+                .is_empty(),
+            "Expected ambiguous domains to not resolve without an api key"
+        );
+        assert_eq!(
+            plausible_request(body, Some("abc")).sources(&configured),
+            vec![test_source, shared_a],
+            "Expected the api key to select the source"
+        );
+
+        // Negative test cases
+        assert!(
+            browser_request("attacker.com")
+                .sources(&configured)
+                .is_empty(),
+            "Expected domains not matching the origin to not resolve"
+        );
+        assert!(
+            plausible_request(body, None)
+                .sources(&configured)
+                .is_empty(),
+            "Expected requests without an origin to require an api key"
+        );
+    }
+
+    #[test]
+    fn test_try_into_event() {
+        let source = IngestEventSource::new(ApiKey::new("abc"), Site::new("test.com"));
+        let session_id = Uuid::now_v7();
+        let pageview = plausible_request(
+            r#"{"n":"pageview","u":"https://test.com/docs","d":"test.com"}"#,
+            None,
+        );
+        let Ok(IngestEvent::Section(section)) =
+            pageview.try_into_event(&source, Uuid::now_v7(), session_id)
+        else {
+            panic!("Expected pageview to become a section event");
+        };
+        assert_eq!(section.parent, session_id);
+        assert_eq!(section.location.as_deref(), Some("https://test.com/docs"));
+
+        let signup = plausible_request(
+            r#"{"n":"Signup","u":"https://test.com/join","d":"test.com","p":{"plan":"pro"}}"#,
+            None,
+        );
+        let Ok(IngestEvent::Custom(custom)) =
+            signup.try_into_event(&source, Uuid::now_v7(), session_id)
+        else {
+            panic!("Expected other names to become custom events");
+        };
+        assert_eq!(custom.name, "Signup");
+        assert_eq!(custom.props["plan"], "pro");
+
+        let now = OffsetDateTime::now_utc();
+        assert_eq!(
+            signup.visitor_key(&source, &now),
+            pageview.visitor_key(&source, &now),
+            "Expected the same visitor for the same ip and user agent"
+        );
+        assert!(!signup.visitor_key(&source, &now).contains("203.0.113.7"));
+    }
+}
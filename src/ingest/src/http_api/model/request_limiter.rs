@@ -0,0 +1,311 @@
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use thiserror::Error;
+
+use crate::domain::model::ingest_event::IngestEvent;
+
+use super::client_event_request::TRAIT_ATTR_PREFIX;
+
+/// Default maximum number of events in a single request
+pub const DEFAULT_MAX_EVENTS: usize = 500;
+
+/// Default maximum number of attrs of a single event
+pub const DEFAULT_MAX_ATTRS: usize = 64;
+
+/// Default maximum number of characters in the title of an event
+pub const DEFAULT_MAX_TITLE: usize = 1024;
+
+/// Default maximum number of characters in the location of an event
+pub const DEFAULT_MAX_LOCATION: usize = 2048;
+
+/// Attr that holds the title of an event
+pub const TITLE_ATTR: &str = "t";
+
+/// Attr that holds the location of an event
+pub const LOCATION_ATTR: &str = "l";
+
+/// Prefix of the attrs that hold the props of a custom event, which have no
+/// equivalent in client requests
+pub const PROP_ATTR_PREFIX: &str = "prop.";
+
+/// `LimitViolation` is the limit that a request exceeded
+#[derive(Clone, Copy, Error, Debug, PartialEq, Eq)]
+pub enum LimitViolation {
+    #[error("Request contains too many events")]
+    Events,
+    #[error("Event contains too many attrs")]
+    Attrs,
+    #[error("Event title is too long")]
+    Title,
+    #[error("Event location is too long")]
+    Location,
+}
+
+/// `LimitViolationCounts` is the number of requests that were rejected for
+/// each `LimitViolation` since the last report
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LimitViolationCounts {
+    pub events: u64,
+    pub attrs: u64,
+    pub title: u64,
+    pub location: u64,
+}
+
+impl LimitViolationCounts {
+    /// Total number of rejected requests
+    pub fn total(&self) -> u64 {
+        self.events + self.attrs + self.title + self.location
+    }
+}
+
+/// `RequestLimiter` bounds the number of events in a request and the number
+/// and size of the attrs of each event, so that a single request cannot make
+/// the server convert and store an unbounded amount of data. Rejected
+/// requests are counted per `LimitViolation`.
+#[derive(Debug)]
+pub struct RequestLimiter {
+    max_events: usize,
+    max_attrs: usize,
+    max_title: usize,
+    max_location: usize,
+    events: AtomicU64,
+    attrs: AtomicU64,
+    title: AtomicU64,
+    location: AtomicU64,
+}
+
+impl Default for RequestLimiter {
+    /// Uses `DEFAULT_MAX_EVENTS`, `DEFAULT_MAX_ATTRS`, `DEFAULT_MAX_TITLE` and
+    /// `DEFAULT_MAX_LOCATION`
+    fn default() -> Self {
+        Self {
+            max_events: DEFAULT_MAX_EVENTS,
+            max_attrs: DEFAULT_MAX_ATTRS,
+            max_title: DEFAULT_MAX_TITLE,
+            max_location: DEFAULT_MAX_LOCATION,
+            events: AtomicU64::new(0),
+            attrs: AtomicU64::new(0),
+            title: AtomicU64::new(0),
+            location: AtomicU64::new(0),
+        }
+    }
+}
+
+impl RequestLimiter {
+    /// Replace the maximum number of events in a single request
+    pub fn with_max_events(mut self, max_events: usize) -> Self {
+        self.max_events = max_events;
+        self
+    }
+
+    /// Replace the maximum number of attrs of a single event
+    pub fn with_max_attrs(mut self, max_attrs: usize) -> Self {
+        self.max_attrs = max_attrs;
+        self
+    }
+
+    /// Replace the maximum number of characters in the title of an event
+    pub fn with_max_title(mut self, max_title: usize) -> Self {
+        self.max_title = max_title;
+        self
+    }
+
+    /// Replace the maximum number of characters in the location of an event
+    pub fn with_max_location(mut self, max_location: usize) -> Self {
+        self.max_location = max_location;
+        self
+    }
+
+    /// Maximum number of events in a single request
+    pub fn max_events(&self) -> usize {
+        self.max_events
+    }
+
+    /// Check a request with the given attrs of each of its events against the
+    /// limits, counting the violation if any
+    pub fn check<'a>(
+        &self,
+        attrs: impl ExactSizeIterator<Item = Option<&'a HashMap<String, String>>>,
+    ) -> Result<(), LimitViolation> {
+        self.violation(attrs).inspect_err(|violation| {
+            self.counter(*violation).fetch_add(1, Ordering::Relaxed);
+        })
+    }
+
+    /// Check a request whose events were converted from another format, such
+    /// as those of the compatibility APIs, against the limits. The title,
+    /// location, props and traits of each event are measured as its attrs.
+    pub fn check_events<'a>(
+        &self,
+        events: impl Iterator<Item = &'a IngestEvent>,
+    ) -> Result<(), LimitViolation> {
+        let attrs: Vec<HashMap<String, String>> = events.map(event_attrs).collect();
+        self.check(attrs.iter().map(Some))
+    }
+
+    /// Return the counts of rejected requests and reset them
+    pub fn report(&self) -> LimitViolationCounts {
+        LimitViolationCounts {
+            events: self.events.swap(0, Ordering::Relaxed),
+            attrs: self.attrs.swap(0, Ordering::Relaxed),
+            title: self.title.swap(0, Ordering::Relaxed),
+            location: self.location.swap(0, Ordering::Relaxed),
+        }
+    }
+
+    fn violation<'a>(
+        &self,
+        mut attrs: impl ExactSizeIterator<Item = Option<&'a HashMap<String, String>>>,
+    ) -> Result<(), LimitViolation> {
+        if attrs.len() > self.max_events {
+            return Err(LimitViolation::Events);
+        }
+        attrs.try_for_each(|attrs| {
+            match attrs.and_then(|attrs| self.attrs_violations(attrs).next()) {
+                Some(violation) => Err(violation),
+                None => Ok(()),
+            }
+        })
+    }
+
+    /// Every limit that the attrs of a single event exceed, in the order in
+    /// which `check` reports them. Unlike `check`, nothing is counted.
+    pub fn attrs_violations(
+        &self,
+        attrs: &HashMap<String, String>,
+    ) -> impl Iterator<Item = LimitViolation> {
+        [
+            (LimitViolation::Attrs, attrs.len() > self.max_attrs),
+            (
+                LimitViolation::Title,
+                exceeds(attrs.get(TITLE_ATTR), self.max_title),
+            ),
+            (
+                LimitViolation::Location,
+                exceeds(attrs.get(LOCATION_ATTR), self.max_location),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(violation, exceeded)| exceeded.then_some(violation))
+    }
+
+    fn counter(&self, violation: LimitViolation) -> &AtomicU64 {
+        match violation {
+            LimitViolation::Events => &self.events,
+            LimitViolation::Attrs => &self.attrs,
+            LimitViolation::Title => &self.title,
+            LimitViolation::Location => &self.location,
+        }
+    }
+}
+
+/// Variable sized fields of an `IngestEvent` as the attrs of a client request
+fn event_attrs(event: &IngestEvent) -> HashMap<String, String> {
+    let mut attrs = HashMap::new();
+    let mut insert = |key: String, value: Option<&String>| {
+        if let Some(value) = value {
+            attrs.insert(key, value.to_owned());
+        }
+    };
+    match event {
+        IngestEvent::Visitor(_) | IngestEvent::Session(_) | IngestEvent::Click(_) => {}
+        IngestEvent::Section(section) => {
+            insert(LOCATION_ATTR.to_owned(), section.location.as_ref());
+            insert(TITLE_ATTR.to_owned(), section.title.as_ref());
+        }
+        IngestEvent::Custom(custom) => {
+            insert(LOCATION_ATTR.to_owned(), custom.location.as_ref());
+            for (key, value) in &custom.props {
+                insert(format!("{PROP_ATTR_PREFIX}{key}"), Some(value));
+            }
+        }
+        IngestEvent::Identify(identify) => {
+            for (key, value) in &identify.traits {
+                insert(format!("{TRAIT_ATTR_PREFIX}{key}"), Some(value));
+            }
+        }
+    }
+    attrs
+}
+
+/// Whether the value has more than `max` characters
+fn exceeds(value: Option<&String>, max: usize) -> bool {
+    value.is_some_and(|value| value.chars().nth(max).is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let limiter = RequestLimiter::default()
+            .with_max_events(2)
+            .with_max_attrs(2)
+            .with_max_title(5)
+            .with_max_location(10);
+        let valid = HashMap::from([
+            ("t".to_owned(), "Títle".to_owned()),
+            ("l".to_owned(), "/location".to_owned()),
+        ]);
+        assert_eq!(
+            limiter.check([Some(&valid), None].into_iter()),
+            Ok(()),
+            "Expected the title to be measured in characters"
+        );
+
+        assert_eq!(
+            limiter.check([None, None, None].into_iter()),
+            Err(LimitViolation::Events)
+        );
+        let attrs = HashMap::from([
+            ("a".to_owned(), "1".to_owned()),
+            ("b".to_owned(), "2".to_owned()),
+            ("c".to_owned(), "3".to_owned()),
+        ]);
+        assert_eq!(
+            limiter.check([None, Some(&attrs)].into_iter()),
+            Err(LimitViolation::Attrs)
+        );
+        let title = HashMap::from([("t".to_owned(), "Titles".to_owned())]);
+        assert_eq!(
+            limiter.check([Some(&title)].into_iter()),
+            Err(LimitViolation::Title)
+        );
+        let location = HashMap::from([("l".to_owned(), "/a/long/location".to_owned())]);
+        assert_eq!(
+            limiter.check([Some(&location)].into_iter()),
+            Err(LimitViolation::Location)
+        );
+
+        let both = HashMap::from([
+            ("t".to_owned(), "Titles".to_owned()),
+            ("l".to_owned(), "/a/long/location".to_owned()),
+        ]);
+        assert_eq!(
+            limiter.attrs_violations(&both).collect::<Vec<_>>(),
+            vec![LimitViolation::Title, LimitViolation::Location],
+            "Expected every exceeded limit of the event"
+        );
+
+        let counts = limiter.report();
+        assert_eq!(
+            counts,
+            LimitViolationCounts {
+                events: 1,
+                attrs: 1,
+                title: 1,
+                location: 1
+            }
+        );
+        assert_eq!(counts.total(), 4);
+        assert_eq!(
+            limiter.report(),
+            LimitViolationCounts::default(),
+            "Expected counts to reset after a report"
+        );
+    }
+}
@@ -0,0 +1,366 @@
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+
+use http::Uri;
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+use uuid::Uuid;
+
+use crate::domain::model::{
+    ingest_event::{
+        ApiKey, CustomEvent, IdentifyEvent, IngestEvent, IngestEventCore, IngestEventOrigin,
+        IngestEventSource, SectionEvent, Site,
+    },
+    session_synthesizer::SyntheticParents,
+};
+
+use super::{
+    client_event_request::ClientEventRequestError,
+    segment_request_components::{SegmentMessage, SegmentMessageType},
+};
+
+/// `SegmentRequest` represents a single Segment message together with the
+/// details of the HTTP request that carried it. The write key is used as the
+/// `ApiKey` and, like the api key of client events, is not a secret, so
+/// Segment messages are held to the `IngestEventOrigin::Client` window.
+#[derive(Debug)]
+pub struct SegmentRequest {
+    pub write_key: String,
+    pub message_type: SegmentMessageType,
+    pub message: SegmentMessage,
+    pub user_agent: String,
+    pub ip: IpAddr,
+}
+
+impl SegmentRequest {
+    /// `true` for messages that are acknowledged without being stored
+    pub fn is_ignored(&self) -> bool {
+        !matches!(
+            self.message_type,
+            SegmentMessageType::Track | SegmentMessageType::Page | SegmentMessageType::Identify
+        )
+    }
+
+    /// Key identifying the visitor within a source, which is the
+    /// `anonymousId` or, for messages without one, the `userId`
+    pub fn try_visitor_key(&self) -> Result<&str, ClientEventRequestError> {
+        self.message
+            .anonymous_id
+            .as_deref()
+            .or(self.message.user_id.as_deref())
+            .filter(|key| !key.is_empty())
+            .ok_or(ClientEventRequestError::InvalidRequestBody)
+    }
+
+    /// Time of the message, corrected for the clock skew of the client when
+    /// the time it was sent is known. Defaults to `received_at`.
+    pub fn try_ts(
+        &self,
+        received_at: OffsetDateTime,
+        batch_sent_at: Option<&str>,
+    ) -> Result<OffsetDateTime, ClientEventRequestError> {
+        let Some(timestamp) = &self.message.timestamp else {
+            return Ok(received_at);
+        };
+        let ts = parse_rfc3339(timestamp)?;
+        match self.message.sent_at.as_deref().or(batch_sent_at) {
+            Some(sent_at) => Ok(ts + (received_at - parse_rfc3339(sent_at)?)),
+            None => Ok(ts),
+        }
+    }
+
+    /// Configured source for the message. The host of the page url selects
+    /// the site when it is configured for the write key, otherwise the write
+    /// key must be configured for exactly one site.
+    pub fn try_source(
+        &self,
+        configured: &HashSet<IngestEventSource>,
+    ) -> Result<IngestEventSource, ClientEventRequestError> {
+        let api_key = ApiKey::new(&self.write_key);
+        let page_source = self
+            .page_url()
+            .and_then(|url| url.parse::<Uri>().ok())
+            .and_then(|uri| uri.host().map(|host| host.to_owned()))
+            .map(|host| IngestEventSource::new(api_key.clone(), Site::new(host)))
+            .filter(|source| configured.contains(source));
+        if let Some(source) = page_source {
+            return Ok(source);
+        }
+        let mut matching = configured.iter().filter(|s| s.api_key() == &api_key);
+        match (matching.next(), matching.next()) {
+            (Some(source), None) => Ok(source.clone()),
+            _ => Err(ClientEventRequestError::ApiKey),
+        }
+    }
+
+    /// Referrer of the page, which is recorded on new sessions
+    pub fn referrer(&self) -> Option<String> {
+        self.property("referrer")
+            .or(self.message.context.page.referrer.clone())
+            .filter(|referrer| !referrer.is_empty())
+    }
+
+    /// Translate into an `IngestEvent` within the synthesized parents. A
+    /// `page` becomes a `SectionEvent` and a `track` becomes a `CustomEvent`
+    /// with the properties as props, both within the session. An `identify`
+    /// becomes an `IdentifyEvent` of the visitor with the traits. Other types
+    /// produce no event.
+    pub fn try_into_event(
+        &self,
+        source: &IngestEventSource,
+        id: Uuid,
+        parents: &SyntheticParents,
+    ) -> Result<Option<IngestEvent>, ClientEventRequestError> {
+        let session_id = parents.session_id;
+        let core = IngestEventCore::try_new_with_origin(
+            source.api_key().clone(),
+            source.site().clone(),
+            id,
+            IngestEventOrigin::Client,
+        )?;
+        match self.message_type {
+            SegmentMessageType::Page => {
+                let title = self
+                    .property("title")
+                    .or(self.message.context.page.title.clone())
+                    .or(self.message.name.clone());
+                Ok(Some(IngestEvent::Section(
+                    SectionEvent::try_new_with_core_event(
+                        core,
+                        session_id,
+                        self.page_url(),
+                        title,
+                    )?,
+                )))
+            }
+            SegmentMessageType::Track => {
+                let name = self
+                    .message
+                    .event
+                    .clone()
+                    .ok_or(ClientEventRequestError::InvalidRequestBody)?;
+                Ok(Some(IngestEvent::Custom(
+                    CustomEvent::try_new_with_core_event(
+                        core,
+                        session_id,
+                        name,
+                        self.message.context.page.url.clone(),
+                        properties_as_strings(&self.message.properties),
+                    )?,
+                )))
+            }
+            SegmentMessageType::Identify => {
+                let user_id = self
+                    .message
+                    .user_id
+                    .clone()
+                    .ok_or(ClientEventRequestError::InvalidRequestBody)?;
+                Ok(Some(IngestEvent::Identify(
+                    IdentifyEvent::try_new_with_core_event(
+                        core,
+                        parents.visitor_id,
+                        user_id,
+                        properties_as_strings(&self.message.traits),
+                    )?,
+                )))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Url of the page, from the properties of a `page` call or the page
+    /// context
+    fn page_url(&self) -> Option<String> {
+        self.property("url")
+            .or(self.message.context.page.url.clone())
+    }
+
+    /// String property of the message
+    fn property(&self, key: &str) -> Option<String> {
+        match self.message.properties.get(key) {
+            Some(serde_json::Value::String(value)) => Some(value.to_owned()),
+            _ => None,
+        }
+    }
+}
+
+fn parse_rfc3339(value: &str) -> Result<OffsetDateTime, ClientEventRequestError> {
+    OffsetDateTime::parse(value, &Rfc3339).map_err(|_| ClientEventRequestError::InvalidRequestBody)
+}
+
+/// Properties or traits as strings, keeping non string values in their JSON
+/// representation and dropping nulls
+fn properties_as_strings(
+    properties: &HashMap<String, serde_json::Value>,
+) -> HashMap<String, String> {
+    properties
+        .iter()
+        .filter_map(|(key, value)| match value {
+            serde_json::Value::Null => None,
+            serde_json::Value::String(value) => Some((key.to_owned(), value.to_owned())),
+            value => Some((key.to_owned(), value.to_string())),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use time::Duration;
+
+    use super::*;
+
+    fn segment_request(message_type: SegmentMessageType, message: &str) -> SegmentRequest {
+        SegmentRequest {
+            write_key: "write_key_123".to_owned(),
+            message_type,
+            message: serde_json::from_str(message).unwrap(),
+            user_agent: "analytics-node/1.0".to_owned(),
+            ip: IpAddr::V4(Ipv4Addr::LOCALHOST),
+        }
+    }
+
+    #[test]
+    fn test_try_ts() {
+        let received_at = OffsetDateTime::parse("2024-03-12T10:00:05Z", &Rfc3339).unwrap();
+        let skewed = segment_request(
+            SegmentMessageType::Track,
+            r#"{"anonymousId":"a","event":"Signup","timestamp":"2024-03-12T10:59:00Z","sentAt":"2024-03-12T11:00:00Z"}"#,
+        );
+        assert_eq!(
+            skewed.try_ts(received_at, None).unwrap(),
+            received_at - Duration::seconds(60),
+            "Expected the clock skew of the client to be corrected"
+        );
+        let batched = segment_request(
+            SegmentMessageType::Track,
+            r#"{"anonymousId":"a","event":"Signup","timestamp":"2024-03-12T10:59:00Z"}"#,
+        );
+        assert_eq!(
+            batched
+                .try_ts(received_at, Some("2024-03-12T11:00:00Z"))
+                .unwrap(),
+            received_at - Duration::seconds(60),
+            "Expected the sentAt of the batch to be used"
+        );
+        assert_eq!(
+            segment_request(SegmentMessageType::Track, r#"{"anonymousId":"a"}"#)
+                .try_ts(received_at, None)
+                .unwrap(),
+            received_at
+        );
+        assert_eq!(
+            segment_request(SegmentMessageType::Track, r#"{"timestamp":"yesterday"}"#)
+                .try_ts(received_at, None)
+                .unwrap_err(),
+            ClientEventRequestError::InvalidRequestBody
+        );
+    }
+
+    #[test]
+    fn test_try_source() {
+        let test_source =
+            IngestEventSource::new(ApiKey::new("write_key_123"), Site::new("test.com"));
+        let other_source =
+            IngestEventSource::new(ApiKey::new("write_key_123"), Site::new("other.com"));
+        let configured = HashSet::from([test_source.clone(), other_source.clone()]);
+
+        let page = segment_request(
+            SegmentMessageType::Page,
+            r#"{"anonymousId":"a","properties":{"url":"https://other.com/docs"}}"#,
+        );
+        assert_eq!(page.try_source(&configured), Ok(other_source));
+
+        let track = segment_request(
+            SegmentMessageType::Track,
+            r#"{"anonymousId":"a","event":"Signup"}"#,
+        );
+        assert_eq!(
+            track.try_source(&configured),
+            Err(ClientEventRequestError::ApiKey),
+            "Expected a page url to be required for write keys with several sites"
+        );
+        assert_eq!(
+            track.try_source(&HashSet::from([test_source.clone()])),
+            Ok(test_source)
+        );
+    }
+
+    #[test]
+    fn test_try_into_event() {
+        let source = IngestEventSource::new(ApiKey::new("write_key_123"), Site::new("test.com"));
+        let parents = SyntheticParents {
+            visitor_id: Uuid::now_v7(),
+            session_id: Uuid::now_v7(),
+        };
+
+        let page = segment_request(
+            SegmentMessageType::Page,
+            r#"{"anonymousId":"a","name":"Docs","properties":{"url":"https://test.com/docs","referrer":"https://search.example/"}}"#,
+        );
+        assert_eq!(page.try_visitor_key(), Ok("a"));
+        assert_eq!(page.referrer().as_deref(), Some("https://search.example/"));
+        let Ok(Some(IngestEvent::Section(section))) =
+            page.try_into_event(&source, Uuid::now_v7(), &parents)
+        else {
+            panic!("Expected page to become a section event");
+        };
+        assert_eq!(section.location.as_deref(), Some("https://test.com/docs"));
+        assert_eq!(section.title.as_deref(), Some("Docs"));
+
+        let track = segment_request(
+            SegmentMessageType::Track,
+            r#"{"userId":"u1","event":"Order Completed","properties":{"total":42.5,"coupon":null},"context":{"page":{"url":"https://test.com/cart"}}}"#,
+        );
+        assert_eq!(track.try_visitor_key(), Ok("u1"));
+        let Ok(Some(IngestEvent::Custom(custom))) =
+            track.try_into_event(&source, Uuid::now_v7(), &parents)
+        else {
+            panic!("Expected track to become a custom event");
+        };
+        assert_eq!(custom.name, "Order Completed");
+        assert_eq!(custom.location.as_deref(), Some("https://test.com/cart"));
+        assert_eq!(
+            custom.props,
+            HashMap::from([("total".to_owned(), "42.5".to_owned())])
+        );
+
+        let identify = segment_request(
+            SegmentMessageType::Identify,
+            r#"{"anonymousId":"a","userId":"u1","traits":{"plan":"pro","seats":3}}"#,
+        );
+        assert!(!identify.is_ignored());
+        let Ok(Some(IngestEvent::Identify(identify_event))) =
+            identify.try_into_event(&source, Uuid::now_v7(), &parents)
+        else {
+            panic!("Expected identify to become an identify event");
+        };
+        assert_eq!(identify_event.parent, parents.visitor_id);
+        assert_eq!(
+            identify_event.traits,
+            HashMap::from([
+                ("plan".to_owned(), "pro".to_owned()),
+                ("seats".to_owned(), "3".to_owned())
+            ])
+        );
+        assert_eq!(
+            segment_request(SegmentMessageType::Identify, r#"{"anonymousId":"a"}"#)
+                .try_into_event(&source, Uuid::now_v7(), &parents)
+                .unwrap_err(),
+            ClientEventRequestError::InvalidRequestBody,
+            "Expected a userId to be required to identify"
+        );
+
+        let group = segment_request(SegmentMessageType::Group, r#"{"anonymousId":"a"}"#);
+        assert!(group.is_ignored());
+        assert!(matches!(
+            group.try_into_event(&source, Uuid::now_v7(), &parents),
+            Ok(None)
+        ));
+        assert_eq!(
+            segment_request(SegmentMessageType::Track, r#"{"event":"Signup"}"#).try_visitor_key(),
+            Err(ClientEventRequestError::InvalidRequestBody),
+            "Expected an anonymousId or userId to be required"
+        );
+    }
+}
@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use axum::extract::FromRequestParts;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use http::{HeaderMap, header};
+use serde::{Deserialize, Serialize};
+
+use super::client_event_request::ClientEventRequestError;
+
+/// `SegmentMessageType` is the `type` of a Segment message. Only `Track`,
+/// `Page` and `Identify` have an equivalent in the event model, the remaining
+/// types are accepted and ignored so that batches containing them succeed.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SegmentMessageType {
+    Track,
+    Page,
+    Identify,
+    Screen,
+    Group,
+    Alias,
+}
+
+/// `SegmentPageContext` is the `context.page` object that Segment libraries
+/// running in a browser attach to each message
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+pub struct SegmentPageContext {
+    pub url: Option<String>,
+    pub title: Option<String>,
+    pub referrer: Option<String>,
+}
+
+/// `SegmentContext` holds the parts of a Segment message `context` that are
+/// used by the event model
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SegmentContext {
+    pub ip: Option<IpAddr>,
+    pub user_agent: Option<String>,
+    #[serde(default)]
+    pub page: SegmentPageContext,
+}
+
+/// `SegmentMessage` is a single message following the Segment spec, either as
+/// the body of a `/v1/track`, `/v1/page` or `/v1/identify` call or as an entry
+/// of a `/v1/batch` call. The `message_type` is only required within a batch.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SegmentMessage {
+    #[serde(rename = "type")]
+    pub message_type: Option<SegmentMessageType>,
+    pub anonymous_id: Option<String>,
+    pub user_id: Option<String>,
+    /// Name of a `track` event
+    pub event: Option<String>,
+    /// Name of a `page`
+    pub name: Option<String>,
+    #[serde(default)]
+    pub properties: HashMap<String, serde_json::Value>,
+    #[serde(default)]
+    pub traits: HashMap<String, serde_json::Value>,
+    #[serde(default)]
+    pub context: SegmentContext,
+    /// RFC 3339 time at which the message was created on the client
+    pub timestamp: Option<String>,
+    /// RFC 3339 time at which the message was sent by the client, used to
+    /// correct for clock skew
+    pub sent_at: Option<String>,
+    pub write_key: Option<String>,
+}
+
+/// `SegmentBatch` is the body of a `/v1/batch` call
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SegmentBatch {
+    pub batch: Vec<SegmentMessage>,
+    pub sent_at: Option<String>,
+    pub write_key: Option<String>,
+}
+
+/// `SegmentResponse` is the body Segment responds with on success
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct SegmentResponse {
+    pub success: bool,
+}
+
+/// `SegmentRequestHeaders` represents the information of a Segment request
+/// that is taken from its HTTP headers. Segment libraries send the write key
+/// as the user name of basic authorization, although it may also be given in
+/// the body, which is why it is optional here.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct SegmentRequestHeaders {
+    pub write_key: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+/// `SegmentRequestHeaders` must be able to be derived from incoming HTTP
+/// headers alone
+impl TryFrom<&HeaderMap> for SegmentRequestHeaders {
+    type Error = ClientEventRequestError;
+
+    fn try_from(value: &HeaderMap) -> Result<Self, Self::Error> {
+        let write_key = match value.get(header::AUTHORIZATION) {
+            None => None,
+            Some(authorization) => {
+                let encoded = authorization
+                    .to_str()
+                    .ok()
+                    .and_then(|a| a.strip_prefix("Basic "))
+                    .ok_or(ClientEventRequestError::ApiKey)?;
+                let decoded = STANDARD
+                    .decode(encoded.trim())
+                    .ok()
+                    .and_then(|d| String::from_utf8(d).ok())
+                    .ok_or(ClientEventRequestError::ApiKey)?;
+                let user = decoded.split_once(':').map_or(&decoded[..], |(u, _)| u);
+                Some(user.to_owned()).filter(|u| !u.is_empty())
+            }
+        };
+        let user_agent = value
+            .get(header::USER_AGENT)
+            .and_then(|u| u.to_str().ok())
+            .map(|u| u.to_owned());
+        Ok(SegmentRequestHeaders {
+            write_key,
+            user_agent,
+        })
+    }
+}
+
+/// `SegmentRequestHeaders` when handled by `FromRequestParts` allows the
+/// handler methods to have arguments of type `SegmentRequestHeaders`
+impl<S> FromRequestParts<S> for SegmentRequestHeaders
+where
+    S: Send + Sync,
+{
+    type Rejection = ClientEventRequestError;
+
+    async fn from_request_parts(
+        parts: &mut http::request::Parts,
+        _: &S,
+    ) -> Result<Self, Self::Rejection> {
+        Self::try_from(&parts.headers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::{HeaderMap, header};
+
+    use super::*;
+
+    #[test]
+    fn test_from_header_map() {
+        let mut valid_headers = HeaderMap::new();
+        // "write_key_123:" as sent by the Segment libraries
+        valid_headers.insert(
+            header::AUTHORIZATION,
+            "Basic d3JpdGVfa2V5XzEyMzo=".parse().unwrap(),
+        );
+        valid_headers.insert(header::USER_AGENT, "analytics-node/1.0".parse().unwrap());
+        let Ok(headers) = SegmentRequestHeaders::try_from(&valid_headers) else {
+            panic!("Expected valid SegmentRequestHeaders for valid HeaderMap");
+        };
+        assert_eq!(headers.write_key.as_deref(), Some("write_key_123"));
+        assert_eq!(headers.user_agent.as_deref(), Some("analytics-node/1.0"));
+
+        let Ok(no_auth) = SegmentRequestHeaders::try_from(&HeaderMap::new()) else {
+            panic!("Expected the write key to be optional in the headers");
+        };
+        assert_eq!(no_auth.write_key, None);
+
+        let mut bearer = HeaderMap::new();
+        bearer.insert(header::AUTHORIZATION, "Bearer abc".parse().unwrap());
+        assert_eq!(
+            SegmentRequestHeaders::try_from(&bearer).unwrap_err(),
+            ClientEventRequestError::ApiKey,
+            "Should fail with authorization other than basic"
+        );
+    }
+}
@@ -0,0 +1,225 @@
+use uuid::Uuid;
+
+use crate::domain::model::ingest_event::{
+    ApiKey, ClickEvent, IdentifyEvent, IngestEvent, IngestEventCore, IngestEventOrigin,
+    SectionEvent, SessionEvent, Site, VisitorEvent,
+};
+
+use super::client_event_request::{
+    ClientEventRequestError, ClientEventRequestType, USER_ID_ATTR, traits_from_attrs,
+};
+use super::server_event_request_components::ServerEventRequestBody;
+
+/// `ServerEventRequest` represents a metrics event from a trusted backend that
+/// has authenticated with a `ServerKey`. The same `IngestEvent` domain rules
+/// apply as for client events, except that the wider
+/// `IngestEventOrigin::Server` timestamp window is used.
+#[derive(Debug)]
+pub struct ServerEventRequest {
+    pub api_key: String,
+    pub body: ServerEventRequestBody,
+}
+
+impl ServerEventRequest {
+    /// `attr` method provides an ergonomic way to access possible attributes
+    /// that were specified in the body of the request for a given event
+    pub fn attr(&self, key: &str) -> Option<&String> {
+        match &self.body.attrs {
+            None => None,
+            Some(attrs) => attrs.get(key),
+        }
+    }
+
+    /// `parent` attribute parsed as a `Uuid`, which all events other than
+    /// `Visitor` require
+    fn try_parent(&self) -> Result<Uuid, ClientEventRequestError> {
+        let parent = self
+            .attr("p")
+            .ok_or(ClientEventRequestError::InvalidRequestBody)?;
+        Uuid::parse_str(parent).map_err(|_| ClientEventRequestError::InvalidRequestBody)
+    }
+
+    /// Translate into an `IngestEvent` held to the timestamp window of the
+    /// given `IngestEventOrigin`. Attributes use the same keys as
+    /// `ClientEventRequest`.
+    pub fn try_into_event(
+        &self,
+        origin: IngestEventOrigin,
+    ) -> Result<IngestEvent, ClientEventRequestError> {
+        let core = IngestEventCore::try_new_with_origin(
+            ApiKey::new(&self.api_key),
+            Site::new(&self.body.site),
+            self.body.id,
+            origin,
+        )?;
+        match &self.body.event_type {
+            ClientEventRequestType::Visitor => Ok(IngestEvent::Visitor(
+                VisitorEvent::try_new_with_core_event(core)?,
+            )),
+            ClientEventRequestType::Session => {
+                let user_agent = self
+                    .body
+                    .user_agent
+                    .to_owned()
+                    .ok_or(ClientEventRequestError::InvalidRequestBody)?;
+                let ip = self
+                    .body
+                    .ip
+                    .ok_or(ClientEventRequestError::InvalidRequestBody)?;
+                Ok(IngestEvent::Session(SessionEvent::try_new_with_core_event(
+                    core,
+                    self.try_parent()?,
+                    user_agent,
+                    ip,
+                )?))
+            }
+            ClientEventRequestType::Section => {
+                Ok(IngestEvent::Section(SectionEvent::try_new_with_core_event(
+                    core,
+                    self.try_parent()?,
+                    self.attr("l").map(|l| l.to_owned()),
+                    self.attr("t").map(|t| t.to_owned()),
+                )?))
+            }
+            ClientEventRequestType::Click => Ok(IngestEvent::Click(
+                ClickEvent::try_new_with_core_event(core, self.try_parent()?)?,
+            )),
+            ClientEventRequestType::Identify => {
+                let user_id = self
+                    .attr(USER_ID_ATTR)
+                    .ok_or(ClientEventRequestError::InvalidRequestBody)?;
+                Ok(IngestEvent::Identify(
+                    IdentifyEvent::try_new_with_core_event(
+                        core,
+                        self.try_parent()?,
+                        user_id.to_owned(),
+                        traits_from_attrs(self.body.attrs.as_ref()),
+                    )?,
+                ))
+            }
+        }
+    }
+}
+
+/// `ServerEventRequest` translated into the domain object of `IngestEvent`
+/// using the `IngestEventOrigin::Server` window
+impl TryFrom<&ServerEventRequest> for IngestEvent {
+    type Error = ClientEventRequestError;
+
+    fn try_from(value: &ServerEventRequest) -> Result<Self, Self::Error> {
+        value.try_into_event(IngestEventOrigin::Server)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        net::{IpAddr, Ipv4Addr},
+    };
+
+    use uuid::Timestamp;
+
+    use super::*;
+    use crate::domain::model::ingest_event::{CommonEvent, IngestEventError};
+
+    const API_KEY: &str = "abc_123";
+    const SITE: &str = "test.com";
+    const USER_AGENT: &str = "backend/1.0";
+
+    fn server_request(
+        event_type: ClientEventRequestType,
+        id: Uuid,
+        attrs: Option<HashMap<String, String>>,
+        ip: Option<IpAddr>,
+    ) -> ServerEventRequest {
+        ServerEventRequest {
+            api_key: API_KEY.to_owned(),
+            body: ServerEventRequestBody {
+                event_type,
+                id,
+                site: SITE.to_owned(),
+                attrs,
+                ip,
+                user_agent: Some(USER_AGENT.to_owned()),
+            },
+        }
+    }
+
+    #[test]
+    fn test_try_from_server_request() {
+        let (ts_now, _) = Uuid::now_v7().get_timestamp().unwrap().to_unix();
+        let two_hours_ago = Uuid::new_v7(Timestamp::from_unix_time(ts_now - 7200, 0, 0, 8));
+        let parent_id = Uuid::now_v7();
+        let forwarded_ip = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7));
+        let parent_attrs = HashMap::from([("p".to_owned(), parent_id.to_string())]);
+
+        // Server events are accepted outside of the client window
+        let visitor_event: IngestEvent =
+            (&server_request(ClientEventRequestType::Visitor, two_hours_ago, None, None))
+                .try_into()
+                .unwrap();
+        let IngestEvent::Visitor(ref visitor) = visitor_event else {
+            panic!("Expected valid visitor event to be generated");
+        };
+        assert_eq!(visitor.site().value(), SITE);
+        assert_eq!(visitor.origin(), IngestEventOrigin::Server);
+
+        // Sessions use the forwarded ip and user agent
+        let session_event: IngestEvent = (&server_request(
+            ClientEventRequestType::Session,
+            Uuid::now_v7(),
+            Some(parent_attrs.clone()),
+            Some(forwarded_ip),
+        ))
+            .try_into()
+            .unwrap();
+        let IngestEvent::Session(ref session) = session_event else {
+            panic!("Expected valid session event to be generated");
+        };
+        assert_eq!(session.ip, forwarded_ip);
+        assert_eq!(session.user_agent, USER_AGENT);
+        assert_eq!(session.parent, parent_id);
+
+        // Sessions without a forwarded ip are rejected
+        let Err(ClientEventRequestError::InvalidRequestBody) =
+            IngestEvent::try_from(&server_request(
+                ClientEventRequestType::Session,
+                Uuid::now_v7(),
+                Some(parent_attrs.clone()),
+                None,
+            ))
+        else {
+            panic!("Expected session without ip to be rejected");
+        };
+
+        // Section and click still require a parent
+        let Err(ClientEventRequestError::InvalidRequestBody) = IngestEvent::try_from(
+            &server_request(ClientEventRequestType::Section, Uuid::now_v7(), None, None),
+        ) else {
+            panic!("Expected section without parent to be rejected");
+        };
+        let Ok(IngestEvent::Click(_)) = IngestEvent::try_from(&server_request(
+            ClientEventRequestType::Click,
+            Uuid::now_v7(),
+            Some(parent_attrs),
+            None,
+        )) else {
+            panic!("Expected valid click event to be generated");
+        };
+
+        // Domain rules still apply
+        let four_days_ago = Uuid::new_v7(Timestamp::from_unix_time(ts_now - 345600, 0, 0, 8));
+        assert_eq!(
+            IngestEvent::try_from(&server_request(
+                ClientEventRequestType::Visitor,
+                four_days_ago,
+                None,
+                None,
+            ))
+            .unwrap_err(),
+            ClientEventRequestError::IngestEvent(IngestEventError::TimestampOutOfRange),
+            "Expected events beyond the server window to be rejected"
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use axum::extract::FromRequestParts;
+use http::HeaderMap;
+use http::header;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::domain::model::server_key::ServerKey;
+
+use super::client_event_request::ClientEventRequestError;
+use super::client_event_request::ClientEventRequestType;
+use super::client_event_request_components::API_KEY_HTTP_HEADER;
+
+/// `SERVER_KEY_AUTH_SCHEME` is the scheme expected in the `Authorization`
+/// header of server-to-server requests, i.e. `Authorization: Bearer <key>`
+pub const SERVER_KEY_AUTH_SCHEME: &str = "Bearer";
+
+/// `ServerEventRequestBody` represents a single event submitted by a trusted
+/// backend. In contrast to `ClientEventRequestBody`, the `site` is explicit for
+/// each event rather than derived from the `Origin` header, and the end-user
+/// `ip` and `user_agent` are forwarded by the backend rather than taken from
+/// the connection. These are required for `Session` events and ignored for all
+/// other types.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ServerEventRequestBody {
+    #[serde(alias = "t")]
+    pub event_type: ClientEventRequestType,
+    #[serde(alias = "i")]
+    #[serde(with = "clickhouse::serde::uuid")]
+    pub id: Uuid,
+    #[serde(alias = "s")]
+    pub site: String,
+    #[serde(alias = "a")]
+    pub attrs: Option<HashMap<String, String>>,
+    pub ip: Option<IpAddr>,
+    #[serde(alias = "ua")]
+    pub user_agent: Option<String>,
+}
+
+/// `ServerEventRequestHeaders` represents the authentication information for a
+/// server-to-server request. The `api_key` is taken from the same header as for
+/// client requests, while the `server_key` is supplied as a bearer token.
+#[derive(Debug, Clone)]
+pub struct ServerEventRequestHeaders {
+    pub api_key: String,
+    pub server_key: ServerKey,
+}
+
+/// `ServerEventRequestHeaders` must be able to be derived from incoming HTTP
+/// headers alone. Requests that carry an `Origin` header were issued by a
+/// browser and are refused, so that a leaked key cannot be used from a page.
+impl TryFrom<&HeaderMap> for ServerEventRequestHeaders {
+    type Error = ClientEventRequestError;
+
+    fn try_from(value: &HeaderMap) -> Result<Self, Self::Error> {
+        if value.contains_key(header::ORIGIN) {
+            return Err(ClientEventRequestError::Forbidden);
+        }
+        let api_key = value
+            .get(API_KEY_HTTP_HEADER)
+            .ok_or(ClientEventRequestError::ApiKey)?
+            .to_str()
+            .map_err(|_| ClientEventRequestError::ApiKey)?
+            .to_string();
+        let authorization = value
+            .get(header::AUTHORIZATION)
+            .ok_or(ClientEventRequestError::Unauthorized)?
+            .to_str()
+            .map_err(|_| ClientEventRequestError::Unauthorized)?;
+        let (scheme, secret) = authorization
+            .split_once(' ')
+            .ok_or(ClientEventRequestError::Unauthorized)?;
+        if !scheme.eq_ignore_ascii_case(SERVER_KEY_AUTH_SCHEME) {
+            return Err(ClientEventRequestError::Unauthorized);
+        }
+        Ok(ServerEventRequestHeaders {
+            api_key,
+            server_key: ServerKey::new(secret),
+        })
+    }
+}
+
+/// `ServerEventRequestHeaders` when handled by `FromRequestParts` allows the
+/// handler methods to have arguments of type `ServerEventRequestHeaders`
+impl<S> FromRequestParts<S> for ServerEventRequestHeaders
+where
+    S: Send + Sync,
+{
+    type Rejection = ClientEventRequestError;
+
+    async fn from_request_parts(
+        parts: &mut http::request::Parts,
+        _: &S,
+    ) -> Result<Self, Self::Rejection> {
+        Self::try_from(&parts.headers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::{HeaderMap, header};
+
+    use super::*;
+
+    #[test]
+    fn test_from_header_map() {
+        // Positive test case
+        let mut valid_headers = HeaderMap::new();
+        valid_headers.insert(API_KEY_HTTP_HEADER, "1234-5678-90".parse().unwrap());
+        valid_headers.insert(header::AUTHORIZATION, "Bearer secret".parse().unwrap());
+        let Ok(headers) = ServerEventRequestHeaders::try_from(&valid_headers) else {
+            panic!("Expected valid ServerEventRequestHeaders for valid HeaderMap");
+        };
+        assert_eq!(headers.api_key, "1234-5678-90");
+        assert_eq!(headers.server_key, ServerKey::new("secret"));
+
+        // Negative test cases
+        let mut browser_headers = valid_headers.clone();
+        browser_headers.insert(header::ORIGIN, "http://test.com".parse().unwrap());
+        assert_eq!(
+            ServerEventRequestHeaders::try_from(&browser_headers).unwrap_err(),
+            ClientEventRequestError::Forbidden,
+            "Should refuse requests issued by a browser"
+        );
+
+        let mut missing_auth = HeaderMap::new();
+        missing_auth.insert(API_KEY_HTTP_HEADER, "1234-5678-90".parse().unwrap());
+        assert_eq!(
+            ServerEventRequestHeaders::try_from(&missing_auth).unwrap_err(),
+            ClientEventRequestError::Unauthorized,
+            "Should fail with no authorization header"
+        );
+
+        let mut wrong_scheme = missing_auth.clone();
+        wrong_scheme.insert(header::AUTHORIZATION, "Basic c2VjcmV0".parse().unwrap());
+        assert_eq!(
+            ServerEventRequestHeaders::try_from(&wrong_scheme).unwrap_err(),
+            ClientEventRequestError::Unauthorized,
+            "Should fail with a non bearer authorization scheme"
+        );
+
+        let mut missing_api_key = HeaderMap::new();
+        missing_api_key.insert(header::AUTHORIZATION, "Bearer secret".parse().unwrap());
+        assert_eq!(
+            ServerEventRequestHeaders::try_from(&missing_api_key).unwrap_err(),
+            ClientEventRequestError::ApiKey,
+            "Should fail with no valid api key"
+        );
+    }
+}
@@ -0,0 +1,302 @@
+use axum::{Json, response::IntoResponse};
+use http::{HeaderMap, StatusCode, Uri, header};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::domain::{
+    model::ingest_event::IngestEventError, service::ingest_event_service::IngestServiceError,
+};
+
+use super::{
+    client_event_request::{ClientEventRequest, ClientEventRequestError},
+    client_event_request_components::API_KEY_HTTP_HEADER,
+    request_limiter::LimitViolation,
+};
+
+/// `ValidationRule` names the rule of the ingestion pipeline that a validated
+/// request or event fails
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationRule {
+    ApiKey,
+    Origin,
+    UserAgent,
+    Events,
+    Attrs,
+    Title,
+    Location,
+    UuidVersion,
+    Timestamp,
+    Parent,
+    UserId,
+    CustomEventName,
+    Source,
+    IdentityKey,
+    Request,
+}
+
+/// `ValidationIssue` is a single failed rule along with the header or body
+/// field that failed it, if any. Attrs are named as `attrs.<key>`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ValidationIssue {
+    pub rule: ValidationRule,
+    pub field: Option<String>,
+    pub message: String,
+}
+
+impl ValidationIssue {
+    fn new(rule: ValidationRule, field: Option<&str>, message: impl ToString) -> Self {
+        Self {
+            rule,
+            field: field.map(str::to_owned),
+            message: message.to_string(),
+        }
+    }
+
+    /// Issue of a request without any events
+    pub fn no_events() -> Self {
+        Self::new(ValidationRule::Events, None, "Request contains no events")
+    }
+
+    /// Issue of a request whose headers could not be converted into
+    /// `ClientEventRequestHeaders`. The failed header is found again from the
+    /// `headers`, as the error does not name it.
+    pub fn from_headers_error(error: &ClientEventRequestError, headers: &HeaderMap) -> Self {
+        if let ClientEventRequestError::ApiKey = error {
+            return Self::new(ValidationRule::ApiKey, Some(API_KEY_HTTP_HEADER), error);
+        }
+        let has_site = headers
+            .get(header::ORIGIN)
+            .and_then(|origin| origin.to_str().ok())
+            .and_then(|origin| origin.parse::<Uri>().ok())
+            .is_some_and(|origin| origin.host().is_some());
+        if has_site {
+            Self::new(
+                ValidationRule::UserAgent,
+                Some(header::USER_AGENT.as_str()),
+                "User agent header is missing or invalid",
+            )
+        } else {
+            Self::new(
+                ValidationRule::Origin,
+                Some(header::ORIGIN.as_str()),
+                "Origin header is missing or has no host",
+            )
+        }
+    }
+
+    /// Issue of a request or an event that exceeds a limit of the
+    /// `RequestLimiter`
+    pub fn from_limit(violation: LimitViolation) -> Self {
+        match violation {
+            LimitViolation::Events => Self::new(ValidationRule::Events, None, violation),
+            LimitViolation::Attrs => Self::new(ValidationRule::Attrs, Some("attrs"), violation),
+            LimitViolation::Title => Self::new(ValidationRule::Title, Some("attrs.t"), violation),
+            LimitViolation::Location => {
+                Self::new(ValidationRule::Location, Some("attrs.l"), violation)
+            }
+        }
+    }
+
+    /// Issue of an event that could not be converted into an `IngestEvent`
+    pub fn from_request_error(
+        error: &ClientEventRequestError,
+        request: &ClientEventRequest,
+    ) -> Self {
+        match error {
+            ClientEventRequestError::IngestEvent(e) => Self::from_event_error(e),
+            // The parent is read before the user id, so a valid parent means
+            // that the user id is missing
+            ClientEventRequestError::InvalidRequestBody
+                if request
+                    .attr("p")
+                    .is_some_and(|parent| Uuid::parse_str(parent).is_ok()) =>
+            {
+                Self::new(
+                    ValidationRule::UserId,
+                    Some("attrs.u"),
+                    "User id attr is missing",
+                )
+            }
+            ClientEventRequestError::InvalidRequestBody => Self::new(
+                ValidationRule::Parent,
+                Some("attrs.p"),
+                "Parent attr is missing or is not a UUID",
+            ),
+            _ => Self::new(ValidationRule::Request, None, error),
+        }
+    }
+
+    /// Issue of an event that was rejected by the `IngestEventService`
+    pub fn from_service_error(error: &IngestServiceError) -> Self {
+        match error {
+            IngestServiceError::InvalidRequest => Self::new(
+                ValidationRule::Source,
+                None,
+                "No event source is configured for the api key and site",
+            ),
+            IngestServiceError::IdentityKey => {
+                Self::new(ValidationRule::IdentityKey, Some("attrs.u"), error)
+            }
+            IngestServiceError::Repository(_) => Self::new(ValidationRule::Request, None, error),
+        }
+    }
+
+    fn from_event_error(error: &IngestEventError) -> Self {
+        let (rule, field) = match error {
+            IngestEventError::ApiKey => (ValidationRule::ApiKey, Some(API_KEY_HTTP_HEADER)),
+            IngestEventError::Site => (ValidationRule::Origin, Some(header::ORIGIN.as_str())),
+            IngestEventError::TimestampOutOfRange | IngestEventError::UuidTimestampConversion => {
+                (ValidationRule::Timestamp, Some("id"))
+            }
+            IngestEventError::UuidVersion => (ValidationRule::UuidVersion, Some("id")),
+            IngestEventError::CustomEventName => (ValidationRule::CustomEventName, None),
+            IngestEventError::UserId => (ValidationRule::UserId, Some("attrs.u")),
+        };
+        Self::new(rule, field, error)
+    }
+}
+
+/// `EventValidation` is the result of validating the event at `index` of a
+/// request
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct EventValidation {
+    pub index: usize,
+    pub id: Uuid,
+    pub valid: bool,
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl EventValidation {
+    /// `EventValidation` constructor, which is valid without any `issues`
+    pub fn new(index: usize, id: Uuid, issues: Vec<ValidationIssue>) -> Self {
+        Self {
+            index,
+            id,
+            valid: issues.is_empty(),
+            issues,
+        }
+    }
+}
+
+/// `ValidationReport` is the result of validating a request of client events
+/// without saving them. `issues` are those of the request as a whole, such
+/// as its headers or number of events, and `events` holds the result of each
+/// event. The request would be accepted only when it is `valid`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ValidationReport {
+    pub valid: bool,
+    pub issues: Vec<ValidationIssue>,
+    pub events: Vec<EventValidation>,
+}
+
+impl ValidationReport {
+    /// `ValidationReport` constructor, which is valid when neither the
+    /// request nor any event has issues
+    pub fn new(issues: Vec<ValidationIssue>, events: Vec<EventValidation>) -> Self {
+        Self {
+            valid: issues.is_empty() && events.iter().all(|event| event.valid),
+            issues,
+            events,
+        }
+    }
+}
+
+/// Reports are returned with HTTP 200 OK whether or not the request is valid,
+/// as the validation itself succeeded
+impl IntoResponse for ValidationReport {
+    fn into_response(self) -> axum::response::Response {
+        (StatusCode::OK, Json(self)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        net::{IpAddr, Ipv4Addr},
+    };
+
+    use crate::http_api::model::{
+        client_event_request::ClientEventRequestType,
+        client_event_request_components::{ClientEventRequestBody, ClientEventRequestHeaders},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_validation_issue() {
+        let request = |attrs: &[(&str, &str)]| ClientEventRequest {
+            headers: ClientEventRequestHeaders::new("abc-123", "test.com", "test-agent"),
+            body: ClientEventRequestBody::new(
+                ClientEventRequestType::Identify,
+                Uuid::now_v7(),
+                Some(
+                    attrs
+                        .iter()
+                        .map(|(key, value)| (key.to_string(), value.to_string()))
+                        .collect::<HashMap<String, String>>(),
+                ),
+            ),
+            ip: IpAddr::V4(Ipv4Addr::LOCALHOST),
+        };
+        let issue = ValidationIssue::from_request_error(
+            &ClientEventRequestError::InvalidRequestBody,
+            &request(&[("p", "not-a-uuid")]),
+        );
+        assert_eq!(issue.rule, ValidationRule::Parent);
+        assert_eq!(issue.field.as_deref(), Some("attrs.p"));
+        let issue = ValidationIssue::from_request_error(
+            &ClientEventRequestError::InvalidRequestBody,
+            &request(&[("p", &Uuid::now_v7().to_string())]),
+        );
+        assert_eq!(
+            issue.field.as_deref(),
+            Some("attrs.u"),
+            "Expected the missing user id to be named"
+        );
+        let issue = ValidationIssue::from_request_error(
+            &IngestEventError::UuidVersion.into(),
+            &request(&[]),
+        );
+        assert_eq!(issue.rule, ValidationRule::UuidVersion);
+        assert_eq!(issue.field.as_deref(), Some("id"));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ORIGIN, "http://test.com".parse().unwrap());
+        assert_eq!(
+            ValidationIssue::from_headers_error(
+                &ClientEventRequestError::InvalidRequestHeaders,
+                &headers
+            )
+            .rule,
+            ValidationRule::UserAgent
+        );
+        assert_eq!(
+            ValidationIssue::from_headers_error(
+                &ClientEventRequestError::InvalidRequestHeaders,
+                &HeaderMap::new()
+            )
+            .rule,
+            ValidationRule::Origin
+        );
+    }
+
+    #[test]
+    fn test_validation_report() {
+        let valid = EventValidation::new(0, Uuid::now_v7(), Vec::new());
+        assert!(ValidationReport::new(Vec::new(), vec![valid.clone()]).valid);
+
+        // Negative test cases
+        let invalid = EventValidation::new(
+            1,
+            Uuid::now_v7(),
+            vec![ValidationIssue::from_limit(LimitViolation::Title)],
+        );
+        assert!(!ValidationReport::new(Vec::new(), vec![valid.clone(), invalid]).valid);
+        assert!(
+            !ValidationReport::new(vec![ValidationIssue::no_events()], Vec::new()).valid,
+            "Expected a request without events to be invalid"
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use axum::response::IntoResponse;
+use http::{HeaderMap, HeaderValue, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    client_event_action_summary::ClientEventActionSummary,
+    client_event_request::ClientEventRequestError,
+    client_event_request_components::{
+        API_KEY_HTTP_HEADER, ClientEventRequestBody, ClientEventRequestHeaders,
+    },
+};
+
+/// Maximum number of bytes of a single WebSocket message
+pub const MAX_WEBSOCKET_MESSAGE_BYTES: usize = 256 * 1024;
+
+/// `WebSocketQuery` is the query string of a WebSocket connection. Browsers
+/// cannot set custom headers when opening a WebSocket, so the api key may be
+/// given as the `api_key` parameter instead of the `API_KEY_HTTP_HEADER`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WebSocketQuery {
+    #[serde(alias = "api-key")]
+    pub api_key: Option<String>,
+}
+
+impl WebSocketQuery {
+    /// Determine the `ClientEventRequestHeaders` of a connection with the same
+    /// rules as any other client request, using the api key of the query when
+    /// the header is absent
+    pub fn try_headers(
+        &self,
+        headers: &HeaderMap,
+    ) -> Result<ClientEventRequestHeaders, ClientEventRequestError> {
+        let Some(api_key) = self
+            .api_key
+            .as_ref()
+            .filter(|_| !headers.contains_key(API_KEY_HTTP_HEADER))
+        else {
+            return ClientEventRequestHeaders::try_from(headers);
+        };
+        let mut headers = headers.clone();
+        headers.insert(
+            API_KEY_HTTP_HEADER,
+            HeaderValue::from_str(api_key).map_err(|_| ClientEventRequestError::ApiKey)?,
+        );
+        ClientEventRequestHeaders::try_from(&headers)
+    }
+}
+
+/// `WebSocketFrame` is the JSON content of a single text message, which is
+/// either one `ClientEventRequestBody` or a list of them
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum WebSocketFrame {
+    Batch(Vec<ClientEventRequestBody>),
+    Single(ClientEventRequestBody),
+}
+
+impl WebSocketFrame {
+    /// The bodies of the events in this frame
+    pub fn into_bodies(self) -> Vec<ClientEventRequestBody> {
+        match self {
+            WebSocketFrame::Batch(bodies) => bodies,
+            WebSocketFrame::Single(body) => vec![body],
+        }
+    }
+}
+
+/// `WebSocketAck` acknowledges a single message of a WebSocket connection.
+/// Messages are numbered from 1 in the order they were received and are
+/// acknowledged in that order. `status` is the HTTP status code that the same
+/// events would have received from `save_client_events`, and `retry` tells
+/// the client whether sending the message again may succeed, which is only
+/// the case for server errors. A retried message is numbered as a new one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebSocketAck {
+    pub frame: u64,
+    pub status: u16,
+    pub retry: bool,
+    pub event_count: usize,
+    pub duplicate_count: usize,
+}
+
+impl WebSocketAck {
+    /// Acknowledge the result of saving the events of a message
+    pub fn new(
+        frame: u64,
+        result: Result<ClientEventActionSummary, ClientEventRequestError>,
+    ) -> Self {
+        match result {
+            Ok(ClientEventActionSummary::Save(summary)) => Self {
+                frame,
+                status: StatusCode::CREATED.as_u16(),
+                retry: false,
+                event_count: summary.event_count,
+                duplicate_count: summary.duplicate_count,
+            },
+            Err(e) => {
+                let status = e.into_response().status();
+                Self {
+                    frame,
+                    status: status.as_u16(),
+                    retry: status.is_server_error(),
+                    event_count: 0,
+                    duplicate_count: 0,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::header;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{
+        domain::{
+            model::ingest_event::IngestEventError,
+            repository::ingest_event_repository::IngestRepositoryError,
+            service::ingest_event_service::IngestServiceError,
+        },
+        http_api::model::{
+            client_event_action_summary::ClientEventSaveSummary,
+            client_event_request::ClientEventRequestType,
+        },
+    };
+
+    #[test]
+    fn test_try_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ORIGIN, "https://test.com".parse().unwrap());
+        headers.insert(header::USER_AGENT, "Mozilla/5.0".parse().unwrap());
+
+        let missing = WebSocketQuery::default().try_headers(&headers);
+        assert!(matches!(missing, Err(ClientEventRequestError::ApiKey)));
+
+        let query = WebSocketQuery {
+            api_key: Some("abc-123".to_owned()),
+        };
+        let from_query = query.try_headers(&headers).unwrap();
+        assert_eq!(from_query.api_key, "abc-123");
+        assert_eq!(from_query.site, "test.com");
+
+        headers.insert(API_KEY_HTTP_HEADER, "def-456".parse().unwrap());
+        assert_eq!(
+            query.try_headers(&headers).unwrap().api_key,
+            "def-456",
+            "Expected the header to take precedence over the query"
+        );
+    }
+
+    #[test]
+    fn test_frame_and_ack() {
+        let id = Uuid::now_v7();
+        let single: WebSocketFrame =
+            serde_json::from_str(&format!(r#"{{"t":1,"i":"{id}"}}"#)).unwrap();
+        let batch: WebSocketFrame =
+            serde_json::from_str(&format!(r#"[{{"t":1,"i":"{id}"}},{{"t":2,"i":"{id}"}}]"#))
+                .unwrap();
+        assert_eq!(single.into_bodies().len(), 1);
+        let bodies = batch.into_bodies();
+        assert_eq!(bodies.len(), 2);
+        assert_eq!(bodies[1].event_type, ClientEventRequestType::Session);
+
+        let saved = WebSocketAck::new(
+            1,
+            Ok(ClientEventActionSummary::Save(ClientEventSaveSummary {
+                event_count: 2,
+                duplicate_count: 1,
+            })),
+        );
+        assert_eq!(
+            saved,
+            WebSocketAck {
+                frame: 1,
+                status: 201,
+                retry: false,
+                event_count: 2,
+                duplicate_count: 1,
+            }
+        );
+        let invalid = WebSocketAck::new(
+            2,
+            Err(ClientEventRequestError::IngestEvent(
+                IngestEventError::UuidVersion,
+            )),
+        );
+        assert_eq!((invalid.status, invalid.retry), (400, false));
+        let failed = WebSocketAck::new(
+            3,
+            Err(ClientEventRequestError::IngestService(
+                IngestServiceError::Repository(IngestRepositoryError::Repository),
+            )),
+        );
+        assert_eq!((failed.status, failed.retry), (500, true));
+    }
+}
@@ -0,0 +1,230 @@
+use axum::{
+    Json,
+    body::Bytes,
+    extract::{FromRequest, Request},
+    response::{IntoResponse, Response},
+};
+use http::{HeaderMap, header};
+use serde::de::DeserializeOwned;
+
+use super::client_event_request::ClientEventRequestError;
+
+/// Content types that select `WireFormat::MessagePack`
+const MESSAGE_PACK_CONTENT_TYPES: &[&str] = &[
+    "application/msgpack",
+    "application/vnd.msgpack",
+    "application/x-msgpack",
+];
+
+/// Content type that selects `WireFormat::Cbor`
+const CBOR_CONTENT_TYPE: &str = "application/cbor";
+
+/// `WireFormat` is the encoding of a request body, as given by its
+/// `Content-Type` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireFormat {
+    Json,
+    MessagePack,
+    Cbor,
+}
+
+impl WireFormat {
+    /// Determine the `WireFormat` of a request from its headers. Anything
+    /// other than MessagePack or CBOR is treated as JSON, which leaves the
+    /// `Content-Type` checks of axum's `Json` extractor in place.
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let Some(content_type) = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+        else {
+            return WireFormat::Json;
+        };
+        let essence = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        if MESSAGE_PACK_CONTENT_TYPES.contains(&essence.as_str()) {
+            WireFormat::MessagePack
+        } else if essence == CBOR_CONTENT_TYPE {
+            WireFormat::Cbor
+        } else {
+            WireFormat::Json
+        }
+    }
+
+    /// Decode a body of this `WireFormat`
+    pub fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, ClientEventRequestError> {
+        match self {
+            WireFormat::Json => serde_json::from_slice(bytes).map_err(|e| {
+                tracing::debug!("Failed to decode JSON body: {e}");
+                ClientEventRequestError::InvalidRequestBody
+            }),
+            WireFormat::MessagePack => rmp_serde::from_slice(bytes).map_err(|e| {
+                tracing::debug!("Failed to decode MessagePack body: {e}");
+                ClientEventRequestError::InvalidRequestBody
+            }),
+            WireFormat::Cbor => ciborium::from_reader(bytes).map_err(|e| {
+                tracing::debug!("Failed to decode CBOR body: {e}");
+                ClientEventRequestError::InvalidRequestBody
+            }),
+        }
+    }
+}
+
+/// `Negotiated` extracts a request body that is encoded in any `WireFormat`.
+/// JSON bodies are extracted with axum's `Json` so that they are rejected
+/// exactly as before, while MessagePack and CBOR bodies that cannot be decoded
+/// are rejected as `ClientEventRequestError::InvalidRequestBody`.
+#[derive(Debug, Clone)]
+pub struct Negotiated<T>(pub T);
+
+impl<T, S> FromRequest<S> for Negotiated<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let format = WireFormat::from_headers(req.headers());
+        if format == WireFormat::Json {
+            let Json(value) = Json::<T>::from_request(req, state)
+                .await
+                .map_err(IntoResponse::into_response)?;
+            return Ok(Negotiated(value));
+        }
+        let bytes = Bytes::from_request(req, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        format
+            .decode(&bytes)
+            .map(Negotiated)
+            .map_err(IntoResponse::into_response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        net::{IpAddr, Ipv4Addr},
+    };
+
+    use http::HeaderValue;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{
+        domain::model::ingest_event::IngestEvent,
+        http_api::model::{
+            client_event_request::{ClientEventRequest, ClientEventRequestType},
+            client_event_request_components::{ClientEventRequestBody, ClientEventRequestHeaders},
+        },
+        repositories::clickhouse_event_record::ClickhouseEventRecord,
+    };
+
+    fn records(bodies: Vec<ClientEventRequestBody>) -> Vec<ClickhouseEventRecord> {
+        let headers = ClientEventRequestHeaders::new(
+            "abc-123",
+            "test.com",
+            "Mozilla/5.0 (X11; Linux x86_64; rv:135.0) Gecko/20100101 Firefox/135.0",
+        );
+        bodies
+            .into_iter()
+            .map(|body| {
+                let request = ClientEventRequest {
+                    headers: headers.clone(),
+                    body,
+                    ip: IpAddr::V4(Ipv4Addr::LOCALHOST),
+                };
+                let event = IngestEvent::try_from(&request).unwrap();
+                ClickhouseEventRecord::try_from(&event)
+                    .unwrap()
+                    .with_sorted_attrs()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_from_headers() {
+        let format = |content_type: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_str(content_type).unwrap(),
+            );
+            WireFormat::from_headers(&headers)
+        };
+        assert_eq!(format("application/json"), WireFormat::Json);
+        assert_eq!(format("application/msgpack"), WireFormat::MessagePack);
+        assert_eq!(format("Application/X-MsgPack"), WireFormat::MessagePack);
+        assert_eq!(format("application/cbor; charset=binary"), WireFormat::Cbor);
+        assert_eq!(format("text/plain"), WireFormat::Json);
+        assert_eq!(
+            WireFormat::from_headers(&HeaderMap::new()),
+            WireFormat::Json
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let visitor_id = Uuid::now_v7();
+        let session_id = Uuid::now_v7();
+        let bodies = vec![
+            ClientEventRequestBody::new(ClientEventRequestType::Visitor, visitor_id, None),
+            ClientEventRequestBody::new(
+                ClientEventRequestType::Session,
+                session_id,
+                Some(HashMap::from([("p".to_owned(), visitor_id.to_string())])),
+            ),
+            ClientEventRequestBody::new(
+                ClientEventRequestType::Section,
+                Uuid::now_v7(),
+                Some(HashMap::from([
+                    ("p".to_owned(), session_id.to_string()),
+                    ("l".to_owned(), "/pricing?plan=team".to_owned()),
+                    ("t".to_owned(), "Pricing".to_owned()),
+                ])),
+            ),
+        ];
+        let expected = records(bodies.clone());
+
+        let json = serde_json::to_vec(&bodies).unwrap();
+        let message_pack = rmp_serde::to_vec_named(&bodies).unwrap();
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&bodies, &mut cbor).unwrap();
+
+        for (format, bytes) in [
+            (WireFormat::Json, json),
+            (WireFormat::MessagePack, message_pack),
+            (WireFormat::Cbor, cbor),
+        ] {
+            let decoded: Vec<ClientEventRequestBody> = format.decode(&bytes).unwrap();
+            assert_eq!(
+                records(decoded),
+                expected,
+                "Expected {format:?} to produce identical records"
+            );
+        }
+
+        // Short field names and binary ids as sent by compact clients
+        let compact = ciborium::Value::Array(vec![ciborium::Value::Map(vec![
+            (ciborium::Value::from("t"), ciborium::Value::from(1)),
+            (
+                ciborium::Value::from("i"),
+                ciborium::Value::from(visitor_id.as_bytes().as_slice()),
+            ),
+        ])]);
+        let mut compact_cbor = Vec::new();
+        ciborium::into_writer(&compact, &mut compact_cbor).unwrap();
+        let decoded: Vec<ClientEventRequestBody> = WireFormat::Cbor.decode(&compact_cbor).unwrap();
+        assert_eq!(records(decoded), expected[..1]);
+
+        assert!(matches!(
+            WireFormat::Cbor.decode::<Vec<ClientEventRequestBody>>(b"not cbor"),
+            Err(ClientEventRequestError::InvalidRequestBody)
+        ));
+    }
+}
@@ -1,18 +1,76 @@
-use axum::{Router, routing::post};
-use conf::domain::service::configuration_service::ConfigurationService;
+use axum::{
+    Router,
+    extract::DefaultBodyLimit,
+    middleware::from_fn_with_state,
+    routing::{get, post},
+};
+use conf::domain::service::configuration_service::{
+    ArchiveFormat, ConfigurationService, ConfigurationServiceError, SinkPolicy as ConfSinkPolicy,
+    SinkTarget,
+};
 use http::Method;
-use std::{error::Error, net::SocketAddr};
+use std::{error::Error, net::SocketAddr, sync::Arc, time::Duration};
 use tower_http::{cors::Any, trace::TraceLayer};
 
 use crate::{
+    domain::{
+        model::{
+            event_deduplicator::EventDeduplicator, event_scrubber::EventScrubber,
+            identity_key::IdentityKey, server_key::ServerKey,
+            session_synthesizer::SessionSynthesizer,
+        },
+        repository::{event_sink::SinkPolicy, ingest_event_repository::IngestEventRepository},
+        service::ingest_event_service::IngestEventService,
+    },
+    grpc_api::service::ingest_grpc_service::IngestGrpcService,
     http_api::{
-        handlers::save_client_events::save_client_events,
-        model::ingest_application_state::IngestApplicationState,
+        handlers::{
+            dead_letters::{list_dead_letters, record_dead_letters},
+            dev_events::{clear_dev_events, list_dev_events},
+            ingest_websocket::ingest_websocket,
+            save_client_events::save_client_events,
+            save_measurement_protocol_events::save_measurement_protocol_events,
+            save_ndjson_events::save_ndjson_events,
+            save_plausible_event::save_plausible_event,
+            save_segment_events::{
+                save_segment_batch, save_segment_identify, save_segment_page, save_segment_track,
+            },
+            save_server_events::save_server_events,
+            tail_events::tail_events,
+            validate_client_events::validate_client_events,
+        },
+        model::{
+            dead_letter_store::{DeadLetterState, DeadLetterStore},
+            ingest_application_state::IngestApplicationState,
+            live_tail::LiveTail,
+            request_limiter::RequestLimiter,
+        },
+    },
+    repositories::{
+        archive_file::ArchiveFileFormat, backend_ingest_repository::BackendIngestRepository,
+        fan_out_ingest_repository::FanOutIngestRepository,
+        rotating_archive_sink::RotatingArchiveSink, webhook_sink::WebhookSink,
     },
-    repositories::clickhouse_ingest_repository::ClickhouseIngestRepository,
     services::ingest_service::IngestService,
 };
 
+/// `IngestEventService` behind every route of the server
+type ServerIngestService = IngestService<FanOutIngestRepository<BackendIngestRepository>>;
+
+/// Interval at which the orphan rates of the event hierarchy are logged
+const HIERARCHY_REPORT_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Interval at which the counts of requests rejected for exceeding a limit
+/// are logged
+const LIMIT_REPORT_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Interval at which the writes to each configured sink are logged
+const SINK_REPORT_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Interval at which the files of a rotating archive are checked for having
+/// reached their maximum age
+const ARCHIVE_ROTATION_INTERVAL: Duration = Duration::from_secs(30);
+
 pub struct HttpServer<T>
 where
     T: ConfigurationService + Sync + Send,
@@ -29,43 +87,488 @@ where
     }
 
     pub async fn start(&self) -> Result<(), Box<dyn Error + 'static>> {
-        let metrics_client = self.conf_service.try_metrics_db_client()?;
+        let (app, rotating_archives) = self.try_app().await?;
+
+        let listener_socket_addr = self.conf_service.try_listener_socket_addr()?;
+        let listener = tokio::net::TcpListener::bind(listener_socket_addr).await?;
+        tracing::debug!("listening on {}", listener.local_addr().unwrap());
 
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(conf::lifecycle::terminate_signal())
+        .await
+        .unwrap();
+        // Close the open archive files so that none is left without a
+        // manifest
+        for rotating_archive in rotating_archives {
+            if let Err(e) = rotating_archive.close_all().await {
+                tracing::error!("Failed to close archive files: {e}");
+            }
+        }
+        Ok(())
+    }
+
+    /// Assemble the routes of the server with every configured layer, along
+    /// with the rotating archives that must be closed on shutdown
+    async fn try_app(
+        &self,
+    ) -> Result<(Router, Vec<RotatingArchiveSink>), Box<dyn Error + 'static>> {
         let compression_layer = self.conf_service.try_compression_layer()?;
         let cors_layer = self
             .conf_service
             .try_cors_layer()?
             .allow_methods([Method::POST])
             .allow_headers(Any);
+        let decompression_layer = self.conf_service.try_decompression_layer()?;
+        let decompression_limit = self.conf_service.try_decompression_limit()?;
+        let body_limit_layer = self.conf_service.try_body_limit_layer()?;
+        let batch_limits = self.conf_service.try_batch_limits()?;
+        let request_limiter = RequestLimiter::default()
+            .with_max_events(batch_limits.events)
+            .with_max_attrs(batch_limits.attrs)
+            .with_max_title(batch_limits.title)
+            .with_max_location(batch_limits.location);
         let ip_source = self.conf_service.try_ip_source()?;
         let timeout_layer = self.conf_service.try_timeout_layer()?;
+        let reload_interval = match self.conf_service.try_reload_interval() {
+            Ok(interval) => Some(interval),
+            Err(ConfigurationServiceError::Missing) => None,
+            Err(e) => return Err(e.into()),
+        };
+        let event_scrubber = match self.conf_service.try_scrub_patterns() {
+            Ok(patterns) => EventScrubber::new(patterns),
+            Err(ConfigurationServiceError::Missing) => EventScrubber::default(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let event_deduplicator = match self.conf_service.try_dedupe_horizon() {
+            Ok(horizon) => EventDeduplicator::default().with_horizon(horizon.try_into()?),
+            Err(ConfigurationServiceError::Missing) => EventDeduplicator::default(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let session_synthesizer = match self.conf_service.try_synthetic_visitor_key() {
+            Ok(visitor_key) => {
+                SessionSynthesizer::default().with_visitor_key(IdentityKey::new(visitor_key))
+            }
+            Err(ConfigurationServiceError::Missing) => {
+                tracing::warn!("Synthesized visitor ids are derived without a secret");
+                SessionSynthesizer::default()
+            }
+            Err(e) => return Err(e.into()),
+        };
 
-        let ingest_repository = ClickhouseIngestRepository::try_new(metrics_client).await?;
-        let ingest_service = IngestService::new(ingest_repository);
-        let state = IngestApplicationState::new(ingest_service);
-        let app = Router::new()
+        let dead_letter_state = match self.conf_service.try_dead_letters() {
+            Ok(dead_letters) => Some(DeadLetterState {
+                dead_letter_store: Arc::new(DeadLetterStore::new(
+                    dead_letters.capacity,
+                    dead_letters.sample,
+                    dead_letters.max_body,
+                )),
+                admin_key: ServerKey::new(dead_letters.admin_key),
+            }),
+            Err(ConfigurationServiceError::Missing) => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        let sinks = match self.conf_service.try_sinks() {
+            Ok(sinks) => sinks,
+            Err(ConfigurationServiceError::Missing) => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let backend_repository = BackendIngestRepository::try_from_conf(&self.conf_service).await?;
+        // Dev mode exposes the events kept in memory for inspection. Like the
+        // server-to-server routes they are kept out of the CORS layer.
+        let dev_routes = match backend_repository {
+            BackendIngestRepository::Memory(ref memory_repository) => Router::new()
+                .route("/dev/events", get(list_dev_events).delete(clear_dev_events))
+                .with_state(memory_repository.clone()),
+            _ => Router::new(),
+        };
+        let mut ingest_repository = FanOutIngestRepository::new(backend_repository);
+        let mut rotating_archives = Vec::new();
+        for sink in sinks {
+            let policy = sink_policy(sink.policy);
+            ingest_repository = match sink.target {
+                SinkTarget::Archive(path) => {
+                    let appending_archive = RotatingArchiveSink::appending(path);
+                    rotating_archives.push(appending_archive.clone());
+                    ingest_repository.with_sink(appending_archive, policy)
+                }
+                SinkTarget::RotatingArchive(archive) => {
+                    let rotating_archive =
+                        RotatingArchiveSink::new(archive.dir, archive_file_format(archive.format)?)
+                            .with_max_bytes(archive.max_bytes)
+                            .with_max_age(archive.max_age);
+                    spawn_archive_rotation(rotating_archive.clone(), ARCHIVE_ROTATION_INTERVAL);
+                    rotating_archives.push(rotating_archive.clone());
+                    ingest_repository.with_sink(rotating_archive, policy)
+                }
+                SinkTarget::Webhook(url) => {
+                    ingest_repository.with_sink(WebhookSink::new(url), policy)
+                }
+            };
+        }
+        if ingest_repository.has_sinks() {
+            spawn_sink_report(ingest_repository.clone(), SINK_REPORT_INTERVAL);
+        }
+        let live_tail = LiveTail::default().with_event_scrubber(event_scrubber.clone());
+        let ingest_service = IngestService::new(ingest_repository)
+            .with_event_scrubber(event_scrubber)
+            .with_event_deduplicator(event_deduplicator);
+        if let Some(reload_interval) = reload_interval {
+            spawn_event_source_reload(ingest_service.clone(), reload_interval);
+        }
+        spawn_hierarchy_report(ingest_service.clone(), HIERARCHY_REPORT_INTERVAL);
+        let state = IngestApplicationState::new(ingest_service)
+            .with_session_synthesizer(session_synthesizer)
+            .with_request_limiter(request_limiter)
+            .with_live_tail(live_tail);
+        spawn_limit_report(state.request_limiter.clone(), LIMIT_REPORT_INTERVAL);
+        // gRPC calls are served on the same listener and authenticated in the
+        // same way as the server-to-server routes. Streaming calls last as
+        // long as the client keeps sending, so they are outside of the timeout.
+        let grpc_routes = IngestGrpcService::new(state.ingest_service.clone())
+            .with_request_limiter(state.request_limiter.clone())
+            .into_router()
+            .layer(TraceLayer::new_for_grpc());
+        // Server-to-server routes are kept out of the CORS layer so that
+        // browsers are never permitted to call them
+        let server_routes = Router::new()
             .route(
-                "/multi",
-                post(save_client_events::<IngestService<ClickhouseIngestRepository>>),
+                "/server/multi",
+                post(save_server_events::<ServerIngestService>),
             )
+            .route(
+                "/mp/collect",
+                post(save_measurement_protocol_events::<ServerIngestService>),
+            );
+        let stream_routes = Router::new()
+            .route("/ws", get(ingest_websocket::<ServerIngestService>))
+            .route("/tail", get(tail_events::<ServerIngestService>))
+            .layer(decompression_layer.clone());
+        // NDJSON streams are answered once the whole body was read, so like
+        // gRPC streams they are outside of the timeout
+        let ndjson_routes = Router::new()
+            .route(
+                "/server/ndjson",
+                post(save_ndjson_events::<ServerIngestService>),
+            )
+            .layer(decompression_layer.clone())
+            .layer(TraceLayer::new_for_http());
+        // Rejected requests are kept for review by admins, who are the only
+        // callers of these routes, so they are kept out of the CORS layer
+        let admin_routes = match dead_letter_state {
+            Some(ref dead_letter_state) => Router::new()
+                .route("/admin/deadletters", get(list_dead_letters))
+                .with_state(dead_letter_state.clone()),
+            None => Router::new(),
+        };
+        // Payloads are validated under the same CORS policy as `/multi` so
+        // that SDKs can call it from a browser, but are never dead letters
+        let validate_routes = Router::new()
+            .route(
+                "/validate",
+                post(validate_client_events::<ServerIngestService>),
+            )
+            .layer(cors_layer.clone());
+        let mut ingest_routes = Router::new()
+            .route("/multi", post(save_client_events::<ServerIngestService>))
+            .route(
+                "/api/event",
+                post(save_plausible_event::<ServerIngestService>),
+            )
+            .route("/v1/track", post(save_segment_track::<ServerIngestService>))
+            .route("/v1/page", post(save_segment_page::<ServerIngestService>))
+            .route(
+                "/v1/identify",
+                post(save_segment_identify::<ServerIngestService>),
+            )
+            .route("/v1/batch", post(save_segment_batch::<ServerIngestService>))
+            .layer(cors_layer)
+            .merge(server_routes);
+        if let Some(dead_letter_state) = dead_letter_state {
+            // Within the decompression layer, so that the kept bodies are
+            // those that the handlers read
+            ingest_routes = ingest_routes.layer(from_fn_with_state(
+                dead_letter_state.dead_letter_store,
+                record_dead_letters,
+            ));
+        }
+        let app = ingest_routes
+            .merge(validate_routes)
+            // The body limit is enforced as extractors read the body, which is
+            // after decompression, so it caps the decompressed size
+            .layer(DefaultBodyLimit::max(decompression_limit))
+            .layer(decompression_layer)
+            // Bounds the body as it is received, before decompression
+            .layer(body_limit_layer)
+            // Streams are read line by line or message by message with a limit
+            // on each and tails only send, so they are exempt from the limits
+            // on the whole body
+            .merge(stream_routes)
+            .merge(dev_routes)
+            .merge(admin_routes)
             .layer(TraceLayer::new_for_http())
             .layer(compression_layer)
-            .layer(cors_layer)
             .layer(timeout_layer)
+            .merge(ndjson_routes)
+            .merge(grpc_routes)
             .layer(ip_source.into_extension())
             .with_state(state);
+        Ok((app, rotating_archives))
+    }
+}
 
-        let listener_socket_addr = self.conf_service.try_listener_socket_addr()?;
-        let listener = tokio::net::TcpListener::bind(listener_socket_addr).await?;
-        tracing::debug!("listening on {}", listener.local_addr().unwrap());
+/// Periodically reload the event sources and their rules so that changes made
+/// in the backing store take effect without a restart. A failed reload keeps
+/// the previously loaded sources and is retried at the next interval.
+fn spawn_event_source_reload<S>(ingest_service: S, reload_interval: Duration)
+where
+    S: IngestEventService + Clone,
+{
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(reload_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        // The first tick completes immediately and sources were just loaded
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            if let Err(e) = ingest_service.reload_event_sources().await {
+                tracing::error!("Failed to reload event sources: {e}");
+            }
+        }
+    });
+}
 
-        axum::serve(
-            listener,
-            app.into_make_service_with_connect_info::<SocketAddr>(),
-        )
-        .with_graceful_shutdown(conf::lifecycle::terminate_signal())
-        .await
-        .unwrap();
-        Ok(())
+/// Periodically log the `HierarchyReport` of every source, so that sites
+/// whose integration sends events with unknown or mismatched parents can be
+/// found in the logs.
+fn spawn_hierarchy_report<S>(ingest_service: S, report_interval: Duration)
+where
+    S: IngestEventService,
+{
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(report_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            for report in ingest_service.hierarchy_report() {
+                let stats = &report.stats;
+                tracing::info!(
+                    api_key = report.source.api_key().value(),
+                    site = report.source.site().value(),
+                    checked = stats.checked,
+                    late = stats.late,
+                    orphaned = stats.orphaned,
+                    mismatched = stats.mismatched,
+                    orphan_rate = stats.orphan_rate(),
+                    "Event hierarchy report"
+                );
+            }
+        }
+    });
+}
+
+/// Periodically log the number of requests that were rejected for exceeding
+/// each limit of the `RequestLimiter`, if any
+fn spawn_limit_report(request_limiter: Arc<RequestLimiter>, report_interval: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(report_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let counts = request_limiter.report();
+            if counts.total() > 0 {
+                tracing::warn!(
+                    events = counts.events,
+                    attrs = counts.attrs,
+                    title = counts.title,
+                    location = counts.location,
+                    "Requests rejected for exceeding limits"
+                );
+            }
+        }
+    });
+}
+
+/// Periodically log the writes to each `EventSink` of the repository, so that
+/// a failing destination can be found in the logs
+fn spawn_sink_report<R>(ingest_repository: FanOutIngestRepository<R>, report_interval: Duration)
+where
+    R: IngestEventRepository,
+{
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(report_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            for report in ingest_repository.report() {
+                let counts = &report.counts;
+                tracing::info!(
+                    sink = report.name,
+                    policy = ?report.policy,
+                    batches = counts.batches,
+                    events = counts.events,
+                    failures = counts.failures,
+                    overflows = counts.overflows,
+                    "Event sink report"
+                );
+            }
+        }
+    });
+}
+
+/// Periodically close the files of a `RotatingArchiveSink` that have reached
+/// their maximum age, so that files of partitions that are no longer written
+/// are closed as well
+fn spawn_archive_rotation(rotating_archive: RotatingArchiveSink, rotation_interval: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(rotation_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            if let Err(e) = rotating_archive.rotate_expired().await {
+                tracing::error!("Failed to rotate archive files: {e}");
+            }
+        }
+    });
+}
+
+/// Map the configured `ArchiveFormat` onto the `ArchiveFileFormat` of the
+/// sink, which is only able to write Parquet with the `parquet` feature
+fn archive_file_format(format: ArchiveFormat) -> Result<ArchiveFileFormat, Box<dyn Error>> {
+    match format {
+        ArchiveFormat::Jsonl => Ok(ArchiveFileFormat::Jsonl),
+        #[cfg(feature = "parquet")]
+        ArchiveFormat::Parquet => Ok(ArchiveFileFormat::Parquet),
+        #[cfg(not(feature = "parquet"))]
+        ArchiveFormat::Parquet => Err("the Parquet archive requires the `parquet` feature".into()),
+    }
+}
+
+/// Map the configured `SinkPolicy` onto that of the ingest domain
+fn sink_policy(policy: ConfSinkPolicy) -> SinkPolicy {
+    match policy {
+        ConfSinkPolicy::Required => SinkPolicy::Required,
+        ConfSinkPolicy::BestEffort => SinkPolicy::BestEffort,
+        ConfSinkPolicy::Async => SinkPolicy::Async,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env::{remove_var, set_var},
+        io::Write,
+        net::{Ipv4Addr, SocketAddr},
+    };
+
+    use axum::{
+        body::Body,
+        extract::{ConnectInfo, Request},
+    };
+    use conf::env_conf::env_conf;
+    use flate2::{Compression, write::GzEncoder};
+    use http::{StatusCode, header};
+    use tower::ServiceExt;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::http_api::model::client_event_request_components::API_KEY_HTTP_HEADER;
+
+    /// Decompressed size of request bodies in the test server
+    const DECOMPRESSION_LIMIT: usize = 4096;
+
+    /// Server in dev mode, so that events are kept in memory
+    fn dev_server() -> HttpServer<impl ConfigurationService> {
+        let app_name = format!("ROUTER_{}", Uuid::now_v7().simple());
+        let settings = [
+            ("DEV_MODE", "true".to_owned()),
+            ("DEV_SOURCES", "abc-123:localhost".to_owned()),
+            ("LAYER_CORS_ORIGINS", "http://localhost".to_owned()),
+            ("LAYER_DECOMPRESSION_LIMIT", DECOMPRESSION_LIMIT.to_string()),
+        ];
+        for (key, value) in &settings {
+            // TODO: Audit that the environment access only happens in single-threaded code.
+            unsafe { set_var(format!("{app_name}_{key}"), value) };
+        }
+        let conf_service = env_conf(&app_name).unwrap();
+        for (key, _) in &settings {
+            // TODO: Audit that the environment access only happens in single-threaded code.
+            unsafe { remove_var(format!("{app_name}_{key}")) };
+        }
+        HttpServer::new(conf_service)
+    }
+
+    fn client_request(content_encoding: &str, body: Vec<u8>) -> Request {
+        let mut request = Request::post("/multi")
+            .header(header::ORIGIN, "http://localhost")
+            .header(API_KEY_HTTP_HEADER, "abc-123")
+            .header(header::USER_AGENT, "test-agent")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::CONTENT_ENCODING, content_encoding)
+            .body(Body::from(body))
+            .unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from((Ipv4Addr::LOCALHOST, 3000))));
+        request
+    }
+
+    fn gzip(body: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(body).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_request_decompression() {
+        let (app, _) = dev_server().try_app().await.unwrap();
+        let events = format!(r#"[{{"event_type":1,"id":"{}"}}]"#, Uuid::now_v7());
+
+        let response = app
+            .clone()
+            .oneshot(client_request("gzip", gzip(events.as_bytes())))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.status(),
+            StatusCode::CREATED,
+            "Expected a gzip compressed request to be accepted"
+        );
+
+        // Negative test cases
+        let response = app
+            .clone()
+            .oneshot(client_request("compress", events.clone().into_bytes()))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.status(),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "Expected an unsupported content encoding to be rejected"
+        );
+
+        // Whitespace compresses well, so the body is small until decompressed
+        let bomb = format!("{}{events}", " ".repeat(DECOMPRESSION_LIMIT * 16));
+        let compressed_bomb = gzip(bomb.as_bytes());
+        assert!(compressed_bomb.len() < DECOMPRESSION_LIMIT);
+        let response = app
+            .oneshot(client_request("gzip", compressed_bomb))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.status(),
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "Expected a body beyond the decompressed limit to be rejected"
+        );
     }
 }
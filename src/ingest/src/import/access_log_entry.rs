@@ -0,0 +1,212 @@
+use std::{net::IpAddr, str::FromStr, sync::LazyLock};
+
+use regex::Regex;
+use thiserror::Error;
+use time::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset};
+
+/// Combined log format as written by both nginx and Apache, i.e.
+/// `ip ident user [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.1" 200 2326 "referrer" "user agent"`.
+/// Quoted fields may contain escaped quotes.
+static COMBINED_LOG_FORMAT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"^(\S+) \S+ \S+ \[(\d{2})/(\w{3})/(\d{4}):(\d{2}):(\d{2}):(\d{2}) ([+-])(\d{2})(\d{2})\] "(\S+) (\S+)[^"]*" (\d{3}) \S+ "(?:[^"\\]|\\.)*" "((?:[^"\\]|\\.)*)""#,
+    )
+    .expect("Combined log format pattern must be valid")
+});
+
+/// User agents of crawlers, monitoring and command line clients
+static BOT_USER_AGENT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)bot|crawl|spider|slurp|archiver|scan|monitor|uptime|pingdom|lighthouse|headless|phantomjs|preview|facebookexternalhit|curl|wget|python|java/|go-http-client|libwww|httpclient|okhttp",
+    )
+    .expect("Bot user agent pattern must be valid")
+});
+
+/// File extensions of requests that are never page views
+const STATIC_ASSET_EXTENSIONS: [&str; 24] = [
+    "avif", "bmp", "css", "eot", "gif", "ico", "jpeg", "jpg", "js", "json", "map", "mjs", "mp3",
+    "mp4", "otf", "png", "svg", "ttf", "txt", "webm", "webp", "woff", "woff2", "xml",
+];
+
+/// `AccessLogError` represents the reasons a line of an access log could not
+/// be parsed
+#[derive(Clone, Error, Debug, PartialEq, Eq)]
+pub enum AccessLogError {
+    #[error("Line is not in combined log format")]
+    Format,
+    #[error("Invalid client address")]
+    Ip,
+    #[error("Invalid request timestamp")]
+    Timestamp,
+}
+
+/// `AccessLogEntry` is a single request from an nginx or Apache access log in
+/// combined log format. Only the fields needed to reconstruct page views are
+/// kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessLogEntry {
+    pub ip: IpAddr,
+    pub ts: OffsetDateTime,
+    pub method: String,
+    /// Request target as sent by the client, i.e. path and query
+    pub target: String,
+    pub status: u16,
+    pub user_agent: String,
+}
+
+impl AccessLogEntry {
+    /// `true` when the request was most likely a page view by a person, i.e.
+    /// a successful `GET` of an absolute path that is not a static asset,
+    /// issued by a user agent that is not a known bot
+    pub fn is_page_view(&self) -> bool {
+        self.method == "GET"
+            && ((200..300).contains(&self.status) || self.status == 304)
+            && self.target.starts_with('/')
+            && !self.is_static_asset()
+            && !self.is_bot()
+    }
+
+    /// `true` when the path of the request ends in a static asset extension
+    pub fn is_static_asset(&self) -> bool {
+        let path = self.target.split(['?', '#']).next().unwrap_or_default();
+        let file_name = path.rsplit('/').next().unwrap_or_default();
+        file_name.rsplit_once('.').is_some_and(|(_, extension)| {
+            STATIC_ASSET_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+        })
+    }
+
+    /// `true` when the user agent is missing or belongs to a known bot
+    pub fn is_bot(&self) -> bool {
+        let user_agent = self.user_agent.trim();
+        user_agent.is_empty() || user_agent == "-" || BOT_USER_AGENT.is_match(user_agent)
+    }
+}
+
+impl FromStr for AccessLogEntry {
+    type Err = AccessLogError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let captures = COMBINED_LOG_FORMAT
+            .captures(s.trim_end())
+            .ok_or(AccessLogError::Format)?;
+        let field = |i: usize| captures.get(i).map_or("", |m| m.as_str());
+        let number = |i: usize| {
+            field(i)
+                .parse::<u8>()
+                .map_err(|_| AccessLogError::Timestamp)
+        };
+
+        let ip = field(1).parse().map_err(|_| AccessLogError::Ip)?;
+        let month = month_from_abbreviation(field(3)).ok_or(AccessLogError::Timestamp)?;
+        let year = field(4).parse().map_err(|_| AccessLogError::Timestamp)?;
+        let date = Date::from_calendar_date(year, month, number(2)?)
+            .map_err(|_| AccessLogError::Timestamp)?;
+        let time = Time::from_hms(number(5)?, number(6)?, number(7)?)
+            .map_err(|_| AccessLogError::Timestamp)?;
+        let sign = if field(8) == "-" { -1 } else { 1 };
+        let offset = UtcOffset::from_hms(sign * number(9)? as i8, sign * number(10)? as i8, 0)
+            .map_err(|_| AccessLogError::Timestamp)?;
+
+        Ok(Self {
+            ip,
+            ts: PrimitiveDateTime::new(date, time).assume_offset(offset),
+            method: field(11).to_owned(),
+            target: field(12).to_owned(),
+            status: field(13).parse().map_err(|_| AccessLogError::Format)?,
+            user_agent: field(14).replace("\\\"", "\""),
+        })
+    }
+}
+
+/// Month from the English abbreviation used in log timestamps
+fn month_from_abbreviation(abbreviation: &str) -> Option<Month> {
+    match abbreviation {
+        "Jan" => Some(Month::January),
+        "Feb" => Some(Month::February),
+        "Mar" => Some(Month::March),
+        "Apr" => Some(Month::April),
+        "May" => Some(Month::May),
+        "Jun" => Some(Month::June),
+        "Jul" => Some(Month::July),
+        "Aug" => Some(Month::August),
+        "Sep" => Some(Month::September),
+        "Oct" => Some(Month::October),
+        "Nov" => Some(Month::November),
+        "Dec" => Some(Month::December),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    const BROWSER: &str = "Mozilla/5.0 (X11; Linux x86_64; rv:128.0) Gecko/20100101 Firefox/128.0";
+
+    #[test]
+    fn test_from_str() {
+        let line = format!(
+            r#"203.0.113.7 - frank [10/Oct/2000:13:55:36 -0700] "GET /docs/?q=1 HTTP/1.1" 200 2326 "https://test.com/" "{BROWSER}""#
+        );
+        let entry: AccessLogEntry = line.parse().unwrap();
+        assert_eq!(entry.ip, IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7)));
+        assert_eq!(entry.ts.unix_timestamp(), 971_211_336);
+        assert_eq!(entry.ts.offset(), UtcOffset::from_hms(-7, 0, 0).unwrap());
+        assert_eq!(entry.method, "GET");
+        assert_eq!(entry.target, "/docs/?q=1");
+        assert_eq!(entry.status, 200);
+        assert_eq!(entry.user_agent, BROWSER);
+        assert!(
+            entry.is_page_view(),
+            "Expected browser GET to be a page view"
+        );
+
+        assert_eq!(
+            "not a log line".parse::<AccessLogEntry>().unwrap_err(),
+            AccessLogError::Format
+        );
+        assert_eq!(
+            r#"::1 - - [10/Foo/2000:13:55:36 +0000] "GET / HTTP/1.1" 200 1 "-" "-""#
+                .parse::<AccessLogEntry>()
+                .unwrap_err(),
+            AccessLogError::Timestamp
+        );
+    }
+
+    #[test]
+    fn test_is_page_view() {
+        let entry = AccessLogEntry {
+            ip: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            ts: OffsetDateTime::UNIX_EPOCH,
+            method: "GET".to_owned(),
+            target: "/about".to_owned(),
+            status: 200,
+            user_agent: BROWSER.to_owned(),
+        };
+        assert!(entry.is_page_view());
+
+        for (target, status, user_agent, reason) in [
+            ("/app.JS", 200, BROWSER, "static asset"),
+            ("/img/logo.png?v=2", 304, BROWSER, "static asset with query"),
+            ("/about", 404, BROWSER, "failed request"),
+            ("/about", 200, "Googlebot/2.1", "bot"),
+            ("/about", 200, "curl/8.5.0", "command line client"),
+            ("/about", 200, "-", "missing user agent"),
+        ] {
+            let filtered = AccessLogEntry {
+                target: target.to_owned(),
+                status,
+                user_agent: user_agent.to_owned(),
+                ..entry.clone()
+            };
+            assert!(!filtered.is_page_view(), "Expected {reason} to be filtered");
+        }
+        let post = AccessLogEntry {
+            method: "POST".to_owned(),
+            ..entry
+        };
+        assert!(!post.is_page_view(), "Expected POST to be filtered");
+    }
+}
@@ -0,0 +1,283 @@
+use std::{collections::HashMap, net::IpAddr};
+
+use sha2::{Digest, Sha256};
+use time::{Duration, OffsetDateTime};
+use uuid::{Builder, Uuid};
+
+use crate::domain::model::ingest_event::{
+    ApiKey, IngestEvent, IngestEventCore, IngestEventError, IngestEventOrigin, SectionEvent,
+    SessionEvent, Site, VisitorEvent,
+};
+
+use super::{
+    access_log_entry::{AccessLogEntry, AccessLogError},
+    event_importer::ImportLineParser,
+};
+
+/// Default period of inactivity after which a visitor starts a new session
+pub const DEFAULT_SESSION_GAP: Duration = Duration::minutes(30);
+
+/// `AccessLogVisitor` is the state kept for each inferred visitor
+#[derive(Debug, Clone)]
+struct AccessLogVisitor {
+    visitor_id: Uuid,
+    session_id: Uuid,
+    last_seen: OffsetDateTime,
+}
+
+/// `AccessLogParser` reconstructs `Visitor`, `Session` and `Section` events
+/// from a chronological access log. A visitor is identified by the client ip
+/// together with the user agent, and a new session starts whenever that
+/// visitor has not made a page request for longer than the session gap. Each
+/// page view becomes a `SectionEvent` whose location is built from the site
+/// and the request target.
+///
+/// Event ids are UUIDv7 with the timestamp of the request. The remaining bits
+/// are derived from the position of the event in the input rather than being
+/// random, so that parsing the same input again yields the same ids. This is
+/// what allows an interrupted import to be resumed with consistent parents.
+#[derive(Debug)]
+pub struct AccessLogParser {
+    api_key: ApiKey,
+    site: Site,
+    scheme: String,
+    session_gap: Duration,
+    visitors: HashMap<(IpAddr, String), AccessLogVisitor>,
+    sequence: u64,
+}
+
+impl AccessLogParser {
+    /// `AccessLogParser` constructor. Locations use the `https` scheme and
+    /// sessions are split by the `DEFAULT_SESSION_GAP`.
+    pub fn new(api_key: ApiKey, site: Site) -> Self {
+        Self {
+            api_key,
+            site,
+            scheme: "https".to_owned(),
+            session_gap: DEFAULT_SESSION_GAP,
+            visitors: HashMap::new(),
+            sequence: 0,
+        }
+    }
+
+    /// Replace the scheme used to build section locations
+    pub fn with_scheme(mut self, scheme: impl AsRef<str>) -> Self {
+        self.scheme = scheme.as_ref().to_owned();
+        self
+    }
+
+    /// Replace the period of inactivity that starts a new session
+    pub fn with_session_gap(mut self, session_gap: Duration) -> Self {
+        self.session_gap = session_gap;
+        self
+    }
+
+    /// Next deterministic UUIDv7 for an event at `ts`
+    fn next_id(&mut self, ts: OffsetDateTime) -> Uuid {
+        self.sequence += 1;
+        let digest = Sha256::digest(format!(
+            "{}|{}|{}",
+            self.api_key.value(),
+            self.site.value(),
+            self.sequence
+        ));
+        let mut counter_random_bytes = [0u8; 10];
+        counter_random_bytes.copy_from_slice(&digest[..10]);
+        let millis = (ts.unix_timestamp_nanos() / 1_000_000).max(0) as u64;
+        Builder::from_unix_timestamp_millis(millis, &counter_random_bytes).into_uuid()
+    }
+
+    fn core(&self, id: Uuid) -> Result<IngestEventCore, IngestEventError> {
+        IngestEventCore::try_new_with_origin(
+            self.api_key.clone(),
+            self.site.clone(),
+            id,
+            IngestEventOrigin::Import,
+        )
+    }
+
+    /// Events for a single page view, creating the visitor and session as
+    /// needed. State is only updated once all events were created.
+    fn page_view_events(
+        &mut self,
+        entry: AccessLogEntry,
+    ) -> Result<Vec<IngestEvent>, IngestEventError> {
+        let key = (entry.ip, entry.user_agent.clone());
+        let mut events = Vec::with_capacity(3);
+        let (visitor_id, session_id) = match self.visitors.get(&key).cloned() {
+            Some(visitor) if entry.ts - visitor.last_seen <= self.session_gap => {
+                (visitor.visitor_id, visitor.session_id)
+            }
+            Some(visitor) => {
+                let session_id = self.session_event(&entry, visitor.visitor_id, &mut events)?;
+                (visitor.visitor_id, session_id)
+            }
+            None => {
+                let visitor_id = self.next_id(entry.ts);
+                events.push(IngestEvent::Visitor(VisitorEvent::try_new_with_core_event(
+                    self.core(visitor_id)?,
+                )?));
+                let session_id = self.session_event(&entry, visitor_id, &mut events)?;
+                (visitor_id, session_id)
+            }
+        };
+        let section_id = self.next_id(entry.ts);
+        let location = format!("{}://{}{}", self.scheme, self.site.value(), entry.target);
+        events.push(IngestEvent::Section(SectionEvent::try_new_with_core_event(
+            self.core(section_id)?,
+            session_id,
+            Some(location),
+            None,
+        )?));
+
+        self.visitors.insert(
+            key,
+            AccessLogVisitor {
+                visitor_id,
+                session_id,
+                last_seen: entry.ts,
+            },
+        );
+        Ok(events)
+    }
+
+    /// Push a new `SessionEvent` for the visitor and return its id
+    fn session_event(
+        &mut self,
+        entry: &AccessLogEntry,
+        visitor_id: Uuid,
+        events: &mut Vec<IngestEvent>,
+    ) -> Result<Uuid, IngestEventError> {
+        let session_id = self.next_id(entry.ts);
+        events.push(IngestEvent::Session(SessionEvent::try_new_with_core_event(
+            self.core(session_id)?,
+            visitor_id,
+            entry.user_agent.clone(),
+            entry.ip,
+        )?));
+        Ok(session_id)
+    }
+}
+
+impl ImportLineParser for AccessLogParser {
+    /// Lines that are not page views, such as static assets, failed requests
+    /// or bots, produce no events
+    fn parse_line(&mut self, line: &str) -> Result<Vec<IngestEvent>, String> {
+        let entry: AccessLogEntry = line.parse().map_err(|e: AccessLogError| e.to_string())?;
+        if !entry.is_page_view() {
+            return Ok(Vec::new());
+        }
+        self.page_view_events(entry).map_err(|e| e.to_string())
+    }
+
+    fn replays_skipped_lines(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::domain::model::ingest_event::CommonEvent;
+
+    const BROWSER: &str = "Mozilla/5.0 (X11; Linux x86_64; rv:128.0) Gecko/20100101 Firefox/128.0";
+
+    fn log_line(ip: &str, time: &str, target: &str, user_agent: &str) -> String {
+        format!(
+            r#"{ip} - - [12/Mar/2024:{time} +0000] "GET {target} HTTP/1.1" 200 512 "-" "{user_agent}""#
+        )
+    }
+
+    fn parse_all(parser: &mut AccessLogParser, lines: &[String]) -> Vec<IngestEvent> {
+        lines
+            .iter()
+            .flat_map(|line| parser.parse_line(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_line() {
+        let lines = vec![
+            log_line("203.0.113.7", "10:00:00", "/", BROWSER),
+            log_line("203.0.113.7", "10:00:01", "/site.css", BROWSER),
+            log_line("203.0.113.7", "10:05:00", "/pricing?plan=pro", BROWSER),
+            log_line("203.0.113.7", "10:06:00", "/", "Googlebot/2.1"),
+            log_line("198.51.100.2", "10:07:00", "/", BROWSER),
+            log_line("203.0.113.7", "11:00:00", "/docs", BROWSER),
+        ];
+        let mut parser = AccessLogParser::new(ApiKey::new("abc_123"), Site::new("test.com"));
+        let events = parse_all(&mut parser, &lines);
+        let kinds: Vec<&str> = events
+            .iter()
+            .map(|event| match event {
+                IngestEvent::Visitor(_) => "visitor",
+                IngestEvent::Session(_) => "session",
+                IngestEvent::Section(_) => "section",
+                IngestEvent::Click(_) => "click",
+                IngestEvent::Custom(_) => "custom",
+                IngestEvent::Identify(_) => "identify",
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                "visitor", "session", "section", "section", "visitor", "session", "section",
+                "session", "section"
+            ],
+            "Expected assets and bots to be filtered and a new session after the gap"
+        );
+
+        let IngestEvent::Visitor(visitor) = &events[0] else {
+            panic!("Expected visitor event first");
+        };
+        let IngestEvent::Session(first_session) = &events[1] else {
+            panic!("Expected session event second");
+        };
+        let IngestEvent::Section(pricing) = &events[3] else {
+            panic!("Expected section event for second page view");
+        };
+        let IngestEvent::Session(second_session) = &events[7] else {
+            panic!("Expected new session after the gap");
+        };
+        assert_eq!(first_session.parent, visitor.id());
+        assert_eq!(second_session.parent, visitor.id());
+        assert_eq!(pricing.parent, first_session.id());
+        assert_eq!(
+            pricing.location.as_deref(),
+            Some("https://test.com/pricing?plan=pro")
+        );
+        assert_eq!(first_session.ip.to_string(), "203.0.113.7");
+        assert_eq!(
+            Uuid::get_timestamp(&pricing.id()).unwrap().to_unix().0,
+            1_710_237_900,
+            "Expected ids to carry the original request timestamp"
+        );
+        assert_eq!(pricing.origin(), IngestEventOrigin::Import);
+
+        // Parsing the same input again yields the same ids
+        let mut replay = AccessLogParser::new(ApiKey::new("abc_123"), Site::new("test.com"));
+        let replayed = parse_all(&mut replay, &lines);
+        assert_eq!(
+            events.iter().map(event_id).collect::<Vec<_>>(),
+            replayed.iter().map(event_id).collect::<Vec<_>>()
+        );
+
+        assert_eq!(
+            parser.parse_line("garbage").unwrap_err(),
+            "Line is not in combined log format"
+        );
+    }
+
+    fn event_id(event: &IngestEvent) -> Uuid {
+        match event {
+            IngestEvent::Visitor(e) => e.id(),
+            IngestEvent::Session(e) => e.id(),
+            IngestEvent::Section(e) => e.id(),
+            IngestEvent::Click(e) => e.id(),
+            IngestEvent::Custom(e) => e.id(),
+            IngestEvent::Identify(e) => e.id(),
+        }
+    }
+}
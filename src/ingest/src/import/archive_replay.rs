@@ -0,0 +1,360 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+use thiserror::Error;
+use time::{Date, UtcOffset};
+
+use crate::{
+    domain::{
+        model::{ingest_action_summary::IngestActionSummary, ingest_event::IngestEvent},
+        repository::ingest_event_repository::{IngestEventRepository, IngestRepositoryError},
+    },
+    repositories::{
+        archive_file::{
+            ARCHIVE_IN_PROGRESS_SUFFIX, ARCHIVE_MANIFEST_SUFFIX, ArchiveFileFormat, ArchiveManifest,
+        },
+        sink_event_record::SinkEventRecord,
+    },
+};
+
+/// Default number of events written per call to `IngestEventRepository::save`
+pub const DEFAULT_REPLAY_BATCH_SIZE: usize = 10_000;
+
+/// `ArchiveReplayError` represents failures that stop a replay. Archive files
+/// that cannot be verified or read and records that cannot be restored are
+/// not errors, they are reported in the `ArchiveReplaySummary` instead.
+#[derive(Debug, Error)]
+pub enum ArchiveReplayError {
+    /// Listing the archive failed
+    #[error("Error reading archive: {0}")]
+    Io(#[from] io::Error),
+    /// Saving a batch failed. All batches before it have been saved.
+    #[error("Error saving replayed events: {0}")]
+    Repository(#[from] IngestRepositoryError),
+}
+
+/// `ArchiveReplayFilter` selects the records to replay by site and by the
+/// range of UTC dates from `from` up to and including `to`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArchiveReplayFilter {
+    pub site: Option<String>,
+    pub from: Option<Date>,
+    pub to: Option<Date>,
+}
+
+impl ArchiveReplayFilter {
+    fn matches(&self, site: &str, date: Date) -> bool {
+        self.site.as_ref().is_none_or(|filter| filter == site)
+            && self.from.is_none_or(|from| from <= date)
+            && self.to.is_none_or(|to| date <= to)
+    }
+
+    fn matches_manifest(&self, manifest: &ArchiveManifest) -> bool {
+        manifest
+            .partition_date()
+            .is_some_and(|date| self.matches(&manifest.site, date))
+    }
+
+    fn matches_record(&self, record: &SinkEventRecord) -> bool {
+        self.matches(&record.site, record.ts.to_offset(UtcOffset::UTC).date())
+    }
+}
+
+/// `ArchiveReplaySummary` is the report produced by a replay
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct ArchiveReplaySummary {
+    /// Archive files whose records were replayed
+    pub files: u64,
+    /// Archive files left out by the filter
+    pub skipped_files: u64,
+    /// Files that were never closed and have no manifest
+    pub unsealed_files: Vec<PathBuf>,
+    /// Files that do not match their manifest or could not be read
+    pub corrupt_files: Vec<PathBuf>,
+    /// Events that were saved
+    pub accepted: u64,
+    /// Events that the repository skipped because they were already stored
+    pub duplicates: u64,
+    /// Records that could not be restored as events
+    pub rejected: u64,
+    /// Number of batches that were saved
+    pub batches: u64,
+}
+
+/// `ArchiveReplayer` re-ingests the records of an archive written by a
+/// `RotatingArchiveSink`, rotated or appending. The records were scrubbed and
+/// pseudonymized before they were archived, so the restored events are saved
+/// directly to the `IngestEventRepository` rather than passing through the
+/// `IngestEventService` again. Files of a rotating archive are only read once
+/// verified against their manifest.
+#[derive(Debug)]
+pub struct ArchiveReplayer<R: IngestEventRepository> {
+    ingest_repository: R,
+    filter: ArchiveReplayFilter,
+    batch_size: usize,
+}
+
+impl<R: IngestEventRepository> ArchiveReplayer<R> {
+    /// `ArchiveReplayer` constructor that saves to `ingest_repository`
+    pub fn new(ingest_repository: R) -> Self {
+        Self {
+            ingest_repository,
+            filter: ArchiveReplayFilter::default(),
+            batch_size: DEFAULT_REPLAY_BATCH_SIZE,
+        }
+    }
+
+    /// Only replay the records selected by `filter`
+    pub fn with_filter(mut self, filter: ArchiveReplayFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Number of events saved per batch, which must be at least one
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Replay the archive at `path`, which is either the directory of a
+    /// rotating archive or a single JSON Lines archive file
+    pub async fn replay(&self, path: &Path) -> Result<ArchiveReplaySummary, ArchiveReplayError> {
+        let mut summary = ArchiveReplaySummary::default();
+        let mut batch = Vec::with_capacity(self.batch_size);
+        if path.is_file() {
+            match ArchiveFileFormat::Jsonl.decode(std::fs::read(path)?) {
+                Ok(records) => {
+                    summary.files += 1;
+                    self.replay_records(records, &mut batch, &mut summary)
+                        .await?;
+                }
+                Err(_) => summary.corrupt_files.push(path.to_owned()),
+            }
+        } else {
+            let (manifests, unsealed) = list_archive(path)?;
+            summary.unsealed_files = unsealed;
+            for manifest_path in manifests {
+                self.replay_file(&manifest_path, &mut batch, &mut summary)
+                    .await?;
+            }
+        }
+        self.save_batch(&mut batch, &mut summary).await?;
+        Ok(summary)
+    }
+
+    /// Replay the file of the manifest at `manifest_path`, unless it is left
+    /// out by the filter or fails verification
+    async fn replay_file(
+        &self,
+        manifest_path: &Path,
+        batch: &mut Vec<IngestEvent>,
+        summary: &mut ArchiveReplaySummary,
+    ) -> Result<(), ArchiveReplayError> {
+        let Some(manifest) = std::fs::read(manifest_path)
+            .ok()
+            .and_then(|manifest| serde_json::from_slice::<ArchiveManifest>(&manifest).ok())
+        else {
+            tracing::error!("Invalid archive manifest {}", manifest_path.display());
+            summary.corrupt_files.push(manifest_path.to_owned());
+            return Ok(());
+        };
+        if !self.filter.matches_manifest(&manifest) {
+            summary.skipped_files += 1;
+            return Ok(());
+        }
+        let file_path = manifest_path.with_file_name(&manifest.file);
+        let records = std::fs::read(&file_path)
+            .ok()
+            .filter(|contents| manifest.verify(contents))
+            .and_then(|contents| manifest.format.decode(contents).ok())
+            .filter(|records| records.len() as u64 == manifest.records);
+        let Some(records) = records else {
+            tracing::error!(
+                "Archive file {} does not match its manifest",
+                file_path.display()
+            );
+            summary.corrupt_files.push(file_path);
+            return Ok(());
+        };
+        summary.files += 1;
+        self.replay_records(records, batch, summary).await
+    }
+
+    async fn replay_records(
+        &self,
+        records: Vec<SinkEventRecord>,
+        batch: &mut Vec<IngestEvent>,
+        summary: &mut ArchiveReplaySummary,
+    ) -> Result<(), ArchiveReplayError> {
+        for record in records {
+            if !self.filter.matches_record(&record) {
+                continue;
+            }
+            match IngestEvent::try_from(&record) {
+                Ok(event) => batch.push(event),
+                Err(_) => {
+                    summary.rejected += 1;
+                    continue;
+                }
+            }
+            if batch.len() >= self.batch_size {
+                self.save_batch(batch, summary).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn save_batch(
+        &self,
+        batch: &mut Vec<IngestEvent>,
+        summary: &mut ArchiveReplaySummary,
+    ) -> Result<(), ArchiveReplayError> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        let IngestActionSummary::Save(save_summary) =
+            self.ingest_repository.save(std::mem::take(batch)).await?;
+        summary.accepted += save_summary.event_count as u64;
+        summary.duplicates += save_summary.duplicate_count as u64;
+        summary.batches += 1;
+        Ok(())
+    }
+}
+
+/// List the manifests below `dir` along with the files that were never
+/// closed, both sorted by path so that files are replayed in the order they
+/// were written within each partition
+fn list_archive(dir: &Path) -> io::Result<(Vec<PathBuf>, Vec<PathBuf>)> {
+    let mut manifests = Vec::new();
+    let mut unsealed = Vec::new();
+    let mut dirs = vec![dir.to_owned()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let name = path.to_string_lossy();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if name.ends_with(ARCHIVE_MANIFEST_SUFFIX) {
+                manifests.push(path);
+            } else if name.ends_with(ARCHIVE_IN_PROGRESS_SUFFIX) {
+                unsealed.push(path);
+            }
+        }
+    }
+    manifests.sort();
+    unsealed.sort();
+    Ok((manifests, unsealed))
+}
+
+#[cfg(test)]
+mod tests {
+    use time::OffsetDateTime;
+    use uuid::Uuid;
+
+    use crate::{
+        domain::{
+            model::ingest_event::{ApiKey, Site, VisitorEvent},
+            repository::event_sink::EventSink,
+        },
+        repositories::{
+            memory_ingest_repository::{MemoryEventQuery, MemoryIngestRepository},
+            rotating_archive_sink::RotatingArchiveSink,
+        },
+    };
+
+    use super::*;
+
+    fn visitor_event(site: &str) -> IngestEvent {
+        IngestEvent::Visitor(
+            VisitorEvent::try_new(ApiKey::new("abc-123"), Site::new(site), Uuid::now_v7()).unwrap(),
+        )
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_archive_replay() {
+        let dir = std::env::temp_dir().join(Uuid::now_v7().to_string());
+        let sink = RotatingArchiveSink::new(&dir, ArchiveFileFormat::Jsonl);
+        let events = vec![
+            visitor_event("test.com"),
+            visitor_event("test.com"),
+            visitor_event("other.com"),
+        ];
+        sink.write(&events).await.unwrap();
+        sink.close_all().await.unwrap();
+        sink.write(&[visitor_event("test.com")]).await.unwrap();
+
+        let memory_repository = MemoryIngestRepository::new(10);
+        let summary = ArchiveReplayer::new(memory_repository.clone())
+            .with_filter(ArchiveReplayFilter {
+                site: Some("test.com".to_owned()),
+                ..ArchiveReplayFilter::default()
+            })
+            .with_batch_size(1)
+            .replay(&dir)
+            .await
+            .unwrap();
+        assert_eq!(summary.files, 1);
+        assert_eq!(summary.skipped_files, 1);
+        assert_eq!(summary.accepted, 2);
+        assert_eq!(summary.batches, 2);
+        assert_eq!(
+            summary.unsealed_files.len(),
+            1,
+            "Expected the open file to be reported"
+        );
+        assert_eq!(
+            memory_repository
+                .events(&MemoryEventQuery::default())
+                .iter()
+                .rev()
+                .map(|record| record.id)
+                .collect::<Vec<Uuid>>(),
+            vec![events[0].id(), events[1].id()]
+        );
+        let replayed_again = ArchiveReplayer::new(memory_repository.clone())
+            .with_filter(ArchiveReplayFilter {
+                site: Some("test.com".to_owned()),
+                ..ArchiveReplayFilter::default()
+            })
+            .replay(&dir)
+            .await
+            .unwrap();
+        assert_eq!(
+            (replayed_again.accepted, replayed_again.duplicates),
+            (0, 2),
+            "Expected events that are already stored to be counted as duplicates"
+        );
+
+        // Negative test case
+        let tomorrow = OffsetDateTime::now_utc().date().next_day();
+        let summary = ArchiveReplayer::new(MemoryIngestRepository::new(10))
+            .with_filter(ArchiveReplayFilter {
+                from: tomorrow,
+                ..ArchiveReplayFilter::default()
+            })
+            .replay(&dir)
+            .await
+            .unwrap();
+        assert_eq!(summary.accepted, 0);
+        assert_eq!(summary.skipped_files, 2);
+        for manifest_path in list_archive(&dir).unwrap().0 {
+            let manifest: ArchiveManifest =
+                serde_json::from_slice(&std::fs::read(&manifest_path).unwrap()).unwrap();
+            std::fs::write(manifest_path.with_file_name(&manifest.file), b"{}\n").unwrap();
+        }
+        let summary = ArchiveReplayer::new(MemoryIngestRepository::new(10))
+            .replay(&dir)
+            .await
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            summary.corrupt_files.len(),
+            2,
+            "Expected changed files to fail verification"
+        );
+        assert_eq!(summary.accepted, 0);
+    }
+}
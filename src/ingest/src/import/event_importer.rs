@@ -0,0 +1,342 @@
+use std::io::{self, BufRead};
+
+use thiserror::Error;
+
+use crate::{
+    domain::{
+        model::{
+            ingest_action_summary::IngestActionSummary,
+            ingest_event::{ApiKey, IngestEvent, IngestEventOrigin, IngestEventSource, Site},
+            server_key::ServerKey,
+        },
+        service::ingest_event_service::{IngestEventService, IngestServiceError},
+    },
+    http_api::model::client_event_request::ClientEventRequestError,
+};
+
+use super::{
+    import_checkpoint::ImportCheckpoint, import_event_record::ImportEventRecord,
+    import_summary::ImportSummary,
+};
+
+/// Default number of events written per call to `IngestEventService::save`
+pub const DEFAULT_IMPORT_BATCH_SIZE: usize = 10_000;
+
+/// `ImportError` represents failures that stop an import run. Individual lines
+/// that cannot be imported are not errors, they are recorded as rejections in
+/// the `ImportSummary` instead.
+#[derive(Debug, Error)]
+pub enum ImportError {
+    /// The api_key / site combination is not a configured `IngestEventSource`
+    #[error("Unknown event source")]
+    UnknownSource,
+    /// The `ServerKey` is not valid for the api_key of the source
+    #[error("Missing or invalid server key")]
+    Unauthorized,
+    /// Reading the input or writing the checkpoint failed
+    #[error("Error reading import data or checkpoint: {0}")]
+    Io(#[from] io::Error),
+    /// Saving a batch failed. All batches before it have been checkpointed.
+    #[error("Error saving imported events: {0}")]
+    IngestService(#[from] IngestServiceError),
+}
+
+/// `ImportLineParser` turns a single line of import input into zero or more
+/// `IngestEvent`s, or the reason the line was rejected
+pub trait ImportLineParser {
+    /// Parse a single, non-blank input line
+    fn parse_line(&mut self, line: &str) -> Result<Vec<IngestEvent>, String>;
+
+    /// Parsers that carry state from one line to the next must see the lines
+    /// before the checkpoint again in order to resume with the same state.
+    /// The events of those lines are discarded.
+    fn replays_skipped_lines(&self) -> bool {
+        false
+    }
+}
+
+/// `ImportLineParser` for JSON Lines of `ImportEventRecord`
+struct ImportEventRecordParser {
+    api_key: ApiKey,
+    site: Site,
+}
+
+impl ImportLineParser for ImportEventRecordParser {
+    fn parse_line(&mut self, line: &str) -> Result<Vec<IngestEvent>, String> {
+        let record: ImportEventRecord =
+            serde_json::from_str(line).map_err(|_| "Invalid JSON record".to_owned())?;
+        record
+            .into_server_request(&self.api_key, &self.site)
+            .try_into_event(IngestEventOrigin::Import)
+            .map(|event| vec![event])
+            .map_err(|e| match e {
+                ClientEventRequestError::IngestEvent(e) => e.to_string(),
+                e => e.to_string(),
+            })
+    }
+}
+
+/// `EventImporter` backfills historical events for a single
+/// `IngestEventSource`. Events are tagged with `IngestEventOrigin::Import`,
+/// which accepts arbitrarily old UUIDv7 timestamps, and are saved in large
+/// batches through the `IngestEventService` so that the source rules apply
+/// exactly as they do for live events.
+#[derive(Debug)]
+pub struct EventImporter<I: IngestEventService> {
+    ingest_service: I,
+    api_key: ApiKey,
+    site: Site,
+    batch_size: usize,
+}
+
+impl<I: IngestEventService> EventImporter<I> {
+    /// `EventImporter` constructor. Importing is restricted to holders of a
+    /// `ServerKey` for the api_key, and the source must be configured.
+    pub async fn try_new(
+        ingest_service: I,
+        api_key: ApiKey,
+        site: Site,
+        server_key: &ServerKey,
+    ) -> Result<Self, ImportError> {
+        if !ingest_service
+            .is_server_key_valid(&api_key, server_key)
+            .await?
+        {
+            return Err(ImportError::Unauthorized);
+        }
+        let source = IngestEventSource::new(api_key.clone(), site.clone());
+        if !ingest_service.event_sources().await?.contains(&source) {
+            return Err(ImportError::UnknownSource);
+        }
+        Ok(Self {
+            ingest_service,
+            api_key,
+            site,
+            batch_size: DEFAULT_IMPORT_BATCH_SIZE,
+        })
+    }
+
+    /// `ApiKey` of the source that events are imported for
+    pub fn api_key(&self) -> &ApiKey {
+        &self.api_key
+    }
+
+    /// `Site` of the source that events are imported for
+    pub fn site(&self) -> &Site {
+        &self.site
+    }
+
+    /// Replace the number of events written per batch. A size of 0 is
+    /// treated as 1.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Import every line of `reader` as an `ImportEventRecord` in JSON Lines
+    /// format. See `import_with` for how the checkpoint is handled.
+    pub async fn import<R: BufRead>(
+        &self,
+        reader: R,
+        checkpoint: &mut ImportCheckpoint,
+    ) -> Result<ImportSummary, ImportError> {
+        let mut parser = ImportEventRecordParser {
+            api_key: self.api_key.clone(),
+            site: self.site.clone(),
+        };
+        self.import_with(reader, &mut parser, checkpoint).await
+    }
+
+    /// Import every line of `reader` using the given `ImportLineParser`.
+    /// Lines up to `checkpoint` are skipped, and the checkpoint is advanced
+    /// after each saved batch so that a failed run can be resumed from the
+    /// last saved batch. Blank lines are ignored.
+    pub async fn import_with<R: BufRead, P: ImportLineParser>(
+        &self,
+        reader: R,
+        parser: &mut P,
+        checkpoint: &mut ImportCheckpoint,
+    ) -> Result<ImportSummary, ImportError> {
+        let mut summary = ImportSummary::default();
+        let mut batch: Vec<IngestEvent> = Vec::with_capacity(self.batch_size);
+        let mut line_number: u64 = 0;
+        for line in reader.split(b'\n') {
+            let line = line?;
+            line_number += 1;
+            let skipped = line_number <= checkpoint.line();
+            if skipped {
+                summary.skipped += 1;
+                if !parser.replays_skipped_lines() {
+                    continue;
+                }
+            }
+            let Ok(line) = std::str::from_utf8(&line) else {
+                if !skipped {
+                    summary.reject(line_number, "Invalid UTF-8");
+                }
+                continue;
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            match parser.parse_line(line) {
+                Ok(_) if skipped => {}
+                Ok(events) if events.is_empty() => summary.filtered += 1,
+                Ok(events) => batch.extend(events),
+                Err(_) if skipped => {}
+                Err(reason) => summary.reject(line_number, reason),
+            }
+            if batch.len() >= self.batch_size {
+                self.save_batch(&mut batch, &mut summary).await?;
+                checkpoint.try_commit(line_number)?;
+            }
+        }
+        if !batch.is_empty() {
+            self.save_batch(&mut batch, &mut summary).await?;
+        }
+        if line_number > checkpoint.line() {
+            checkpoint.try_commit(line_number)?;
+        }
+        Ok(summary)
+    }
+
+    async fn save_batch(
+        &self,
+        batch: &mut Vec<IngestEvent>,
+        summary: &mut ImportSummary,
+    ) -> Result<(), ImportError> {
+        let events = std::mem::replace(batch, Vec::with_capacity(self.batch_size));
+        let IngestActionSummary::Save(save_summary) = self.ingest_service.save(events).await?;
+        summary.accepted += save_summary.event_count as u64;
+        summary.duplicates += save_summary.duplicate_count as u64;
+        summary.batches += 1;
+        tracing::info!(
+            "Imported batch {} with {} events and {} duplicates",
+            summary.batches,
+            save_summary.event_count,
+            save_summary.duplicate_count
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::io::Cursor;
+
+    use uuid::{Timestamp, Uuid};
+
+    use super::*;
+    use crate::{
+        domain::{
+            model::{
+                ingest_action_summary::IngestEventSaveSummary,
+                ingest_source_rules::IngestSourceRules,
+            },
+            repository::ingest_event_repository::test::MockIngestEventRepository,
+        },
+        services::ingest_service::IngestService,
+    };
+
+    const API_KEY: &str = "abc_123";
+    const SITE: &str = "test.com";
+
+    fn mock_service(server_key_valid: bool) -> IngestService<MockIngestEventRepository> {
+        IngestService::new(MockIngestEventRepository {
+            save_result: Ok(IngestActionSummary::Save(IngestEventSaveSummary {
+                event_count: 2,
+                duplicate_count: 0,
+            })),
+            event_source_result: Ok(HashSet::from([IngestEventSource::new(
+                ApiKey::new(API_KEY),
+                Site::new(SITE),
+            )])),
+            source_rules_result: Ok(IngestSourceRules::default()),
+            server_key_result: Ok(server_key_valid),
+        })
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_try_new() {
+        let server_key = ServerKey::new("secret");
+        let Err(ImportError::Unauthorized) = EventImporter::try_new(
+            mock_service(false),
+            ApiKey::new(API_KEY),
+            Site::new(SITE),
+            &server_key,
+        )
+        .await
+        else {
+            panic!("Expected invalid server key to be refused");
+        };
+        let Err(ImportError::UnknownSource) = EventImporter::try_new(
+            mock_service(true),
+            ApiKey::new(API_KEY),
+            Site::new("other.com"),
+            &server_key,
+        )
+        .await
+        else {
+            panic!("Expected unknown source to be refused");
+        };
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_import() {
+        let (ts_now, _) = Uuid::now_v7().get_timestamp().unwrap().to_unix();
+        let last_year = Uuid::new_v7(Timestamp::from_unix_time(ts_now - 31_536_000, 0, 0, 8));
+        let visitor_line = format!(r#"{{"t":1,"i":"{last_year}"}}"#);
+        let section_line = format!(
+            r#"{{"event_type":3,"id":"{}","attrs":{{"p":"{last_year}","l":"https://test.com/"}}}}"#,
+            Uuid::new_v7(Timestamp::from_unix_time(ts_now - 31_535_000, 0, 0, 8))
+        );
+        let input = [
+            visitor_line.as_str(),
+            "",
+            "{not json",
+            section_line.as_str(),
+            r#"{"t":1,"i":"4e2abe52-5e86-4023-9f8b-34eba8d2cc59"}"#,
+            visitor_line.as_str(),
+        ]
+        .join("\n");
+
+        let importer = EventImporter::try_new(
+            mock_service(true),
+            ApiKey::new(API_KEY),
+            Site::new(SITE),
+            &ServerKey::new("secret"),
+        )
+        .await
+        .unwrap()
+        .with_batch_size(2);
+        let mut checkpoint = ImportCheckpoint::new();
+        let summary = importer
+            .import(Cursor::new(input.clone()), &mut checkpoint)
+            .await
+            .unwrap();
+        assert_eq!(summary.accepted, 2, "Expected 2 events to be accepted");
+        assert_eq!(
+            summary.duplicates, 1,
+            "Expected the repeated visitor to be counted as a duplicate"
+        );
+        assert_eq!(summary.rejected, 2, "Expected 2 lines to be rejected");
+        assert_eq!(summary.batches, 2, "Expected events to be saved in batches");
+        assert_eq!(summary.rejected_lines, vec![3, 5]);
+        assert_eq!(summary.rejections["Invalid JSON record"], 1);
+        assert_eq!(
+            summary.rejections["UUID version mismatch - must be UUIDv7"],
+            1
+        );
+        assert_eq!(checkpoint.line(), 6, "Expected checkpoint at end of input");
+
+        // Resuming after the end of the input imports nothing further
+        let resumed = importer
+            .import(Cursor::new(input), &mut checkpoint)
+            .await
+            .unwrap();
+        assert_eq!(resumed.skipped, 6);
+        assert_eq!(resumed.accepted, 0);
+        assert_eq!(resumed.batches, 0);
+    }
+}
@@ -0,0 +1,83 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// `ImportCheckpoint` records the number of input lines that have been fully
+/// handled by an import, so that an interrupted import can be resumed without
+/// writing the same events twice. When backed by a file, the checkpoint is
+/// persisted every time a batch has been saved.
+#[derive(Debug, Default)]
+pub struct ImportCheckpoint {
+    path: Option<PathBuf>,
+    line: u64,
+}
+
+impl ImportCheckpoint {
+    /// `ImportCheckpoint` that is only held in memory, i.e. a fresh import
+    /// that cannot be resumed
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load the checkpoint stored at `path`. A missing file is treated as an
+    /// import that has not yet started.
+    pub fn try_load(path: impl AsRef<Path>) -> Result<Self, io::Error> {
+        let path = path.as_ref().to_path_buf();
+        let line = match fs::read_to_string(&path) {
+            Ok(contents) => contents
+                .trim()
+                .parse::<u64>()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(e),
+        };
+        Ok(Self {
+            path: Some(path),
+            line,
+        })
+    }
+
+    /// Number of input lines that were handled by previous runs
+    pub fn line(&self) -> u64 {
+        self.line
+    }
+
+    /// Record that all input up to and including `line` has been handled.
+    /// The file is replaced atomically so that a crash cannot leave a
+    /// partially written checkpoint behind.
+    pub fn try_commit(&mut self, line: u64) -> Result<(), io::Error> {
+        if let Some(path) = &self.path {
+            let mut tmp_path = path.clone().into_os_string();
+            tmp_path.push(".tmp");
+            fs::write(&tmp_path, line.to_string())?;
+            fs::rename(&tmp_path, path)?;
+        }
+        self.line = line;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_roundtrip() {
+        let path =
+            std::env::temp_dir().join(format!("salus_import_checkpoint_{}", uuid::Uuid::now_v7()));
+        let mut checkpoint = ImportCheckpoint::try_load(&path).unwrap();
+        assert_eq!(checkpoint.line(), 0, "Expected missing file to start at 0");
+
+        checkpoint.try_commit(42).unwrap();
+        let resumed = ImportCheckpoint::try_load(&path).unwrap();
+        assert_eq!(resumed.line(), 42, "Expected committed line to persist");
+
+        fs::write(&path, "not a number").unwrap();
+        assert!(
+            ImportCheckpoint::try_load(&path).is_err(),
+            "Expected corrupt checkpoint to be refused"
+        );
+        fs::remove_file(&path).unwrap();
+    }
+}
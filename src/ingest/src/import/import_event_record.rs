@@ -0,0 +1,47 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::domain::model::ingest_event::{ApiKey, Site};
+use crate::http_api::model::{
+    client_event_request::ClientEventRequestType, server_event_request::ServerEventRequest,
+    server_event_request_components::ServerEventRequestBody,
+};
+
+/// `ImportEventRecord` represents a single line of a JSON Lines import file.
+/// The fields and their aliases match `ServerEventRequestBody`, except that
+/// the `site` is omitted since every import run is for a single source. The
+/// `id` must be a UUIDv7 that carries the original timestamp of the event.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ImportEventRecord {
+    #[serde(alias = "t")]
+    pub event_type: ClientEventRequestType,
+    #[serde(alias = "i")]
+    #[serde(with = "clickhouse::serde::uuid")]
+    pub id: Uuid,
+    #[serde(alias = "a")]
+    pub attrs: Option<HashMap<String, String>>,
+    pub ip: Option<IpAddr>,
+    #[serde(alias = "ua")]
+    pub user_agent: Option<String>,
+}
+
+impl ImportEventRecord {
+    /// Attach the source of the import run so that the record can be
+    /// translated with the same rules as a `ServerEventRequest`
+    pub fn into_server_request(self, api_key: &ApiKey, site: &Site) -> ServerEventRequest {
+        ServerEventRequest {
+            api_key: api_key.value().to_owned(),
+            body: ServerEventRequestBody {
+                event_type: self.event_type,
+                id: self.id,
+                site: site.value().to_owned(),
+                attrs: self.attrs,
+                ip: self.ip,
+                user_agent: self.user_agent,
+            },
+        }
+    }
+}
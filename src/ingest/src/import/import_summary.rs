@@ -0,0 +1,42 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+/// Maximum number of rejected line numbers kept for the report. Counts per
+/// reason are always complete.
+pub const MAX_REPORTED_REJECTED_LINES: usize = 1000;
+
+/// `ImportSummary` is the report produced by an import run. Lines skipped
+/// because of a checkpoint from a previous run are counted separately and are
+/// not included in the accepted or rejected totals.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct ImportSummary {
+    /// Lines that were already handled by a previous run
+    pub skipped: u64,
+    /// Events that were saved
+    pub accepted: u64,
+    /// Events that were not saved again because they had already been saved
+    pub duplicates: u64,
+    /// Lines that could not be turned into a valid event
+    pub rejected: u64,
+    /// Valid lines that intentionally produced no events, such as requests
+    /// for static assets in an access log
+    pub filtered: u64,
+    /// Number of batches that were saved
+    pub batches: u64,
+    /// Rejected line counts keyed by the reason for rejection
+    pub rejections: BTreeMap<String, u64>,
+    /// Line numbers of the first rejected lines
+    pub rejected_lines: Vec<u64>,
+}
+
+impl ImportSummary {
+    /// Record a rejected `line` for the given `reason`
+    pub fn reject(&mut self, line: u64, reason: impl ToString) {
+        self.rejected += 1;
+        *self.rejections.entry(reason.to_string()).or_default() += 1;
+        if self.rejected_lines.len() < MAX_REPORTED_REJECTED_LINES {
+            self.rejected_lines.push(line);
+        }
+    }
+}
@@ -0,0 +1,7 @@
+pub mod access_log_entry;
+pub mod access_log_parser;
+pub mod archive_replay;
+pub mod event_importer;
+pub mod import_checkpoint;
+pub mod import_event_record;
+pub mod import_summary;
@@ -3,12 +3,52 @@
 //! on the CORS accept list for this particular setup.
 //!
 //! `bin/ingest_server.rs` can be run as a HTTP server and relies on axum
-//! for all HTTP handling. Configuration strictly follows the 12 factor
-//! approach with ENV variables used to specify all configuration options.
+//! for all HTTP handling. The same listener serves the gRPC service of
+//! `proto/salus/ingest/v1/ingest.proto` for trusted backends. Configuration
+//! strictly follows the 12 factor approach with ENV variables used to specify
+//! all configuration options.
+//! `bin/ingest_import.rs` backfills historical events from a JSON Lines file
+//! using the same configuration as the server, and
+//! `bin/ingest_import_access_log.rs` does the same for page views in nginx or
+//! Apache access logs.
 //!
 //! All ENV variables are prefixed with `SALUS_INGEST_` and use the `conf`
 //! crate for getting all configuration. The list of possible settings for
 //! this app are as follows:
+//! - `SALUS_INGEST_BACKEND_KIND` - OPTIONAL - `Clickhouse`, `Postgres` or
+//!   `Sqlite` to select the database in which events are stored. Defaults to
+//!   `Clickhouse`. `Postgres` and `Sqlite` require the cargo feature of the
+//!   same name in lowercase
+//! - `SALUS_INGEST_BACKEND_PATH` - REQUIRED for SQLite only - path of the
+//!   database file, which is created if it does not exist
+//! - `SALUS_INGEST_BACKEND_TIMESCALE` - OPTIONAL - values of `true` or `false`
+//!   to make the PostgreSQL `event` table a TimescaleDB hypertable
+//! - `SALUS_INGEST_BACKEND_URL` - REQUIRED for PostgreSQL only - connection
+//!   string of the PostgreSQL database
+//! - `SALUS_INGEST_DEADLETTER_ADMINKEY` - REQUIRED for the dead-letter store
+//!   only - key that `/admin/deadletters` must be called with as a bearer token
+//! - `SALUS_INGEST_DEADLETTER_CAPACITY` - OPTIONAL - Integer maximum number of
+//!   rejected requests kept per site. Setting any `DEADLETTER` variable turns
+//!   on the dead-letter store. Defaults to 100
+//! - `SALUS_INGEST_DEADLETTER_MAXBODY` - OPTIONAL - Integer maximum number of
+//!   bytes kept of each rejected body. Defaults to 16384
+//! - `SALUS_INGEST_DEADLETTER_SAMPLE` - OPTIONAL - Integer N to keep one in
+//!   every N rejected requests of a site. Defaults to 1
+//! - `SALUS_INGEST_DEDUPE_SECS` - OPTIONAL - Integer number of seconds for
+//!   which the ids of saved events are remembered, so that events submitted
+//!   again within that time are skipped as duplicates. Defaults to one hour
+//! - `SALUS_INGEST_DEV_CAPACITY` - OPTIONAL - Integer maximum number of events
+//!   kept in memory in dev mode. Defaults to 10000
+//! - `SALUS_INGEST_DEV_MODE` - OPTIONAL - values of `true` or `false` to keep
+//!   events in memory instead of in the configured backend and to serve them
+//!   at `/dev/events`. Never to be used in production
+//! - `SALUS_INGEST_DEV_SERVERKEY` - OPTIONAL - server key that is accepted for
+//!   every api key in dev mode
+//! - `SALUS_INGEST_DEV_SOURCES` - OPTIONAL - space separated list of
+//!   `api_key:site` pairs that events are accepted from in dev mode. Defaults
+//!   to accepting every source
+//! - `SALUS_INGEST_IMPORT_SERVER_KEY` - REQUIRED for the import binaries only -
+//!   server key that authorizes the import for the given api key
 //! - `SALUS_INGEST_LAYER_COMPRESSION_DEFLATE` - OPTIONAL - values of `true` or `false` to
 //!   enable or disable deflate compression. If neither this nor gzip are set,
 //!   both default to true.
@@ -20,28 +60,81 @@
 //!   an HTTP OPTIONS call for every CORS request will be used.
 //! - `SALUS_INGEST_LAYER_CORS_ORIGINS` - REQUIRED - list of strings that specify
 //!   the domains that are allowed to submite CORS requests to this server.
+//! - `SALUS_INGEST_LAYER_DECOMPRESSION_BR`, `SALUS_INGEST_LAYER_DECOMPRESSION_DEFLATE`,
+//!   `SALUS_INGEST_LAYER_DECOMPRESSION_GZIP` and
+//!   `SALUS_INGEST_LAYER_DECOMPRESSION_ZSTD` - OPTIONAL - values of `true` or
+//!   `false` to accept or reject request bodies with that `Content-Encoding`.
+//!   Each defaults to true. Any other encoding is rejected with 415.
+//! - `SALUS_INGEST_LAYER_DECOMPRESSION_LIMIT` - OPTIONAL - Integer maximum
+//!   number of bytes that a request body may expand to once decompressed.
+//!   Larger bodies are rejected with 413. Defaults to 8 MiB
 //! - `SALUS_INGEST_LAYER_TIMEOUT_MILLIS` - OPTIONAL - Integer accepted to
 //!   specify the timeout for all requests. If no value is provided, default of
 //!   30 seconds will be used.
+//! - `SALUS_INGEST_LIMIT_ATTRS` - OPTIONAL - Integer maximum number of attrs
+//!   of a single event. Defaults to 64
+//! - `SALUS_INGEST_LIMIT_BODY` - OPTIONAL - Integer maximum number of bytes of
+//!   a request body as it is received, before decompression. Larger bodies are
+//!   rejected with 413. Defaults to 1 MiB
+//! - `SALUS_INGEST_LIMIT_EVENTS` - OPTIONAL - Integer maximum number of events
+//!   in a single request to `/multi` or `/server/multi`, message to `/ws` or
+//!   gRPC batch, and per chunk saved from `/server/ndjson`. Larger batches are
+//!   rejected with 413. Defaults to 500
+//! - `SALUS_INGEST_LIMIT_LOCATION` and `SALUS_INGEST_LIMIT_TITLE` - OPTIONAL -
+//!   Integer maximum number of characters in the location and title of an
+//!   event. Default to 2048 and 1024
 //! - `SALUS_INGEST_LISTENER_IPV4` or `SALUS_INGEST_LISTENER_IPV6` - OPTIONAL -
 //!   These are mutually exclusive and an error will be returned if both are set.
 //!   The value determines the IP address on which the server will listen. If
 //!   neither value is provided, then the IPv4 `0.0.0.0` will be used.
 //! - `SALUS_INGEST_LISTENER_PORT` - REQUIRED - Integer value accepted to
 //!   specify the port on which this server will listen and respond to requests
-//! - `SALUS_INGEST_METRICSDB_DATABASE` - REQUIRED - Name of the metrics
-//!   database in the specified Clickhouse instance in which data should be
-//!   stored
-//! - `SALUS_INGEST_METRICSDB_PASS` - REQUIRED - Password to log into the
+//! - `SALUS_INGEST_METRICSDB_DATABASE` - REQUIRED for ClickHouse - Name of
+//!   the metrics database in the specified Clickhouse instance in which data
+//!   should be stored
+//! - `SALUS_INGEST_METRICSDB_PASS` - REQUIRED for ClickHouse - Password to log
+//!   into the associated Clickhouse instance
+//! - `SALUS_INGEST_METRICSDB_URL` - REQUIRED for ClickHouse - URL for the
 //!   associated Clickhouse instance
-//! - `SALUS_INGEST_METRICSDB_URL` - REQUIRED - URL for the associated
-//!   Clickhouse instance
-//! - `SALUS_INGEST_METRICSDB_USER` - REQUIRED - User on Clickhouse instance
-//!   that should be used for recording data
+//! - `SALUS_INGEST_METRICSDB_USER` - REQUIRED for ClickHouse - User on
+//!   Clickhouse instance that should be used for recording data
+//! - `SALUS_INGEST_RELOAD_SECS` - OPTIONAL - Integer number of seconds between
+//!   reloads of the api keys and per-site rules from Clickhouse. When not set,
+//!   these are only loaded at startup
+//! - `SALUS_INGEST_SCRUB_PATTERNS` - OPTIONAL - space separated list of regex
+//!   patterns that are redacted from section locations and titles, session
+//!   referrers and custom event locations and properties before they are
+//!   stored. When set, this list replaces the default patterns for emails,
+//!   card-like numbers and long hex or base64 tokens.
+//! - `SALUS_INGEST_SINK_ARCHIVE_DIR` - OPTIONAL - directory within which every
+//!   saved event is archived to files partitioned by site and date, which are
+//!   closed with a manifest once they reach their maximum size or age. Cannot
+//!   be combined with `SALUS_INGEST_SINK_ARCHIVE_PATH`
+//! - `SALUS_INGEST_SINK_ARCHIVE_FORMAT` - OPTIONAL - `Jsonl` or `Parquet`, the
+//!   format of the files within the archive directory. Defaults to `Jsonl`,
+//!   and `Parquet` requires the `parquet` feature
+//! - `SALUS_INGEST_SINK_ARCHIVE_MAXBYTES` - OPTIONAL - Integer size in bytes
+//!   at which an archive file is closed. Defaults to 134217728 (128 MiB)
+//! - `SALUS_INGEST_SINK_ARCHIVE_MAXSECS` - OPTIONAL - Integer number of seconds
+//!   after which an archive file is closed. Defaults to 3600
+//! - `SALUS_INGEST_SINK_ARCHIVE_PATH` - OPTIONAL - path of a file to which
+//!   every saved event is appended as a line of JSON
+//! - `SALUS_INGEST_SINK_ARCHIVE_POLICY` and `SALUS_INGEST_SINK_WEBHOOK_POLICY` -
+//!   OPTIONAL - one of `Required`, `BestEffort` or `Async`, determining whether
+//!   a failure of the sink fails the save. Default to `BestEffort` for the
+//!   archive and `Async` for the webhook
+//! - `SALUS_INGEST_SINK_WEBHOOK_URL` - OPTIONAL - `https` URL to which every
+//!   saved batch of events is POSTed as a JSON array. `http` is only accepted
+//!   for the local host
+//! - `SALUS_INGEST_SYNTHETIC_VISITORKEY` - OPTIONAL - secret from which the
+//!   ids of visitors synthesized for Plausible, Measurement Protocol and
+//!   Segment events are derived. Defaults to an empty key
 //! - `SALUS_INGEST_TRACING` - OPTIONAL - string which must be a valid tracing
 //!   subscriber directive. Defaults to `error` if no value is provided
 
 pub mod domain;
+pub mod grpc_api;
 pub mod http_api;
+pub mod import;
 pub mod repositories;
 pub mod services;
@@ -0,0 +1,201 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use time::{Date, OffsetDateTime, format_description::well_known::Iso8601};
+
+use crate::domain::repository::ingest_event_repository::IngestRepositoryError;
+
+use super::sink_event_record::SinkEventRecord;
+
+/// Suffix of the `ArchiveManifest` written next to every closed archive file
+pub const ARCHIVE_MANIFEST_SUFFIX: &str = ".manifest.json";
+
+/// Suffix of an archive file or manifest that is still being written. Files
+/// left with this suffix were not closed and have no manifest.
+pub const ARCHIVE_IN_PROGRESS_SUFFIX: &str = ".inprogress";
+
+/// `ArchiveFileFormat` is the format of the files of a rotating archive. Both
+/// formats hold `SinkEventRecord`s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveFileFormat {
+    /// A line of JSON for every record
+    Jsonl,
+    /// Parquet with a column for every field of the record, which requires
+    /// the `parquet` feature
+    Parquet,
+}
+
+impl ArchiveFileFormat {
+    /// File name extension of the format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ArchiveFileFormat::Jsonl => "jsonl",
+            ArchiveFileFormat::Parquet => "parquet",
+        }
+    }
+
+    /// Encode the records as the contents of a complete file
+    pub fn encode(&self, records: &[SinkEventRecord]) -> Result<Vec<u8>, IngestRepositoryError> {
+        match self {
+            ArchiveFileFormat::Jsonl => {
+                let mut contents = Vec::new();
+                for record in records {
+                    serde_json::to_writer(&mut contents, record).map_err(|e| {
+                        tracing::error!("Error serializing archive record: {e}");
+                        IngestRepositoryError::Conversion
+                    })?;
+                    contents.push(b'\n');
+                }
+                Ok(contents)
+            }
+            #[cfg(feature = "parquet")]
+            ArchiveFileFormat::Parquet => super::parquet_archive_file::encode(records),
+            #[cfg(not(feature = "parquet"))]
+            ArchiveFileFormat::Parquet => {
+                tracing::error!("Parquet archives require the `parquet` feature");
+                Err(IngestRepositoryError::InvalidRequest)
+            }
+        }
+    }
+
+    /// Decode the records from the contents of a complete file
+    pub fn decode(&self, contents: Vec<u8>) -> Result<Vec<SinkEventRecord>, IngestRepositoryError> {
+        match self {
+            ArchiveFileFormat::Jsonl => contents
+                .split(|byte| *byte == b'\n')
+                .filter(|line| !line.is_empty())
+                .map(|line| {
+                    serde_json::from_slice(line).map_err(|e| {
+                        tracing::error!("Error parsing archive record: {e}");
+                        IngestRepositoryError::Conversion
+                    })
+                })
+                .collect(),
+            #[cfg(feature = "parquet")]
+            ArchiveFileFormat::Parquet => super::parquet_archive_file::decode(contents),
+            #[cfg(not(feature = "parquet"))]
+            ArchiveFileFormat::Parquet => {
+                tracing::error!("Parquet archives require the `parquet` feature");
+                Err(IngestRepositoryError::InvalidRequest)
+            }
+        }
+    }
+}
+
+/// `ArchiveManifest` describes a closed archive file, so that the file can be
+/// verified before it is read and selected without being read. It is written
+/// next to the file, named after it with the `ARCHIVE_MANIFEST_SUFFIX`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ArchiveManifest {
+    /// Name of the archive file within the directory of the manifest
+    pub file: String,
+    pub format: ArchiveFileFormat,
+    pub site: String,
+    /// UTC date of the records as `YYYY-MM-DD`
+    pub date: String,
+    /// Number of records within the file
+    pub records: u64,
+    /// Size of the file
+    pub bytes: u64,
+    /// Lowercase hex encoded SHA-256 digest of the file
+    pub sha256: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub first_ts: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub last_ts: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub closed_at: OffsetDateTime,
+}
+
+impl ArchiveManifest {
+    /// Path of the manifest of the archive file at `file`
+    pub fn path_for(file: &Path) -> PathBuf {
+        let mut path = file.as_os_str().to_owned();
+        path.push(ARCHIVE_MANIFEST_SUFFIX);
+        PathBuf::from(path)
+    }
+
+    /// UTC date of the records, if the manifest holds a valid date
+    pub fn partition_date(&self) -> Option<Date> {
+        Date::parse(&self.date, &Iso8601::DATE).ok()
+    }
+
+    /// Whether `contents` are those of the described file
+    pub fn verify(&self, contents: &[u8]) -> bool {
+        contents.len() as u64 == self.bytes && sha256_hex(Sha256::digest(contents)) == self.sha256
+    }
+}
+
+/// Lowercase hex encoding of a SHA-256 digest
+pub(crate) fn sha256_hex(digest: impl AsRef<[u8]>) -> String {
+    digest.as_ref().iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::domain::model::ingest_event::{ApiKey, IngestEvent, Site, VisitorEvent};
+
+    use super::*;
+
+    #[test]
+    fn test_archive_file() {
+        let records: Vec<SinkEventRecord> = (0..2)
+            .map(|_| {
+                SinkEventRecord::try_from(&IngestEvent::Visitor(
+                    VisitorEvent::try_new(
+                        ApiKey::new("abc-123"),
+                        Site::new("test.com"),
+                        Uuid::now_v7(),
+                    )
+                    .unwrap(),
+                ))
+                .unwrap()
+            })
+            .collect();
+        let contents = ArchiveFileFormat::Jsonl.encode(&records).unwrap();
+        assert_eq!(
+            ArchiveFileFormat::Jsonl.decode(contents.clone()).unwrap(),
+            records
+        );
+
+        let manifest = ArchiveManifest {
+            file: "part-1.jsonl".to_owned(),
+            format: ArchiveFileFormat::Jsonl,
+            site: "test.com".to_owned(),
+            date: "2025-03-01".to_owned(),
+            records: 2,
+            bytes: contents.len() as u64,
+            sha256: sha256_hex(Sha256::digest(&contents)),
+            first_ts: records[0].ts,
+            last_ts: records[1].ts,
+            closed_at: OffsetDateTime::now_utc(),
+        };
+        assert!(manifest.verify(&contents));
+        assert_eq!(
+            manifest.partition_date(),
+            Some(Date::from_calendar_date(2025, time::Month::March, 1).unwrap())
+        );
+        assert_eq!(
+            ArchiveManifest::path_for(Path::new("/archive/part-1.jsonl")),
+            PathBuf::from("/archive/part-1.jsonl.manifest.json")
+        );
+
+        // Negative test cases
+        let mut tampered = contents.clone();
+        tampered[0] = b' ';
+        assert!(
+            !manifest.verify(&tampered),
+            "Expected a changed file to fail verification"
+        );
+        assert_eq!(
+            ArchiveFileFormat::Jsonl
+                .decode(b"{\"not\":\"a record\"}\n".to_vec())
+                .unwrap_err(),
+            IngestRepositoryError::Conversion
+        );
+    }
+}
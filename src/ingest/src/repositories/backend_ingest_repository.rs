@@ -0,0 +1,153 @@
+use std::{collections::HashSet, error::Error};
+
+use conf::domain::service::configuration_service::{
+    ConfigurationService, MemoryBackend, MetricsBackend,
+};
+
+use crate::domain::{
+    model::{
+        ingest_action_summary::IngestActionSummary,
+        ingest_event::{ApiKey, IngestEvent, IngestEventSource, Site},
+        ingest_source_rules::IngestSourceRules,
+        server_key::ServerKey,
+    },
+    repository::ingest_event_repository::{IngestEventRepository, IngestRepositoryError},
+};
+
+use super::clickhouse_ingest_repository::ClickhouseIngestRepository;
+use super::memory_ingest_repository::MemoryIngestRepository;
+#[cfg(feature = "postgres")]
+use super::postgres_ingest_repository::PostgresIngestRepository;
+#[cfg(feature = "sqlite")]
+use super::sqlite_ingest_repository::SqliteIngestRepository;
+
+/// `BackendIngestRepository` is the `IngestEventRepository` of whichever
+/// storage backend the server is configured to use, so that the server is
+/// built around a single repository type. Backends other than ClickHouse are
+/// only available when their cargo feature is enabled.
+#[derive(Clone, Debug)]
+pub enum BackendIngestRepository {
+    Clickhouse(ClickhouseIngestRepository),
+    Memory(MemoryIngestRepository),
+    #[cfg(feature = "postgres")]
+    Postgres(PostgresIngestRepository),
+    #[cfg(feature = "sqlite")]
+    Sqlite(SqliteIngestRepository),
+}
+
+impl BackendIngestRepository {
+    /// Connect to the storage backend selected by the `ConfigurationService`
+    pub async fn try_from_conf(
+        conf_service: &impl ConfigurationService,
+    ) -> Result<Self, Box<dyn Error + 'static>> {
+        match conf_service.try_metrics_backend()? {
+            MetricsBackend::Clickhouse => {
+                let metrics_client = conf_service.try_metrics_db_client()?;
+                Ok(Self::Clickhouse(
+                    ClickhouseIngestRepository::try_new(metrics_client).await?,
+                ))
+            }
+            #[cfg(feature = "postgres")]
+            MetricsBackend::Postgres(postgres) => Ok(Self::Postgres(
+                PostgresIngestRepository::try_new(&postgres.url, postgres.timescale).await?,
+            )),
+            #[cfg(not(feature = "postgres"))]
+            MetricsBackend::Postgres(_) => {
+                Err("the PostgreSQL backend requires the `postgres` feature".into())
+            }
+            MetricsBackend::Memory(memory) => Ok(Self::Memory(memory_repository(memory))),
+            #[cfg(feature = "sqlite")]
+            MetricsBackend::Sqlite(sqlite) => Ok(Self::Sqlite(
+                SqliteIngestRepository::try_new(&sqlite.path).await?,
+            )),
+            #[cfg(not(feature = "sqlite"))]
+            MetricsBackend::Sqlite(_) => {
+                Err("the SQLite backend requires the `sqlite` feature".into())
+            }
+        }
+    }
+}
+
+/// Build the `MemoryIngestRepository` of dev mode
+fn memory_repository(memory: MemoryBackend) -> MemoryIngestRepository {
+    let mut repository = MemoryIngestRepository::new(memory.capacity);
+    for source in memory.sources {
+        repository = repository.with_event_source(IngestEventSource::new(
+            ApiKey::new(source.api_key),
+            Site::new(source.site),
+        ));
+    }
+    if let Some(server_key) = memory.server_key {
+        repository = repository.with_server_key(&ServerKey::new(server_key));
+    }
+    repository
+}
+
+impl IngestEventRepository for BackendIngestRepository {
+    async fn save(
+        &self,
+        events: Vec<IngestEvent>,
+    ) -> Result<IngestActionSummary, IngestRepositoryError> {
+        match self {
+            Self::Clickhouse(repository) => repository.save(events).await,
+            Self::Memory(repository) => repository.save(events).await,
+            #[cfg(feature = "postgres")]
+            Self::Postgres(repository) => repository.save(events).await,
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(repository) => repository.save(events).await,
+        }
+    }
+
+    async fn event_sources(&self) -> Result<HashSet<IngestEventSource>, IngestRepositoryError> {
+        match self {
+            Self::Clickhouse(repository) => repository.event_sources().await,
+            Self::Memory(repository) => repository.event_sources().await,
+            #[cfg(feature = "postgres")]
+            Self::Postgres(repository) => repository.event_sources().await,
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(repository) => repository.event_sources().await,
+        }
+    }
+
+    async fn source_rules(
+        &self,
+        source: &IngestEventSource,
+    ) -> Result<IngestSourceRules, IngestRepositoryError> {
+        match self {
+            Self::Clickhouse(repository) => repository.source_rules(source).await,
+            Self::Memory(repository) => repository.source_rules(source).await,
+            #[cfg(feature = "postgres")]
+            Self::Postgres(repository) => repository.source_rules(source).await,
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(repository) => repository.source_rules(source).await,
+        }
+    }
+
+    async fn is_server_key_valid(
+        &self,
+        api_key: &ApiKey,
+        server_key: &ServerKey,
+    ) -> Result<bool, IngestRepositoryError> {
+        match self {
+            Self::Clickhouse(repository) => {
+                repository.is_server_key_valid(api_key, server_key).await
+            }
+            Self::Memory(repository) => repository.is_server_key_valid(api_key, server_key).await,
+            #[cfg(feature = "postgres")]
+            Self::Postgres(repository) => repository.is_server_key_valid(api_key, server_key).await,
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(repository) => repository.is_server_key_valid(api_key, server_key).await,
+        }
+    }
+
+    async fn reload_event_sources(&self) -> Result<(), IngestRepositoryError> {
+        match self {
+            Self::Clickhouse(repository) => repository.reload_event_sources().await,
+            Self::Memory(repository) => repository.reload_event_sources().await,
+            #[cfg(feature = "postgres")]
+            Self::Postgres(repository) => repository.reload_event_sources().await,
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(repository) => repository.reload_event_sources().await,
+        }
+    }
+}
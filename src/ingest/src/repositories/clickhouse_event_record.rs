@@ -9,7 +9,8 @@ use uuid::Uuid;
 
 use crate::domain::{
     model::ingest_event::{
-        ClickEvent, CommonEvent, IngestEvent, SectionEvent, SessionEvent, VisitorEvent,
+        ClickEvent, CommonEvent, CustomEvent, IdentifyEvent, IngestEvent, IngestEventOrigin,
+        SectionEvent, SessionEvent, VisitorEvent,
     },
     repository::ingest_event_repository::IngestRepositoryError,
 };
@@ -24,8 +25,35 @@ pub enum ClickhouseEventRecordType {
     Session = 2,
     Section = 3,
     Click = 4,
+    Custom = 5,
+    Identify = 6,
 }
 
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+impl ClickhouseEventRecordType {
+    /// Name of the type, which matches the names of the ClickHouse Enum8 and
+    /// is stored by the backends that have no enum of their own
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::Visitor => "Visitor",
+            Self::Session => "Session",
+            Self::Section => "Section",
+            Self::Click => "Click",
+            Self::Custom => "Custom",
+            Self::Identify => "Identify",
+        }
+    }
+}
+
+/// `CUSTOM_PROP_ATTR_PREFIX` is prepended to the name of every `CustomEvent`
+/// prop when it is stored in `attrs`, so that props can never collide with the
+/// attributes that are set by the system
+pub const CUSTOM_PROP_ATTR_PREFIX: &str = "prop.";
+
+/// `IDENTIFY_TRAIT_ATTR_PREFIX` is prepended to the name of every
+/// `IdentifyEvent` trait when it is stored in `attrs`
+pub const IDENTIFY_TRAIT_ATTR_PREFIX: &str = "trait.";
+
 impl From<&IngestEvent> for ClickhouseEventRecordType {
     #[instrument]
     fn from(value: &IngestEvent) -> Self {
@@ -34,6 +62,8 @@ impl From<&IngestEvent> for ClickhouseEventRecordType {
             IngestEvent::Session(_) => Self::Session,
             IngestEvent::Section(_) => Self::Section,
             IngestEvent::Click(_) => Self::Click,
+            IngestEvent::Custom(_) => Self::Custom,
+            IngestEvent::Identify(_) => Self::Identify,
         }
     }
 }
@@ -48,14 +78,24 @@ impl From<&IngestEvent> for ClickhouseEventRecordType {
 /// a (String, String) tuple.
 #[derive(Debug, Row, Deserialize, Serialize, Clone, PartialEq, Eq)]
 pub struct ClickhouseEventRecord {
-    api_key: String,
-    site: String,
-    event_type: ClickhouseEventRecordType,
+    pub(crate) api_key: String,
+    pub(crate) site: String,
+    pub(crate) event_type: ClickhouseEventRecordType,
     #[serde(with = "clickhouse::serde::uuid")]
-    id: Uuid,
+    pub(crate) id: Uuid,
     #[serde(with = "clickhouse::serde::time::datetime")]
-    ts: OffsetDateTime,
-    attrs: Vec<(String, String)>,
+    pub(crate) ts: OffsetDateTime,
+    pub(crate) attrs: Vec<(String, String)>,
+}
+
+impl ClickhouseEventRecord {
+    /// Sort the attrs, whose order otherwise depends on hashing, so that
+    /// records can be compared
+    #[cfg(test)]
+    pub(crate) fn with_sorted_attrs(mut self) -> Self {
+        self.attrs.sort();
+        self
+    }
 }
 
 /// `ClickhouseEventRecord` translates from the core `IngestEvent` domain
@@ -69,6 +109,8 @@ impl TryFrom<&IngestEvent> for ClickhouseEventRecord {
             IngestEvent::Session(event) => event.try_into(),
             IngestEvent::Section(event) => event.try_into(),
             IngestEvent::Click(event) => event.try_into(),
+            IngestEvent::Custom(event) => event.try_into(),
+            IngestEvent::Identify(event) => event.try_into(),
         }
     }
 }
@@ -99,12 +141,15 @@ impl TryFrom<&SessionEvent> for ClickhouseEventRecord {
             "ipv6".to_owned()
         };
 
-        builder
+        let mut builder = builder
             .event_type(ClickhouseEventRecordType::Session)
             .parent(event.parent)
             .add_attr("user_agent".to_owned(), event.user_agent.to_owned())
-            .add_attr(ip_key, event.ip.to_string())
-            .try_build()
+            .add_attr(ip_key, event.ip.to_string());
+        if let Some(referrer) = &event.referrer {
+            builder = builder.add_attr("referrer".to_owned(), referrer.to_owned());
+        }
+        builder.try_build()
     }
 }
 
@@ -124,6 +169,9 @@ impl TryFrom<&SectionEvent> for ClickhouseEventRecord {
         if let Some(title) = &event.title {
             builder = builder.add_attr("title".to_owned(), title.to_owned());
         }
+        if let Some(path) = &event.path {
+            builder = builder.add_attr("path".to_owned(), path.to_owned());
+        }
         builder.try_build()
     }
 }
@@ -142,6 +190,48 @@ impl TryFrom<&ClickEvent> for ClickhouseEventRecord {
     }
 }
 
+/// `ClickhouseEventRecord` derived from each `IngestEvent` type's discriminant
+/// `Custom` discriminant
+impl TryFrom<&CustomEvent> for ClickhouseEventRecord {
+    type Error = IngestRepositoryError;
+    #[instrument]
+    fn try_from(event: &CustomEvent) -> Result<Self, Self::Error> {
+        let mut builder = ClickhouseEventRecordBuilder::from(&event)
+            .event_type(ClickhouseEventRecordType::Custom)
+            .parent(event.parent)
+            .add_attr("name".to_owned(), event.name.to_owned());
+        if let Some(location) = &event.location {
+            builder = builder.add_attr("location".to_owned(), location.to_owned());
+        }
+        for (key, value) in event.props.iter() {
+            builder = builder.add_attr(format!("{CUSTOM_PROP_ATTR_PREFIX}{key}"), value.to_owned());
+        }
+        builder.try_build()
+    }
+}
+
+/// `ClickhouseEventRecord` derived from each `IngestEvent` type's discriminant
+/// `Identify` discriminant. Events that were not pseudonymized are refused so
+/// that a user id can never be stored in clear.
+impl TryFrom<&IdentifyEvent> for ClickhouseEventRecord {
+    type Error = IngestRepositoryError;
+    #[instrument]
+    fn try_from(event: &IdentifyEvent) -> Result<Self, Self::Error> {
+        let user_hash = event.user_hash().ok_or(IngestRepositoryError::Conversion)?;
+        let mut builder = ClickhouseEventRecordBuilder::from(&event)
+            .event_type(ClickhouseEventRecordType::Identify)
+            .parent(event.parent)
+            .add_attr("user_hash".to_owned(), user_hash.to_owned());
+        for (key, value) in event.traits.iter() {
+            builder = builder.add_attr(
+                format!("{IDENTIFY_TRAIT_ATTR_PREFIX}{key}"),
+                value.to_owned(),
+            );
+        }
+        builder.try_build()
+    }
+}
+
 /// `ClickhouseEventRecordBuilder` is an internal struct used to build up a
 /// `ClickhouseEventRecord` in an ergonomic way. Part of this relies on the
 /// `CommonEvent` trait that is provided in the domain to represent the fields
@@ -157,19 +247,24 @@ struct ClickhouseEventRecordBuilder {
 
 /// `ClickhouseEventRecordBuilder` ergonomic conversion from the `CommonEvent`
 /// trait. This takes care of the core data fields of `api_key`, `site`, `id`
-/// and `ts`
+/// and `ts`. Events that did not come from a client are tagged with an
+/// `origin` attribute
 impl<T> From<&T> for ClickhouseEventRecordBuilder
 where
     T: CommonEvent,
 {
     fn from(event: &T) -> Self {
+        let mut attrs = HashSet::new();
+        if event.origin() != IngestEventOrigin::Client {
+            attrs.insert(("origin".to_owned(), event.origin().as_str().to_owned()));
+        }
         Self {
             api_key: event.api_key().value().to_owned(),
             site: event.site().value().to_owned(),
             id: event.id().to_owned(),
             ts: event.ts().to_owned(),
             event_type: None,
-            attrs: HashSet::new(),
+            attrs,
         }
     }
 }
@@ -216,7 +311,10 @@ impl ClickhouseEventRecordBuilder {
 mod tests {
     use std::net::{IpAddr, Ipv4Addr};
 
-    use crate::domain::model::ingest_event::{ApiKey, Site};
+    use crate::domain::model::{
+        identity_key::IdentityKey,
+        ingest_event::{ApiKey, Site},
+    };
 
     use super::*;
 
@@ -247,6 +345,18 @@ mod tests {
             click_discriminant, 4,
             "ClickhouseEventRecordType::Click discriminant does not match expected value"
         );
+
+        let custom_discriminant = ClickhouseEventRecordType::Custom as u32;
+        assert_eq!(
+            custom_discriminant, 5,
+            "ClickhouseEventRecordType::Custom discriminant does not match expected value"
+        );
+
+        let identify_discriminant = ClickhouseEventRecordType::Identify as u32;
+        assert_eq!(
+            identify_discriminant, 6,
+            "ClickhouseEventRecordType::Identify discriminant does not match expected value"
+        );
     }
 
     #[test]
@@ -310,5 +420,75 @@ mod tests {
         let Ok(_) = ClickhouseEventRecord::try_from(&IngestEvent::Click(valid_click_event)) else {
             panic!("Expected valid Click ClickhouseEventRecord to be created from valid event");
         };
+
+        let Ok(valid_custom_event) = CustomEvent::try_new(
+            ApiKey::new("abc-124"),
+            Site::new("http://salusmetrics.com"),
+            Uuid::now_v7(),
+            uuid_session,
+            "signup".to_owned(),
+            None,
+            std::collections::HashMap::from([("plan".to_owned(), "pro".to_owned())]),
+        ) else {
+            panic!("Expected valid CustomEvent to be created");
+        };
+        let Ok(custom_record) =
+            ClickhouseEventRecord::try_from(&IngestEvent::Custom(valid_custom_event))
+        else {
+            panic!("Expected valid Custom ClickhouseEventRecord to be created from valid event");
+        };
+        assert!(
+            custom_record
+                .attrs
+                .contains(&("prop.plan".to_owned(), "pro".to_owned())),
+            "Expected props to be stored with the prop prefix"
+        );
+        assert!(
+            custom_record
+                .attrs
+                .contains(&("name".to_owned(), "signup".to_owned()))
+        );
+
+        let Ok(mut valid_identify_event) = IdentifyEvent::try_new(
+            ApiKey::new("abc-124"),
+            Site::new("http://salusmetrics.com"),
+            Uuid::now_v7(),
+            uuid_visitor,
+            "user-1".to_owned(),
+            std::collections::HashMap::from([("plan".to_owned(), "pro".to_owned())]),
+        ) else {
+            panic!("Expected valid IdentifyEvent to be created");
+        };
+        let Err(IngestRepositoryError::Conversion) =
+            ClickhouseEventRecord::try_from(&IngestEvent::Identify(valid_identify_event.clone()))
+        else {
+            panic!("Expected IdentifyEvent to be refused before it was pseudonymized");
+        };
+        let identity_key = IdentityKey::new("key");
+        valid_identify_event.pseudonymize(&identity_key);
+        let Ok(identify_record) =
+            ClickhouseEventRecord::try_from(&IngestEvent::Identify(valid_identify_event))
+        else {
+            panic!("Expected valid Identify ClickhouseEventRecord to be created from valid event");
+        };
+        assert!(
+            identify_record
+                .attrs
+                .contains(&("user_hash".to_owned(), identity_key.pseudonymize("user-1"))),
+            "Expected the hashed user id to be stored"
+        );
+        assert!(
+            !identify_record
+                .attrs
+                .iter()
+                .any(|(_, value)| value == "user-1"),
+            "Expected the user id to never be stored in clear"
+        );
+        assert!(
+            identify_record
+                .attrs
+                .contains(&("trait.plan".to_owned(), "pro".to_owned())),
+            "Expected traits to be stored with the trait prefix"
+        );
     }
 }
@@ -1,17 +1,22 @@
 use std::collections::HashSet;
-use std::sync::Arc;
 
 use clickhouse::Client;
+use sha2::{Digest, Sha256};
 use tracing::instrument;
 
 use crate::domain::model::ingest_action_summary::{IngestActionSummary, IngestEventSaveSummary};
-use crate::domain::model::ingest_event::{IngestEvent, IngestEventSource};
+use crate::domain::model::ingest_event::{ApiKey, IngestEvent, IngestEventSource};
+use crate::domain::model::ingest_source_rules::IngestSourceRules;
+use crate::domain::model::server_key::ServerKey;
 use crate::domain::repository::ingest_event_repository::{
     IngestEventRepository, IngestRepositoryError,
 };
 
 use super::clickhouse_event_record::ClickhouseEventRecord;
-use super::clickhouse_source_record::ClickhouseSourceRecord;
+use super::server_key_record::ServerKeyRecord;
+use super::source_catalog::{SharedSourceCatalog, SourceCatalog};
+use super::source_record::SourceRecord;
+use super::source_rule_record::{IdentityKeyRecord, PathRuleRecord, QueryParamRecord};
 
 /// `ClickhouseIngestRepository` is an implementation of the
 /// `IngestEventRepository` trait that utilizes ClickHouse as the back end.
@@ -23,7 +28,19 @@ use super::clickhouse_source_record::ClickhouseSourceRecord;
 #[derive(Clone)]
 pub struct ClickhouseIngestRepository {
     metrics_db_client: Client,
-    event_sources: Arc<HashSet<IngestEventSource>>,
+    catalog: SharedSourceCatalog,
+}
+
+/// Load a `SourceCatalog` from the ClickHouse tables of the sources, their
+/// rules and the server keys
+async fn load_source_catalog(client: &Client) -> Result<SourceCatalog, IngestRepositoryError> {
+    Ok(SourceCatalog::from_records(
+        &retrieve_event_sources(client.clone()).await?,
+        &retrieve_query_param_rules(client.clone()).await?,
+        &retrieve_path_rules(client.clone()).await?,
+        &retrieve_identity_keys(client.clone()).await?,
+        &retrieve_server_keys(client.clone()).await?,
+    ))
 }
 
 impl std::fmt::Debug for ClickhouseIngestRepository {
@@ -34,14 +51,10 @@ impl std::fmt::Debug for ClickhouseIngestRepository {
 
 impl ClickhouseIngestRepository {
     pub async fn try_new(metrics_db_client: Client) -> Result<Self, IngestRepositoryError> {
-        let sources: HashSet<IngestEventSource> = retrieve_event_sources(metrics_db_client.clone())
-            .await?
-            .iter()
-            .map(IngestEventSource::from)
-            .collect();
+        let catalog = load_source_catalog(&metrics_db_client).await?;
         Ok(Self {
             metrics_db_client,
-            event_sources: Arc::new(sources),
+            catalog: SharedSourceCatalog::new(catalog),
         })
     }
 }
@@ -57,27 +70,56 @@ impl IngestEventRepository for ClickhouseIngestRepository {
         if events.is_empty() {
             return Err(IngestRepositoryError::InvalidRequest);
         }
+        let catalog = self.catalog.snapshot();
         let mut records: Vec<ClickhouseEventRecord> = Vec::with_capacity(events.len());
         for event in events.iter() {
             tracing::debug!("Incoming Record: {:?}", &event);
             match event {
                 IngestEvent::Visitor(evt) => {
-                    if !self.event_sources.contains(&IngestEventSource::from(&evt)) {
+                    if !catalog
+                        .event_sources
+                        .contains(&IngestEventSource::from(&evt))
+                    {
                         return Err(IngestRepositoryError::InvalidRequest);
                     }
                 }
                 IngestEvent::Session(evt) => {
-                    if !self.event_sources.contains(&IngestEventSource::from(&evt)) {
+                    if !catalog
+                        .event_sources
+                        .contains(&IngestEventSource::from(&evt))
+                    {
                         return Err(IngestRepositoryError::InvalidRequest);
                     }
                 }
                 IngestEvent::Section(evt) => {
-                    if !self.event_sources.contains(&IngestEventSource::from(&evt)) {
+                    if !catalog
+                        .event_sources
+                        .contains(&IngestEventSource::from(&evt))
+                    {
                         return Err(IngestRepositoryError::InvalidRequest);
                     }
                 }
                 IngestEvent::Click(evt) => {
-                    if !self.event_sources.contains(&IngestEventSource::from(&evt)) {
+                    if !catalog
+                        .event_sources
+                        .contains(&IngestEventSource::from(&evt))
+                    {
+                        return Err(IngestRepositoryError::InvalidRequest);
+                    }
+                }
+                IngestEvent::Custom(evt) => {
+                    if !catalog
+                        .event_sources
+                        .contains(&IngestEventSource::from(&evt))
+                    {
+                        return Err(IngestRepositoryError::InvalidRequest);
+                    }
+                }
+                IngestEvent::Identify(evt) => {
+                    if !catalog
+                        .event_sources
+                        .contains(&IngestEventSource::from(&evt))
+                    {
                         return Err(IngestRepositoryError::InvalidRequest);
                     }
                 }
@@ -85,8 +127,16 @@ impl IngestEventRepository for ClickhouseIngestRepository {
             records.push(ClickhouseEventRecord::try_from(event)?);
         }
 
+        // A retried batch carries the same token, so that ClickHouse drops it
+        // in the event tables if the original insert did succeed
         let mut insert = self
             .metrics_db_client
+            .clone()
+            .with_option(
+                "insert_deduplication_token",
+                insert_deduplication_token(&events),
+            )
+            .with_option("deduplicate_blocks_in_dependent_materialized_views", "1")
             .insert::<ClickhouseEventRecord>("EVENT")
             .map_err(|e| {
                 tracing::error!("Encountered error initiating ClickHouse Insert: {e}");
@@ -108,20 +158,66 @@ impl IngestEventRepository for ClickhouseIngestRepository {
 
         Ok(IngestActionSummary::Save(IngestEventSaveSummary {
             event_count: records.len(),
+            duplicate_count: 0,
         }))
     }
 
     async fn event_sources(&self) -> Result<HashSet<IngestEventSource>, IngestRepositoryError> {
-        Ok(self.event_sources.iter().map(|es| es.to_owned()).collect())
+        Ok(self.catalog.snapshot().event_sources.clone())
     }
+
+    async fn source_rules(
+        &self,
+        source: &IngestEventSource,
+    ) -> Result<IngestSourceRules, IngestRepositoryError> {
+        Ok(self.catalog.snapshot().source_rules(source))
+    }
+
+    async fn is_server_key_valid(
+        &self,
+        api_key: &ApiKey,
+        server_key: &ServerKey,
+    ) -> Result<bool, IngestRepositoryError> {
+        Ok(self
+            .catalog
+            .snapshot()
+            .is_server_key_valid(api_key, server_key))
+    }
+
+    /// `reload_event_sources` for ClickHouse loads a fresh snapshot of the
+    /// sources and rules. The current snapshot is kept if loading fails.
+    async fn reload_event_sources(&self) -> Result<(), IngestRepositoryError> {
+        let catalog = load_source_catalog(&self.metrics_db_client).await?;
+        self.catalog.replace(catalog);
+        Ok(())
+    }
+}
+
+/// Token identifying the events of an insert, which is the SHA-256 digest of
+/// the source and id of each event in order
+fn insert_deduplication_token(events: &[IngestEvent]) -> String {
+    let mut hasher = Sha256::new();
+    for event in events.iter() {
+        let source = IngestEventSource::from(event);
+        hasher.update(source.api_key().value().as_bytes());
+        hasher.update([0]);
+        hasher.update(source.site().value().as_bytes());
+        hasher.update([0]);
+        hasher.update(event.id().as_bytes());
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
 }
 
 async fn retrieve_event_sources(
     client: Client,
-) -> Result<Vec<ClickhouseSourceRecord>, IngestRepositoryError> {
+) -> Result<Vec<SourceRecord>, IngestRepositoryError> {
     client
         .query("SELECT api_key, site FROM API_KEY")
-        .fetch_all::<ClickhouseSourceRecord>()
+        .fetch_all::<SourceRecord>()
         .await
         .map_err(|e| {
             tracing::error!("Encountered error fetching event source records {e}. This is likely due to connection problems with Clickhouse.");
@@ -129,6 +225,56 @@ async fn retrieve_event_sources(
         })
 }
 
+async fn retrieve_query_param_rules(
+    client: Client,
+) -> Result<Vec<QueryParamRecord>, IngestRepositoryError> {
+    client
+        .query("SELECT api_key, site, param, rule FROM SOURCE_QUERY_PARAM FINAL")
+        .fetch_all::<QueryParamRecord>()
+        .await
+        .map_err(|e| {
+            tracing::error!("Encountered error fetching query param rule records {e}. This is likely due to connection problems with Clickhouse.");
+            IngestRepositoryError::Repository
+        })
+}
+
+async fn retrieve_path_rules(client: Client) -> Result<Vec<PathRuleRecord>, IngestRepositoryError> {
+    client
+        .query("SELECT api_key, site, rewrites, collapse_numeric, collapse_uuid, trim_trailing_slash, lowercase FROM SOURCE_PATH_RULE FINAL")
+        .fetch_all::<PathRuleRecord>()
+        .await
+        .map_err(|e| {
+            tracing::error!("Encountered error fetching path rule records {e}. This is likely due to connection problems with Clickhouse.");
+            IngestRepositoryError::Repository
+        })
+}
+
+async fn retrieve_identity_keys(
+    client: Client,
+) -> Result<Vec<IdentityKeyRecord>, IngestRepositoryError> {
+    client
+        .query("SELECT api_key, site, identity_key FROM SOURCE_IDENTITY_KEY FINAL")
+        .fetch_all::<IdentityKeyRecord>()
+        .await
+        .map_err(|e| {
+            tracing::error!("Encountered error fetching identity key records {e}. This is likely due to connection problems with Clickhouse.");
+            IngestRepositoryError::Repository
+        })
+}
+
+async fn retrieve_server_keys(
+    client: Client,
+) -> Result<Vec<ServerKeyRecord>, IngestRepositoryError> {
+    client
+        .query("SELECT api_key, key_sha256 FROM SERVER_KEY FINAL")
+        .fetch_all::<ServerKeyRecord>()
+        .await
+        .map_err(|e| {
+            tracing::error!("Encountered error fetching server key records {e}. This is likely due to connection problems with Clickhouse.");
+            IngestRepositoryError::Repository
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use clickhouse::{Client, test};
@@ -136,12 +282,17 @@ mod tests {
 
     use super::*;
     use crate::domain::model::ingest_event::{ApiKey, Site, VisitorEvent};
+    use crate::repositories::source_rule_record::QueryParamRule;
 
     #[tokio::test(flavor = "multi_thread")]
     async fn test_save() {
-        let mock_sources = Vec::from([ClickhouseSourceRecord::new("abc-123", "test.com")]);
+        let mock_sources = Vec::from([SourceRecord::new("abc-123", "test.com")]);
         let mock = test::Mock::new();
         mock.add(test::handlers::provide(mock_sources));
+        mock.add(test::handlers::provide(Vec::<QueryParamRecord>::new()));
+        mock.add(test::handlers::provide(Vec::<PathRuleRecord>::new()));
+        mock.add(test::handlers::provide(Vec::<IdentityKeyRecord>::new()));
+        mock.add(test::handlers::provide(Vec::<ServerKeyRecord>::new()));
         let recording = mock.add(test::handlers::record());
         let mock_client = Client::default().with_url(mock.url());
         let test_repository = ClickhouseIngestRepository::try_new(mock_client)
@@ -199,4 +350,183 @@ mod tests {
             "Expected error to be of type InvalidRequest for save of invalid api_key"
         );
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_source_rules() {
+        let mock_sources = Vec::from([SourceRecord::new("abc-123", "test.com")]);
+        let mock_query_params = Vec::from([QueryParamRecord::new(
+            "abc-123",
+            "test.com",
+            "sid",
+            QueryParamRule::Deny,
+        )]);
+        let mock = test::Mock::new();
+        mock.add(test::handlers::provide(mock_sources));
+        mock.add(test::handlers::provide(mock_query_params));
+        mock.add(test::handlers::provide(Vec::<PathRuleRecord>::new()));
+        mock.add(test::handlers::provide(Vec::<IdentityKeyRecord>::new()));
+        mock.add(test::handlers::provide(Vec::<ServerKeyRecord>::new()));
+        let mock_client = Client::default().with_url(mock.url());
+        let test_repository = ClickhouseIngestRepository::try_new(mock_client)
+            .await
+            .unwrap();
+
+        let configured_rules = test_repository
+            .source_rules(&IngestEventSource::new(
+                ApiKey::new("abc-123"),
+                Site::new("test.com"),
+            ))
+            .await
+            .unwrap();
+        assert!(
+            !configured_rules.query_params.is_kept("sid"),
+            "Expected configured deny rule to be returned"
+        );
+
+        let unconfigured_rules = test_repository
+            .source_rules(&IngestEventSource::new(
+                ApiKey::new("xyz-789"),
+                Site::new("other.com"),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(
+            unconfigured_rules,
+            IngestSourceRules::default(),
+            "Expected default rules for a source without configuration"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_is_server_key_valid() {
+        let mock = test::Mock::new();
+        mock.add(test::handlers::provide(Vec::from([SourceRecord::new(
+            "abc-123", "test.com",
+        )])));
+        mock.add(test::handlers::provide(Vec::<QueryParamRecord>::new()));
+        mock.add(test::handlers::provide(Vec::<PathRuleRecord>::new()));
+        mock.add(test::handlers::provide(Vec::<IdentityKeyRecord>::new()));
+        mock.add(test::handlers::provide(Vec::from([ServerKeyRecord::new(
+            "abc-123",
+            ServerKey::new("secret").digest(),
+        )])));
+        let mock_client = Client::default().with_url(mock.url());
+        let test_repository = ClickhouseIngestRepository::try_new(mock_client)
+            .await
+            .unwrap();
+
+        assert!(
+            test_repository
+                .is_server_key_valid(&ApiKey::new("abc-123"), &ServerKey::new("secret"))
+                .await
+                .unwrap(),
+            "Expected issued server key to be valid"
+        );
+        assert!(
+            !test_repository
+                .is_server_key_valid(&ApiKey::new("abc-123"), &ServerKey::new("other"))
+                .await
+                .unwrap(),
+            "Expected unknown server key to be invalid"
+        );
+        assert!(
+            !test_repository
+                .is_server_key_valid(&ApiKey::new("xyz-789"), &ServerKey::new("secret"))
+                .await
+                .unwrap(),
+            "Expected server key to be tied to the api_key it was issued for"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_reload_event_sources() {
+        let mock = test::Mock::new();
+        mock.add(test::handlers::provide(Vec::from([SourceRecord::new(
+            "abc-123", "test.com",
+        )])));
+        mock.add(test::handlers::provide(Vec::<QueryParamRecord>::new()));
+        mock.add(test::handlers::provide(Vec::<PathRuleRecord>::new()));
+        mock.add(test::handlers::provide(Vec::<IdentityKeyRecord>::new()));
+        mock.add(test::handlers::provide(Vec::<ServerKeyRecord>::new()));
+        mock.add(test::handlers::provide(Vec::from([
+            SourceRecord::new("abc-123", "test.com"),
+            SourceRecord::new("xyz-789", "other.com"),
+        ])));
+        mock.add(test::handlers::provide(Vec::<QueryParamRecord>::new()));
+        mock.add(test::handlers::provide(Vec::from([PathRuleRecord::new(
+            "xyz-789",
+            "other.com",
+            Vec::new(),
+            true,
+            true,
+            true,
+            true,
+        )])));
+        mock.add(test::handlers::provide(Vec::<IdentityKeyRecord>::new()));
+        mock.add(test::handlers::provide(Vec::<ServerKeyRecord>::new()));
+        mock.add(test::handlers::failure(test::status::INTERNAL_SERVER_ERROR));
+        let mock_client = Client::default().with_url(mock.url());
+        let test_repository = ClickhouseIngestRepository::try_new(mock_client)
+            .await
+            .unwrap();
+        let other_source = IngestEventSource::new(ApiKey::new("xyz-789"), Site::new("other.com"));
+        assert!(
+            !test_repository
+                .event_sources()
+                .await
+                .unwrap()
+                .contains(&other_source),
+            "Expected other source to be unknown before reload"
+        );
+
+        test_repository.reload_event_sources().await.unwrap();
+        assert!(
+            test_repository
+                .event_sources()
+                .await
+                .unwrap()
+                .contains(&other_source),
+            "Expected other source to be known after reload"
+        );
+        let reloaded_rules = test_repository.source_rules(&other_source).await.unwrap();
+        assert!(
+            reloaded_rules.path_normalization.collapse_numeric,
+            "Expected path rules to be reloaded with the sources"
+        );
+
+        // A failed reload keeps the previous snapshot
+        assert!(
+            test_repository.reload_event_sources().await.is_err(),
+            "Expected reload to fail when ClickHouse returns an error"
+        );
+        assert!(
+            test_repository
+                .event_sources()
+                .await
+                .unwrap()
+                .contains(&other_source),
+            "Expected previous sources to be kept after a failed reload"
+        );
+    }
+
+    #[test]
+    fn test_insert_deduplication_token() {
+        let event = |site: &str, id: Uuid| {
+            IngestEvent::Visitor(
+                VisitorEvent::try_new(ApiKey::new("abc-123"), Site::new(site), id).unwrap(),
+            )
+        };
+        let (first, second) = (Uuid::now_v7(), Uuid::now_v7());
+        let batch = vec![event("test.com", first), event("test.com", second)];
+        assert_eq!(
+            insert_deduplication_token(&batch),
+            insert_deduplication_token(&batch.clone()),
+            "Expected a retried batch to carry the same token"
+        );
+        assert_ne!(
+            insert_deduplication_token(&batch),
+            insert_deduplication_token(&[event("other.com", first), event("test.com", second)]),
+            "Expected the source to be part of the token"
+        );
+    }
 }
@@ -0,0 +1,457 @@
+use std::{
+    collections::HashSet,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use futures_util::future::join_all;
+use tokio::sync::Semaphore;
+
+use crate::domain::{
+    model::{
+        ingest_action_summary::IngestActionSummary,
+        ingest_event::{ApiKey, IngestEvent, IngestEventSource},
+        ingest_source_rules::IngestSourceRules,
+        server_key::ServerKey,
+    },
+    repository::{
+        event_sink::{EventSink, SinkPolicy},
+        ingest_event_repository::{IngestEventRepository, IngestRepositoryError},
+    },
+};
+
+/// Number of batches that are written to an `Async` sink at a time. Batches
+/// saved while that many are in flight are dropped from the sink, so that a
+/// slow sink cannot accumulate unbounded tasks and memory.
+const ASYNC_SINK_CAPACITY: usize = 64;
+
+/// `SinkCounts` are the writes to an `EventSink` since the last report
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SinkCounts {
+    /// Number of batches that were written
+    pub batches: u64,
+    /// Number of events within the written batches
+    pub events: u64,
+    /// Number of batches that failed to be written
+    pub failures: u64,
+    /// Number of batches dropped as too many were in flight to an `Async` sink
+    pub overflows: u64,
+}
+
+/// `SinkReport` is the `SinkCounts` of one configured `EventSink`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SinkReport {
+    pub name: String,
+    pub policy: SinkPolicy,
+    pub counts: SinkCounts,
+}
+
+/// `ConfiguredSink` is an `EventSink` with its `SinkPolicy` and the counters
+/// of its writes
+#[derive(Debug, Clone)]
+struct ConfiguredSink {
+    sink: Arc<dyn EventSink>,
+    policy: SinkPolicy,
+    batches: Arc<AtomicU64>,
+    events: Arc<AtomicU64>,
+    failures: Arc<AtomicU64>,
+    overflows: Arc<AtomicU64>,
+    in_flight: Arc<Semaphore>,
+}
+
+impl ConfiguredSink {
+    /// Write the events to the sink, counting and logging the outcome.
+    /// Returns whether the write succeeded.
+    async fn write(&self, events: &[IngestEvent]) -> bool {
+        match self.sink.write(events).await {
+            Ok(()) => {
+                self.batches.fetch_add(1, Ordering::Relaxed);
+                self.events
+                    .fetch_add(events.len() as u64, Ordering::Relaxed);
+                true
+            }
+            Err(e) => {
+                self.failures.fetch_add(1, Ordering::Relaxed);
+                tracing::error!(
+                    sink = self.sink.name(),
+                    policy = ?self.policy,
+                    events = events.len(),
+                    "Failed to write events to sink: {e}"
+                );
+                false
+            }
+        }
+    }
+
+    /// Write the events to the sink in the background, unless the sink already
+    /// has `ASYNC_SINK_CAPACITY` batches in flight, in which case they are
+    /// dropped and counted as an overflow
+    fn spawn_write(&self, events: Arc<Vec<IngestEvent>>) {
+        let Ok(permit) = self.in_flight.clone().try_acquire_owned() else {
+            self.overflows.fetch_add(1, Ordering::Relaxed);
+            tracing::error!(
+                sink = self.sink.name(),
+                events = events.len(),
+                "Dropped events as too many batches are in flight to sink"
+            );
+            return;
+        };
+        let sink = self.clone();
+        tokio::spawn(async move {
+            sink.write(&events).await;
+            drop(permit);
+        });
+    }
+
+    fn report(&self) -> SinkReport {
+        SinkReport {
+            name: self.sink.name().to_owned(),
+            policy: self.policy,
+            counts: SinkCounts {
+                batches: self.batches.swap(0, Ordering::Relaxed),
+                events: self.events.swap(0, Ordering::Relaxed),
+                failures: self.failures.swap(0, Ordering::Relaxed),
+                overflows: self.overflows.swap(0, Ordering::Relaxed),
+            },
+        }
+    }
+}
+
+/// `FanOutIngestRepository` is an `IngestEventRepository` that saves events
+/// to a primary repository and then mirrors them to any number of
+/// `EventSink`s. The primary repository is always required and provides the
+/// event sources, rules and server keys. Each sink is written according to
+/// its `SinkPolicy`, so the failure of one sink never prevents the others
+/// from being written. As a save that fails on a required sink is retried by
+/// the client as a whole, delivery to every destination is at least once.
+#[derive(Debug, Clone)]
+pub struct FanOutIngestRepository<R: IngestEventRepository> {
+    primary: R,
+    sinks: Vec<ConfiguredSink>,
+}
+
+impl<R: IngestEventRepository> FanOutIngestRepository<R> {
+    /// `FanOutIngestRepository` constructor with no sinks
+    pub fn new(primary: R) -> Self {
+        Self {
+            primary,
+            sinks: Vec::new(),
+        }
+    }
+
+    /// Add an `EventSink` that is written according to the given policy
+    pub fn with_sink(mut self, sink: impl EventSink, policy: SinkPolicy) -> Self {
+        self.sinks.push(ConfiguredSink {
+            sink: Arc::new(sink),
+            policy,
+            batches: Arc::default(),
+            events: Arc::default(),
+            failures: Arc::default(),
+            overflows: Arc::default(),
+            in_flight: Arc::new(Semaphore::new(ASYNC_SINK_CAPACITY)),
+        });
+        self
+    }
+
+    /// Whether any `EventSink` has been added
+    pub fn has_sinks(&self) -> bool {
+        !self.sinks.is_empty()
+    }
+
+    /// Take the `SinkReport` of every sink, resetting its counts
+    pub fn report(&self) -> Vec<SinkReport> {
+        self.sinks.iter().map(ConfiguredSink::report).collect()
+    }
+}
+
+impl<R: IngestEventRepository> IngestEventRepository for FanOutIngestRepository<R> {
+    async fn save(
+        &self,
+        events: Vec<IngestEvent>,
+    ) -> Result<IngestActionSummary, IngestRepositoryError> {
+        if self.sinks.is_empty() {
+            return self.primary.save(events).await;
+        }
+        let mirrored = Arc::new(events.clone());
+        let summary = self.primary.save(events).await?;
+        for sink in self.sinks.iter().filter(|s| s.policy == SinkPolicy::Async) {
+            sink.spawn_write(mirrored.clone());
+        }
+        let written = join_all(
+            self.sinks
+                .iter()
+                .filter(|s| s.policy != SinkPolicy::Async)
+                .map(|sink| async {
+                    sink.write(&mirrored).await || sink.policy != SinkPolicy::Required
+                }),
+        )
+        .await;
+        if written.contains(&false) {
+            return Err(IngestRepositoryError::Repository);
+        }
+        Ok(summary)
+    }
+
+    async fn event_sources(&self) -> Result<HashSet<IngestEventSource>, IngestRepositoryError> {
+        self.primary.event_sources().await
+    }
+
+    async fn source_rules(
+        &self,
+        source: &IngestEventSource,
+    ) -> Result<IngestSourceRules, IngestRepositoryError> {
+        self.primary.source_rules(source).await
+    }
+
+    async fn is_server_key_valid(
+        &self,
+        api_key: &ApiKey,
+        server_key: &ServerKey,
+    ) -> Result<bool, IngestRepositoryError> {
+        self.primary.is_server_key_valid(api_key, server_key).await
+    }
+
+    async fn reload_event_sources(&self) -> Result<(), IngestRepositoryError> {
+        self.primary.reload_event_sources().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use futures_util::{FutureExt, future::BoxFuture};
+    use uuid::Uuid;
+
+    use crate::domain::{
+        model::{
+            ingest_action_summary::IngestEventSaveSummary,
+            ingest_event::{Site, VisitorEvent},
+        },
+        repository::ingest_event_repository::test::MockIngestEventRepository,
+    };
+
+    use super::*;
+
+    /// Sink that records the ids it is written, or fails every write
+    #[derive(Debug, Default)]
+    struct RecordingSink {
+        fail: bool,
+        written: Arc<Mutex<Vec<Uuid>>>,
+    }
+
+    impl EventSink for RecordingSink {
+        fn name(&self) -> &str {
+            "recording"
+        }
+
+        fn write<'a>(
+            &'a self,
+            events: &'a [IngestEvent],
+        ) -> BoxFuture<'a, Result<(), IngestRepositoryError>> {
+            async move {
+                if self.fail {
+                    return Err(IngestRepositoryError::Repository);
+                }
+                self.written
+                    .lock()
+                    .unwrap()
+                    .extend(events.iter().map(IngestEvent::id));
+                Ok(())
+            }
+            .boxed()
+        }
+    }
+
+    /// Sink whose writes wait until the gate is given a permit
+    #[derive(Debug)]
+    struct GatedSink {
+        gate: Arc<Semaphore>,
+    }
+
+    impl EventSink for GatedSink {
+        fn name(&self) -> &str {
+            "gated"
+        }
+
+        fn write<'a>(
+            &'a self,
+            _events: &'a [IngestEvent],
+        ) -> BoxFuture<'a, Result<(), IngestRepositoryError>> {
+            async move {
+                self.gate.acquire().await.unwrap().forget();
+                Ok(())
+            }
+            .boxed()
+        }
+    }
+
+    fn mock_repo() -> MockIngestEventRepository {
+        MockIngestEventRepository {
+            save_result: Ok(IngestActionSummary::Save(IngestEventSaveSummary {
+                event_count: 1,
+                duplicate_count: 0,
+            })),
+            event_source_result: Ok(HashSet::new()),
+            source_rules_result: Ok(IngestSourceRules::default()),
+            server_key_result: Ok(true),
+        }
+    }
+
+    fn events() -> Vec<IngestEvent> {
+        vec![IngestEvent::Visitor(
+            VisitorEvent::try_new(
+                ApiKey::new("abc-123"),
+                Site::new("test.com"),
+                Uuid::now_v7(),
+            )
+            .unwrap(),
+        )]
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_fan_out_save() {
+        let required = Arc::new(Mutex::new(Vec::new()));
+        let async_written = Arc::new(Mutex::new(Vec::new()));
+        let repo = FanOutIngestRepository::new(mock_repo())
+            .with_sink(
+                RecordingSink {
+                    fail: false,
+                    written: required.clone(),
+                },
+                SinkPolicy::Required,
+            )
+            .with_sink(
+                RecordingSink {
+                    fail: true,
+                    ..RecordingSink::default()
+                },
+                SinkPolicy::BestEffort,
+            )
+            .with_sink(
+                RecordingSink {
+                    fail: false,
+                    written: async_written.clone(),
+                },
+                SinkPolicy::Async,
+            );
+        let events = events();
+        assert!(
+            repo.save(events.clone()).await.is_ok(),
+            "Expected a failed best effort sink not to fail the save"
+        );
+        assert_eq!(*required.lock().unwrap(), vec![events[0].id()]);
+        for _ in 0..100 {
+            if !async_written.lock().unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(*async_written.lock().unwrap(), vec![events[0].id()]);
+
+        let reports = repo.report();
+        assert_eq!(
+            reports
+                .iter()
+                .map(|r| r.counts)
+                .collect::<Vec<SinkCounts>>(),
+            vec![
+                SinkCounts {
+                    batches: 1,
+                    events: 1,
+                    failures: 0,
+                    overflows: 0
+                },
+                SinkCounts {
+                    batches: 0,
+                    events: 0,
+                    failures: 1,
+                    overflows: 0
+                },
+                SinkCounts {
+                    batches: 1,
+                    events: 1,
+                    failures: 0,
+                    overflows: 0
+                },
+            ]
+        );
+        assert_eq!(
+            repo.report()[0].counts,
+            SinkCounts::default(),
+            "Expected counts to be reset by a report"
+        );
+
+        // Negative test cases
+        let failing_required = FanOutIngestRepository::new(mock_repo()).with_sink(
+            RecordingSink {
+                fail: true,
+                ..RecordingSink::default()
+            },
+            SinkPolicy::Required,
+        );
+        assert_eq!(
+            failing_required.save(events.clone()).await.unwrap_err(),
+            IngestRepositoryError::Repository,
+            "Expected a failed required sink to fail the save"
+        );
+
+        let not_written = Arc::new(Mutex::new(Vec::new()));
+        let failing_primary = FanOutIngestRepository::new(MockIngestEventRepository {
+            save_result: Err(IngestRepositoryError::Repository),
+            ..mock_repo()
+        })
+        .with_sink(
+            RecordingSink {
+                fail: false,
+                written: not_written.clone(),
+            },
+            SinkPolicy::BestEffort,
+        );
+        assert!(failing_primary.save(events).await.is_err());
+        assert!(
+            not_written.lock().unwrap().is_empty(),
+            "Expected sinks not to be written when the primary save fails"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_async_sink_overflow() {
+        let gate = Arc::new(Semaphore::new(0));
+        let repo = FanOutIngestRepository::new(mock_repo())
+            .with_sink(GatedSink { gate: gate.clone() }, SinkPolicy::Async);
+        for _ in 0..ASYNC_SINK_CAPACITY + 2 {
+            assert!(
+                repo.save(events()).await.is_ok(),
+                "Expected an overflowing async sink not to fail the save"
+            );
+        }
+        gate.add_permits(ASYNC_SINK_CAPACITY);
+        let mut counts = SinkCounts::default();
+        for _ in 0..100 {
+            let report = repo.report().remove(0).counts;
+            counts.batches += report.batches;
+            counts.events += report.events;
+            counts.overflows += report.overflows;
+            if counts.batches == ASYNC_SINK_CAPACITY as u64 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(
+            counts,
+            SinkCounts {
+                batches: ASYNC_SINK_CAPACITY as u64,
+                events: ASYNC_SINK_CAPACITY as u64,
+                failures: 0,
+                overflows: 2
+            }
+        );
+        assert!(
+            repo.save(events()).await.is_ok() && repo.report()[0].counts.overflows == 0,
+            "Expected finished writes to free their place in the sink"
+        );
+    }
+}
@@ -0,0 +1,349 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::domain::{
+    model::{
+        ingest_action_summary::{IngestActionSummary, IngestEventSaveSummary},
+        ingest_event::{ApiKey, IngestEvent, IngestEventSource},
+        ingest_source_rules::IngestSourceRules,
+        server_key::ServerKey,
+    },
+    repository::ingest_event_repository::{IngestEventRepository, IngestRepositoryError},
+};
+
+use super::sink_event_record::{SinkEventRecord, SinkEventRecordType, sink_event_records};
+
+/// `MemoryEventQuery` selects events kept by a `MemoryIngestRepository`. Every
+/// field that is set must match, and at most `limit` events are returned.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct MemoryEventQuery {
+    pub api_key: Option<String>,
+    pub site: Option<String>,
+    pub event_type: Option<SinkEventRecordType>,
+    pub limit: Option<usize>,
+}
+
+impl MemoryEventQuery {
+    fn matches(&self, record: &SinkEventRecord) -> bool {
+        self.api_key
+            .as_ref()
+            .is_none_or(|api_key| *api_key == record.api_key)
+            && self.site.as_ref().is_none_or(|site| *site == record.site)
+            && self
+                .event_type
+                .is_none_or(|event_type| event_type == record.event_type)
+    }
+}
+
+/// `MemoryEventBuffer` is the ring buffer of saved records along with the ids
+/// it holds, which are used to skip events that are saved again
+#[derive(Debug, Default)]
+struct MemoryEventBuffer {
+    records: VecDeque<SinkEventRecord>,
+    ids: HashSet<Uuid>,
+}
+
+/// `MemoryIngestRepository` is an implementation of the
+/// `IngestEventRepository` trait that keeps the most recent events in memory,
+/// so that the server can run locally without a database and the events that
+/// a client sends can be inspected. Once `capacity` events are held the
+/// oldest are dropped. Events are held as the `SinkEventRecord`s that a sink
+/// would receive. Without any configured event sources, events from every
+/// source are accepted.
+#[derive(Debug, Clone)]
+pub struct MemoryIngestRepository {
+    capacity: usize,
+    event_sources: HashSet<IngestEventSource>,
+    source_rules: HashMap<IngestEventSource, IngestSourceRules>,
+    server_key_digest: Option<String>,
+    buffer: Arc<Mutex<MemoryEventBuffer>>,
+}
+
+impl MemoryIngestRepository {
+    /// `MemoryIngestRepository` constructor that keeps at most `capacity`
+    /// events
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            event_sources: HashSet::new(),
+            source_rules: HashMap::new(),
+            server_key_digest: None,
+            buffer: Arc::default(),
+        }
+    }
+
+    /// Accept events from `source`. Once any source is added, events from
+    /// other sources are refused.
+    pub fn with_event_source(mut self, source: IngestEventSource) -> Self {
+        self.event_sources.insert(source);
+        self
+    }
+
+    /// Apply `rules` to the events of `source`
+    pub fn with_source_rules(
+        mut self,
+        source: IngestEventSource,
+        rules: IngestSourceRules,
+    ) -> Self {
+        self.source_rules.insert(source, rules);
+        self
+    }
+
+    /// Accept `server_key` for every api key
+    pub fn with_server_key(mut self, server_key: &ServerKey) -> Self {
+        self.server_key_digest = Some(server_key.digest());
+        self
+    }
+
+    /// The kept events that match `query`, newest first
+    pub fn events(&self, query: &MemoryEventQuery) -> Vec<SinkEventRecord> {
+        self.buffer()
+            .records
+            .iter()
+            .rev()
+            .filter(|record| query.matches(record))
+            .take(query.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect()
+    }
+
+    /// Number of events that are kept
+    pub fn len(&self) -> usize {
+        self.buffer().records.len()
+    }
+
+    /// Whether no events are kept
+    pub fn is_empty(&self) -> bool {
+        self.buffer().records.is_empty()
+    }
+
+    /// Drop every kept event
+    pub fn clear(&self) {
+        let mut buffer = self.buffer();
+        buffer.records.clear();
+        buffer.ids.clear();
+    }
+
+    fn buffer(&self) -> MutexGuard<'_, MemoryEventBuffer> {
+        self.buffer
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn accepts(&self, source: &IngestEventSource) -> bool {
+        self.event_sources.is_empty() || self.event_sources.contains(source)
+    }
+}
+
+impl IngestEventRepository for MemoryIngestRepository {
+    /// `save` method for memory appends the events to the ring buffer and
+    /// skips any event that is already held, so that a retried batch is not
+    /// kept twice. The skipped events are counted as duplicates.
+    async fn save(
+        &self,
+        events: Vec<IngestEvent>,
+    ) -> Result<IngestActionSummary, IngestRepositoryError> {
+        if events.is_empty() {
+            return Err(IngestRepositoryError::InvalidRequest);
+        }
+        if !events
+            .iter()
+            .all(|event| self.accepts(&IngestEventSource::from(event)))
+        {
+            return Err(IngestRepositoryError::InvalidRequest);
+        }
+        let records = sink_event_records(&events)?;
+        let mut summary = IngestEventSaveSummary::new(0);
+
+        let mut buffer = self.buffer();
+        for record in records {
+            if !buffer.ids.insert(record.id) {
+                summary.duplicate_count += 1;
+                continue;
+            }
+            summary.event_count += 1;
+            if buffer.records.len() == self.capacity
+                && let Some(dropped) = buffer.records.pop_front()
+            {
+                buffer.ids.remove(&dropped.id);
+            }
+            buffer.records.push_back(record);
+        }
+        Ok(IngestActionSummary::Save(summary))
+    }
+
+    async fn event_sources(&self) -> Result<HashSet<IngestEventSource>, IngestRepositoryError> {
+        Ok(self.event_sources.clone())
+    }
+
+    async fn source_rules(
+        &self,
+        source: &IngestEventSource,
+    ) -> Result<IngestSourceRules, IngestRepositoryError> {
+        Ok(self.source_rules.get(source).cloned().unwrap_or_default())
+    }
+
+    async fn is_server_key_valid(
+        &self,
+        _api_key: &ApiKey,
+        server_key: &ServerKey,
+    ) -> Result<bool, IngestRepositoryError> {
+        Ok(self
+            .server_key_digest
+            .as_ref()
+            .is_some_and(|digest| *digest == server_key.digest()))
+    }
+
+    /// `reload_event_sources` for memory has nothing to reload, as the sources
+    /// are only configured on construction
+    async fn reload_event_sources(&self) -> Result<(), IngestRepositoryError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::model::ingest_event::{ClickEvent, Site, VisitorEvent};
+
+    use super::*;
+
+    fn visitor_event(site: &str) -> IngestEvent {
+        IngestEvent::Visitor(
+            VisitorEvent::try_new(ApiKey::new("abc-123"), Site::new(site), Uuid::now_v7()).unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_save() {
+        let source = IngestEventSource::new(ApiKey::new("abc-123"), Site::new("test.com"));
+        let test_repository = MemoryIngestRepository::new(3)
+            .with_event_source(source.clone())
+            .with_server_key(&ServerKey::new("secret"));
+        assert_eq!(
+            test_repository.event_sources().await.unwrap(),
+            HashSet::from([source])
+        );
+        assert!(
+            test_repository
+                .is_server_key_valid(&ApiKey::new("any"), &ServerKey::new("secret"))
+                .await
+                .unwrap()
+        );
+
+        let events: Vec<IngestEvent> = (0..4).map(|_| visitor_event("test.com")).collect();
+        let Ok(IngestActionSummary::Save(save_summary)) =
+            test_repository.save(events[..2].to_vec()).await
+        else {
+            panic!("Expected action save summary to be returned");
+        };
+        assert_eq!(
+            (save_summary.event_count, save_summary.duplicate_count),
+            (2, 0)
+        );
+        let Ok(IngestActionSummary::Save(retried_summary)) =
+            test_repository.save(events[..2].to_vec()).await
+        else {
+            panic!("Expected action save summary to be returned");
+        };
+        assert_eq!(
+            (retried_summary.event_count, retried_summary.duplicate_count),
+            (0, 2),
+            "Expected the events of a retried batch to be counted as duplicates"
+        );
+        assert_eq!(
+            test_repository.len(),
+            2,
+            "Expected a retried batch not to be kept twice"
+        );
+        test_repository.save(events[2..].to_vec()).await.unwrap();
+        assert_eq!(
+            test_repository
+                .events(&MemoryEventQuery::default())
+                .iter()
+                .map(|record| record.id)
+                .collect::<Vec<Uuid>>(),
+            vec![events[3].id(), events[2].id(), events[1].id()],
+            "Expected the oldest event to be dropped and the newest returned first"
+        );
+
+        test_repository.clear();
+        assert!(test_repository.is_empty());
+
+        // Negative test cases
+        assert_eq!(
+            test_repository.save(Vec::new()).await.unwrap_err(),
+            IngestRepositoryError::InvalidRequest
+        );
+        assert_eq!(
+            test_repository
+                .save(vec![visitor_event("other.com")])
+                .await
+                .unwrap_err(),
+            IngestRepositoryError::InvalidRequest,
+            "Expected events of unknown sources to be refused"
+        );
+        assert!(
+            !test_repository
+                .is_server_key_valid(&ApiKey::new("abc-123"), &ServerKey::new("wrong"))
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_events_query() {
+        let test_repository = MemoryIngestRepository::new(10);
+        let visitor = visitor_event("test.com");
+        let click = IngestEvent::Click(
+            ClickEvent::try_new(
+                ApiKey::new("abc-123"),
+                Site::new("test.com"),
+                Uuid::now_v7(),
+                visitor.id(),
+            )
+            .unwrap(),
+        );
+        let other_site = visitor_event("other.com");
+        test_repository
+            .save(vec![visitor.clone(), click.clone(), other_site])
+            .await
+            .unwrap();
+        assert_eq!(
+            test_repository.len(),
+            3,
+            "Expected every source to be accepted without configured sources"
+        );
+
+        let site_query = MemoryEventQuery {
+            site: Some("test.com".to_owned()),
+            ..MemoryEventQuery::default()
+        };
+        assert_eq!(test_repository.events(&site_query).len(), 2);
+        let click_query = MemoryEventQuery {
+            event_type: Some(SinkEventRecordType::Click),
+            ..site_query.clone()
+        };
+        assert_eq!(
+            test_repository
+                .events(&click_query)
+                .iter()
+                .map(|record| record.id)
+                .collect::<Vec<Uuid>>(),
+            vec![click.id()]
+        );
+        let limit_query = MemoryEventQuery {
+            limit: Some(1),
+            ..site_query
+        };
+        assert_eq!(
+            test_repository.events(&limit_query)[0].id,
+            click.id(),
+            "Expected the limit to keep the newest events"
+        );
+    }
+}
@@ -1,3 +1,23 @@
+pub mod archive_file;
+pub mod backend_ingest_repository;
 pub(crate) mod clickhouse_event_record;
 pub mod clickhouse_ingest_repository;
-pub(crate) mod clickhouse_source_record;
+pub mod fan_out_ingest_repository;
+pub mod memory_ingest_repository;
+#[cfg(feature = "parquet")]
+pub(crate) mod parquet_archive_file;
+#[cfg(feature = "postgres")]
+pub(crate) mod postgres_event_record;
+#[cfg(feature = "postgres")]
+pub mod postgres_ingest_repository;
+pub mod rotating_archive_sink;
+pub(crate) mod server_key_record;
+pub mod sink_event_record;
+pub(crate) mod source_catalog;
+pub(crate) mod source_record;
+pub(crate) mod source_rule_record;
+#[cfg(feature = "sqlite")]
+pub(crate) mod sqlite_event_record;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_ingest_repository;
+pub mod webhook_sink;
@@ -0,0 +1,229 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use parquet::{
+    basic::{Compression, LogicalType, Repetition, TimeUnit, Type as PhysicalType, ZstdLevel},
+    data_type::{ByteArray, ByteArrayType, FixedLenByteArray, FixedLenByteArrayType, Int64Type},
+    file::{
+        properties::WriterProperties,
+        reader::{FileReader, SerializedFileReader},
+        writer::SerializedFileWriter,
+    },
+    format::MilliSeconds,
+    record::RowAccessor,
+    schema::types::{Type, TypePtr},
+};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::domain::repository::ingest_event_repository::IngestRepositoryError;
+
+use super::sink_event_record::{SinkEventRecord, SinkEventRecordType};
+
+/// Maximum number of records within a single row group
+const PARQUET_ROW_GROUP_SIZE: usize = 65_536;
+
+/// Schema of a Parquet archive file. `id` is stored as a UUID, `ts` as
+/// milliseconds since the Unix epoch and `attrs` as a JSON array of pairs,
+/// exactly as within the JSON Lines archive.
+fn archive_schema() -> parquet::errors::Result<TypePtr> {
+    let string_column = |name: &str| {
+        Type::primitive_type_builder(name, PhysicalType::BYTE_ARRAY)
+            .with_repetition(Repetition::REQUIRED)
+            .with_logical_type(Some(LogicalType::String))
+            .build()
+            .map(Arc::new)
+    };
+    let fields = vec![
+        string_column("api_key")?,
+        string_column("site")?,
+        string_column("event_type")?,
+        Arc::new(
+            Type::primitive_type_builder("id", PhysicalType::FIXED_LEN_BYTE_ARRAY)
+                .with_repetition(Repetition::REQUIRED)
+                .with_length(16)
+                .with_logical_type(Some(LogicalType::Uuid))
+                .build()?,
+        ),
+        Arc::new(
+            Type::primitive_type_builder("ts", PhysicalType::INT64)
+                .with_repetition(Repetition::REQUIRED)
+                .with_logical_type(Some(LogicalType::Timestamp {
+                    is_adjusted_to_u_t_c: true,
+                    unit: TimeUnit::MILLIS(MilliSeconds {}),
+                }))
+                .build()?,
+        ),
+        string_column("attrs")?,
+    ];
+    Ok(Arc::new(
+        Type::group_type_builder("event")
+            .with_fields(fields)
+            .build()?,
+    ))
+}
+
+/// Encode the records as a zstd compressed Parquet file
+pub(crate) fn encode(records: &[SinkEventRecord]) -> Result<Vec<u8>, IngestRepositoryError> {
+    try_encode(records).map_err(|e| {
+        tracing::error!("Error encoding Parquet archive: {e}");
+        IngestRepositoryError::Conversion
+    })
+}
+
+/// Decode the records of a Parquet file written by `encode`
+pub(crate) fn decode(contents: Vec<u8>) -> Result<Vec<SinkEventRecord>, IngestRepositoryError> {
+    try_decode(contents).map_err(|e| {
+        tracing::error!("Error decoding Parquet archive: {e}");
+        IngestRepositoryError::Conversion
+    })
+}
+
+fn try_encode(records: &[SinkEventRecord]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::ZSTD(ZstdLevel::default()))
+        .build();
+    let mut contents = Vec::new();
+    let mut writer =
+        SerializedFileWriter::new(&mut contents, archive_schema()?, properties.into())?;
+    for chunk in records.chunks(PARQUET_ROW_GROUP_SIZE) {
+        let strings = |field: fn(&SinkEventRecord) -> &str| {
+            chunk
+                .iter()
+                .map(|record| ByteArray::from(field(record)))
+                .collect::<Vec<ByteArray>>()
+        };
+        let attrs = chunk
+            .iter()
+            .map(|record| serde_json::to_vec(&record.attrs).map(ByteArray::from))
+            .collect::<Result<Vec<ByteArray>, serde_json::Error>>()?;
+        let string_columns = [
+            strings(|record| &record.api_key),
+            strings(|record| &record.site),
+            strings(|record| event_type_name(record.event_type)),
+        ];
+        let ids: Vec<FixedLenByteArray> = chunk
+            .iter()
+            .map(|record| FixedLenByteArray::from(record.id.as_bytes().to_vec()))
+            .collect();
+        let timestamps: Vec<i64> = chunk
+            .iter()
+            .map(|record| (record.ts.unix_timestamp_nanos() / 1_000_000) as i64)
+            .collect();
+
+        let mut row_group = writer.next_row_group()?;
+        for values in string_columns.iter() {
+            let mut column = row_group.next_column()?.ok_or("missing column")?;
+            column
+                .typed::<ByteArrayType>()
+                .write_batch(values, None, None)?;
+            column.close()?;
+        }
+        let mut column = row_group.next_column()?.ok_or("missing id column")?;
+        column
+            .typed::<FixedLenByteArrayType>()
+            .write_batch(&ids, None, None)?;
+        column.close()?;
+        let mut column = row_group.next_column()?.ok_or("missing ts column")?;
+        column
+            .typed::<Int64Type>()
+            .write_batch(&timestamps, None, None)?;
+        column.close()?;
+        let mut column = row_group.next_column()?.ok_or("missing attrs column")?;
+        column
+            .typed::<ByteArrayType>()
+            .write_batch(&attrs, None, None)?;
+        column.close()?;
+        row_group.close()?;
+    }
+    writer.close()?;
+    Ok(contents)
+}
+
+fn try_decode(contents: Vec<u8>) -> Result<Vec<SinkEventRecord>, Box<dyn std::error::Error>> {
+    let reader = SerializedFileReader::new(Bytes::from(contents))?;
+    let mut records = Vec::with_capacity(reader.metadata().file_metadata().num_rows() as usize);
+    for row in reader.get_row_iter(None)? {
+        let row = row?;
+        records.push(SinkEventRecord {
+            api_key: row.get_string(0)?.to_owned(),
+            site: row.get_string(1)?.to_owned(),
+            event_type: event_type_from_name(row.get_string(2)?).ok_or("unknown event_type")?,
+            id: Uuid::from_slice(row.get_bytes(3)?.data())?,
+            ts: OffsetDateTime::from_unix_timestamp_nanos(
+                i128::from(row.get_timestamp_millis(4)?) * 1_000_000,
+            )?,
+            attrs: serde_json::from_str(row.get_string(5)?)?,
+        });
+    }
+    Ok(records)
+}
+
+/// Name of the type as serialized within a `SinkEventRecord`
+fn event_type_name(event_type: SinkEventRecordType) -> &'static str {
+    match event_type {
+        SinkEventRecordType::Visitor => "visitor",
+        SinkEventRecordType::Session => "session",
+        SinkEventRecordType::Section => "section",
+        SinkEventRecordType::Click => "click",
+        SinkEventRecordType::Custom => "custom",
+        SinkEventRecordType::Identify => "identify",
+    }
+}
+
+fn event_type_from_name(name: &str) -> Option<SinkEventRecordType> {
+    match name {
+        "visitor" => Some(SinkEventRecordType::Visitor),
+        "session" => Some(SinkEventRecordType::Session),
+        "section" => Some(SinkEventRecordType::Section),
+        "click" => Some(SinkEventRecordType::Click),
+        "custom" => Some(SinkEventRecordType::Custom),
+        "identify" => Some(SinkEventRecordType::Identify),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::model::ingest_event::{ApiKey, ClickEvent, IngestEvent, Site};
+
+    use super::*;
+
+    #[test]
+    fn test_parquet_archive_file() {
+        let records: Vec<SinkEventRecord> = (0..3)
+            .map(|_| {
+                SinkEventRecord::try_from(&IngestEvent::Click(
+                    ClickEvent::try_new(
+                        ApiKey::new("abc-123"),
+                        Site::new("test.com"),
+                        Uuid::now_v7(),
+                        Uuid::now_v7(),
+                    )
+                    .unwrap(),
+                ))
+                .unwrap()
+            })
+            .collect();
+        let contents = encode(&records).unwrap();
+        assert_eq!(&contents[..4], b"PAR1", "Expected a Parquet file");
+        let decoded = decode(contents).unwrap();
+        assert_eq!(decoded.len(), records.len());
+        for (decoded, record) in decoded.iter().zip(records.iter()) {
+            assert_eq!(decoded.id, record.id);
+            assert_eq!(decoded.event_type, SinkEventRecordType::Click);
+            assert_eq!(decoded.attrs, record.attrs);
+            assert_eq!(
+                decoded.ts.unix_timestamp_nanos() / 1_000_000,
+                record.ts.unix_timestamp_nanos() / 1_000_000,
+                "Expected ts to be kept in milliseconds"
+            );
+        }
+
+        // Negative test case
+        assert_eq!(
+            decode(b"not parquet".to_vec()).unwrap_err(),
+            IngestRepositoryError::Conversion
+        );
+    }
+}
@@ -0,0 +1,82 @@
+use serde_json::{Map, Value};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::domain::{
+    model::ingest_event::IngestEvent, repository::ingest_event_repository::IngestRepositoryError,
+};
+
+use super::clickhouse_event_record::ClickhouseEventRecord;
+
+/// `PostgresEventRecord` is a row of the PostgreSQL `event` table. It holds
+/// the same data as a `ClickhouseEventRecord`, with `attrs` as a JSON object
+/// in place of the ClickHouse Map.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PostgresEventRecord {
+    pub(crate) api_key: String,
+    pub(crate) site: String,
+    pub(crate) event_type: &'static str,
+    pub(crate) id: Uuid,
+    pub(crate) ts: OffsetDateTime,
+    pub(crate) attrs: Value,
+}
+
+impl From<ClickhouseEventRecord> for PostgresEventRecord {
+    fn from(value: ClickhouseEventRecord) -> Self {
+        Self {
+            api_key: value.api_key,
+            site: value.site,
+            event_type: value.event_type.name(),
+            id: value.id,
+            ts: value.ts,
+            attrs: Value::Object(
+                value
+                    .attrs
+                    .into_iter()
+                    .map(|(key, value)| (key, Value::String(value)))
+                    .collect::<Map<String, Value>>(),
+            ),
+        }
+    }
+}
+
+impl TryFrom<&IngestEvent> for PostgresEventRecord {
+    type Error = IngestRepositoryError;
+    fn try_from(value: &IngestEvent) -> Result<Self, Self::Error> {
+        ClickhouseEventRecord::try_from(value).map(Self::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use crate::domain::model::ingest_event::{ApiKey, SessionEvent, Site};
+
+    use super::*;
+
+    #[test]
+    fn test_postgres_event_record() {
+        let id = Uuid::now_v7();
+        let parent = Uuid::now_v7();
+        let event = IngestEvent::Session(
+            SessionEvent::try_new(
+                ApiKey::new("abc-123"),
+                Site::new("test.com"),
+                id,
+                parent,
+                "Mozilla/5.0".to_owned(),
+                IpAddr::V4(Ipv4Addr::LOCALHOST),
+            )
+            .unwrap(),
+        );
+        let record = PostgresEventRecord::try_from(&event).unwrap();
+        assert_eq!(record.event_type, "Session");
+        assert_eq!(record.id, id);
+        assert_eq!(record.attrs["parent"], parent.to_string());
+        assert_eq!(
+            record.attrs["ipv4"], "127.0.0.1",
+            "Expected attrs to be stored as a JSON object"
+        );
+    }
+}
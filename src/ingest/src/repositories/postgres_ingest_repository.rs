@@ -0,0 +1,490 @@
+use std::collections::HashSet;
+use std::pin::pin;
+
+use deadpool_postgres::{Manager, Pool};
+use tokio_postgres::{NoTls, Row, binary_copy::BinaryCopyInWriter, types::Type};
+use tracing::instrument;
+
+use crate::domain::model::ingest_action_summary::{IngestActionSummary, IngestEventSaveSummary};
+use crate::domain::model::ingest_event::{ApiKey, IngestEvent, IngestEventSource};
+use crate::domain::model::ingest_source_rules::IngestSourceRules;
+use crate::domain::model::server_key::ServerKey;
+use crate::domain::repository::ingest_event_repository::{
+    IngestEventRepository, IngestRepositoryError,
+};
+
+use super::postgres_event_record::PostgresEventRecord;
+use super::server_key_record::ServerKeyRecord;
+use super::source_catalog::{SharedSourceCatalog, SourceCatalog};
+use super::source_record::SourceRecord;
+use super::source_rule_record::{
+    IdentityKeyRecord, PathRuleRecord, QueryParamRecord, QueryParamRule,
+};
+
+/// Tables of the PostgreSQL backend, which are created on startup
+const POSTGRES_SCHEMA: &str = include_str!("../../../../sql/postgres/schema/ingest.sql");
+
+/// Statements that turn the `event` table into a TimescaleDB hypertable
+const TIMESCALE_SCHEMA: &str = "CREATE EXTENSION IF NOT EXISTS timescaledb;
+SELECT create_hypertable('event', 'ts', if_not_exists => TRUE, migrate_data => TRUE);";
+
+/// Column types of the binary COPY of `PostgresEventRecord`s
+const EVENT_COLUMN_TYPES: [Type; 6] = [
+    Type::TEXT,
+    Type::TEXT,
+    Type::TEXT,
+    Type::UUID,
+    Type::TIMESTAMPTZ,
+    Type::JSONB,
+];
+
+/// `PostgresIngestRepository` is an implementation of the
+/// `IngestEventRepository` trait for PostgreSQL, optionally with TimescaleDB,
+/// for deployments that do not run ClickHouse. Events are stored in a single
+/// `event` table and the event sources and their rules are loaded from tables
+/// that mirror those of ClickHouse.
+#[derive(Clone)]
+pub struct PostgresIngestRepository {
+    pool: Pool,
+    catalog: SharedSourceCatalog,
+}
+
+/// Load a `SourceCatalog` from the PostgreSQL tables of the sources, their
+/// rules and the server keys. The rows are read into the same records as those
+/// of the other backends, so that they are folded into rules in the same way.
+async fn load_source_catalog(pool: &Pool) -> Result<SourceCatalog, IngestRepositoryError> {
+    let client = pool.get().await.map_err(|e| {
+        tracing::error!("Encountered error connecting to PostgreSQL: {e}");
+        IngestRepositoryError::Repository
+    })?;
+    let source_records: Vec<SourceRecord> =
+        query_rows(&client, "SELECT api_key, site FROM api_key")
+            .await?
+            .iter()
+            .map(|row| SourceRecord::new(row.get::<_, &str>(0), row.get::<_, &str>(1)))
+            .collect();
+    let query_param_records: Vec<QueryParamRecord> = query_rows(
+        &client,
+        "SELECT api_key, site, param, rule FROM source_query_param",
+    )
+    .await?
+    .iter()
+    .map(|row| {
+        let rule = match row.get::<_, &str>(3) {
+            "Allow" => QueryParamRule::Allow,
+            _ => QueryParamRule::Deny,
+        };
+        QueryParamRecord::new(
+            row.get::<_, &str>(0),
+            row.get::<_, &str>(1),
+            row.get::<_, &str>(2),
+            rule,
+        )
+    })
+    .collect();
+    let path_rule_records = query_rows(
+            &client,
+            "SELECT api_key, site, rewrites, collapse_numeric, collapse_uuid, trim_trailing_slash, lowercase FROM source_path_rule",
+        )
+        .await?
+        .iter()
+        .map(|row| {
+            let rewrites = serde_json::from_value(row.get(2)).map_err(|e| {
+                tracing::error!("Encountered invalid path rewrites in PostgreSQL: {e}");
+                IngestRepositoryError::Conversion
+            })?;
+            Ok(PathRuleRecord::new(
+                row.get::<_, &str>(0),
+                row.get::<_, &str>(1),
+                rewrites,
+                row.get(3),
+                row.get(4),
+                row.get(5),
+                row.get(6),
+            ))
+        })
+        .collect::<Result<Vec<PathRuleRecord>, IngestRepositoryError>>()?;
+    let identity_key_records: Vec<IdentityKeyRecord> = query_rows(
+        &client,
+        "SELECT api_key, site, identity_key FROM source_identity_key",
+    )
+    .await?
+    .iter()
+    .map(|row| {
+        IdentityKeyRecord::new(
+            row.get::<_, &str>(0),
+            row.get::<_, &str>(1),
+            row.get::<_, &str>(2),
+        )
+    })
+    .collect();
+    let server_key_records: Vec<ServerKeyRecord> =
+        query_rows(&client, "SELECT api_key, key_sha256 FROM server_key")
+            .await?
+            .iter()
+            .map(|row| ServerKeyRecord::new(row.get::<_, &str>(0), row.get::<_, &str>(1)))
+            .collect();
+    Ok(SourceCatalog::from_records(
+        &source_records,
+        &query_param_records,
+        &path_rule_records,
+        &identity_key_records,
+        &server_key_records,
+    ))
+}
+
+impl std::fmt::Debug for PostgresIngestRepository {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PostgresIngestRepository").finish()
+    }
+}
+
+impl PostgresIngestRepository {
+    /// Connect to the database at `url`, create the schema if needed and load
+    /// the event sources. With `timescale` the `event` table is converted to
+    /// a TimescaleDB hypertable, which requires the extension to be available.
+    pub async fn try_new(url: &str, timescale: bool) -> Result<Self, IngestRepositoryError> {
+        let pg_config = url.parse::<tokio_postgres::Config>().map_err(|e| {
+            tracing::error!("Encountered invalid PostgreSQL url: {e}");
+            IngestRepositoryError::InvalidRequest
+        })?;
+        let pool = Pool::builder(Manager::new(pg_config, NoTls))
+            .build()
+            .map_err(|e| {
+                tracing::error!("Encountered error creating PostgreSQL pool: {e}");
+                IngestRepositoryError::Repository
+            })?;
+        let client = pool.get().await.map_err(|e| {
+            tracing::error!("Encountered error connecting to PostgreSQL: {e}");
+            IngestRepositoryError::Repository
+        })?;
+        client.batch_execute(POSTGRES_SCHEMA).await.map_err(|e| {
+            tracing::error!("Encountered error creating PostgreSQL schema: {e}");
+            IngestRepositoryError::Repository
+        })?;
+        if timescale {
+            client.batch_execute(TIMESCALE_SCHEMA).await.map_err(|e| {
+                tracing::error!("Encountered error creating TimescaleDB hypertable: {e}");
+                IngestRepositoryError::Repository
+            })?;
+        }
+        drop(client);
+        let catalog = load_source_catalog(&pool).await?;
+        Ok(Self {
+            pool,
+            catalog: SharedSourceCatalog::new(catalog),
+        })
+    }
+}
+
+impl IngestEventRepository for PostgresIngestRepository {
+    /// `save` method for PostgreSQL copies the events into a temporary table
+    /// and moves them into `event` from there, skipping any event whose id
+    /// has already been stored, so that neither a retried batch nor a
+    /// synthesized visitor emitted again with a later `ts` is stored twice.
+    /// The skipped events are counted as duplicates.
+    #[instrument]
+    async fn save(
+        &self,
+        events: Vec<IngestEvent>,
+    ) -> Result<IngestActionSummary, IngestRepositoryError> {
+        if events.is_empty() {
+            return Err(IngestRepositoryError::InvalidRequest);
+        }
+        let catalog = self.catalog.snapshot();
+        let mut records: Vec<PostgresEventRecord> = Vec::with_capacity(events.len());
+        for event in events.iter() {
+            if !catalog
+                .event_sources
+                .contains(&IngestEventSource::from(event))
+            {
+                return Err(IngestRepositoryError::InvalidRequest);
+            }
+            records.push(PostgresEventRecord::try_from(event)?);
+        }
+
+        let mut client = self.pool.get().await.map_err(|e| {
+            tracing::error!("Encountered error connecting to PostgreSQL: {e}");
+            IngestRepositoryError::Repository
+        })?;
+        let transaction = client.transaction().await.map_err(|e| {
+            tracing::error!("Encountered error starting PostgreSQL transaction: {e}");
+            IngestRepositoryError::Repository
+        })?;
+        transaction
+            .batch_execute(
+                "CREATE TEMPORARY TABLE event_staging (LIKE event INCLUDING DEFAULTS) ON COMMIT DROP",
+            )
+            .await
+            .map_err(|e| {
+                tracing::error!("Encountered error creating staging table: {e}");
+                IngestRepositoryError::Repository
+            })?;
+        let sink = transaction
+            .copy_in(
+                "COPY event_staging (api_key, site, event_type, id, ts, attrs) FROM STDIN BINARY",
+            )
+            .await
+            .map_err(|e| {
+                tracing::error!("Encountered error initiating PostgreSQL COPY: {e}");
+                IngestRepositoryError::Repository
+            })?;
+        let mut writer = pin!(BinaryCopyInWriter::new(sink, &EVENT_COLUMN_TYPES));
+        for record in records.iter() {
+            writer
+                .as_mut()
+                .write(&[
+                    &record.api_key,
+                    &record.site,
+                    &record.event_type,
+                    &record.id,
+                    &record.ts,
+                    &record.attrs,
+                ])
+                .await
+                .map_err(|e| {
+                    tracing::error!("Encountered error copying records: {e}");
+                    IngestRepositoryError::Repository
+                })?;
+        }
+        writer.finish().await.map_err(|e| {
+            tracing::error!("Encountered error ending PostgreSQL COPY: {e}");
+            IngestRepositoryError::Repository
+        })?;
+        let inserted = transaction
+            .execute(
+                "INSERT INTO event SELECT DISTINCT ON (api_key, site, id) * FROM event_staging s
+                WHERE NOT EXISTS (
+                    SELECT 1 FROM event e
+                    WHERE e.api_key = s.api_key AND e.site = s.site AND e.id = s.id
+                )
+                ORDER BY api_key, site, id, ts
+                ON CONFLICT DO NOTHING",
+                &[],
+            )
+            .await
+            .map_err(|e| {
+                tracing::error!("Encountered error inserting records: {e}");
+                IngestRepositoryError::Repository
+            })? as usize;
+        transaction.commit().await.map_err(|e| {
+            tracing::error!("Encountered error committing PostgreSQL transaction: {e}");
+            IngestRepositoryError::Repository
+        })?;
+
+        Ok(IngestActionSummary::Save(IngestEventSaveSummary {
+            event_count: inserted,
+            duplicate_count: records.len() - inserted,
+        }))
+    }
+
+    async fn event_sources(&self) -> Result<HashSet<IngestEventSource>, IngestRepositoryError> {
+        Ok(self.catalog.snapshot().event_sources.clone())
+    }
+
+    async fn source_rules(
+        &self,
+        source: &IngestEventSource,
+    ) -> Result<IngestSourceRules, IngestRepositoryError> {
+        Ok(self.catalog.snapshot().source_rules(source))
+    }
+
+    async fn is_server_key_valid(
+        &self,
+        api_key: &ApiKey,
+        server_key: &ServerKey,
+    ) -> Result<bool, IngestRepositoryError> {
+        Ok(self
+            .catalog
+            .snapshot()
+            .is_server_key_valid(api_key, server_key))
+    }
+
+    /// `reload_event_sources` for PostgreSQL loads a fresh snapshot of the
+    /// sources and rules. The current snapshot is kept if loading fails.
+    async fn reload_event_sources(&self) -> Result<(), IngestRepositoryError> {
+        let catalog = load_source_catalog(&self.pool).await?;
+        self.catalog.replace(catalog);
+        Ok(())
+    }
+}
+
+async fn query_rows(
+    client: &tokio_postgres::Client,
+    query: &str,
+) -> Result<Vec<Row>, IngestRepositoryError> {
+    client.query(query, &[]).await.map_err(|e| {
+        tracing::error!("Encountered error fetching {query}: {e}. This is likely due to connection problems with PostgreSQL.");
+        IngestRepositoryError::Repository
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use time::{Duration, OffsetDateTime};
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::domain::model::ingest_event::{
+        IngestEventCore, IngestEventOrigin, Site, VisitorEvent,
+    };
+
+    /// Url of the database used by the tests, which must allow the creation
+    /// of tables. The tests are skipped unless it is set.
+    fn test_postgres_url() -> Option<String> {
+        std::env::var("SALUS_INGEST_TEST_POSTGRES_URL").ok()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_save() {
+        let Some(url) = test_postgres_url() else {
+            eprintln!("Skipping test_save as SALUS_INGEST_TEST_POSTGRES_URL is not set");
+            return;
+        };
+        // Sources are unique to this run so that runs never see each other
+        let api_key = Uuid::now_v7().to_string();
+        let test_repository = PostgresIngestRepository::try_new(&url, false)
+            .await
+            .unwrap();
+        let client = test_repository.pool.get().await.unwrap();
+        client
+            .execute(
+                "INSERT INTO api_key (api_key, site) VALUES ($1, 'test.com')",
+                &[&api_key],
+            )
+            .await
+            .unwrap();
+        client
+            .execute(
+                "INSERT INTO server_key (api_key, key_sha256) VALUES ($1, $2)",
+                &[&api_key, &ServerKey::new("secret").digest()],
+            )
+            .await
+            .unwrap();
+        client
+            .execute(
+                "INSERT INTO source_path_rule (api_key, site, rewrites, lowercase) VALUES ($1, 'test.com', '[[\"^/p/\", \"/product/\"]]', true)",
+                &[&api_key],
+            )
+            .await
+            .unwrap();
+        test_repository.reload_event_sources().await.unwrap();
+
+        let source = IngestEventSource::new(ApiKey::new(&api_key), Site::new("test.com"));
+        assert!(
+            test_repository
+                .event_sources()
+                .await
+                .unwrap()
+                .contains(&source),
+            "Expected source to be loaded from the api_key table"
+        );
+        assert!(
+            test_repository
+                .source_rules(&source)
+                .await
+                .unwrap()
+                .path_normalization
+                .lowercase,
+            "Expected path rules to be loaded"
+        );
+        assert!(
+            test_repository
+                .is_server_key_valid(&ApiKey::new(&api_key), &ServerKey::new("secret"))
+                .await
+                .unwrap()
+        );
+
+        let events: Vec<IngestEvent> = (0..3)
+            .map(|_| {
+                IngestEvent::Visitor(
+                    VisitorEvent::try_new(
+                        ApiKey::new(&api_key),
+                        Site::new("test.com"),
+                        Uuid::now_v7(),
+                    )
+                    .unwrap(),
+                )
+            })
+            .collect();
+        let Ok(IngestActionSummary::Save(save_summary)) =
+            test_repository.save(events.clone()).await
+        else {
+            panic!("Expected action save summary to be returned");
+        };
+        assert_eq!(
+            (save_summary.event_count, save_summary.duplicate_count),
+            (3, 0)
+        );
+        let Ok(IngestActionSummary::Save(retried_summary)) = test_repository.save(events).await
+        else {
+            panic!("Expected action save summary to be returned");
+        };
+        assert_eq!(
+            (retried_summary.event_count, retried_summary.duplicate_count),
+            (0, 3),
+            "Expected the events of a retried batch to be counted as duplicates"
+        );
+        let stored: i64 = client
+            .query_one("SELECT count(*) FROM event WHERE api_key = $1", &[&api_key])
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(stored, 3, "Expected a retried batch not to be stored twice");
+
+        let visitor_id = Uuid::now_v7();
+        let now = OffsetDateTime::now_utc();
+        let synthesized_visitor = |ts| {
+            IngestEvent::Visitor(
+                VisitorEvent::try_new_with_core_event(
+                    IngestEventCore::try_new_at(
+                        ApiKey::new(&api_key),
+                        Site::new("test.com"),
+                        visitor_id,
+                        ts,
+                        IngestEventOrigin::Client,
+                    )
+                    .unwrap(),
+                )
+                .unwrap(),
+            )
+        };
+        test_repository
+            .save(vec![synthesized_visitor(now)])
+            .await
+            .unwrap();
+        let Ok(IngestActionSummary::Save(emitted_again_summary)) = test_repository
+            .save(vec![synthesized_visitor(now + Duration::minutes(1))])
+            .await
+        else {
+            panic!("Expected action save summary to be returned");
+        };
+        assert_eq!(
+            (
+                emitted_again_summary.event_count,
+                emitted_again_summary.duplicate_count
+            ),
+            (0, 1),
+            "Expected a visitor emitted again with a later ts to be counted as a duplicate"
+        );
+
+        // Negative test cases
+        assert_eq!(
+            test_repository.save(Vec::new()).await.unwrap_err(),
+            IngestRepositoryError::InvalidRequest
+        );
+        let unknown_source = vec![IngestEvent::Visitor(
+            VisitorEvent::try_new(
+                ApiKey::new(&api_key),
+                Site::new("other.com"),
+                Uuid::now_v7(),
+            )
+            .unwrap(),
+        )];
+        assert_eq!(
+            test_repository.save(unknown_source).await.unwrap_err(),
+            IngestRepositoryError::InvalidRequest,
+            "Expected events of unknown sources to be refused"
+        );
+    }
+}
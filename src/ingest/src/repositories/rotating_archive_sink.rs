@@ -0,0 +1,710 @@
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, hash_map::Entry},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use futures_util::{FutureExt, future::BoxFuture};
+use sha2::{Digest, Sha256};
+use time::{Date, OffsetDateTime, UtcOffset};
+use tokio::{
+    fs::{self, File},
+    io::AsyncWriteExt,
+    sync::Mutex,
+};
+use uuid::Uuid;
+
+use crate::domain::{
+    model::ingest_event::IngestEvent,
+    repository::{event_sink::EventSink, ingest_event_repository::IngestRepositoryError},
+};
+
+use super::{
+    archive_file::{ARCHIVE_IN_PROGRESS_SUFFIX, ArchiveFileFormat, ArchiveManifest, sha256_hex},
+    sink_event_record::{SinkEventRecord, sink_event_records},
+};
+
+/// Size in bytes at which an archive file is closed by default
+pub const DEFAULT_ROTATION_BYTES: u64 = 128 * 1024 * 1024;
+
+/// Age at which an archive file is closed by default
+pub const DEFAULT_ROTATION_AGE: Duration = Duration::from_secs(3600);
+
+/// Size in bytes of the records that the files of all partitions may hold in
+/// memory by default, before the largest are closed
+pub const DEFAULT_MAX_BUFFERED_BYTES: u64 = 256 * 1024 * 1024;
+
+/// `ArchivePartition` is the site and UTC date of the records within an
+/// archive file
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ArchivePartition {
+    site: String,
+    date: Date,
+}
+
+impl From<&SinkEventRecord> for ArchivePartition {
+    fn from(value: &SinkEventRecord) -> Self {
+        Self {
+            site: value.site.to_owned(),
+            date: value.ts.to_offset(UtcOffset::UTC).date(),
+        }
+    }
+}
+
+impl ArchivePartition {
+    /// Directory of the partition within the archive at `root`, named in the
+    /// `key=value` style that query engines recognize as partitions
+    fn dir(&self, root: &Path) -> PathBuf {
+        root.join(format!("site={}", path_component(&self.site)))
+            .join(format!("date={}", self.date))
+    }
+}
+
+/// The site as a single path component. Sites are host names, so anything
+/// else is replaced in order that a site can never point outside the archive.
+fn path_component(site: &str) -> String {
+    let component: String = site
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if component.trim_matches('.').is_empty() {
+        "_".repeat(component.len().max(1))
+    } else {
+        component
+    }
+}
+
+/// Path of the file at `path` while it is still being written
+fn in_progress_path(path: &Path) -> PathBuf {
+    let mut in_progress = path.as_os_str().to_owned();
+    in_progress.push(ARCHIVE_IN_PROGRESS_SUFFIX);
+    PathBuf::from(in_progress)
+}
+
+/// Log a failed file operation and map it onto `IngestRepositoryError`
+fn io_error(
+    action: &'static str,
+    path: &Path,
+) -> impl FnOnce(std::io::Error) -> IngestRepositoryError {
+    move |e| {
+        tracing::error!("Error {action} archive {}: {e}", path.display());
+        IngestRepositoryError::Repository
+    }
+}
+
+/// `ArchiveLayout` is where a `RotatingArchiveSink` writes its files
+#[derive(Debug, Clone)]
+enum ArchiveLayout {
+    /// Files partitioned by site and UTC date within the directory
+    Partitioned(PathBuf),
+    /// A single file that is appended to in place
+    Appending(PathBuf),
+}
+
+/// `ArchiveContents` are the records written to an open archive file
+#[derive(Debug)]
+enum ArchiveContents {
+    /// JSON Lines are appended to the in progress file as they arrive
+    Jsonl { file: File, hasher: Sha256 },
+    /// Formats that can only be written whole hold their records until the
+    /// file is closed
+    Buffered(Vec<SinkEventRecord>),
+}
+
+/// `OpenArchiveFile` is the archive file of a partition that is being written
+#[derive(Debug)]
+struct OpenArchiveFile {
+    /// Partition of a rotated file, which is renamed and given a manifest
+    /// when it is closed. The file of an appending archive has none.
+    partition: Option<ArchivePartition>,
+    path: PathBuf,
+    opened_at: Instant,
+    records: u64,
+    bytes: u64,
+    first_ts: Option<OffsetDateTime>,
+    last_ts: Option<OffsetDateTime>,
+    contents: ArchiveContents,
+}
+
+impl OpenArchiveFile {
+    /// Append the records, counting their size as JSON Lines
+    async fn append(&mut self, records: Vec<SinkEventRecord>) -> Result<(), IngestRepositoryError> {
+        let lines = ArchiveFileFormat::Jsonl.encode(&records)?;
+        for record in records.iter() {
+            self.first_ts = Some(self.first_ts.map_or(record.ts, |ts| ts.min(record.ts)));
+            self.last_ts = Some(self.last_ts.map_or(record.ts, |ts| ts.max(record.ts)));
+        }
+        self.records += records.len() as u64;
+        self.bytes += lines.len() as u64;
+        match &mut self.contents {
+            ArchiveContents::Jsonl { file, hasher } => {
+                file.write_all(&lines)
+                    .await
+                    .map_err(io_error("writing", &self.path))?;
+                hasher.update(&lines);
+            }
+            ArchiveContents::Buffered(buffered) => buffered.extend(records),
+        }
+        Ok(())
+    }
+}
+
+/// `RotatingArchiveSink` is an `EventSink` that keeps an immutable archive of
+/// every event as `SinkEventRecord`s, within a directory partitioned by site
+/// and UTC date. Each partition has one open file, which carries the
+/// `ARCHIVE_IN_PROGRESS_SUFFIX` while it is written. Once the file holds
+/// `max_bytes` of records or has been open for `max_age` it is closed: it is
+/// renamed to its final name and never written again, and an
+/// `ArchiveManifest` with its checksum is written next to it.
+///
+/// JSON Lines are appended as they arrive. Parquet files can only be written
+/// whole, so their records are held in memory until the file is closed and
+/// `max_bytes` bounds their size as JSON. Once the files of all partitions
+/// hold `max_buffered_bytes`, the largest are closed early. Files are encoded
+/// and closed outside the lock on the open files, so that writes to other
+/// partitions continue meanwhile. As files are only closed by writes,
+/// `rotate_expired` must be called periodically and `close_all` on shutdown.
+#[derive(Debug, Clone)]
+pub struct RotatingArchiveSink {
+    layout: ArchiveLayout,
+    format: ArchiveFileFormat,
+    max_bytes: u64,
+    max_age: Duration,
+    max_buffered_bytes: u64,
+    files: Arc<Mutex<HashMap<Option<ArchivePartition>, OpenArchiveFile>>>,
+}
+
+impl RotatingArchiveSink {
+    /// `RotatingArchiveSink` constructor that writes files of the given
+    /// format within `dir`, creating any missing directories
+    pub fn new(dir: impl Into<PathBuf>, format: ArchiveFileFormat) -> Self {
+        Self {
+            layout: ArchiveLayout::Partitioned(dir.into()),
+            format,
+            max_bytes: DEFAULT_ROTATION_BYTES,
+            max_age: DEFAULT_ROTATION_AGE,
+            max_buffered_bytes: DEFAULT_MAX_BUFFERED_BYTES,
+            files: Arc::default(),
+        }
+    }
+
+    /// `RotatingArchiveSink` constructor that appends every event as a line
+    /// of JSON to the single file at `path`, creating it if it does not
+    /// exist. The file is never rotated and has no manifest, and it is
+    /// reopened after any failed write.
+    pub fn appending(path: impl Into<PathBuf>) -> Self {
+        Self {
+            layout: ArchiveLayout::Appending(path.into()),
+            format: ArchiveFileFormat::Jsonl,
+            max_bytes: u64::MAX,
+            max_age: Duration::MAX,
+            max_buffered_bytes: DEFAULT_MAX_BUFFERED_BYTES,
+            files: Arc::default(),
+        }
+    }
+
+    /// Close each file once it holds `max_bytes` of records
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Close each file once it has been open for `max_age`
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Close the largest files held in memory once they hold
+    /// `max_buffered_bytes` of records together
+    pub fn with_max_buffered_bytes(mut self, max_buffered_bytes: u64) -> Self {
+        self.max_buffered_bytes = max_buffered_bytes;
+        self
+    }
+
+    /// Close every file that has been open for `max_age`
+    pub async fn rotate_expired(&self) -> Result<(), IngestRepositoryError> {
+        self.close_where(|file| file.opened_at.elapsed() >= self.max_age)
+            .await
+    }
+
+    /// Close every open file, which is required before shutting down in
+    /// order that no file is left without a manifest
+    pub async fn close_all(&self) -> Result<(), IngestRepositoryError> {
+        self.close_where(|_| true).await
+    }
+
+    async fn close_where(
+        &self,
+        predicate: impl Fn(&OpenArchiveFile) -> bool,
+    ) -> Result<(), IngestRepositoryError> {
+        let closing: Vec<OpenArchiveFile> = {
+            let mut files = self.files.lock().await;
+            let partitions: Vec<Option<ArchivePartition>> = files
+                .iter()
+                .filter(|(_, file)| predicate(file))
+                .map(|(partition, _)| partition.clone())
+                .collect();
+            partitions
+                .iter()
+                .filter_map(|partition| files.remove(partition))
+                .collect()
+        };
+        self.close_files(closing).await
+    }
+
+    /// Partition of the record within the archive
+    fn partition(&self, record: &SinkEventRecord) -> Option<ArchivePartition> {
+        match self.layout {
+            ArchiveLayout::Partitioned(_) => Some(ArchivePartition::from(record)),
+            ArchiveLayout::Appending(_) => None,
+        }
+    }
+
+    async fn append(&self, events: &[IngestEvent]) -> Result<(), IngestRepositoryError> {
+        let mut partitions: Vec<(Option<ArchivePartition>, Vec<SinkEventRecord>)> = Vec::new();
+        for record in sink_event_records(events)? {
+            let partition = self.partition(&record);
+            match partitions.iter_mut().find(|(key, _)| *key == partition) {
+                Some((_, records)) => records.push(record),
+                None => partitions.push((partition, vec![record])),
+            }
+        }
+        let mut closing = Vec::new();
+        let appended = {
+            // Writes are serialized so that the records of batches never
+            // interleave and each partition has a single open file
+            let mut files = self.files.lock().await;
+            let appended = self
+                .append_partitions(&mut files, partitions, &mut closing)
+                .await;
+            closing.extend(self.take_excess_buffered(&mut files));
+            appended
+        };
+        let closed = self.close_files(closing).await;
+        appended.and(closed)
+    }
+
+    /// Append the records of each partition to its open file, taking the
+    /// files that are due to be closed into `closing`
+    async fn append_partitions(
+        &self,
+        files: &mut HashMap<Option<ArchivePartition>, OpenArchiveFile>,
+        partitions: Vec<(Option<ArchivePartition>, Vec<SinkEventRecord>)>,
+        closing: &mut Vec<OpenArchiveFile>,
+    ) -> Result<(), IngestRepositoryError> {
+        for (partition, records) in partitions {
+            if files
+                .get(&partition)
+                .is_some_and(|file| file.opened_at.elapsed() >= self.max_age)
+            {
+                closing.extend(files.remove(&partition));
+            }
+            let file = match files.entry(partition.clone()) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(self.open(partition.as_ref()).await?),
+            };
+            // A file that failed to be written is abandoned and left without
+            // a manifest, so that it is never mistaken for a complete one
+            if let Err(e) = file.append(records).await {
+                files.remove(&partition);
+                return Err(e);
+            }
+            if file.bytes >= self.max_bytes {
+                closing.extend(files.remove(&partition));
+            }
+        }
+        Ok(())
+    }
+
+    /// Take the largest files held in memory until the rest hold less than
+    /// `max_buffered_bytes` of records
+    fn take_excess_buffered(
+        &self,
+        files: &mut HashMap<Option<ArchivePartition>, OpenArchiveFile>,
+    ) -> Vec<OpenArchiveFile> {
+        let mut buffered: Vec<(Option<ArchivePartition>, u64)> = files
+            .iter()
+            .filter(|(_, file)| matches!(file.contents, ArchiveContents::Buffered(_)))
+            .map(|(partition, file)| (partition.clone(), file.bytes))
+            .collect();
+        let mut buffered_bytes: u64 = buffered.iter().map(|(_, bytes)| bytes).sum();
+        buffered.sort_by_key(|(_, bytes)| Reverse(*bytes));
+        let mut taken = Vec::new();
+        for (partition, bytes) in buffered {
+            if buffered_bytes < self.max_buffered_bytes {
+                break;
+            }
+            buffered_bytes -= bytes;
+            taken.extend(files.remove(&partition));
+        }
+        taken
+    }
+
+    /// Close the files, continuing after a failure so that one file cannot
+    /// keep the others open
+    async fn close_files(&self, files: Vec<OpenArchiveFile>) -> Result<(), IngestRepositoryError> {
+        let mut result = Ok(());
+        for file in files {
+            if let Err(e) = self.close(file).await {
+                result = Err(e);
+            }
+        }
+        result
+    }
+
+    async fn open(
+        &self,
+        partition: Option<&ArchivePartition>,
+    ) -> Result<OpenArchiveFile, IngestRepositoryError> {
+        let (path, contents) = match (&self.layout, partition) {
+            (ArchiveLayout::Partitioned(root), Some(partition)) => {
+                let dir = partition.dir(root);
+                fs::create_dir_all(&dir)
+                    .await
+                    .map_err(io_error("creating", &dir))?;
+                let path = dir.join(format!(
+                    "part-{}.{}",
+                    Uuid::now_v7(),
+                    self.format.extension()
+                ));
+                let contents = match self.format {
+                    ArchiveFileFormat::Jsonl => {
+                        let in_progress = in_progress_path(&path);
+                        ArchiveContents::Jsonl {
+                            file: File::options()
+                                .create_new(true)
+                                .write(true)
+                                .open(&in_progress)
+                                .await
+                                .map_err(io_error("opening", &in_progress))?,
+                            hasher: Sha256::new(),
+                        }
+                    }
+                    ArchiveFileFormat::Parquet => ArchiveContents::Buffered(Vec::new()),
+                };
+                (path, contents)
+            }
+            (ArchiveLayout::Appending(path), None) => {
+                let contents = ArchiveContents::Jsonl {
+                    file: File::options()
+                        .create(true)
+                        .append(true)
+                        .open(path)
+                        .await
+                        .map_err(io_error("opening", path))?,
+                    hasher: Sha256::new(),
+                };
+                (path.to_owned(), contents)
+            }
+            _ => {
+                tracing::error!("Archive partition does not match the archive layout");
+                return Err(IngestRepositoryError::InvalidRequest);
+            }
+        };
+        Ok(OpenArchiveFile {
+            partition: partition.cloned(),
+            path,
+            opened_at: Instant::now(),
+            records: 0,
+            bytes: 0,
+            first_ts: None,
+            last_ts: None,
+            contents,
+        })
+    }
+
+    async fn close(&self, file: OpenArchiveFile) -> Result<(), IngestRepositoryError> {
+        let Some(partition) = file.partition else {
+            // The file of an appending archive is written in place
+            if let ArchiveContents::Jsonl {
+                file: open_file, ..
+            } = file.contents
+            {
+                open_file
+                    .sync_all()
+                    .await
+                    .map_err(io_error("syncing", &file.path))?;
+            }
+            return Ok(());
+        };
+        let in_progress = in_progress_path(&file.path);
+        let (bytes, sha256) = match file.contents {
+            ArchiveContents::Jsonl {
+                file: open_file,
+                hasher,
+            } => {
+                open_file
+                    .sync_all()
+                    .await
+                    .map_err(io_error("syncing", &in_progress))?;
+                (file.bytes, sha256_hex(hasher.finalize()))
+            }
+            ArchiveContents::Buffered(records) => {
+                let format = self.format;
+                let contents = tokio::task::spawn_blocking(move || format.encode(&records))
+                    .await
+                    .map_err(|e| {
+                        tracing::error!("Archive encoding task failed: {e}");
+                        IngestRepositoryError::Repository
+                    })??;
+                let mut open_file = File::create(&in_progress)
+                    .await
+                    .map_err(io_error("creating", &in_progress))?;
+                open_file
+                    .write_all(&contents)
+                    .await
+                    .map_err(io_error("writing", &in_progress))?;
+                open_file
+                    .sync_all()
+                    .await
+                    .map_err(io_error("syncing", &in_progress))?;
+                (contents.len() as u64, sha256_hex(Sha256::digest(&contents)))
+            }
+        };
+        fs::rename(&in_progress, &file.path)
+            .await
+            .map_err(io_error("renaming", &in_progress))?;
+
+        let now = OffsetDateTime::now_utc();
+        let manifest = ArchiveManifest {
+            file: file
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            format: self.format,
+            site: partition.site,
+            date: partition.date.to_string(),
+            records: file.records,
+            bytes,
+            sha256,
+            first_ts: file.first_ts.unwrap_or(now),
+            last_ts: file.last_ts.unwrap_or(now),
+            closed_at: now,
+        };
+        let manifest_path = ArchiveManifest::path_for(&file.path);
+        let manifest_in_progress = in_progress_path(&manifest_path);
+        let manifest_json = serde_json::to_vec_pretty(&manifest).map_err(|e| {
+            tracing::error!("Error serializing archive manifest: {e}");
+            IngestRepositoryError::Conversion
+        })?;
+        fs::write(&manifest_in_progress, manifest_json)
+            .await
+            .map_err(io_error("writing manifest of", &file.path))?;
+        fs::rename(&manifest_in_progress, &manifest_path)
+            .await
+            .map_err(io_error("renaming manifest of", &file.path))?;
+        tracing::info!(
+            file = %file.path.display(),
+            records = manifest.records,
+            bytes = manifest.bytes,
+            "Closed archive file"
+        );
+        Ok(())
+    }
+}
+
+impl EventSink for RotatingArchiveSink {
+    fn name(&self) -> &str {
+        "archive"
+    }
+
+    fn write<'a>(
+        &'a self,
+        events: &'a [IngestEvent],
+    ) -> BoxFuture<'a, Result<(), IngestRepositoryError>> {
+        self.append(events).boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::model::ingest_event::{ApiKey, Site, VisitorEvent};
+
+    use super::*;
+
+    fn visitor_events(site: &str, count: usize) -> Vec<IngestEvent> {
+        (0..count)
+            .map(|_| {
+                IngestEvent::Visitor(
+                    VisitorEvent::try_new(ApiKey::new("abc-123"), Site::new(site), Uuid::now_v7())
+                        .unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    /// Read every closed file below `dir` and verify it against its manifest
+    async fn closed_files(dir: &Path) -> Vec<(ArchiveManifest, Vec<SinkEventRecord>)> {
+        let mut closed = Vec::new();
+        let mut dirs = vec![dir.to_owned()];
+        while let Some(dir) = dirs.pop() {
+            let mut entries = fs::read_dir(&dir).await.unwrap();
+            while let Some(entry) = entries.next_entry().await.unwrap() {
+                let path = entry.path();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if path.to_string_lossy().ends_with(".manifest.json") {
+                    let manifest: ArchiveManifest =
+                        serde_json::from_slice(&fs::read(&path).await.unwrap()).unwrap();
+                    let contents = fs::read(dir.join(&manifest.file)).await.unwrap();
+                    assert!(
+                        manifest.verify(&contents),
+                        "Expected the file to match its manifest"
+                    );
+                    let records = manifest.format.decode(contents).unwrap();
+                    closed.push((manifest, records));
+                }
+            }
+        }
+        closed.sort_by(|(a, _), (b, _)| a.file.cmp(&b.file));
+        closed
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_rotating_archive_sink() {
+        let dir = std::env::temp_dir().join(Uuid::now_v7().to_string());
+        let sink = RotatingArchiveSink::new(&dir, ArchiveFileFormat::Jsonl).with_max_bytes(1);
+        let events = visitor_events("test.com", 3);
+        sink.write(&events[..2]).await.unwrap();
+        sink.write(&events[2..]).await.unwrap();
+        let closed = closed_files(&dir).await;
+        assert_eq!(
+            closed.len(),
+            2,
+            "Expected a file to be closed once it reached max bytes"
+        );
+        assert_eq!(closed[0].0.records, 2);
+        assert_eq!(closed[0].0.site, "test.com");
+        assert_eq!(closed[0].1[0].id, events[0].id());
+        let date = OffsetDateTime::now_utc().date();
+        assert!(
+            dir.join("site=test.com")
+                .join(format!("date={date}"))
+                .is_dir(),
+            "Expected files to be partitioned by site and date"
+        );
+
+        let sink = RotatingArchiveSink::new(&dir, ArchiveFileFormat::Jsonl);
+        sink.write(&visitor_events("test.com", 1)).await.unwrap();
+        sink.write(&visitor_events("other.com", 1)).await.unwrap();
+        sink.rotate_expired().await.unwrap();
+        assert_eq!(
+            closed_files(&dir).await.len(),
+            2,
+            "Expected files to stay open until they expire"
+        );
+        sink.close_all().await.unwrap();
+        assert_eq!(closed_files(&dir).await.len(), 4);
+        fs::remove_dir_all(&dir).await.unwrap();
+
+        assert_eq!(path_component("../etc"), ".._etc");
+        assert_eq!(path_component(".."), "__");
+
+        // Negative test case
+        let file_in_place_of_dir = std::env::temp_dir().join(format!("{}.jsonl", Uuid::now_v7()));
+        fs::write(&file_in_place_of_dir, b"").await.unwrap();
+        assert_eq!(
+            RotatingArchiveSink::new(&file_in_place_of_dir, ArchiveFileFormat::Jsonl)
+                .write(&events)
+                .await
+                .unwrap_err(),
+            IngestRepositoryError::Repository
+        );
+        fs::remove_file(&file_in_place_of_dir).await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_appending_archive_sink() {
+        let path = std::env::temp_dir().join(format!("{}.jsonl", Uuid::now_v7()));
+        let sink = RotatingArchiveSink::appending(&path);
+        let events = visitor_events("test.com", 3);
+        sink.write(&events[..1]).await.unwrap();
+        sink.close_all().await.unwrap();
+        sink.write(&events[1..]).await.unwrap();
+        sink.close_all().await.unwrap();
+
+        let archived = fs::read(&path).await.unwrap();
+        fs::remove_file(&path).await.unwrap();
+        assert_eq!(
+            ArchiveFileFormat::Jsonl
+                .decode(archived)
+                .unwrap()
+                .iter()
+                .map(|record| record.id)
+                .collect::<Vec<Uuid>>(),
+            events.iter().map(IngestEvent::id).collect::<Vec<Uuid>>(),
+            "Expected every event to be appended to the same file in order"
+        );
+        assert!(
+            !ArchiveManifest::path_for(&path).exists(),
+            "Expected no manifest for an appending archive"
+        );
+
+        // Negative test case
+        let missing_dir = std::env::temp_dir()
+            .join(Uuid::now_v7().to_string())
+            .join("events.jsonl");
+        assert_eq!(
+            RotatingArchiveSink::appending(missing_dir)
+                .write(&events)
+                .await
+                .unwrap_err(),
+            IngestRepositoryError::Repository
+        );
+    }
+
+    #[cfg(feature = "parquet")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_rotating_parquet_archive_sink() {
+        let dir = std::env::temp_dir().join(Uuid::now_v7().to_string());
+        let sink = RotatingArchiveSink::new(&dir, ArchiveFileFormat::Parquet);
+        let events = visitor_events("test.com", 3);
+        sink.write(&events).await.unwrap();
+        assert!(
+            closed_files(&dir).await.is_empty(),
+            "Expected Parquet records to be held until the file is closed"
+        );
+        sink.close_all().await.unwrap();
+        let closed = closed_files(&dir).await;
+        fs::remove_dir_all(&dir).await.unwrap();
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].0.format, ArchiveFileFormat::Parquet);
+        assert_eq!(
+            closed[0]
+                .1
+                .iter()
+                .map(|record| record.id)
+                .collect::<Vec<Uuid>>(),
+            events.iter().map(IngestEvent::id).collect::<Vec<Uuid>>()
+        );
+
+        let sink = RotatingArchiveSink::new(&dir, ArchiveFileFormat::Parquet)
+            .with_max_buffered_bytes(1024);
+        sink.write(&visitor_events("test.com", 1)).await.unwrap();
+        assert!(
+            closed_files(&dir).await.is_empty(),
+            "Expected records below the buffered limit to be held"
+        );
+        sink.write(&visitor_events("other.com", 8)).await.unwrap();
+        let closed = closed_files(&dir).await;
+        fs::remove_dir_all(&dir).await.unwrap();
+        assert_eq!(
+            closed
+                .iter()
+                .map(|(manifest, _)| (manifest.site.as_str(), manifest.records))
+                .collect::<Vec<(&str, u64)>>(),
+            vec![("other.com", 8)],
+            "Expected the largest partition to be closed once the buffered limit is reached"
+        );
+    }
+}
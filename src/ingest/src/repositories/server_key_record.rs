@@ -0,0 +1,63 @@
+use std::collections::{HashMap, HashSet};
+
+use clickhouse::Row;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::model::ingest_event::ApiKey;
+
+/// `ServerKeyRecord` represents a single server key that has been
+/// issued for an api_key. Only the SHA-256 digest of the key is stored.
+#[derive(Debug, Clone, PartialEq, Eq, Row, Deserialize, Serialize)]
+pub struct ServerKeyRecord {
+    api_key: String,
+    key_sha256: String,
+}
+
+impl ServerKeyRecord {
+    #[cfg(any(test, feature = "postgres", feature = "sqlite"))]
+    pub fn new(api_key: impl AsRef<str>, key_sha256: impl AsRef<str>) -> Self {
+        Self {
+            api_key: api_key.as_ref().to_string(),
+            key_sha256: key_sha256.as_ref().to_string(),
+        }
+    }
+}
+
+/// Group the server key digests by the `ApiKey` they were issued for
+pub(crate) fn server_keys_from_records(
+    records: &[ServerKeyRecord],
+) -> HashMap<ApiKey, HashSet<String>> {
+    let mut server_keys: HashMap<ApiKey, HashSet<String>> = HashMap::new();
+    for record in records.iter() {
+        server_keys
+            .entry(ApiKey::new(&record.api_key))
+            .or_default()
+            .insert(record.key_sha256.trim().to_lowercase());
+    }
+    server_keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_keys_from_records() {
+        let records = vec![
+            ServerKeyRecord::new("abc-123", "AAAA"),
+            ServerKeyRecord::new("abc-123", "bbbb"),
+            ServerKeyRecord::new("xyz-789", "cccc"),
+        ];
+        let server_keys = server_keys_from_records(&records);
+        let abc_keys = server_keys.get(&ApiKey::new("abc-123")).unwrap();
+        assert_eq!(abc_keys.len(), 2, "Expected both keys for abc-123");
+        assert!(
+            abc_keys.contains("aaaa"),
+            "Expected digests to be normalized to lowercase"
+        );
+        assert!(
+            !abc_keys.contains("cccc"),
+            "Expected keys to be grouped by api_key"
+        );
+    }
+}
@@ -0,0 +1,295 @@
+use std::{collections::HashMap, net::IpAddr};
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::domain::{
+    model::ingest_event::{
+        ApiKey, ClickEvent, CustomEvent, IdentifyEvent, IngestEvent, IngestEventCore,
+        IngestEventOrigin, SectionEvent, SessionEvent, Site, VisitorEvent,
+    },
+    repository::ingest_event_repository::IngestRepositoryError,
+};
+
+use super::clickhouse_event_record::{
+    CUSTOM_PROP_ATTR_PREFIX, ClickhouseEventRecord, ClickhouseEventRecordType,
+    IDENTIFY_TRAIT_ATTR_PREFIX,
+};
+
+/// `SinkEventRecordType` names the type of a `SinkEventRecord`, in place of
+/// the ClickHouse Enum8 value of `ClickhouseEventRecordType`
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SinkEventRecordType {
+    Visitor,
+    Session,
+    Section,
+    Click,
+    Custom,
+    Identify,
+}
+
+impl From<&ClickhouseEventRecordType> for SinkEventRecordType {
+    fn from(value: &ClickhouseEventRecordType) -> Self {
+        match value {
+            ClickhouseEventRecordType::Visitor => Self::Visitor,
+            ClickhouseEventRecordType::Session => Self::Session,
+            ClickhouseEventRecordType::Section => Self::Section,
+            ClickhouseEventRecordType::Click => Self::Click,
+            ClickhouseEventRecordType::Custom => Self::Custom,
+            ClickhouseEventRecordType::Identify => Self::Identify,
+        }
+    }
+}
+
+/// `SinkEventRecord` is the JSON form of a `ClickhouseEventRecord` that is
+/// written to every `EventSink`. It holds the same row as is inserted into
+/// the `EVENT` table, with the type named and the timestamp in RFC 3339, so
+/// that archives and webhooks receive exactly what ClickHouse does.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct SinkEventRecord {
+    pub api_key: String,
+    pub site: String,
+    pub event_type: SinkEventRecordType,
+    pub id: Uuid,
+    #[serde(with = "time::serde::rfc3339")]
+    pub ts: OffsetDateTime,
+    pub attrs: Vec<(String, String)>,
+}
+
+impl From<ClickhouseEventRecord> for SinkEventRecord {
+    fn from(value: ClickhouseEventRecord) -> Self {
+        Self {
+            api_key: value.api_key,
+            site: value.site,
+            event_type: (&value.event_type).into(),
+            id: value.id,
+            ts: value.ts,
+            attrs: value.attrs,
+        }
+    }
+}
+
+impl TryFrom<&IngestEvent> for SinkEventRecord {
+    type Error = IngestRepositoryError;
+    fn try_from(value: &IngestEvent) -> Result<Self, Self::Error> {
+        ClickhouseEventRecord::try_from(value).map(Self::from)
+    }
+}
+
+/// `IngestEvent` restored from a `SinkEventRecord`, such as one read back from
+/// an archive. The record holds the event as it was saved, after scrubbing
+/// and with the user id already hashed, so the restored event can be saved
+/// again as is. Its `ts` and `origin` attribute are kept, and the timestamp
+/// is only checked against the window of imported events.
+impl TryFrom<&SinkEventRecord> for IngestEvent {
+    type Error = IngestRepositoryError;
+    fn try_from(value: &SinkEventRecord) -> Result<Self, Self::Error> {
+        let mut attrs: HashMap<String, String> = value.attrs.iter().cloned().collect();
+        let origin = match attrs.remove("origin").as_deref() {
+            None => IngestEventOrigin::Client,
+            Some("server") => IngestEventOrigin::Server,
+            Some("import") => IngestEventOrigin::Import,
+            Some(_) => return Err(IngestRepositoryError::Conversion),
+        };
+        let core = IngestEventCore::try_new_archived(
+            ApiKey::new(&value.api_key),
+            Site::new(&value.site),
+            value.id,
+            value.ts,
+            origin,
+        )
+        .map_err(|_| IngestRepositoryError::Conversion)?;
+        let event = match value.event_type {
+            SinkEventRecordType::Visitor => {
+                VisitorEvent::try_new_with_core_event(core).map(IngestEvent::Visitor)
+            }
+            SinkEventRecordType::Session => {
+                let ip = attrs
+                    .remove("ipv4")
+                    .or_else(|| attrs.remove("ipv6"))
+                    .and_then(|ip| ip.parse::<IpAddr>().ok())
+                    .ok_or(IngestRepositoryError::Conversion)?;
+                SessionEvent::try_new_with_core_event(
+                    core,
+                    parent_attr(&mut attrs)?,
+                    attrs
+                        .remove("user_agent")
+                        .ok_or(IngestRepositoryError::Conversion)?,
+                    ip,
+                )
+                .map(|event| IngestEvent::Session(event.with_referrer(attrs.remove("referrer"))))
+            }
+            SinkEventRecordType::Section => SectionEvent::try_new_with_core_event(
+                core,
+                parent_attr(&mut attrs)?,
+                attrs.remove("location"),
+                attrs.remove("title"),
+            )
+            .map(|mut event| {
+                event.path = attrs.remove("path");
+                IngestEvent::Section(event)
+            }),
+            SinkEventRecordType::Click => {
+                ClickEvent::try_new_with_core_event(core, parent_attr(&mut attrs)?)
+                    .map(IngestEvent::Click)
+            }
+            SinkEventRecordType::Custom => CustomEvent::try_new_with_core_event(
+                core,
+                parent_attr(&mut attrs)?,
+                attrs.remove("name").unwrap_or_default(),
+                attrs.remove("location"),
+                prefixed_attrs(&attrs, CUSTOM_PROP_ATTR_PREFIX),
+            )
+            .map(IngestEvent::Custom),
+            SinkEventRecordType::Identify => IdentifyEvent::try_new_pseudonymized(
+                core,
+                parent_attr(&mut attrs)?,
+                attrs.remove("user_hash").unwrap_or_default(),
+                prefixed_attrs(&attrs, IDENTIFY_TRAIT_ATTR_PREFIX),
+            )
+            .map(IngestEvent::Identify),
+        };
+        event.map_err(|_| IngestRepositoryError::Conversion)
+    }
+}
+
+/// Remove and parse the `parent` attribute of a record
+fn parent_attr(attrs: &mut HashMap<String, String>) -> Result<Uuid, IngestRepositoryError> {
+    attrs
+        .remove("parent")
+        .and_then(|parent| parent.parse().ok())
+        .ok_or(IngestRepositoryError::Conversion)
+}
+
+/// The attributes whose names start with `prefix`, with the prefix removed
+fn prefixed_attrs(attrs: &HashMap<String, String>, prefix: &str) -> HashMap<String, String> {
+    attrs
+        .iter()
+        .filter_map(|(key, value)| {
+            key.strip_prefix(prefix)
+                .map(|key| (key.to_owned(), value.to_owned()))
+        })
+        .collect()
+}
+
+/// Convert a batch of `IngestEvent`s into the `SinkEventRecord`s to write
+pub(crate) fn sink_event_records(
+    events: &[IngestEvent],
+) -> Result<Vec<SinkEventRecord>, IngestRepositoryError> {
+    events.iter().map(SinkEventRecord::try_from).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::model::identity_key::IdentityKey;
+
+    use super::*;
+
+    #[test]
+    fn test_sink_event_record() {
+        let id = Uuid::now_v7();
+        let parent = Uuid::now_v7();
+        let event = IngestEvent::Click(
+            ClickEvent::try_new(ApiKey::new("abc-123"), Site::new("test.com"), id, parent).unwrap(),
+        );
+        let record = SinkEventRecord::try_from(&event).unwrap();
+        assert_eq!(record.event_type, SinkEventRecordType::Click);
+        assert_eq!(record.id, id);
+
+        let json: serde_json::Value = serde_json::to_value(&record).unwrap();
+        assert_eq!(json["event_type"], "click");
+        assert_eq!(json["id"], id.to_string());
+        assert!(
+            json["ts"].as_str().unwrap().contains('T'),
+            "Expected ts to be formatted as RFC 3339"
+        );
+        assert_eq!(
+            serde_json::from_value::<SinkEventRecord>(json).unwrap(),
+            record,
+            "Expected record to survive a round trip through JSON"
+        );
+    }
+
+    #[test]
+    fn test_ingest_event_from_sink_event_record() {
+        let api_key = ApiKey::new("abc-123");
+        let site = Site::new("test.com");
+        let core = IngestEventCore::try_new_with_origin(
+            api_key.clone(),
+            site.clone(),
+            Uuid::now_v7(),
+            IngestEventOrigin::Server,
+        )
+        .unwrap();
+        let mut identify = IdentifyEvent::try_new_with_core_event(
+            core,
+            Uuid::now_v7(),
+            "user-1".to_owned(),
+            HashMap::from([("plan".to_owned(), "pro".to_owned())]),
+        )
+        .unwrap();
+        identify.pseudonymize(&IdentityKey::new("key"));
+        let mut section = SectionEvent::try_new(
+            api_key.clone(),
+            site.clone(),
+            Uuid::now_v7(),
+            Uuid::now_v7(),
+            Some("https://test.com/a?b=c".to_owned()),
+            Some("Title".to_owned()),
+        )
+        .unwrap();
+        section.path = Some("/a".to_owned());
+        let events = [
+            IngestEvent::Session(
+                SessionEvent::try_new(
+                    api_key.clone(),
+                    site.clone(),
+                    Uuid::now_v7(),
+                    Uuid::now_v7(),
+                    "Mozilla/5.0".to_owned(),
+                    IpAddr::from([10, 0, 0, 1]),
+                )
+                .unwrap()
+                .with_referrer(Some("https://example.com".to_owned())),
+            ),
+            IngestEvent::Section(section),
+            IngestEvent::Custom(
+                CustomEvent::try_new(
+                    api_key,
+                    site,
+                    Uuid::now_v7(),
+                    Uuid::now_v7(),
+                    "signup".to_owned(),
+                    None,
+                    HashMap::from([("plan".to_owned(), "pro".to_owned())]),
+                )
+                .unwrap(),
+            ),
+            IngestEvent::Identify(identify),
+        ];
+        for event in events.iter() {
+            let record = SinkEventRecord::try_from(event).unwrap();
+            let restored = IngestEvent::try_from(&record).unwrap();
+            assert_eq!(
+                ClickhouseEventRecord::try_from(&restored)
+                    .unwrap()
+                    .with_sorted_attrs(),
+                ClickhouseEventRecord::try_from(event)
+                    .unwrap()
+                    .with_sorted_attrs(),
+                "Expected the restored event to be saved exactly as the original"
+            );
+        }
+
+        // Negative test case
+        let mut record = SinkEventRecord::try_from(&events[2]).unwrap();
+        record.attrs.retain(|(key, _)| key != "parent");
+        assert_eq!(
+            IngestEvent::try_from(&record).unwrap_err(),
+            IngestRepositoryError::Conversion,
+            "Expected a record without a parent to be refused"
+        );
+    }
+}
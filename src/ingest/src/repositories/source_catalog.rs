@@ -0,0 +1,143 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+use crate::domain::model::{
+    ingest_event::{ApiKey, IngestEventSource},
+    ingest_source_rules::IngestSourceRules,
+    server_key::ServerKey,
+};
+
+use super::server_key_record::{ServerKeyRecord, server_keys_from_records};
+use super::source_record::SourceRecord;
+use super::source_rule_record::{
+    IdentityKeyRecord, PathRuleRecord, QueryParamRecord, source_rules_from_records,
+};
+
+/// `SourceCatalog` is a snapshot of the event sources, their rules and the
+/// server keys as loaded from a backend
+#[derive(Debug, Default)]
+pub(crate) struct SourceCatalog {
+    pub(crate) event_sources: HashSet<IngestEventSource>,
+    source_rules: HashMap<IngestEventSource, IngestSourceRules>,
+    server_keys: HashMap<ApiKey, HashSet<String>>,
+}
+
+impl SourceCatalog {
+    /// Fold the records loaded from a backend into a `SourceCatalog`
+    pub(crate) fn from_records(
+        source_records: &[SourceRecord],
+        query_param_records: &[QueryParamRecord],
+        path_rule_records: &[PathRuleRecord],
+        identity_key_records: &[IdentityKeyRecord],
+        server_key_records: &[ServerKeyRecord],
+    ) -> Self {
+        Self {
+            event_sources: source_records.iter().map(IngestEventSource::from).collect(),
+            source_rules: source_rules_from_records(
+                query_param_records,
+                path_rule_records,
+                identity_key_records,
+            ),
+            server_keys: server_keys_from_records(server_key_records),
+        }
+    }
+
+    /// The rules of the source, which are the defaults if none are stored
+    pub(crate) fn source_rules(&self, source: &IngestEventSource) -> IngestSourceRules {
+        self.source_rules.get(source).cloned().unwrap_or_default()
+    }
+
+    /// Whether the server key has been issued for the api key
+    pub(crate) fn is_server_key_valid(&self, api_key: &ApiKey, server_key: &ServerKey) -> bool {
+        self.server_keys
+            .get(api_key)
+            .is_some_and(|digests| digests.contains(&server_key.digest()))
+    }
+}
+
+/// `SharedSourceCatalog` holds the current `SourceCatalog` of a repository.
+/// The whole snapshot is swapped on reload so that a save never observes
+/// sources and rules from different loads.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SharedSourceCatalog(Arc<RwLock<Arc<SourceCatalog>>>);
+
+impl SharedSourceCatalog {
+    /// `SharedSourceCatalog` constructor holding the given snapshot
+    pub(crate) fn new(catalog: SourceCatalog) -> Self {
+        Self(Arc::new(RwLock::new(Arc::new(catalog))))
+    }
+
+    /// Current snapshot of the source catalog
+    pub(crate) fn snapshot(&self) -> Arc<SourceCatalog> {
+        self.0
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Replace the snapshot with a freshly loaded one
+    pub(crate) fn replace(&self, catalog: SourceCatalog) {
+        tracing::debug!(
+            "Reloaded {} event sources and {} source rules",
+            catalog.event_sources.len(),
+            catalog.source_rules.len()
+        );
+        *self
+            .0
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Arc::new(catalog);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::model::ingest_event::Site;
+
+    use super::*;
+
+    #[test]
+    fn test_shared_source_catalog() {
+        let source = IngestEventSource::new(ApiKey::new("abc-123"), Site::new("test.com"));
+        let shared = SharedSourceCatalog::default();
+        let previous = shared.snapshot();
+        shared.replace(SourceCatalog::from_records(
+            &[SourceRecord::new("abc-123", "test.com")],
+            &[],
+            &[PathRuleRecord::new(
+                "abc-123",
+                "test.com",
+                Vec::new(),
+                false,
+                false,
+                false,
+                true,
+            )],
+            &[],
+            &[ServerKeyRecord::new(
+                "abc-123",
+                ServerKey::new("secret").digest(),
+            )],
+        ));
+        let catalog = shared.snapshot();
+        assert!(catalog.event_sources.contains(&source));
+        assert!(catalog.source_rules(&source).path_normalization.lowercase);
+        assert!(catalog.is_server_key_valid(&ApiKey::new("abc-123"), &ServerKey::new("secret")));
+        assert!(
+            previous.event_sources.is_empty(),
+            "Expected a taken snapshot not to change on reload"
+        );
+
+        // Negative test cases
+        assert!(!catalog.is_server_key_valid(&ApiKey::new("xyz-789"), &ServerKey::new("secret")));
+        assert!(
+            !catalog
+                .source_rules(&IngestEventSource::new(
+                    ApiKey::new("abc-123"),
+                    Site::new("other.com"),
+                ))
+                .path_normalization
+                .lowercase,
+            "Expected default rules for sources without rules"
+        );
+    }
+}
@@ -3,13 +3,17 @@ use serde::{Deserialize, Serialize};
 
 use crate::domain::model::ingest_event::{ApiKey, IngestEventSource, Site};
 
+/// `SourceRecord` is an api_key and site combination for which events are
+/// accepted. It and the records of the source rules and server keys are shared
+/// by every backend: ClickHouse fetches its rows into them directly, while the
+/// other backends build them from their rows.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Row, Deserialize, Serialize)]
-pub struct ClickhouseSourceRecord {
+pub struct SourceRecord {
     api_key: String,
     site: String,
 }
 
-impl ClickhouseSourceRecord {
+impl SourceRecord {
     pub fn new(api_key: impl AsRef<str>, site: impl AsRef<str>) -> Self {
         Self {
             api_key: api_key.as_ref().to_string(),
@@ -18,8 +22,8 @@ impl ClickhouseSourceRecord {
     }
 }
 
-impl From<&ClickhouseSourceRecord> for IngestEventSource {
-    fn from(value: &ClickhouseSourceRecord) -> Self {
+impl From<&SourceRecord> for IngestEventSource {
+    fn from(value: &SourceRecord) -> Self {
         Self::new(ApiKey::new(&value.api_key), Site::new(&value.site))
     }
 }
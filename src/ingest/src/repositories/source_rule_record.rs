@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+
+use clickhouse::Row;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+use crate::domain::model::{
+    identity_key::IdentityKey,
+    ingest_event::{ApiKey, IngestEventSource, Site},
+    ingest_source_rules::IngestSourceRules,
+    path_normalizer::{PathNormalizationRules, PathRewrite},
+};
+
+/// `QueryParamRule` determines whether a query parameter is kept. It maps to
+/// the ClickHouse Enum8 with identical values, see definition of table
+/// `SALUS_METRICS.SOURCE_QUERY_PARAM` and field `rule`, while the other
+/// backends store the name of the variant.
+#[derive(Debug, Deserialize_repr, PartialEq, Eq, Serialize_repr, Clone)]
+#[repr(u8)]
+pub enum QueryParamRule {
+    Allow = 1,
+    Deny = 2,
+}
+
+/// `QueryParamRecord` represents a single query parameter rule for
+/// a given api_key and site combination
+#[derive(Debug, Clone, PartialEq, Eq, Row, Deserialize, Serialize)]
+pub struct QueryParamRecord {
+    api_key: String,
+    site: String,
+    param: String,
+    rule: QueryParamRule,
+}
+
+impl QueryParamRecord {
+    #[cfg(any(test, feature = "postgres", feature = "sqlite"))]
+    pub fn new(
+        api_key: impl AsRef<str>,
+        site: impl AsRef<str>,
+        param: impl AsRef<str>,
+        rule: QueryParamRule,
+    ) -> Self {
+        Self {
+            api_key: api_key.as_ref().to_string(),
+            site: site.as_ref().to_string(),
+            param: param.as_ref().to_string(),
+            rule,
+        }
+    }
+}
+
+/// `PathRuleRecord` represents the path normalization rules for a
+/// given api_key and site combination. `rewrites` holds `(pattern,
+/// replacement)` pairs that are applied in order.
+#[derive(Debug, Clone, PartialEq, Eq, Row, Deserialize, Serialize)]
+pub struct PathRuleRecord {
+    api_key: String,
+    site: String,
+    rewrites: Vec<(String, String)>,
+    collapse_numeric: bool,
+    collapse_uuid: bool,
+    trim_trailing_slash: bool,
+    lowercase: bool,
+}
+
+impl PathRuleRecord {
+    #[cfg(any(test, feature = "postgres", feature = "sqlite"))]
+    pub fn new(
+        api_key: impl AsRef<str>,
+        site: impl AsRef<str>,
+        rewrites: Vec<(String, String)>,
+        collapse_numeric: bool,
+        collapse_uuid: bool,
+        trim_trailing_slash: bool,
+        lowercase: bool,
+    ) -> Self {
+        Self {
+            api_key: api_key.as_ref().to_string(),
+            site: site.as_ref().to_string(),
+            rewrites,
+            collapse_numeric,
+            collapse_uuid,
+            trim_trailing_slash,
+            lowercase,
+        }
+    }
+}
+
+impl From<&PathRuleRecord> for PathNormalizationRules {
+    /// Rewrites with an invalid pattern are logged and skipped so that a single
+    /// bad rule does not prevent the remaining rules from loading
+    fn from(value: &PathRuleRecord) -> Self {
+        let rewrites = value
+            .rewrites
+            .iter()
+            .filter_map(|(pattern, replacement)| match Regex::new(pattern) {
+                Ok(regex) => Some(PathRewrite::new(regex, replacement)),
+                Err(e) => {
+                    tracing::error!(
+                        "Skipping invalid path rewrite pattern {pattern} for {}/{}: {e}",
+                        value.api_key,
+                        value.site
+                    );
+                    None
+                }
+            })
+            .collect();
+        Self {
+            lowercase: value.lowercase,
+            rewrites,
+            collapse_uuid: value.collapse_uuid,
+            collapse_numeric: value.collapse_numeric,
+            trim_trailing_slash: value.trim_trailing_slash,
+        }
+    }
+}
+
+/// `IdentityKeyRecord` represents the `IdentityKey` that user ids
+/// are pseudonymized with for a given api_key and site combination
+#[derive(Debug, Clone, PartialEq, Eq, Row, Deserialize, Serialize)]
+pub struct IdentityKeyRecord {
+    api_key: String,
+    site: String,
+    identity_key: String,
+}
+
+impl IdentityKeyRecord {
+    #[cfg(any(test, feature = "postgres", feature = "sqlite"))]
+    pub fn new(
+        api_key: impl AsRef<str>,
+        site: impl AsRef<str>,
+        identity_key: impl AsRef<str>,
+    ) -> Self {
+        Self {
+            api_key: api_key.as_ref().to_string(),
+            site: site.as_ref().to_string(),
+            identity_key: identity_key.as_ref().to_string(),
+        }
+    }
+}
+
+/// Fold all rule records into the `IngestSourceRules` for each source. Blank
+/// identity keys are skipped so that such sources refuse `IdentifyEvent`s.
+pub(crate) fn source_rules_from_records(
+    query_param_records: &[QueryParamRecord],
+    path_rule_records: &[PathRuleRecord],
+    identity_key_records: &[IdentityKeyRecord],
+) -> HashMap<IngestEventSource, IngestSourceRules> {
+    let mut source_rules: HashMap<IngestEventSource, IngestSourceRules> = HashMap::new();
+    for record in query_param_records.iter() {
+        let rules = source_rules
+            .entry(IngestEventSource::new(
+                ApiKey::new(&record.api_key),
+                Site::new(&record.site),
+            ))
+            .or_default();
+        match record.rule {
+            QueryParamRule::Allow => rules.query_params.allow(&record.param),
+            QueryParamRule::Deny => rules.query_params.deny(&record.param),
+        }
+    }
+    for record in path_rule_records.iter() {
+        source_rules
+            .entry(IngestEventSource::new(
+                ApiKey::new(&record.api_key),
+                Site::new(&record.site),
+            ))
+            .or_default()
+            .path_normalization = PathNormalizationRules::from(record);
+    }
+    for record in identity_key_records.iter() {
+        let identity_key = IdentityKey::new(&record.identity_key);
+        if identity_key.is_empty() {
+            tracing::error!(
+                "Skipping blank identity key for {}/{}",
+                record.api_key,
+                record.site
+            );
+            continue;
+        }
+        source_rules
+            .entry(IngestEventSource::new(
+                ApiKey::new(&record.api_key),
+                Site::new(&record.site),
+            ))
+            .or_default()
+            .identity_key = Some(identity_key);
+    }
+    source_rules
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// This is very important in order to keep the mapping in ClickHouse in
+    /// line with this library
+    #[test]
+    fn test_query_param_rule_discriminant() {
+        assert_eq!(
+            QueryParamRule::Allow as u32,
+            1,
+            "QueryParamRule::Allow discriminant does not match expected value"
+        );
+        assert_eq!(
+            QueryParamRule::Deny as u32,
+            2,
+            "QueryParamRule::Deny discriminant does not match expected value"
+        );
+    }
+
+    #[test]
+    fn test_source_rules_from_records() {
+        let records = vec![
+            QueryParamRecord::new("abc-123", "test.com", "q", QueryParamRule::Allow),
+            QueryParamRecord::new("abc-123", "test.com", "sid", QueryParamRule::Deny),
+        ];
+        let path_records = vec![PathRuleRecord::new(
+            "abc-123",
+            "test.com",
+            vec![
+                (r"^/(en|fr)/".to_owned(), "/".to_owned()),
+                (r"(unclosed".to_owned(), "".to_owned()),
+            ],
+            true,
+            false,
+            true,
+            false,
+        )];
+        let identity_key_records = vec![
+            IdentityKeyRecord::new("abc-123", "test.com", "key"),
+            IdentityKeyRecord::new("abc-123", "other.com", " "),
+        ];
+        let source_rules =
+            source_rules_from_records(&records, &path_records, &identity_key_records);
+        let rules = source_rules
+            .get(&IngestEventSource::new(
+                ApiKey::new("abc-123"),
+                Site::new("test.com"),
+            ))
+            .unwrap();
+        assert!(rules.query_params.is_kept("q"), "Expected q to be allowed");
+        assert!(
+            !rules.query_params.is_kept("sid"),
+            "Expected sid to be denied"
+        );
+        assert!(
+            !rules.query_params.is_kept("page"),
+            "Expected params outside the allow list to be removed"
+        );
+        assert_eq!(
+            rules.path_normalization.rewrites.len(),
+            1,
+            "Expected invalid rewrite pattern to be skipped"
+        );
+        assert_eq!(
+            rules.path_normalization.normalize("/en/orders/42/"),
+            "/orders/:id",
+            "Expected path rules to be loaded from the record"
+        );
+        assert_eq!(
+            rules.identity_key,
+            Some(IdentityKey::new("key")),
+            "Expected identity key to be loaded from the record"
+        );
+        assert!(
+            !source_rules.contains_key(&IngestEventSource::new(
+                ApiKey::new("abc-123"),
+                Site::new("other.com"),
+            )),
+            "Expected blank identity key to be skipped"
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use serde_json::{Map, Value};
+
+use crate::domain::{
+    model::ingest_event::IngestEvent, repository::ingest_event_repository::IngestRepositoryError,
+};
+
+use super::clickhouse_event_record::ClickhouseEventRecord;
+
+/// `SqliteEventRecord` is a row of the SQLite `event` table. It holds the same
+/// data as a `ClickhouseEventRecord`, with the id as text, the timestamp as
+/// milliseconds since the Unix epoch and `attrs` as a JSON object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SqliteEventRecord {
+    pub(crate) api_key: String,
+    pub(crate) site: String,
+    pub(crate) event_type: &'static str,
+    pub(crate) id: String,
+    pub(crate) ts: i64,
+    pub(crate) attrs: String,
+}
+
+impl From<ClickhouseEventRecord> for SqliteEventRecord {
+    fn from(value: ClickhouseEventRecord) -> Self {
+        Self {
+            api_key: value.api_key,
+            site: value.site,
+            event_type: value.event_type.name(),
+            id: value.id.to_string(),
+            ts: (value.ts.unix_timestamp_nanos() / 1_000_000) as i64,
+            attrs: Value::Object(
+                value
+                    .attrs
+                    .into_iter()
+                    .map(|(key, value)| (key, Value::String(value)))
+                    .collect::<Map<String, Value>>(),
+            )
+            .to_string(),
+        }
+    }
+}
+
+impl TryFrom<&IngestEvent> for SqliteEventRecord {
+    type Error = IngestRepositoryError;
+    fn try_from(value: &IngestEvent) -> Result<Self, Self::Error> {
+        ClickhouseEventRecord::try_from(value).map(Self::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::domain::model::ingest_event::{ApiKey, ClickEvent, Site};
+
+    use super::*;
+
+    #[test]
+    fn test_sqlite_event_record() {
+        let id = Uuid::now_v7();
+        let parent = Uuid::now_v7();
+        let event = IngestEvent::Click(
+            ClickEvent::try_new(ApiKey::new("abc-123"), Site::new("test.com"), id, parent).unwrap(),
+        );
+        let record = SqliteEventRecord::try_from(&event).unwrap();
+        assert_eq!(record.event_type, "Click");
+        assert_eq!(record.id, id.to_string());
+        let (secs, _) = id.get_timestamp().unwrap().to_unix();
+        assert_eq!(
+            record.ts / 1000,
+            secs as i64,
+            "Expected ts in milliseconds derived from the id"
+        );
+        let attrs: Value = serde_json::from_str(&record.attrs).unwrap();
+        assert_eq!(
+            attrs["parent"],
+            parent.to_string(),
+            "Expected attrs to be stored as a JSON object"
+        );
+    }
+}
@@ -0,0 +1,462 @@
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use rusqlite::{Connection, Row, params};
+use tracing::instrument;
+
+use crate::domain::model::ingest_action_summary::{IngestActionSummary, IngestEventSaveSummary};
+use crate::domain::model::ingest_event::{ApiKey, IngestEvent, IngestEventSource};
+use crate::domain::model::ingest_source_rules::IngestSourceRules;
+use crate::domain::model::server_key::ServerKey;
+use crate::domain::repository::ingest_event_repository::{
+    IngestEventRepository, IngestRepositoryError,
+};
+
+use super::server_key_record::ServerKeyRecord;
+use super::source_catalog::{SharedSourceCatalog, SourceCatalog};
+use super::source_record::SourceRecord;
+use super::source_rule_record::{
+    IdentityKeyRecord, PathRuleRecord, QueryParamRecord, QueryParamRule,
+};
+use super::sqlite_event_record::SqliteEventRecord;
+
+/// Tables of the SQLite backend, which are created on startup
+const SQLITE_SCHEMA: &str = include_str!("../../../../sql/sqlite/schema/ingest.sql");
+
+/// Settings of every connection. WAL lets readers of the file, such as a
+/// reporting tool, run alongside the server.
+const SQLITE_PRAGMAS: &str = "PRAGMA journal_mode = WAL;
+PRAGMA synchronous = NORMAL;
+PRAGMA busy_timeout = 5000;";
+
+/// `SqliteIngestRepository` is an implementation of the
+/// `IngestEventRepository` trait for an embedded SQLite database, so that a
+/// single-node deployment needs no external services. Events are stored in a
+/// single `event` table and the event sources and their rules are loaded from
+/// tables that mirror those of PostgreSQL. All database work runs on the
+/// blocking thread pool behind a single connection.
+#[derive(Clone)]
+pub struct SqliteIngestRepository {
+    connection: Arc<Mutex<Connection>>,
+    catalog: SharedSourceCatalog,
+}
+
+/// Load a `SourceCatalog` from the SQLite tables of the sources, their rules
+/// and the server keys. The rows are read into the same records as those of
+/// the other backends, so that they are folded into rules in the same way.
+fn load_source_catalog(connection: &Connection) -> Result<SourceCatalog, IngestRepositoryError> {
+    let source_records = query_rows(connection, "SELECT api_key, site FROM api_key", |row| {
+        Ok(SourceRecord::new(
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+        ))
+    })?;
+    let query_param_records = query_rows(
+        connection,
+        "SELECT api_key, site, param, rule FROM source_query_param",
+        |row| {
+            let rule = match row.get::<_, String>(3)?.as_str() {
+                "Allow" => QueryParamRule::Allow,
+                _ => QueryParamRule::Deny,
+            };
+            Ok(QueryParamRecord::new(
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                rule,
+            ))
+        },
+    )?;
+    let path_rule_records = query_rows(
+            connection,
+            "SELECT api_key, site, rewrites, collapse_numeric, collapse_uuid, trim_trailing_slash, lowercase FROM source_path_rule",
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, bool>(3)?,
+                    row.get::<_, bool>(4)?,
+                    row.get::<_, bool>(5)?,
+                    row.get::<_, bool>(6)?,
+                ))
+            },
+        )?
+        .into_iter()
+        .map(
+            |(api_key, site, rewrites, collapse_numeric, collapse_uuid, trim, lowercase)| {
+                let rewrites = serde_json::from_str(&rewrites).map_err(|e| {
+                    tracing::error!("Encountered invalid path rewrites in SQLite: {e}");
+                    IngestRepositoryError::Conversion
+                })?;
+                Ok(PathRuleRecord::new(
+                    api_key,
+                    site,
+                    rewrites,
+                    collapse_numeric,
+                    collapse_uuid,
+                    trim,
+                    lowercase,
+                ))
+            },
+        )
+        .collect::<Result<Vec<PathRuleRecord>, IngestRepositoryError>>()?;
+    let identity_key_records = query_rows(
+        connection,
+        "SELECT api_key, site, identity_key FROM source_identity_key",
+        |row| {
+            Ok(IdentityKeyRecord::new(
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        },
+    )?;
+    let server_key_records = query_rows(
+        connection,
+        "SELECT api_key, key_sha256 FROM server_key",
+        |row| {
+            Ok(ServerKeyRecord::new(
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+            ))
+        },
+    )?;
+    Ok(SourceCatalog::from_records(
+        &source_records,
+        &query_param_records,
+        &path_rule_records,
+        &identity_key_records,
+        &server_key_records,
+    ))
+}
+
+impl std::fmt::Debug for SqliteIngestRepository {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SqliteIngestRepository").finish()
+    }
+}
+
+impl SqliteIngestRepository {
+    /// Open the database file at `path`, creating it and the schema if
+    /// needed, and load the event sources
+    pub async fn try_new(path: impl AsRef<Path>) -> Result<Self, IngestRepositoryError> {
+        let path = path.as_ref().to_owned();
+        let (connection, catalog) = tokio::task::spawn_blocking(move || {
+            let connection = Connection::open(&path).map_err(|e| {
+                tracing::error!("Encountered error opening SQLite database {path:?}: {e}");
+                IngestRepositoryError::Repository
+            })?;
+            connection.execute_batch(SQLITE_PRAGMAS).map_err(|e| {
+                tracing::error!("Encountered error configuring SQLite: {e}");
+                IngestRepositoryError::Repository
+            })?;
+            connection.execute_batch(SQLITE_SCHEMA).map_err(|e| {
+                tracing::error!("Encountered error creating SQLite schema: {e}");
+                IngestRepositoryError::Repository
+            })?;
+            let catalog = load_source_catalog(&connection)?;
+            Ok::<_, IngestRepositoryError>((connection, catalog))
+        })
+        .await
+        .map_err(|e| {
+            tracing::error!("SQLite task failed: {e}");
+            IngestRepositoryError::Repository
+        })??;
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+            catalog: SharedSourceCatalog::new(catalog),
+        })
+    }
+
+    /// Run `f` with the connection on the blocking thread pool
+    async fn with_connection<T, F>(&self, f: F) -> Result<T, IngestRepositoryError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T, IngestRepositoryError> + Send + 'static,
+    {
+        let connection = self.connection.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut connection = connection
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            f(&mut connection)
+        })
+        .await
+        .map_err(|e| {
+            tracing::error!("SQLite task failed: {e}");
+            IngestRepositoryError::Repository
+        })?
+    }
+}
+
+impl IngestEventRepository for SqliteIngestRepository {
+    /// `save` method for SQLite inserts the events in a single transaction and
+    /// skips any event that has already been stored, so that a retried batch
+    /// is not stored twice. The skipped events are counted as duplicates.
+    #[instrument]
+    async fn save(
+        &self,
+        events: Vec<IngestEvent>,
+    ) -> Result<IngestActionSummary, IngestRepositoryError> {
+        if events.is_empty() {
+            return Err(IngestRepositoryError::InvalidRequest);
+        }
+        let catalog = self.catalog.snapshot();
+        let mut records: Vec<SqliteEventRecord> = Vec::with_capacity(events.len());
+        for event in events.iter() {
+            if !catalog
+                .event_sources
+                .contains(&IngestEventSource::from(event))
+            {
+                return Err(IngestRepositoryError::InvalidRequest);
+            }
+            records.push(SqliteEventRecord::try_from(event)?);
+        }
+
+        let event_count = records.len();
+        let inserted = self.with_connection(move |connection| {
+            let transaction = connection.transaction().map_err(|e| {
+                tracing::error!("Encountered error starting SQLite transaction: {e}");
+                IngestRepositoryError::Repository
+            })?;
+            let mut inserted = 0;
+            {
+                let mut insert = transaction
+                    .prepare_cached(
+                        "INSERT OR IGNORE INTO event (api_key, site, event_type, id, ts, attrs) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    )
+                    .map_err(|e| {
+                        tracing::error!("Encountered error preparing SQLite insert: {e}");
+                        IngestRepositoryError::Repository
+                    })?;
+                for record in records.iter() {
+                    inserted += insert
+                        .execute(params![
+                            record.api_key,
+                            record.site,
+                            record.event_type,
+                            record.id,
+                            record.ts,
+                            record.attrs,
+                        ])
+                        .map_err(|e| {
+                            tracing::error!("Encountered error inserting records: {e}");
+                            IngestRepositoryError::Repository
+                        })?;
+                }
+            }
+            transaction.commit().map_err(|e| {
+                tracing::error!("Encountered error committing SQLite transaction: {e}");
+                IngestRepositoryError::Repository
+            })?;
+            Ok(inserted)
+        })
+        .await?;
+
+        Ok(IngestActionSummary::Save(IngestEventSaveSummary {
+            event_count: inserted,
+            duplicate_count: event_count - inserted,
+        }))
+    }
+
+    async fn event_sources(&self) -> Result<HashSet<IngestEventSource>, IngestRepositoryError> {
+        Ok(self.catalog.snapshot().event_sources.clone())
+    }
+
+    async fn source_rules(
+        &self,
+        source: &IngestEventSource,
+    ) -> Result<IngestSourceRules, IngestRepositoryError> {
+        Ok(self.catalog.snapshot().source_rules(source))
+    }
+
+    async fn is_server_key_valid(
+        &self,
+        api_key: &ApiKey,
+        server_key: &ServerKey,
+    ) -> Result<bool, IngestRepositoryError> {
+        Ok(self
+            .catalog
+            .snapshot()
+            .is_server_key_valid(api_key, server_key))
+    }
+
+    /// `reload_event_sources` for SQLite loads a fresh snapshot of the sources
+    /// and rules. The current snapshot is kept if loading fails.
+    async fn reload_event_sources(&self) -> Result<(), IngestRepositoryError> {
+        let catalog = self
+            .with_connection(|connection| load_source_catalog(connection))
+            .await?;
+        self.catalog.replace(catalog);
+        Ok(())
+    }
+}
+
+fn query_rows<T>(
+    connection: &Connection,
+    query: &str,
+    map: impl FnMut(&Row<'_>) -> rusqlite::Result<T>,
+) -> Result<Vec<T>, IngestRepositoryError> {
+    connection
+        .prepare(query)
+        .and_then(|mut statement| statement.query_map([], map)?.collect())
+        .map_err(|e| {
+            tracing::error!("Encountered error fetching {query}: {e}");
+            IngestRepositoryError::Repository
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use time::{Duration, OffsetDateTime};
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::domain::model::ingest_event::{
+        IngestEventCore, IngestEventOrigin, Site, VisitorEvent,
+    };
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_save() {
+        let test_repository = SqliteIngestRepository::try_new(":memory:").await.unwrap();
+        test_repository
+            .with_connection(|connection| {
+                connection
+                    .execute_batch(&format!(
+                        "INSERT INTO api_key (api_key, site) VALUES ('abc-123', 'test.com');
+                        INSERT INTO server_key (api_key, key_sha256) VALUES ('abc-123', '{}');
+                        INSERT INTO source_path_rule (api_key, site, rewrites, lowercase) VALUES ('abc-123', 'test.com', '[[\"^/p/\", \"/product/\"]]', 1);",
+                        ServerKey::new("secret").digest()
+                    ))
+                    .map_err(|_| IngestRepositoryError::Repository)
+            })
+            .await
+            .unwrap();
+        test_repository.reload_event_sources().await.unwrap();
+
+        let source = IngestEventSource::new(ApiKey::new("abc-123"), Site::new("test.com"));
+        assert!(
+            test_repository
+                .event_sources()
+                .await
+                .unwrap()
+                .contains(&source),
+            "Expected source to be loaded from the api_key table"
+        );
+        let source_rules = test_repository.source_rules(&source).await.unwrap();
+        assert!(
+            source_rules.path_normalization.lowercase,
+            "Expected path rules to be loaded"
+        );
+        assert!(
+            test_repository
+                .is_server_key_valid(&ApiKey::new("abc-123"), &ServerKey::new("secret"))
+                .await
+                .unwrap()
+        );
+
+        let events: Vec<IngestEvent> = (0..3)
+            .map(|_| {
+                IngestEvent::Visitor(
+                    VisitorEvent::try_new(
+                        ApiKey::new("abc-123"),
+                        Site::new("test.com"),
+                        Uuid::now_v7(),
+                    )
+                    .unwrap(),
+                )
+            })
+            .collect();
+        let Ok(IngestActionSummary::Save(save_summary)) =
+            test_repository.save(events.clone()).await
+        else {
+            panic!("Expected action save summary to be returned");
+        };
+        assert_eq!(
+            (save_summary.event_count, save_summary.duplicate_count),
+            (3, 0)
+        );
+        let Ok(IngestActionSummary::Save(retried_summary)) = test_repository.save(events).await
+        else {
+            panic!("Expected action save summary to be returned");
+        };
+        assert_eq!(
+            (retried_summary.event_count, retried_summary.duplicate_count),
+            (0, 3),
+            "Expected the events of a retried batch to be counted as duplicates"
+        );
+        let stored: i64 = test_repository
+            .with_connection(|connection| {
+                connection
+                    .query_row("SELECT count(*) FROM event", [], |row| row.get(0))
+                    .map_err(|_| IngestRepositoryError::Repository)
+            })
+            .await
+            .unwrap();
+        assert_eq!(stored, 3, "Expected a retried batch not to be stored twice");
+
+        let visitor_id = Uuid::now_v7();
+        let now = OffsetDateTime::now_utc();
+        let synthesized_visitor = |ts| {
+            IngestEvent::Visitor(
+                VisitorEvent::try_new_with_core_event(
+                    IngestEventCore::try_new_at(
+                        ApiKey::new("abc-123"),
+                        Site::new("test.com"),
+                        visitor_id,
+                        ts,
+                        IngestEventOrigin::Client,
+                    )
+                    .unwrap(),
+                )
+                .unwrap(),
+            )
+        };
+        test_repository
+            .save(vec![synthesized_visitor(now)])
+            .await
+            .unwrap();
+        let Ok(IngestActionSummary::Save(emitted_again_summary)) = test_repository
+            .save(vec![synthesized_visitor(now + Duration::minutes(1))])
+            .await
+        else {
+            panic!("Expected action save summary to be returned");
+        };
+        assert_eq!(
+            (
+                emitted_again_summary.event_count,
+                emitted_again_summary.duplicate_count
+            ),
+            (0, 1),
+            "Expected a visitor emitted again with a later ts to be counted as a duplicate"
+        );
+
+        // Negative test cases
+        assert_eq!(
+            test_repository.save(Vec::new()).await.unwrap_err(),
+            IngestRepositoryError::InvalidRequest
+        );
+        let unknown_source = vec![IngestEvent::Visitor(
+            VisitorEvent::try_new(
+                ApiKey::new("abc-123"),
+                Site::new("other.com"),
+                Uuid::now_v7(),
+            )
+            .unwrap(),
+        )];
+        assert_eq!(
+            test_repository.save(unknown_source).await.unwrap_err(),
+            IngestRepositoryError::InvalidRequest,
+            "Expected events of unknown sources to be refused"
+        );
+        assert_eq!(
+            SqliteIngestRepository::try_new("/nonexistent/salus/salus.db")
+                .await
+                .unwrap_err(),
+            IngestRepositoryError::Repository,
+            "Expected an error for a database that cannot be created"
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use std::time::Duration;
+
+use axum::body::Bytes;
+use futures_util::{FutureExt, future::BoxFuture};
+use http::{Method, Request, Uri, header::CONTENT_TYPE};
+use http_body_util::Full;
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use hyper_util::{
+    client::legacy::{Client, connect::HttpConnector},
+    rt::TokioExecutor,
+};
+
+use crate::domain::{
+    model::ingest_event::IngestEvent,
+    repository::{event_sink::EventSink, ingest_event_repository::IngestRepositoryError},
+};
+
+use super::sink_event_record::sink_event_records;
+
+/// Time allowed for a webhook to accept a batch before the write fails
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// `WebhookSink` is an `EventSink` that POSTs every batch to a URL as a JSON
+/// array of `SinkEventRecord`s. Any response other than a success status is
+/// a failed write. Servers of `https` URLs are verified against the webpki
+/// root certificates.
+#[derive(Debug)]
+pub struct WebhookSink {
+    url: Uri,
+    client: Client<HttpsConnector<HttpConnector>, Full<Bytes>>,
+}
+
+impl WebhookSink {
+    /// `WebhookSink` constructor that POSTs to the given `https` or `http` URL
+    pub fn new(url: Uri) -> Self {
+        let connector = HttpsConnectorBuilder::new()
+            .with_webpki_roots()
+            .https_or_http()
+            .enable_http1()
+            .build();
+        Self {
+            url,
+            client: Client::builder(TokioExecutor::new()).build(connector),
+        }
+    }
+
+    async fn post(&self, events: &[IngestEvent]) -> Result<(), IngestRepositoryError> {
+        let body = serde_json::to_vec(&sink_event_records(events)?).map_err(|e| {
+            tracing::error!("Error serializing webhook records: {e}");
+            IngestRepositoryError::Conversion
+        })?;
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(self.url.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .body(Full::new(Bytes::from(body)))
+            .map_err(|e| {
+                tracing::error!("Error building webhook request: {e}");
+                IngestRepositoryError::InvalidRequest
+            })?;
+        let response = tokio::time::timeout(WEBHOOK_TIMEOUT, self.client.request(request))
+            .await
+            .map_err(|_| {
+                tracing::error!("Webhook {} timed out", self.url);
+                IngestRepositoryError::Repository
+            })?
+            .map_err(|e| {
+                tracing::error!("Error calling webhook {}: {e}", self.url);
+                IngestRepositoryError::Repository
+            })?;
+        if !response.status().is_success() {
+            tracing::error!(
+                "Webhook {} responded with status {}",
+                self.url,
+                response.status()
+            );
+            return Err(IngestRepositoryError::Repository);
+        }
+        Ok(())
+    }
+}
+
+impl EventSink for WebhookSink {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    fn write<'a>(
+        &'a self,
+        events: &'a [IngestEvent],
+    ) -> BoxFuture<'a, Result<(), IngestRepositoryError>> {
+        self.post(events).boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::{Json, Router, extract::State, http::StatusCode, routing::post};
+    use tokio::net::TcpListener;
+    use uuid::Uuid;
+
+    use crate::{
+        domain::model::ingest_event::{ApiKey, Site, VisitorEvent},
+        repositories::sink_event_record::SinkEventRecord,
+    };
+
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_webhook_sink() {
+        let received: Arc<Mutex<Vec<SinkEventRecord>>> = Arc::default();
+        let router = Router::new()
+            .route(
+                "/events",
+                post(
+                    |State(received): State<Arc<Mutex<Vec<SinkEventRecord>>>>,
+                     Json(records): Json<Vec<SinkEventRecord>>| async move {
+                        received.lock().unwrap().extend(records);
+                        StatusCode::NO_CONTENT
+                    },
+                ),
+            )
+            .with_state(received.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let event = IngestEvent::Visitor(
+            VisitorEvent::try_new(
+                ApiKey::new("abc-123"),
+                Site::new("test.com"),
+                Uuid::now_v7(),
+            )
+            .unwrap(),
+        );
+        let sink = WebhookSink::new(format!("http://{addr}/events").parse().unwrap());
+        sink.write(std::slice::from_ref(&event)).await.unwrap();
+        assert_eq!(
+            received
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|r| r.id)
+                .collect::<Vec<Uuid>>(),
+            vec![event.id()],
+            "Expected the webhook to receive the event"
+        );
+
+        // Negative test case
+        let not_found = WebhookSink::new(format!("http://{addr}/missing").parse().unwrap());
+        assert_eq!(
+            not_found.write(&[event]).await.unwrap_err(),
+            IngestRepositoryError::Repository,
+            "Expected a failed write for an unsuccessful status"
+        );
+    }
+}
@@ -1,9 +1,21 @@
-use std::{collections::HashSet, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
 use tracing::instrument;
+use uuid::Uuid;
 
 use crate::domain::{
-    model::{ingest_action_summary::IngestActionSummary, ingest_event::IngestEventSource},
+    model::{
+        event_deduplicator::EventDeduplicator,
+        event_scrubber::EventScrubber,
+        hierarchy_cache::{HierarchyCache, HierarchyCheck, HierarchyLevel, HierarchyReport},
+        ingest_action_summary::{IngestActionSummary, IngestEventSaveSummary},
+        ingest_event::{ApiKey, IngestEvent, IngestEventSource},
+        ingest_source_rules::IngestSourceRules,
+        server_key::ServerKey,
+    },
     repository::ingest_event_repository::{IngestEventRepository, IngestRepositoryError},
     service::ingest_event_service::{IngestEventService, IngestServiceError},
 };
@@ -17,17 +29,147 @@ where
     T: IngestEventRepository + std::fmt::Debug,
 {
     ingest_event_repository: Arc<T>,
+    event_scrubber: Arc<EventScrubber>,
+    hierarchy_cache: Arc<HierarchyCache>,
+    event_deduplicator: Arc<EventDeduplicator>,
 }
 
 impl<T> IngestService<T>
 where
     T: IngestEventRepository + std::fmt::Debug,
 {
-    /// `IngestService<T>` constructor
+    /// `IngestService<T>` constructor. Uses the default `EventScrubber`,
+    /// `HierarchyCache` and `EventDeduplicator`
     pub fn new(ingest_event_repository: T) -> Self {
         Self {
             ingest_event_repository: Arc::new(ingest_event_repository),
+            event_scrubber: Arc::new(EventScrubber::default()),
+            hierarchy_cache: Arc::new(HierarchyCache::default()),
+            event_deduplicator: Arc::new(EventDeduplicator::default()),
+        }
+    }
+
+    /// Replace the `EventScrubber` that is applied to all events before they
+    /// are handed to the `IngestEventRepository`
+    pub fn with_event_scrubber(mut self, event_scrubber: EventScrubber) -> Self {
+        self.event_scrubber = Arc::new(event_scrubber);
+        self
+    }
+
+    /// Replace the `HierarchyCache` that the parents of events are checked
+    /// against
+    pub fn with_hierarchy_cache(mut self, hierarchy_cache: HierarchyCache) -> Self {
+        self.hierarchy_cache = Arc::new(hierarchy_cache);
+        self
+    }
+
+    /// Replace the `EventDeduplicator` that skips events which were already
+    /// saved
+    pub fn with_event_deduplicator(mut self, event_deduplicator: EventDeduplicator) -> Self {
+        self.event_deduplicator = Arc::new(event_deduplicator);
+        self
+    }
+
+    /// Check the parents of the saved events against the `HierarchyCache`.
+    /// Events are saved regardless, as the cache only knows recent ids of this
+    /// server, but parents of the wrong type are logged as they can never be
+    /// joined into the hierarchy. Only called once the repository accepted the
+    /// events, so that neither unconfigured sources nor ids of failed saves
+    /// are remembered.
+    fn check_hierarchy(&self, events: &[IngestEvent]) {
+        let checks = self.hierarchy_cache.observe(events);
+        for (event, check) in events.iter().zip(checks) {
+            let Some((level, parent)) = HierarchyLevel::parent_of(event) else {
+                continue;
+            };
+            let source = IngestEventSource::from(event);
+            match check {
+                HierarchyCheck::Valid => {}
+                HierarchyCheck::Unknown => tracing::debug!(
+                    "Parent {parent} for {}/{} is not known yet",
+                    source.api_key().value(),
+                    source.site().value()
+                ),
+                HierarchyCheck::Mismatched => tracing::warn!(
+                    "Parent {parent} for {}/{} is not a {level:?}",
+                    source.api_key().value(),
+                    source.site().value()
+                ),
+            }
+        }
+    }
+
+    /// Apply the source rules to events that passed the `EventDeduplicator`
+    /// and hand them to the `IngestEventRepository`
+    async fn save_unsaved(
+        &self,
+        mut events: Vec<IngestEvent>,
+    ) -> Result<IngestEventSaveSummary, IngestServiceError> {
+        self.apply_source_rules(&mut events).await?;
+        let IngestActionSummary::Save(summary) = self
+            .ingest_event_repository
+            .save(events.clone())
+            .await
+            .map_err(|e| match e {
+                IngestRepositoryError::InvalidRequest => IngestServiceError::InvalidRequest,
+                IngestRepositoryError::Conversion => e.into(),
+                IngestRepositoryError::Repository => e.into(),
+            })?;
+        self.check_hierarchy(&events);
+        Ok(summary)
+    }
+
+    /// The `IngestSourceRules` of the source, fetched from the repository
+    /// once per distinct source and kept in `source_rules`
+    async fn cached_source_rules<'a>(
+        &self,
+        source_rules: &'a mut HashMap<IngestEventSource, IngestSourceRules>,
+        source: IngestEventSource,
+    ) -> Result<&'a IngestSourceRules, IngestServiceError> {
+        if !source_rules.contains_key(&source) {
+            let rules = self.ingest_event_repository.source_rules(&source).await?;
+            source_rules.insert(source.clone(), rules);
         }
+        Ok(&source_rules[&source])
+    }
+
+    /// Apply the `EventScrubber` and path normalization to the event using
+    /// the `IngestSourceRules` of its source. Scrubbing happens first so that
+    /// the normalized path is derived from the scrubbed location.
+    /// `IdentifyEvent`s are pseudonymized with the `IdentityKey` of their
+    /// source and rejected when there is none. Shared by `save` and
+    /// `validate`, so that validation applies exactly the rules of a save.
+    fn apply_event_rules(
+        &self,
+        event: &mut IngestEvent,
+        rules: &IngestSourceRules,
+    ) -> Result<(), IngestServiceError> {
+        self.event_scrubber.scrub(event, rules);
+        rules.path_normalization.normalize_event(event);
+        if let IngestEvent::Identify(identify) = event {
+            let identity_key = rules
+                .identity_key
+                .as_ref()
+                .ok_or(IngestServiceError::IdentityKey)?;
+            identify.pseudonymize(identity_key);
+        }
+        Ok(())
+    }
+
+    /// Apply the rules of each event's source to every event with
+    /// `apply_event_rules`
+    async fn apply_source_rules(
+        &self,
+        events: &mut [IngestEvent],
+    ) -> Result<(), IngestServiceError> {
+        let mut source_rules = HashMap::new();
+        for event in events.iter_mut() {
+            let rules = self
+                .cached_source_rules(&mut source_rules, IngestEventSource::from(&*event))
+                .await?;
+            self.apply_event_rules(event, rules)?;
+        }
+        Ok(())
     }
 }
 
@@ -37,23 +179,64 @@ where
 {
     /// `IngestService` implementation of the `save` method that is used to
     /// persist a `Vec` of `<IngestEvent>` to the underlying
-    /// `IngestEventRepository`
-    #[instrument]
+    /// `IngestEventRepository`. Events that were already saved are skipped
+    /// and counted as duplicates, so that retried requests are safe.
+    #[instrument(skip(events))]
     async fn save(
         &self,
-        events: Vec<crate::domain::model::ingest_event::IngestEvent>,
+        mut events: Vec<IngestEvent>,
     ) -> Result<IngestActionSummary, IngestServiceError> {
         if events.is_empty() {
             return Err(IngestServiceError::InvalidRequest);
         }
-        self.ingest_event_repository
-            .save(events)
-            .await
-            .map_err(|e| match e {
-                IngestRepositoryError::InvalidRequest => IngestServiceError::InvalidRequest,
-                IngestRepositoryError::Conversion => e.into(),
-                IngestRepositoryError::Repository => e.into(),
-            })
+        let duplicate_count = self.event_deduplicator.retain_unsaved(&mut events);
+        if events.is_empty() {
+            return Ok(IngestActionSummary::Save(IngestEventSaveSummary {
+                event_count: 0,
+                duplicate_count,
+            }));
+        }
+        let reserved: Vec<(IngestEventSource, Uuid)> = events
+            .iter()
+            .map(|event| (IngestEventSource::from(event), event.id()))
+            .collect();
+        match self.save_unsaved(events).await {
+            Ok(mut summary) => {
+                self.event_deduplicator.record(&reserved);
+                summary.duplicate_count += duplicate_count;
+                Ok(IngestActionSummary::Save(summary))
+            }
+            Err(e) => {
+                self.event_deduplicator.release(&reserved);
+                Err(e)
+            }
+        }
+    }
+
+    /// `IngestService` implementation of the `validate` method. Events are
+    /// checked against the configured event sources, which the repository
+    /// checks on save, and then have the rules of their source applied to a
+    /// copy with `apply_event_rules`. Nothing is recorded, so validated events
+    /// are neither counted as duplicates nor observed as parents.
+    #[instrument(skip(events))]
+    async fn validate(
+        &self,
+        events: &[IngestEvent],
+    ) -> Result<Vec<Option<IngestServiceError>>, IngestServiceError> {
+        let event_sources = self.event_sources().await?;
+        let mut source_rules = HashMap::new();
+        let mut violations = Vec::with_capacity(events.len());
+        for event in events {
+            let source = IngestEventSource::from(event);
+            if !event_sources.contains(&source) {
+                violations.push(Some(IngestServiceError::InvalidRequest));
+                continue;
+            }
+            let rules = self.cached_source_rules(&mut source_rules, source).await?;
+            let mut event = event.clone();
+            violations.push(self.apply_event_rules(&mut event, rules).err());
+        }
+        Ok(violations)
     }
 
     /// `IngestService` implementation of the `event_sources` method that
@@ -65,18 +248,56 @@ where
             .await
             .map_err(|e| e.into())
     }
+
+    /// `IngestService` implementation of the `is_server_key_valid` method.
+    /// Empty keys are rejected without consulting the repository
+    async fn is_server_key_valid(
+        &self,
+        api_key: &ApiKey,
+        server_key: &ServerKey,
+    ) -> Result<bool, IngestServiceError> {
+        if server_key.is_empty() {
+            return Ok(false);
+        }
+        self.ingest_event_repository
+            .is_server_key_valid(api_key, server_key)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    /// `IngestService` implementation of the `reload_event_sources` method
+    /// that refreshes the sources and rules of the `IngestEventRepository`
+    async fn reload_event_sources(&self) -> Result<(), IngestServiceError> {
+        self.ingest_event_repository
+            .reload_event_sources()
+            .await
+            .map_err(|e| e.into())
+    }
+
+    /// `IngestService` implementation of the `hierarchy_report` method that
+    /// reports the stats of the `HierarchyCache`
+    fn hierarchy_report(&self) -> Vec<HierarchyReport> {
+        self.hierarchy_cache.report()
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashSet;
+    use std::collections::{HashMap, HashSet};
 
     use uuid::Uuid;
 
     use crate::domain::{
         model::{
+            identity_key::IdentityKey,
             ingest_action_summary::{IngestActionSummary, IngestEventSaveSummary},
-            ingest_event::{ApiKey, IngestEvent, IngestEventSource, Site, VisitorEvent},
+            ingest_event::{
+                ApiKey, IdentifyEvent, IngestEvent, IngestEventSource, SectionEvent, Site,
+                VisitorEvent,
+            },
+            ingest_source_rules::IngestSourceRules,
+            path_normalizer::PathNormalizationRules,
+            server_key::ServerKey,
         },
         repository::ingest_event_repository::{
             IngestRepositoryError, test::MockIngestEventRepository,
@@ -94,11 +315,14 @@ mod tests {
         let mock_success_repo = MockIngestEventRepository {
             save_result: Ok(IngestActionSummary::Save(IngestEventSaveSummary {
                 event_count: 1,
+                duplicate_count: 0,
             })),
             event_source_result: Ok(HashSet::from([IngestEventSource::new(
                 ApiKey::new("abc-123"),
                 Site::new("test.com"),
             )])),
+            source_rules_result: Ok(IngestSourceRules::default()),
+            server_key_result: Ok(false),
         };
         let test_success_service = IngestService::new(mock_success_repo);
         let test_events: Vec<IngestEvent> = vec![IngestEvent::Visitor(
@@ -140,6 +364,8 @@ mod tests {
         let mock_err_repo = MockIngestEventRepository {
             save_result: Err(IngestRepositoryError::Repository),
             event_source_result: Err(IngestRepositoryError::Repository),
+            source_rules_result: Ok(IngestSourceRules::default()),
+            server_key_result: Ok(false),
         };
         let test_err_service = IngestService::new(mock_err_repo);
         let test_events: Vec<IngestEvent> = vec![IngestEvent::Visitor(
@@ -154,4 +380,401 @@ mod tests {
             "Expected to encounter IngestRepositoryError::Repository error"
         );
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_is_server_key_valid() {
+        let mock_repo = MockIngestEventRepository {
+            save_result: Err(IngestRepositoryError::Repository),
+            event_source_result: Ok(HashSet::new()),
+            source_rules_result: Ok(IngestSourceRules::default()),
+            server_key_result: Ok(true),
+        };
+        let test_service = IngestService::new(mock_repo);
+        assert!(
+            test_service
+                .is_server_key_valid(&ApiKey::new("abc-123"), &ServerKey::new("secret"))
+                .await
+                .unwrap(),
+            "Expected repository result to be returned for a non-empty key"
+        );
+        assert!(
+            !test_service
+                .is_server_key_valid(&ApiKey::new("abc-123"), &ServerKey::new(" "))
+                .await
+                .unwrap(),
+            "Expected empty key to be rejected"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_save_scrubs_events() {
+        // Rules lookup failure should prevent the save
+        let mock_rules_err_repo = MockIngestEventRepository {
+            save_result: Ok(IngestActionSummary::Save(IngestEventSaveSummary {
+                event_count: 1,
+                duplicate_count: 0,
+            })),
+            event_source_result: Ok(HashSet::new()),
+            source_rules_result: Err(IngestRepositoryError::Repository),
+            server_key_result: Ok(false),
+        };
+        let test_rules_err_service = IngestService::new(mock_rules_err_repo);
+        let test_events: Vec<IngestEvent> = vec![IngestEvent::Section(
+            SectionEvent::try_new(
+                ApiKey::new("abc-123"),
+                Site::new("test.com"),
+                Uuid::now_v7(),
+                Uuid::now_v7(),
+                Some("/welcome?email=jane@example.com".to_owned()),
+                None,
+            )
+            .unwrap(),
+        )];
+        let Err(rules_err_result) = test_rules_err_service.save(test_events.clone()).await else {
+            panic!("Expected save to fail when source rules cannot be fetched");
+        };
+        assert_eq!(
+            rules_err_result,
+            IngestServiceError::Repository(IngestRepositoryError::Repository),
+            "Expected to encounter IngestRepositoryError::Repository error"
+        );
+
+        // Scrubbing happens before the repository sees the event
+        let mock_success_repo = MockIngestEventRepository {
+            save_result: Ok(IngestActionSummary::Save(IngestEventSaveSummary {
+                event_count: 1,
+                duplicate_count: 0,
+            })),
+            event_source_result: Ok(HashSet::new()),
+            source_rules_result: Ok(IngestSourceRules::default()),
+            server_key_result: Ok(false),
+        };
+        let test_service = IngestService::new(mock_success_repo);
+        let mut scrubbed_events = test_events;
+        test_service
+            .apply_source_rules(&mut scrubbed_events)
+            .await
+            .unwrap();
+        let IngestEvent::Section(ref section) = scrubbed_events[0] else {
+            panic!("Expected section event to remain a section event");
+        };
+        assert_eq!(
+            section.location.as_deref(),
+            Some("/welcome?email=[REDACTED]"),
+            "Expected email to be redacted before save"
+        );
+        assert_eq!(
+            section.path.as_deref(),
+            Some("/welcome"),
+            "Expected path to be derived from the location"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_save_normalizes_paths() {
+        let mock_repo = MockIngestEventRepository {
+            save_result: Ok(IngestActionSummary::Save(IngestEventSaveSummary {
+                event_count: 1,
+                duplicate_count: 0,
+            })),
+            event_source_result: Ok(HashSet::new()),
+            source_rules_result: Ok(IngestSourceRules {
+                path_normalization: PathNormalizationRules {
+                    lowercase: true,
+                    collapse_numeric: true,
+                    trim_trailing_slash: true,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }),
+            server_key_result: Ok(false),
+        };
+        let test_service = IngestService::new(mock_repo);
+        let mut test_events: Vec<IngestEvent> = vec![IngestEvent::Section(
+            SectionEvent::try_new(
+                ApiKey::new("abc-123"),
+                Site::new("test.com"),
+                Uuid::now_v7(),
+                Uuid::now_v7(),
+                Some("https://test.com/Users/123/?tab=orders".to_owned()),
+                None,
+            )
+            .unwrap(),
+        )];
+        test_service
+            .apply_source_rules(&mut test_events)
+            .await
+            .unwrap();
+        let IngestEvent::Section(ref section) = test_events[0] else {
+            panic!("Expected section event to remain a section event");
+        };
+        assert_eq!(
+            section.path.as_deref(),
+            Some("/users/:id"),
+            "Expected path to be normalized with the source rules"
+        );
+        assert_eq!(
+            section.location.as_deref(),
+            Some("https://test.com/Users/123/?tab=orders"),
+            "Expected raw location to be preserved"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_save_skips_duplicates() {
+        let mock_repo = |save_result| MockIngestEventRepository {
+            save_result,
+            event_source_result: Ok(HashSet::new()),
+            source_rules_result: Ok(IngestSourceRules::default()),
+            server_key_result: Ok(false),
+        };
+        let visitor = IngestEvent::Visitor(
+            VisitorEvent::try_new(
+                ApiKey::new("abc-123"),
+                Site::new("test.com"),
+                Uuid::now_v7(),
+            )
+            .unwrap(),
+        );
+
+        let failing_service = IngestService::new(mock_repo(Err(IngestRepositoryError::Repository)));
+        for _ in 0..2 {
+            assert_eq!(
+                failing_service
+                    .save(vec![visitor.clone()])
+                    .await
+                    .unwrap_err(),
+                IngestServiceError::Repository(IngestRepositoryError::Repository),
+                "Expected events of a failed save to be retried rather than skipped"
+            );
+        }
+
+        let test_service = IngestService::new(mock_repo(Ok(IngestActionSummary::Save(
+            IngestEventSaveSummary {
+                event_count: 1,
+                duplicate_count: 0,
+            },
+        ))));
+        let Ok(IngestActionSummary::Save(first)) = test_service.save(vec![visitor.clone()]).await
+        else {
+            panic!("Expected to save the event with mock");
+        };
+        assert_eq!((first.event_count, first.duplicate_count), (1, 0));
+
+        let Ok(IngestActionSummary::Save(retried)) = test_service
+            .save(vec![visitor.clone(), visitor.clone()])
+            .await
+        else {
+            panic!("Expected a retried batch to succeed");
+        };
+        assert_eq!(
+            (retried.event_count, retried.duplicate_count),
+            (0, 2),
+            "Expected saved events to be counted as duplicates"
+        );
+
+        let stored_service = IngestService::new(mock_repo(Ok(IngestActionSummary::Save(
+            IngestEventSaveSummary {
+                event_count: 0,
+                duplicate_count: 1,
+            },
+        ))));
+        let Ok(IngestActionSummary::Save(stored)) = stored_service.save(vec![visitor]).await else {
+            panic!("Expected to save the event with mock");
+        };
+        assert_eq!(
+            (stored.event_count, stored.duplicate_count),
+            (0, 1),
+            "Expected duplicates skipped by the repository to be counted"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_save_reports_hierarchy() {
+        let test_service = IngestService::new(MockIngestEventRepository {
+            save_result: Ok(IngestActionSummary::Save(IngestEventSaveSummary {
+                event_count: 2,
+                duplicate_count: 0,
+            })),
+            event_source_result: Ok(HashSet::new()),
+            source_rules_result: Ok(IngestSourceRules::default()),
+            server_key_result: Ok(false),
+        });
+        let visitor_id = Uuid::now_v7();
+        let test_events: Vec<IngestEvent> = vec![
+            IngestEvent::Visitor(
+                VisitorEvent::try_new(ApiKey::new("abc-123"), Site::new("test.com"), visitor_id)
+                    .unwrap(),
+            ),
+            IngestEvent::Section(
+                SectionEvent::try_new(
+                    ApiKey::new("abc-123"),
+                    Site::new("test.com"),
+                    Uuid::now_v7(),
+                    visitor_id,
+                    None,
+                    None,
+                )
+                .unwrap(),
+            ),
+        ];
+        let failing_service = IngestService::new(MockIngestEventRepository {
+            save_result: Err(IngestRepositoryError::InvalidRequest),
+            event_source_result: Ok(HashSet::new()),
+            source_rules_result: Ok(IngestSourceRules::default()),
+            server_key_result: Ok(false),
+        });
+        failing_service.save(test_events.clone()).await.unwrap_err();
+        assert!(
+            failing_service.hierarchy_report().is_empty(),
+            "Expected events of a failed save to not be observed"
+        );
+
+        test_service.save(test_events).await.unwrap();
+
+        let [report] = &test_service.hierarchy_report()[..] else {
+            panic!("Expected a hierarchy report for the source");
+        };
+        assert_eq!(
+            report.source,
+            IngestEventSource::new(ApiKey::new("abc-123"), Site::new("test.com"))
+        );
+        assert_eq!(
+            (report.stats.checked, report.stats.mismatched),
+            (1, 1),
+            "Expected a section under a visitor to be reported as mismatched"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_save_pseudonymizes_identify_events() {
+        let identify_events = || {
+            vec![IngestEvent::Identify(
+                IdentifyEvent::try_new(
+                    ApiKey::new("abc-123"),
+                    Site::new("test.com"),
+                    Uuid::now_v7(),
+                    Uuid::now_v7(),
+                    "user-1".to_owned(),
+                    HashMap::new(),
+                )
+                .unwrap(),
+            )]
+        };
+        let mock_repo = |identity_key: Option<IdentityKey>| MockIngestEventRepository {
+            save_result: Ok(IngestActionSummary::Save(IngestEventSaveSummary {
+                event_count: 1,
+                duplicate_count: 0,
+            })),
+            event_source_result: Ok(HashSet::new()),
+            source_rules_result: Ok(IngestSourceRules {
+                identity_key,
+                ..Default::default()
+            }),
+            server_key_result: Ok(false),
+        };
+
+        let test_service = IngestService::new(mock_repo(Some(IdentityKey::new("key"))));
+        let mut test_events = identify_events();
+        test_service
+            .apply_source_rules(&mut test_events)
+            .await
+            .unwrap();
+        let IngestEvent::Identify(ref identify) = test_events[0] else {
+            panic!("Expected identify event to remain an identify event");
+        };
+        assert_eq!(
+            identify.user_hash(),
+            Some(IdentityKey::new("key").pseudonymize("user-1").as_str()),
+            "Expected user id to be hashed with the identity key of the source"
+        );
+
+        let Err(missing_key_result) = IngestService::new(mock_repo(None))
+            .save(identify_events())
+            .await
+        else {
+            panic!("Expected save to fail without an identity key");
+        };
+        assert_eq!(
+            missing_key_result,
+            IngestServiceError::IdentityKey,
+            "Expected IdentityKey error"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_validate() {
+        let mock_repo = |event_source_result| MockIngestEventRepository {
+            save_result: Ok(IngestActionSummary::Save(IngestEventSaveSummary {
+                event_count: 1,
+                duplicate_count: 0,
+            })),
+            event_source_result,
+            source_rules_result: Ok(IngestSourceRules::default()),
+            server_key_result: Ok(false),
+        };
+        let event_sources = HashSet::from([IngestEventSource::new(
+            ApiKey::new("abc-123"),
+            Site::new("test.com"),
+        )]);
+        let test_service = IngestService::new(mock_repo(Ok(event_sources.clone())));
+        let visitor = |site: &str| {
+            IngestEvent::Visitor(
+                VisitorEvent::try_new(ApiKey::new("abc-123"), Site::new(site), Uuid::now_v7())
+                    .unwrap(),
+            )
+        };
+        let identify = IngestEvent::Identify(
+            IdentifyEvent::try_new(
+                ApiKey::new("abc-123"),
+                Site::new("test.com"),
+                Uuid::now_v7(),
+                Uuid::now_v7(),
+                "user-1".to_owned(),
+                HashMap::new(),
+            )
+            .unwrap(),
+        );
+        let valid = visitor("test.com");
+        assert_eq!(
+            test_service
+                .validate(&[valid.clone(), visitor("other.com"), identify.clone()])
+                .await
+                .unwrap(),
+            vec![
+                None,
+                Some(IngestServiceError::InvalidRequest),
+                Some(IngestServiceError::IdentityKey)
+            ]
+        );
+        let keyed_service = IngestService::new(MockIngestEventRepository {
+            source_rules_result: Ok(IngestSourceRules {
+                identity_key: Some(IdentityKey::new("key")),
+                ..Default::default()
+            }),
+            ..mock_repo(Ok(event_sources))
+        });
+        assert_eq!(
+            keyed_service.validate(&[identify]).await.unwrap(),
+            vec![None],
+            "Expected the rules of a save to accept a keyed source"
+        );
+        let Ok(IngestActionSummary::Save(save_summary)) = test_service.save(vec![valid]).await
+        else {
+            panic!("Expected the validated event to be saved");
+        };
+        assert_eq!(
+            save_summary.duplicate_count, 0,
+            "Expected validation to not record the event as saved"
+        );
+
+        // Negative test case
+        assert_eq!(
+            IngestService::new(mock_repo(Err(IngestRepositoryError::Repository)))
+                .validate(&[visitor("test.com")])
+                .await
+                .unwrap_err(),
+            IngestServiceError::Repository(IngestRepositoryError::Repository)
+        );
+    }
 }